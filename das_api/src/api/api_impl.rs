@@ -8,10 +8,12 @@ use digital_asset_types::{
         SearchAssetsQuery,
     },
     dapi::{
-        get_asset, get_assets_by_authority, get_assets_by_creators, get_assets_by_group,
-        get_assets_by_owner, get_proof_for_asset, search_assets,
+        get_asset, get_asset_history, get_assets_by_authority, get_assets_by_creators,
+        get_assets_by_group, get_assets_by_owner, get_candy_machine, get_fanout,
+        get_listings_by_asset, get_listings_by_collection, get_listings_by_seller,
+        get_proof_for_asset, search_assets,
     },
-    rpc::filter::SearchConditionType,
+    rpc::filter::{Commitment, SearchConditionType},
     rpc::{OwnershipModel, RoyaltyModel},
 };
 use open_rpc_derive::document_rpc;
@@ -25,7 +27,9 @@ use {
     crate::validation::validate_pubkey,
     crate::DasApiError,
     async_trait::async_trait,
-    digital_asset_types::rpc::{response::AssetList, Asset, AssetProof},
+    digital_asset_types::rpc::{
+        response::AssetList, Asset, AssetHistory, AssetProof, CandyMachine, Fanout, ListingList,
+    },
     sea_orm::{DatabaseConnection, DbErr, SqlxPostgresConnector},
     sqlx::postgres::PgPoolOptions,
 };
@@ -82,6 +86,29 @@ impl DasApi {
 
         Ok(())
     }
+
+    /// Only `getAsset` takes the state back to an earlier slot, the lists are as processed.
+    fn validate_commitment(&self, commitment: &Option<Commitment>) -> Result<(), DasApiError> {
+        match commitment {
+            None | Some(Commitment::Processed) => Ok(()),
+            Some(_) => Err(DasApiError::ValidationError(
+                "Only getAsset supports a commitment other than processed".to_string(),
+            )),
+        }
+    }
+
+    /// Listings and the asset history are only paged by number, starting at the first page.
+    fn page_params(
+        &self,
+        limit: Option<u32>,
+        page: Option<u32>,
+    ) -> Result<(u64, u64), DasApiError> {
+        let limit = limit.unwrap_or(1000);
+        if limit > 1000 {
+            return Err(DasApiError::PaginationError);
+        }
+        Ok((limit as u64, page.unwrap_or(1).max(1) as u64))
+    }
 }
 
 pub fn not_found(asset_id: &String) -> DbErr {
@@ -116,12 +143,22 @@ impl ApiContract for DasApi {
             .map_err(Into::into)
     }
 
-    async fn get_asset(self: &DasApi, asset_id: String) -> Result<Asset, DasApiError> {
+    async fn get_asset(
+        self: &DasApi,
+        asset_id: String,
+        show_collection_metadata: Option<bool>,
+        commitment: Option<Commitment>,
+    ) -> Result<Asset, DasApiError> {
         let id = validate_pubkey(asset_id.clone())?;
         let id_bytes = id.to_bytes().to_vec();
-        get_asset(&self.db_connection, id_bytes)
-            .await
-            .map_err(Into::into)
+        get_asset(
+            &self.db_connection,
+            id_bytes,
+            show_collection_metadata.unwrap_or(false),
+            &commitment.unwrap_or_default(),
+        )
+        .await
+        .map_err(Into::into)
     }
 
     async fn get_assets_by_owner(
@@ -130,12 +167,17 @@ impl ApiContract for DasApi {
     ) -> Result<AssetList, DasApiError> {
         let GetAssetsByOwner {
             owner_address,
+            show_fungible,
+            show_beneficially_owned,
+            show_collection_metadata,
             sort_by,
             limit,
             page,
             before,
             after,
+            commitment,
         } = payload;
+        self.validate_commitment(&commitment)?;
         let before: Option<String> = before.filter(|before| !before.is_empty());
         let after: Option<String> = after.filter(|after| !after.is_empty());
         let owner_address = validate_pubkey(owner_address.clone())?;
@@ -145,6 +187,9 @@ impl ApiContract for DasApi {
         get_assets_by_owner(
             &self.db_connection,
             owner_address_bytes,
+            show_fungible.unwrap_or(false),
+            show_beneficially_owned.unwrap_or(false),
+            show_collection_metadata.unwrap_or(false),
             sort_by,
             limit.map(|x| x as u64).unwrap_or(1000),
             page.map(|x| x as u64),
@@ -162,12 +207,16 @@ impl ApiContract for DasApi {
         let GetAssetsByGroup {
             group_key,
             group_value,
+            show_unverified,
+            show_collection_metadata,
             sort_by,
             limit,
             page,
             before,
             after,
+            commitment,
         } = payload;
+        self.validate_commitment(&commitment)?;
         let before: Option<String> = before.filter(|before| !before.is_empty());
         let after: Option<String> = after.filter(|after| !after.is_empty());
         let sort_by = sort_by.unwrap_or_default();
@@ -176,6 +225,8 @@ impl ApiContract for DasApi {
             &self.db_connection,
             group_key,
             group_value,
+            show_unverified.unwrap_or(false),
+            show_collection_metadata.unwrap_or(false),
             sort_by,
             limit.map(|x| x as u64).unwrap_or(1000),
             page.map(|x| x as u64),
@@ -193,12 +244,15 @@ impl ApiContract for DasApi {
         let GetAssetsByCreator {
             creator_address,
             only_verified,
+            show_collection_metadata,
             sort_by,
             limit,
             page,
             before,
             after,
+            commitment,
         } = payload;
+        self.validate_commitment(&commitment)?;
         let creator_address = validate_pubkey(creator_address)
             .unwrap()
             .to_bytes()
@@ -211,6 +265,7 @@ impl ApiContract for DasApi {
             &self.db_connection,
             vec![creator_address],
             only_verified,
+            show_collection_metadata.unwrap_or(false),
             sort_by,
             limit.map(|x| x as u64).unwrap_or(1000),
             page.map(|x| x as u64),
@@ -227,12 +282,15 @@ impl ApiContract for DasApi {
     ) -> Result<AssetList, DasApiError> {
         let GetAssetsByAuthority {
             authority_address,
+            show_collection_metadata,
             sort_by,
             limit,
             page,
             before,
             after,
+            commitment,
         } = payload;
+        self.validate_commitment(&commitment)?;
         let sort_by = sort_by.unwrap_or_default();
         let authority_address = validate_pubkey(authority_address)
             .unwrap()
@@ -243,6 +301,7 @@ impl ApiContract for DasApi {
         get_assets_by_authority(
             &self.db_connection,
             authority_address,
+            show_collection_metadata.unwrap_or(false),
             sort_by,
             limit.map(|x| x as u64).unwrap_or(1000),
             page.map(|x| x as u64),
@@ -275,12 +334,15 @@ impl ApiContract for DasApi {
             royalty_target,
             royalty_amount,
             burnt,
+            show_collection_metadata,
             sort_by,
             limit,
             page,
             before,
             after,
+            commitment,
         } = payload;
+        self.validate_commitment(&commitment)?;
         // Deserialize search assets query
         self.validate_pagination(&limit, &page, &before, &after)?;
         let spec: Option<(SpecificationVersions, SpecificationAssetClass)> =
@@ -334,6 +396,7 @@ impl ApiContract for DasApi {
         search_assets(
             &self.db_connection,
             saq,
+            show_collection_metadata.unwrap_or(false),
             sort_by,
             limit.map(|x| x as u64).unwrap_or(1000),
             page.map(|x| x as u64),
@@ -343,4 +406,74 @@ impl ApiContract for DasApi {
         .await
         .map_err(Into::into)
     }
+
+    async fn get_candy_machine(self: &DasApi, id: String) -> Result<CandyMachine, DasApiError> {
+        let id = validate_pubkey(id)?;
+        get_candy_machine(&self.db_connection, id.to_bytes().to_vec())
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn get_fanout(self: &DasApi, id: String) -> Result<Fanout, DasApiError> {
+        let id = validate_pubkey(id)?;
+        get_fanout(&self.db_connection, id.to_bytes().to_vec())
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn get_listings_by_asset(
+        self: &DasApi,
+        payload: GetListingsByAsset,
+    ) -> Result<ListingList, DasApiError> {
+        let GetListingsByAsset { id, limit, page } = payload;
+        let id = validate_pubkey(id)?;
+        let (limit, page) = self.page_params(limit, page)?;
+        get_listings_by_asset(&self.db_connection, id.to_bytes().to_vec(), limit, page)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn get_listings_by_collection(
+        self: &DasApi,
+        payload: GetListingsByCollection,
+    ) -> Result<ListingList, DasApiError> {
+        let GetListingsByCollection {
+            collection,
+            limit,
+            page,
+        } = payload;
+        let collection = validate_pubkey(collection)?;
+        let (limit, page) = self.page_params(limit, page)?;
+        get_listings_by_collection(&self.db_connection, collection.to_string(), limit, page)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn get_listings_by_seller(
+        self: &DasApi,
+        payload: GetListingsBySeller,
+    ) -> Result<ListingList, DasApiError> {
+        let GetListingsBySeller {
+            seller_address,
+            limit,
+            page,
+        } = payload;
+        let seller = validate_pubkey(seller_address)?;
+        let (limit, page) = self.page_params(limit, page)?;
+        get_listings_by_seller(&self.db_connection, seller.to_bytes().to_vec(), limit, page)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn get_asset_history(
+        self: &DasApi,
+        payload: GetAssetHistory,
+    ) -> Result<AssetHistory, DasApiError> {
+        let GetAssetHistory { id, limit, page } = payload;
+        let id = validate_pubkey(id)?;
+        let (limit, page) = self.page_params(limit, page)?;
+        get_asset_history(&self.db_connection, id.to_bytes().to_vec(), limit, page)
+            .await
+            .map_err(Into::into)
+    }
 }
@@ -1,9 +1,13 @@
 use crate::{DasApiError, RpcModule};
 use async_trait::async_trait;
 use digital_asset_types::rpc::filter::AssetSorting;
+use digital_asset_types::rpc::filter::Commitment;
 use digital_asset_types::rpc::filter::SearchConditionType;
 use digital_asset_types::rpc::response::AssetList;
-use digital_asset_types::rpc::{Asset, AssetProof, Interface, OwnershipModel, RoyaltyModel};
+use digital_asset_types::rpc::{
+    Asset, AssetHistory, AssetProof, CandyMachine, Fanout, Interface, ListingList, OwnershipModel,
+    RoyaltyModel,
+};
 use open_rpc_derive::{document_rpc, rpc};
 use open_rpc_schema::schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -16,28 +20,81 @@ pub use api_impl::*;
 pub struct GetAssetsByGroup {
     pub group_key: String,
     pub group_value: String,
+    pub show_unverified: Option<bool>,
+    pub show_collection_metadata: Option<bool>,
     pub sort_by: Option<AssetSorting>,
     pub limit: Option<u32>,
     pub page: Option<u32>,
     pub before: Option<String>,
     pub after: Option<String>,
+    pub commitment: Option<Commitment>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GetAssetsByOwner {
     pub owner_address: String,
+    pub show_fungible: Option<bool>,
+    pub show_beneficially_owned: Option<bool>,
+    pub show_collection_metadata: Option<bool>,
     pub sort_by: Option<AssetSorting>,
     pub limit: Option<u32>,
     pub page: Option<u32>,
     pub before: Option<String>,
     pub after: Option<String>,
+    pub commitment: Option<Commitment>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GetAsset {
     pub id: String,
+    pub show_collection_metadata: Option<bool>,
+    pub commitment: Option<Commitment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GetCandyMachine {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GetFanout {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GetListingsByAsset {
+    pub id: String,
+    pub limit: Option<u32>,
+    pub page: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GetListingsByCollection {
+    pub collection: String,
+    pub limit: Option<u32>,
+    pub page: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GetListingsBySeller {
+    pub seller_address: String,
+    pub limit: Option<u32>,
+    pub page: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GetAssetHistory {
+    pub id: String,
+    pub limit: Option<u32>,
+    pub page: Option<u32>,
 }
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -45,11 +102,13 @@ pub struct GetAsset {
 pub struct GetAssetsByCreator {
     pub creator_address: String,
     pub only_verified: Option<bool>,
+    pub show_collection_metadata: Option<bool>,
     pub sort_by: Option<AssetSorting>,
     pub limit: Option<u32>,
     pub page: Option<u32>,
     pub before: Option<String>,
     pub after: Option<String>,
+    pub commitment: Option<Commitment>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
@@ -74,11 +133,13 @@ pub struct SearchAssets {
     pub royalty_target: Option<String>,
     pub royalty_amount: Option<u32>,
     pub burnt: Option<bool>,
+    pub show_collection_metadata: Option<bool>,
     pub sort_by: Option<AssetSorting>,
     pub limit: Option<u32>,
     pub page: Option<u32>,
     pub before: Option<String>,
     pub after: Option<String>,
+    pub commitment: Option<Commitment>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
@@ -86,11 +147,13 @@ pub struct SearchAssets {
 
 pub struct GetAssetsByAuthority {
     pub authority_address: String,
+    pub show_collection_metadata: Option<bool>,
     pub sort_by: Option<AssetSorting>,
     pub limit: Option<u32>,
     pub page: Option<u32>,
     pub before: Option<String>,
     pub after: Option<String>,
+    pub commitment: Option<Commitment>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
@@ -112,7 +175,12 @@ pub trait ApiContract: Send + Sync + 'static {
     #[rpc]
     async fn get_asset_proof(&self, asset_id: String) -> Result<AssetProof, DasApiError>;
     #[rpc]
-    async fn get_asset(&self, asset_id: String) -> Result<Asset, DasApiError>;
+    async fn get_asset(
+        &self,
+        asset_id: String,
+        show_collection_metadata: Option<bool>,
+        commitment: Option<Commitment>,
+    ) -> Result<Asset, DasApiError>;
     #[rpc]
     async fn get_assets_by_owner(
         &self,
@@ -135,4 +203,28 @@ pub trait ApiContract: Send + Sync + 'static {
     ) -> Result<AssetList, DasApiError>;
     #[rpc]
     async fn search_assets(&self, payload: SearchAssets) -> Result<AssetList, DasApiError>;
+    #[rpc]
+    async fn get_candy_machine(&self, id: String) -> Result<CandyMachine, DasApiError>;
+    #[rpc]
+    async fn get_fanout(&self, id: String) -> Result<Fanout, DasApiError>;
+    #[rpc]
+    async fn get_listings_by_asset(
+        &self,
+        payload: GetListingsByAsset,
+    ) -> Result<ListingList, DasApiError>;
+    #[rpc]
+    async fn get_listings_by_collection(
+        &self,
+        payload: GetListingsByCollection,
+    ) -> Result<ListingList, DasApiError>;
+    #[rpc]
+    async fn get_listings_by_seller(
+        &self,
+        payload: GetListingsBySeller,
+    ) -> Result<ListingList, DasApiError>;
+    #[rpc]
+    async fn get_asset_history(
+        &self,
+        payload: GetAssetHistory,
+    ) -> Result<AssetHistory, DasApiError>;
 }
@@ -27,12 +27,19 @@ impl RpcApiBuilder {
 
         module.register_async_method("get_asset", |rpc_params, rpc_context| async move {
             let payload = rpc_params.parse::<GetAsset>();
-            let asset_id = match payload {
-                Ok(payload) => Ok(payload.id),
-                Err(_) => rpc_params.one::<String>(),
+            let (asset_id, show_collection_metadata, commitment) = match payload {
+                Ok(payload) => Ok((
+                    payload.id,
+                    payload.show_collection_metadata,
+                    payload.commitment,
+                )),
+                Err(_) => rpc_params.one::<String>().map(|id| (id, None, None)),
             }?;
             println!("Asset Id {}", asset_id);
-            rpc_context.get_asset(asset_id).await.map_err(Into::into)
+            rpc_context
+                .get_asset(asset_id, show_collection_metadata, commitment)
+                .await
+                .map_err(Into::into)
         })?;
         module.register_alias("getAsset", "get_asset")?;
 
@@ -89,6 +96,77 @@ impl RpcApiBuilder {
         })?;
         module.register_alias("searchAssets", "search_assets")?;
 
+        module.register_async_method(
+            "get_candy_machine",
+            |rpc_params, rpc_context| async move {
+                let payload = rpc_params.parse::<GetCandyMachine>();
+                let id = match payload {
+                    Ok(payload) => Ok(payload.id),
+                    Err(_) => rpc_params.one::<String>(),
+                }?;
+                rpc_context.get_candy_machine(id).await.map_err(Into::into)
+            },
+        )?;
+        module.register_alias("getCandyMachine", "get_candy_machine")?;
+
+        module.register_async_method("get_fanout", |rpc_params, rpc_context| async move {
+            let payload = rpc_params.parse::<GetFanout>();
+            let id = match payload {
+                Ok(payload) => Ok(payload.id),
+                Err(_) => rpc_params.one::<String>(),
+            }?;
+            rpc_context.get_fanout(id).await.map_err(Into::into)
+        })?;
+        module.register_alias("getFanout", "get_fanout")?;
+
+        module.register_async_method(
+            "get_listings_by_asset",
+            |rpc_params, rpc_context| async move {
+                let payload = rpc_params.parse::<GetListingsByAsset>()?;
+                rpc_context
+                    .get_listings_by_asset(payload)
+                    .await
+                    .map_err(Into::into)
+            },
+        )?;
+        module.register_alias("getListingsByAsset", "get_listings_by_asset")?;
+
+        module.register_async_method(
+            "get_listings_by_collection",
+            |rpc_params, rpc_context| async move {
+                let payload = rpc_params.parse::<GetListingsByCollection>()?;
+                rpc_context
+                    .get_listings_by_collection(payload)
+                    .await
+                    .map_err(Into::into)
+            },
+        )?;
+        module.register_alias("getListingsByCollection", "get_listings_by_collection")?;
+
+        module.register_async_method(
+            "get_listings_by_seller",
+            |rpc_params, rpc_context| async move {
+                let payload = rpc_params.parse::<GetListingsBySeller>()?;
+                rpc_context
+                    .get_listings_by_seller(payload)
+                    .await
+                    .map_err(Into::into)
+            },
+        )?;
+        module.register_alias("getListingsBySeller", "get_listings_by_seller")?;
+
+        module.register_async_method(
+            "get_asset_history",
+            |rpc_params, rpc_context| async move {
+                let payload = rpc_params.parse::<GetAssetHistory>()?;
+                rpc_context
+                    .get_asset_history(payload)
+                    .await
+                    .map_err(Into::into)
+            },
+        )?;
+        module.register_alias("getAssetHistory", "get_asset_history")?;
+
         module.register_async_method("schema", |_, rpc_context| async move {
             Ok(rpc_context.schema())
         })?;
@@ -1,4 +1,6 @@
-use crate::dao::{asset, asset_authority, asset_creators, asset_data, asset_grouping};
+use crate::dao::{
+    asset, asset_authority, asset_creators, asset_data, asset_grouping, collection, tokens,
+};
 use sea_orm::FromQueryResult;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -8,6 +10,16 @@ pub struct FullAsset {
     pub authorities: Vec<asset_authority::Model>,
     pub creators: Vec<asset_creators::Model>,
     pub groups: Vec<asset_grouping::Model>,
+    pub token: Option<tokens::Model>,
+    pub token_balance: Option<i64>,
+    pub collections: Vec<FullCollection>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FullCollection {
+    pub id: Vec<u8>,
+    pub data: Option<asset_data::Model>,
+    pub details: Option<collection::Model>,
 }
 #[derive(Clone, Debug, PartialEq)]
 pub struct AssetRelated {
@@ -43,6 +43,11 @@ pub struct Model {
     pub slot_updated: i64,
     pub data_hash: Option<String>,
     pub creator_hash: Option<String>,
+    pub metadata_address: Option<Vec<u8>>,
+    pub beneficial_owner: Option<Vec<u8>>,
+    pub write_version: i64,
+    pub owner_slot_updated: i64,
+    pub owner_write_version: i64,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -72,6 +77,11 @@ pub enum Column {
     SlotUpdated,
     DataHash,
     CreatorHash,
+    MetadataAddress,
+    BeneficialOwner,
+    WriteVersion,
+    OwnerSlotUpdated,
+    OwnerWriteVersion,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -124,6 +134,11 @@ impl ColumnTrait for Column {
             Self::SlotUpdated => ColumnType::BigInteger.def(),
             Self::DataHash => ColumnType::Char(Some(50u32)).def().null(),
             Self::CreatorHash => ColumnType::Char(Some(50u32)).def().null(),
+            Self::MetadataAddress => ColumnType::Binary.def().null(),
+            Self::BeneficialOwner => ColumnType::Binary.def().null(),
+            Self::WriteVersion => ColumnType::BigInteger.def(),
+            Self::OwnerSlotUpdated => ColumnType::BigInteger.def(),
+            Self::OwnerWriteVersion => ColumnType::BigInteger.def(),
         }
     }
 }
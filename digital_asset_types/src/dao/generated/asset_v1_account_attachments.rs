@@ -21,6 +21,7 @@ pub struct Model {
     pub initialized: bool,
     pub data: Option<Json>,
     pub slot_updated: i64,
+    pub write_version: i64,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -31,6 +32,7 @@ pub enum Column {
     Initialized,
     Data,
     SlotUpdated,
+    WriteVersion,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -60,6 +62,7 @@ impl ColumnTrait for Column {
             Self::Initialized => ColumnType::Boolean.def(),
             Self::Data => ColumnType::JsonBinary.def().null(),
             Self::SlotUpdated => ColumnType::BigInteger.def(),
+            Self::WriteVersion => ColumnType::BigInteger.def(),
         }
     }
 }
@@ -7,11 +7,28 @@ pub mod asset_authority;
 pub mod asset_creators;
 pub mod asset_data;
 pub mod asset_grouping;
+pub mod asset_history;
 pub mod asset_v1_account_attachments;
 pub mod backfill_items;
+pub mod bids;
+pub mod candy_guard;
+pub mod candy_guard_group;
+pub mod candy_machine;
 pub mod cl_items;
+pub mod closed_accounts;
+pub mod collection;
+pub mod dead_letters;
+pub mod fanout;
+pub mod fanout_membership;
+pub mod listings;
 pub mod raw_txn;
 pub mod sea_orm_active_enums;
+pub mod signature_crawl_slots;
+pub mod signature_crawls;
+pub mod slots;
 pub mod tasks;
 pub mod token_accounts;
+pub mod token_owned_escrow;
 pub mod tokens;
+pub mod tree_config;
+pub mod unconfirmed_writes;
@@ -5,10 +5,27 @@ pub use super::asset_authority::Entity as AssetAuthority;
 pub use super::asset_creators::Entity as AssetCreators;
 pub use super::asset_data::Entity as AssetData;
 pub use super::asset_grouping::Entity as AssetGrouping;
+pub use super::asset_history::Entity as AssetHistory;
 pub use super::asset_v1_account_attachments::Entity as AssetV1AccountAttachments;
 pub use super::backfill_items::Entity as BackfillItems;
+pub use super::bids::Entity as Bids;
+pub use super::candy_guard::Entity as CandyGuard;
+pub use super::candy_guard_group::Entity as CandyGuardGroup;
+pub use super::candy_machine::Entity as CandyMachine;
 pub use super::cl_items::Entity as ClItems;
+pub use super::closed_accounts::Entity as ClosedAccounts;
+pub use super::collection::Entity as Collection;
+pub use super::dead_letters::Entity as DeadLetters;
+pub use super::fanout::Entity as Fanout;
+pub use super::fanout_membership::Entity as FanoutMembership;
+pub use super::listings::Entity as Listings;
 pub use super::raw_txn::Entity as RawTxn;
+pub use super::signature_crawl_slots::Entity as SignatureCrawlSlots;
+pub use super::signature_crawls::Entity as SignatureCrawls;
+pub use super::slots::Entity as Slots;
 pub use super::tasks::Entity as Tasks;
 pub use super::token_accounts::Entity as TokenAccounts;
+pub use super::token_owned_escrow::Entity as TokenOwnedEscrow;
 pub use super::tokens::Entity as Tokens;
+pub use super::tree_config::Entity as TreeConfig;
+pub use super::unconfirmed_writes::Entity as UnconfirmedWrites;
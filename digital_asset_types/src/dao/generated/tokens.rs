@@ -23,6 +23,7 @@ pub struct Model {
     pub close_authority: Option<Vec<u8>>,
     pub extension_data: Option<Vec<u8>>,
     pub slot_updated: i64,
+    pub write_version: i64,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -36,6 +37,7 @@ pub enum Column {
     CloseAuthority,
     ExtensionData,
     SlotUpdated,
+    WriteVersion,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -66,6 +68,7 @@ impl ColumnTrait for Column {
             Self::CloseAuthority => ColumnType::Binary.def().null(),
             Self::ExtensionData => ColumnType::Binary.def().null(),
             Self::SlotUpdated => ColumnType::BigInteger.def(),
+            Self::WriteVersion => ColumnType::BigInteger.def(),
         }
     }
 }
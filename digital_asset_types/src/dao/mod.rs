@@ -207,7 +207,8 @@ impl SearchAssetsQuery {
         if let Some(g) = self.grouping.to_owned() {
             let cond = Condition::all()
                 .add(asset_grouping::Column::GroupKey.eq(g.0))
-                .add(asset_grouping::Column::GroupValue.eq(g.1));
+                .add(asset_grouping::Column::GroupValue.eq(g.1))
+                .add(asset_grouping::Column::Verified.eq(true));
             conditions = conditions.add(cond);
             let rel = asset_grouping::Relation::Asset
                 .def()
@@ -1,5 +1,6 @@
 use crate::dao::{
-    asset, asset_authority, asset_creators, asset_data, asset_grouping, FullAsset, Pagination,
+    asset, asset_authority, asset_creators, asset_data, asset_grouping, collection, token_accounts,
+    tokens, FullAsset, FullCollection, Pagination,
 };
 use sea_orm::{
     entity::*,
@@ -7,7 +8,7 @@ use sea_orm::{
     sea_query::{ColumnRef, IntoColumnRef, TableRef},
     ConnectionTrait, DbBackend, DbErr, Order, Value,
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 pub fn paginate<'db, T>(pagination: &Pagination, limit: u64, stmt: T) -> T
 where
@@ -72,17 +73,21 @@ pub async fn get_by_grouping(
     conn: &impl ConnectionTrait,
     group_key: String,
     group_value: String,
+    show_unverified: bool,
     sort_by: asset::Column,
     sort_direction: Order,
     pagination: &Pagination,
     limit: u64,
 ) -> Result<Vec<FullAsset>, DbErr> {
-    let condition = asset_grouping::Column::GroupKey
-        .eq(group_key)
-        .and(asset_grouping::Column::GroupValue.eq(group_value));
+    let mut condition = Condition::all()
+        .add(asset_grouping::Column::GroupKey.eq(group_key))
+        .add(asset_grouping::Column::GroupValue.eq(group_value));
+    if !show_unverified {
+        condition = condition.add(asset_grouping::Column::Verified.eq(true));
+    }
     get_by_related_condition(
         conn,
-        Condition::all().add(condition),
+        condition,
         asset::Relation::AssetGrouping,
         sort_by,
         sort_direction,
@@ -95,21 +100,43 @@ pub async fn get_by_grouping(
 pub async fn get_assets_by_owner(
     conn: &impl ConnectionTrait,
     owner: Vec<u8>,
+    show_fungible: bool,
+    show_beneficially_owned: bool,
     sort_by: asset::Column,
     sort_direction: Order,
     pagination: &Pagination,
     limit: u64,
 ) -> Result<Vec<FullAsset>, DbErr> {
-    get_assets_by_column(
+    let mut condition = Condition::any().add(asset::Column::Owner.eq(owner.clone()));
+    if show_fungible {
+        // Fungible assets have no single owner, so they are found through the token accounts
+        // held by the owner instead.
+        condition = condition.add(
+            asset::Column::Id.in_subquery(
+                token_accounts::Entity::find()
+                    .select_only()
+                    .column(token_accounts::Column::Mint)
+                    .filter(token_accounts::Column::Owner.eq(owner.clone()))
+                    .filter(token_accounts::Column::Amount.gt(0))
+                    .into_query(),
+            ),
+        );
+    }
+    if show_beneficially_owned {
+        // Assets held in escrow or staked on behalf of the owner.
+        condition = condition.add(asset::Column::BeneficialOwner.eq(owner.clone()));
+    }
+    let assets = get_assets_by_condition(
         conn,
-        owner,
-        asset::Column::Owner,
+        condition,
+        vec![],
         sort_by,
         sort_direction,
         pagination,
         limit,
     )
-    .await
+    .await?;
+    get_owner_balances_for_assets(conn, owner, assets).await
 }
 
 pub async fn get_by_authority(
@@ -174,6 +201,9 @@ pub async fn get_related_for_assets(
                 authorities: vec![],
                 creators: vec![],
                 groups: vec![],
+                token: None,
+                token_balance: None,
+                collections: vec![],
             };
 
             x.insert(id.clone(), fa);
@@ -215,9 +245,121 @@ pub async fn get_related_for_assets(
         }
     }
 
+    // Uncompressed assets share their id with the mint.
+    let tokens = tokens::Entity::find()
+        .filter(tokens::Column::Mint.is_in(ids.clone()))
+        .all(conn)
+        .await?;
+    for t in tokens.into_iter() {
+        if let Some(asset) = assets_map.get_mut(&t.mint) {
+            asset.token = Some(t);
+        }
+    }
+
     Ok(assets_map.into_iter().map(|(_, v)| v).collect())
 }
 
+pub async fn get_owner_balances_for_assets(
+    conn: &impl ConnectionTrait,
+    owner: Vec<u8>,
+    assets: Vec<FullAsset>,
+) -> Result<Vec<FullAsset>, DbErr> {
+    let mints: Vec<Vec<u8>> = assets
+        .iter()
+        .filter(|a| a.token.is_some())
+        .map(|a| a.asset.id.clone())
+        .collect();
+    if mints.is_empty() {
+        return Ok(assets);
+    }
+    let token_accounts = token_accounts::Entity::find()
+        .filter(token_accounts::Column::Owner.eq(owner))
+        .filter(token_accounts::Column::Mint.is_in(mints))
+        .all(conn)
+        .await?;
+    // An owner can hold the same mint in more than one token account.
+    let balances = token_accounts
+        .into_iter()
+        .fold(BTreeMap::new(), |mut acc, ta| {
+            *acc.entry(ta.mint).or_insert(0i64) += ta.amount;
+            acc
+        });
+    Ok(assets
+        .into_iter()
+        .map(|mut a| {
+            if a.token.is_some() {
+                a.token_balance = Some(balances.get(&a.asset.id).copied().unwrap_or(0));
+            }
+            a
+        })
+        .collect())
+}
+
+pub async fn get_collections_for_assets(
+    conn: &impl ConnectionTrait,
+    assets: Vec<FullAsset>,
+) -> Result<Vec<FullAsset>, DbErr> {
+    // Groupings store the collection mint base58 encoded, the collection tables are keyed by
+    // the raw mint.
+    let ids: BTreeSet<Vec<u8>> = assets
+        .iter()
+        .flat_map(|a| a.groups.iter())
+        .filter(|g| g.group_key == "collection")
+        .filter_map(|g| bs58::decode(&g.group_value).into_vec().ok())
+        .collect();
+    if ids.is_empty() {
+        return Ok(assets);
+    }
+    let ids: Vec<Vec<u8>> = ids.into_iter().collect();
+    let mut collections = ids
+        .iter()
+        .map(|id| {
+            (
+                id.clone(),
+                FullCollection {
+                    id: id.clone(),
+                    data: None,
+                    details: None,
+                },
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let data = asset_data::Entity::find()
+        .filter(asset_data::Column::Id.is_in(ids.clone()))
+        .all(conn)
+        .await?;
+    for d in data.into_iter() {
+        if let Some(c) = collections.get_mut(&d.id) {
+            c.data = Some(d);
+        }
+    }
+
+    let details = collection::Entity::find()
+        .filter(collection::Column::Id.is_in(ids))
+        .all(conn)
+        .await?;
+    for d in details.into_iter() {
+        if let Some(c) = collections.get_mut(&d.id) {
+            c.details = Some(d);
+        }
+    }
+
+    Ok(assets
+        .into_iter()
+        .map(|mut a| {
+            a.collections = a
+                .groups
+                .iter()
+                .filter(|g| g.group_key == "collection")
+                .filter_map(|g| bs58::decode(&g.group_value).into_vec().ok())
+                .filter_map(|id| collections.get(&id).cloned())
+                .collect();
+            a
+        })
+        .collect())
+}
+
 pub async fn get_assets_by_column(
     conn: &impl ConnectionTrait,
     target_value: impl Into<Value>,
@@ -285,11 +427,17 @@ pub async fn get_by_id(conn: &impl ConnectionTrait, asset_id: Vec<u8>) -> Result
         .filter(asset_grouping::Column::AssetId.eq(asset.id.clone()))
         .all(conn)
         .await?;
+    let token: Option<tokens::Model> = tokens::Entity::find_by_id(asset.id.clone())
+        .one(conn)
+        .await?;
     Ok(FullAsset {
         asset,
         data,
         authorities,
         creators,
         groups: grouping,
+        token,
+        token_balance: None,
+        collections: vec![],
     })
 }
@@ -0,0 +1,158 @@
+//! State as of an earlier slot.  While a slot isn't rooted, the ingester records every row it
+//! writes to the asset tables in `unconfirmed_writes` along with what the row was before, so
+//! the state of a slot is the current one with the newer writes taken back.
+use crate::dao::{
+    asset, asset_authority, asset_creators, asset_data, asset_grouping, collection,
+    sea_orm_active_enums::SlotStatus, slots, tokens, unconfirmed_writes, FullAsset, FullCollection,
+};
+use sea_orm::{
+    entity::prelude::Json, entity::*, query::*, sea_query::Expr, ConnectionTrait, DbBackend, DbErr,
+    SelectModel, SelectorRaw, Statement,
+};
+use std::collections::HashMap;
+
+// Writes to the rows of an asset's creators, groupings or authorities, before or after them.
+const OF_ASSET: &str = "(unconfirmed_writes.previous -> 'asset_id' = $1 \
+                        OR unconfirmed_writes.written -> 'asset_id' = $1)";
+
+/// The newest slot that reached any of `statuses`, none while slots aren't tracked.
+pub async fn last_slot(
+    conn: &impl ConnectionTrait,
+    statuses: Vec<SlotStatus>,
+) -> Result<Option<i64>, DbErr> {
+    Ok(slots::Entity::find()
+        .filter(slots::Column::Status.is_in(statuses))
+        .order_by_desc(slots::Column::Slot)
+        .one(conn)
+        .await?
+        .map(|s| s.slot))
+}
+
+/// The key of a row as recorded in `unconfirmed_writes`, where binary keys are rendered the way
+/// Postgres renders them in JSON.
+pub fn row_key(key: &[u8]) -> Json {
+    let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+    Json::String(format!("\\x{}", hex))
+}
+
+/// Selects the row of `entity` described by a JSON image of it.
+fn populated<E: EntityTrait>(entity: E, image: Json) -> SelectorRaw<SelectModel<E::Model>> {
+    let table = entity.table_name().to_string();
+    let sql = E::find().build(DbBackend::Postgres).sql.replacen(
+        &format!(r#"FROM "{}""#, table),
+        &format!(
+            r#"FROM jsonb_populate_record(NULL::"{0}", $1) AS "{0}""#,
+            table
+        ),
+        1,
+    );
+    E::find().from_raw_sql(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &sql,
+        vec![image.into()],
+    ))
+}
+
+/// The row of `entity` keyed by `key` as it was at `slot`: none if it hasn't been written since,
+/// `Some(None)` if it didn't exist yet.
+pub async fn row_at_slot<E: EntityTrait>(
+    conn: &impl ConnectionTrait,
+    entity: E,
+    key: Json,
+    slot: i64,
+) -> Result<Option<Option<E::Model>>, DbErr> {
+    let first_write = unconfirmed_writes::Entity::find()
+        .filter(unconfirmed_writes::Column::TableName.eq(entity.table_name()))
+        .filter(unconfirmed_writes::Column::RowKey.eq(key))
+        .filter(unconfirmed_writes::Column::Slot.gt(slot))
+        .order_by_asc(unconfirmed_writes::Column::Id)
+        .one(conn)
+        .await?;
+    match first_write.map(|w| w.previous) {
+        None => Ok(None),
+        Some(None) => Ok(Some(None)),
+        Some(Some(image)) => populated(entity, image).one(conn).await.map(Some),
+    }
+}
+
+/// The rows of `E` belonging to the asset `asset_id` as they were at `slot`, from the current
+/// `rows`.  Rows are keyed by the `id` returned by `key`, rows written since are replaced by what
+/// they were, left out if they didn't exist yet, and rows deleted since are brought back.
+async fn rows_at_slot<E: EntityTrait>(
+    conn: &impl ConnectionTrait,
+    asset_id: &[u8],
+    rows: Vec<E::Model>,
+    key: impl Fn(&E::Model) -> i64,
+    slot: i64,
+) -> Result<Vec<E::Model>, DbErr> {
+    let writes = unconfirmed_writes::Entity::find()
+        .filter(unconfirmed_writes::Column::TableName.eq(E::default().table_name()))
+        .filter(unconfirmed_writes::Column::Slot.gt(slot))
+        .filter(Expr::cust_with_values(OF_ASSET, vec![row_key(asset_id)]))
+        .order_by_asc(unconfirmed_writes::Column::Id)
+        .all(conn)
+        .await?;
+    // The first write of a row since the slot holds what it was.
+    let mut first_writes: HashMap<i64, Option<Json>> = HashMap::new();
+    for write in writes {
+        if let Some(id) = write.row_key.as_i64() {
+            first_writes.entry(id).or_insert(write.previous);
+        }
+    }
+    let mut at_slot: Vec<E::Model> = rows
+        .into_iter()
+        .filter(|row| !first_writes.contains_key(&key(row)))
+        .collect();
+    for image in first_writes.into_values().flatten() {
+        at_slot.extend(populated(E::default(), image).one(conn).await?);
+    }
+    at_slot.sort_by_key(|row| key(row));
+    Ok(at_slot)
+}
+
+/// The asset as it was at `slot`, none if it didn't exist yet.  Its ownership, its flags, its
+/// metadata, its creators, groupings and authorities and its token are all taken back.
+pub async fn asset_at_slot(
+    conn: &impl ConnectionTrait,
+    mut asset: FullAsset,
+    slot: i64,
+) -> Result<Option<FullAsset>, DbErr> {
+    match row_at_slot(conn, asset::Entity, row_key(&asset.asset.id), slot).await? {
+        Some(Some(previous)) => asset.asset = previous,
+        Some(None) => return Ok(None),
+        None => {}
+    }
+    match row_at_slot(conn, asset_data::Entity, row_key(&asset.data.id), slot).await? {
+        Some(Some(previous)) => asset.data = previous,
+        Some(None) => return Ok(None),
+        None => {}
+    }
+    let id = asset.asset.id.clone();
+    asset.authorities =
+        rows_at_slot::<asset_authority::Entity>(conn, &id, asset.authorities, |a| a.id, slot)
+            .await?;
+    asset.creators =
+        rows_at_slot::<asset_creators::Entity>(conn, &id, asset.creators, |c| c.id, slot).await?;
+    asset.groups =
+        rows_at_slot::<asset_grouping::Entity>(conn, &id, asset.groups, |g| g.id, slot).await?;
+    if let Some(previous) = row_at_slot(conn, tokens::Entity, row_key(&id), slot).await? {
+        asset.token = previous;
+    }
+    Ok(Some(asset))
+}
+
+/// The metadata and details of a collection as they were at `slot`.
+pub async fn collection_at_slot(
+    conn: &impl ConnectionTrait,
+    mut collection: FullCollection,
+    slot: i64,
+) -> Result<FullCollection, DbErr> {
+    let key = row_key(&collection.id);
+    if let Some(previous) = row_at_slot(conn, asset_data::Entity, key.clone(), slot).await? {
+        collection.data = previous;
+    }
+    if let Some(previous) = row_at_slot(conn, collection::Entity, key, slot).await? {
+        collection.details = previous;
+    }
+    Ok(collection)
+}
@@ -9,6 +9,7 @@ use super::common::{build_asset_response, create_pagination, create_sorting};
 pub async fn get_assets_by_authority(
     db: &DatabaseConnection,
     authority: Vec<u8>,
+    show_collection_metadata: bool,
     sorting: AssetSorting,
     limit: u64,
     page: Option<u64>,
@@ -26,5 +27,10 @@ pub async fn get_assets_by_authority(
         limit,
     )
     .await?;
+    let assets = if show_collection_metadata {
+        scopes::asset::get_collections_for_assets(db, assets).await?
+    } else {
+        assets
+    };
     Ok(build_asset_response(assets, limit, &pagination))
 }
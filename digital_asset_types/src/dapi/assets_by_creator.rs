@@ -10,6 +10,7 @@ pub async fn get_assets_by_creators(
     db: &DatabaseConnection,
     creators: Vec<Vec<u8>>,
     only_verified: bool,
+    show_collection_metadata: bool,
     sorting: AssetSorting,
     limit: u64,
     page: Option<u64>,
@@ -28,5 +29,10 @@ pub async fn get_assets_by_creators(
         limit,
     )
     .await?;
+    let assets = if show_collection_metadata {
+        scopes::asset::get_collections_for_assets(db, assets).await?
+    } else {
+        assets
+    };
     Ok(build_asset_response(assets, limit, &pagination))
 }
@@ -9,6 +9,8 @@ pub async fn get_assets_by_group(
     db: &DatabaseConnection,
     group_key: String,
     group_value: String,
+    show_unverified: bool,
+    show_collection_metadata: bool,
     sorting: AssetSorting,
     limit: u64,
     page: Option<u64>,
@@ -21,11 +23,17 @@ pub async fn get_assets_by_group(
         db,
         group_key,
         group_value,
+        show_unverified,
         sort_column,
         sort_direction,
         &pagination,
         limit,
     )
     .await?;
+    let assets = if show_collection_metadata {
+        scopes::asset::get_collections_for_assets(db, assets).await?
+    } else {
+        assets
+    };
     Ok(build_asset_response(assets, limit, &pagination))
 }
@@ -9,6 +9,9 @@ use super::common::{build_asset_response, create_pagination, create_sorting};
 pub async fn get_assets_by_owner(
     db: &DatabaseConnection,
     owner_address: Vec<u8>,
+    show_fungible: bool,
+    show_beneficially_owned: bool,
+    show_collection_metadata: bool,
     sort_by: AssetSorting,
     limit: u64,
     page: Option<u64>,
@@ -20,11 +23,18 @@ pub async fn get_assets_by_owner(
     let assets = scopes::asset::get_assets_by_owner(
         db,
         owner_address,
+        show_fungible,
+        show_beneficially_owned,
         sort_column,
         sort_direction,
         &pagination,
         limit,
     )
     .await?;
+    let assets = if show_collection_metadata {
+        scopes::asset::get_collections_for_assets(db, assets).await?
+    } else {
+        assets
+    };
     Ok(build_asset_response(assets, limit, &pagination))
 }
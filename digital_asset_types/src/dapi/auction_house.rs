@@ -0,0 +1,119 @@
+use sea_orm::{entity::*, query::*, DatabaseConnection, DbErr};
+
+use crate::{
+    dao::{asset_grouping, asset_history, listings},
+    rpc::{AssetEvent, AssetHistory, Listing, ListingList},
+};
+
+/// Active listings of an asset.  Listings whose asset isn't indexed yet are left out until it
+/// is.
+pub async fn get_listings_by_asset(
+    db: &DatabaseConnection,
+    asset_id: Vec<u8>,
+    limit: u64,
+    page: u64,
+) -> Result<ListingList, DbErr> {
+    get_listings(db, listings::Column::AssetId.eq(asset_id), limit, page).await
+}
+
+/// Active listings of the verified members of a collection.
+pub async fn get_listings_by_collection(
+    db: &DatabaseConnection,
+    collection: String,
+    limit: u64,
+    page: u64,
+) -> Result<ListingList, DbErr> {
+    let members = asset_grouping::Entity::find()
+        .select_only()
+        .column(asset_grouping::Column::AssetId)
+        .filter(asset_grouping::Column::GroupKey.eq("collection"))
+        .filter(asset_grouping::Column::GroupValue.eq(collection))
+        .filter(asset_grouping::Column::Verified.eq(true))
+        .into_query();
+    get_listings(
+        db,
+        listings::Column::AssetId.in_subquery(members),
+        limit,
+        page,
+    )
+    .await
+}
+
+pub async fn get_listings_by_seller(
+    db: &DatabaseConnection,
+    seller: Vec<u8>,
+    limit: u64,
+    page: u64,
+) -> Result<ListingList, DbErr> {
+    get_listings(db, listings::Column::Seller.eq(seller), limit, page).await
+}
+
+async fn get_listings(
+    db: &DatabaseConnection,
+    condition: impl IntoCondition,
+    limit: u64,
+    page: u64,
+) -> Result<ListingList, DbErr> {
+    let items = listings::Entity::find()
+        .filter(condition)
+        .filter(listings::Column::Active.eq(true))
+        .filter(listings::Column::AssetId.is_not_null())
+        .order_by_asc(listings::Column::Price)
+        .order_by_asc(listings::Column::TradeState)
+        .limit(limit)
+        .offset(page.saturating_sub(1) * limit)
+        .all(db)
+        .await?;
+
+    Ok(ListingList {
+        total: items.len() as u32,
+        limit: limit as u32,
+        page: page as u32,
+        items: items
+            .into_iter()
+            .filter_map(|l| {
+                Some(Listing {
+                    trade_state: bs58::encode(l.trade_state).into_string(),
+                    auction_house: bs58::encode(l.auction_house).into_string(),
+                    asset_id: bs58::encode(l.asset_id?).into_string(),
+                    seller: bs58::encode(l.seller).into_string(),
+                    token_account: bs58::encode(l.token_account).into_string(),
+                    price: l.price as u64,
+                    token_size: l.token_size as u64,
+                })
+            })
+            .collect(),
+    })
+}
+
+/// Events of an asset, most recent first.
+pub async fn get_asset_history(
+    db: &DatabaseConnection,
+    asset_id: Vec<u8>,
+    limit: u64,
+    page: u64,
+) -> Result<AssetHistory, DbErr> {
+    let items = asset_history::Entity::find()
+        .filter(asset_history::Column::AssetId.eq(asset_id))
+        .order_by_desc(asset_history::Column::Slot)
+        .order_by_desc(asset_history::Column::Id)
+        .limit(limit)
+        .offset(page.saturating_sub(1) * limit)
+        .all(db)
+        .await?;
+
+    Ok(AssetHistory {
+        total: items.len() as u32,
+        limit: limit as u32,
+        page: page as u32,
+        items: items
+            .into_iter()
+            .map(|e| AssetEvent {
+                event: e.event,
+                signature: e.txn_id,
+                slot: e.slot as u64,
+                data: e.data,
+            })
+            .collect(),
+    })
+}
@@ -0,0 +1,70 @@
+use sea_orm::{entity::*, query::*, DatabaseConnection, DbErr};
+
+use crate::{
+    dao::{candy_guard, candy_guard_group, candy_machine},
+    rpc::{CandyGuard, CandyGuardGroup, CandyMachine},
+};
+
+pub async fn get_candy_machine(
+    db: &DatabaseConnection,
+    id: Vec<u8>,
+) -> Result<CandyMachine, DbErr> {
+    let candy_machine = candy_machine::Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Candy Machine Not Found".to_string()))?;
+    // A machine wrapped by a candy guard has the guard as its mint authority.
+    let candy_guard = match candy_guard::Entity::find_by_id(candy_machine.mint_authority.clone())
+        .one(db)
+        .await?
+    {
+        Some(guard) => {
+            let groups = candy_guard_group::Entity::find()
+                .filter(candy_guard_group::Column::CandyGuardId.eq(guard.id.clone()))
+                .order_by_asc(candy_guard_group::Column::Position)
+                .all(db)
+                .await?;
+            Some(candy_guard_to_rpc(guard, groups))
+        }
+        None => None,
+    };
+
+    Ok(CandyMachine {
+        id: bs58::encode(candy_machine.id).into_string(),
+        authority: bs58::encode(candy_machine.authority).into_string(),
+        mint_authority: bs58::encode(candy_machine.mint_authority).into_string(),
+        collection_mint: bs58::encode(candy_machine.collection_mint).into_string(),
+        items_available: candy_machine.items_available as u64,
+        items_redeemed: candy_machine.items_redeemed as u64,
+        symbol: candy_machine.symbol,
+        seller_fee_basis_points: candy_machine.seller_fee_basis_points as u16,
+        max_supply: candy_machine.max_supply as u64,
+        is_mutable: candy_machine.is_mutable,
+        candy_guard,
+    })
+}
+
+/// The unlabelled group is the default guard set, the labelled ones are the guard groups.
+fn candy_guard_to_rpc(
+    guard: candy_guard::Model,
+    groups: Vec<candy_guard_group::Model>,
+) -> CandyGuard {
+    let mut default = serde_json::Value::Object(Default::default());
+    let mut labelled = Vec::with_capacity(groups.len());
+    for group in groups {
+        match group.label {
+            Some(label) => labelled.push(CandyGuardGroup {
+                label,
+                guards: group.guards,
+            }),
+            None => default = group.guards,
+        }
+    }
+    CandyGuard {
+        id: bs58::encode(guard.id).into_string(),
+        base: bs58::encode(guard.base).into_string(),
+        authority: bs58::encode(guard.authority).into_string(),
+        guards: default,
+        groups: labelled,
+    }
+}
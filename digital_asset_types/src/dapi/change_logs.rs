@@ -1,13 +1,20 @@
 use sea_orm::sea_query::Expr;
 use sea_orm::{DatabaseConnection, DbBackend};
+use solana_sdk::{pubkey, pubkey::Pubkey};
 use {
     crate::dao::asset,
     crate::dao::cl_items,
-    crate::rpc::AssetProof,
+    crate::dao::tree_config,
+    crate::rpc::{AssetProof, TreeConfig},
     sea_orm::{entity::*, query::*, DbErr, FromQueryResult},
-    spl_concurrent_merkle_tree::node::empty_node,
+    spl_concurrent_merkle_tree::{
+        hash::recompute,
+        node::{empty_node, Node},
+    },
 };
 
+const BUBBLEGUM_PROGRAM_ID: Pubkey = pubkey!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+
 #[derive(FromQueryResult, Debug, Default, Clone, Eq, PartialEq)]
 struct SimpleChangeLog {
     hash: Vec<u8>,
@@ -46,28 +53,7 @@ pub async fn get_proof_for_asset(
     let expected_proof_size = req_indexes.len();
     let mut final_node_list: Vec<SimpleChangeLog> =
         vec![SimpleChangeLog::default(); expected_proof_size];
-    let mut query = cl_items::Entity::find()
-        .select_only()
-        .column(cl_items::Column::NodeIdx)
-        .column(cl_items::Column::Hash)
-        .column(cl_items::Column::Level)
-        .column(cl_items::Column::Seq)
-        .column(cl_items::Column::Tree)
-        .filter(cl_items::Column::NodeIdx.is_in(req_indexes.clone()))
-        .filter(cl_items::Column::Tree.eq(leaf.tree.clone()))
-        .order_by_desc(cl_items::Column::NodeIdx)
-        .order_by_desc(cl_items::Column::Id)
-        .order_by_desc(cl_items::Column::Seq)
-        .build(DbBackend::Postgres);
-    query.sql = query
-        .sql
-        .replace("SELECT", "SELECT DISTINCT ON (cl_items.node_idx)");
-    println!("sql {} ", query.sql);
-    let nodes: Vec<SimpleChangeLog> = db.query_all(query).await.map(|qr| {
-        qr.iter()
-            .map(|q| SimpleChangeLog::from_query_result(q, "").unwrap())
-            .collect()
-    })?;
+    let nodes = get_latest_nodes(db, &leaf.tree, &req_indexes).await?;
     if nodes.len() != expected_proof_size {
         for node in nodes.iter() {
             if node.level < final_node_list.len().try_into().unwrap() {
@@ -89,6 +75,7 @@ pub async fn get_proof_for_asset(
             bs58::encode(&n.hash).into_string()
         );
     }
+    let tree_config = get_tree_config(db, &leaf.tree).await?;
     Ok(AssetProof {
         root: bs58::encode(final_node_list.pop().unwrap().hash).into_string(),
         leaf: bs58::encode(&leaf.hash).into_string(),
@@ -98,9 +85,104 @@ pub async fn get_proof_for_asset(
             .collect(),
         node_index: leaf.node_idx,
         tree_id: bs58::encode(&leaf.tree).into_string(),
+        tree_config,
+    })
+}
+
+/// Loads the `TreeConfig` account of a tree, which lives at the PDA derived from the tree.
+pub async fn get_tree_config(
+    db: &DatabaseConnection,
+    tree: &[u8],
+) -> Result<Option<TreeConfig>, DbErr> {
+    let (config, _) = Pubkey::find_program_address(&[tree], &BUBBLEGUM_PROGRAM_ID);
+    let config = tree_config::Entity::find_by_id(config.to_bytes().to_vec())
+        .one(db)
+        .await?;
+    Ok(config.map(|c| TreeConfig {
+        tree_creator: bs58::encode(c.tree_creator).into_string(),
+        tree_delegate: bs58::encode(c.tree_delegate).into_string(),
+        total_mint_capacity: c.total_mint_capacity as u64,
+        num_minted: c.num_minted as u64,
+        is_public: c.is_public,
+    }))
+}
+
+/// Root of a tree recomputed from its most recently changed leaf.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeRoot {
+    pub root: Vec<u8>,
+    pub seq: i64,
+}
+
+/// Recomputes the root of a tree from the indexed nodes along the path of the last leaf that
+/// changed, which is the path every proof handed out right now would be checked against.
+pub async fn recompute_tree_root(
+    db: &DatabaseConnection,
+    tree: &[u8],
+) -> Result<Option<TreeRoot>, DbErr> {
+    let leaf = cl_items::Entity::find()
+        .filter(cl_items::Column::Tree.eq(tree))
+        .filter(cl_items::Column::Level.eq(0i64))
+        .order_by_desc(cl_items::Column::Seq)
+        .one(db)
+        .await?;
+    let leaf = match leaf {
+        Some(leaf) => leaf,
+        None => return Ok(None),
+    };
+    let req_indexes = get_required_nodes_for_proof(leaf.node_idx);
+    // The last required node is the root itself.
+    let depth = req_indexes.len() - 1;
+    let nodes = get_latest_nodes(db, tree, &req_indexes).await?;
+    let mut proof = Vec::with_capacity(depth);
+    for (level, idx) in req_indexes[..depth].iter().enumerate() {
+        let node = match nodes.iter().find(|n| n.node_idx == *idx) {
+            Some(n) => to_node(&n.hash)?,
+            None => empty_node(level as u32),
+        };
+        proof.push(node);
+    }
+    let leaf_index = leaf.node_idx - 2i64.pow(depth as u32);
+    let root = recompute(to_node(&leaf.hash)?, &proof, leaf_index as u32);
+    Ok(Some(TreeRoot {
+        root: root.to_vec(),
+        seq: leaf.seq,
+    }))
+}
+
+/// Loads the latest version of each of the given nodes of a tree.
+async fn get_latest_nodes(
+    db: &DatabaseConnection,
+    tree: &[u8],
+    indexes: &[i64],
+) -> Result<Vec<SimpleChangeLog>, DbErr> {
+    let mut query = cl_items::Entity::find()
+        .select_only()
+        .column(cl_items::Column::NodeIdx)
+        .column(cl_items::Column::Hash)
+        .column(cl_items::Column::Level)
+        .column(cl_items::Column::Seq)
+        .column(cl_items::Column::Tree)
+        .filter(cl_items::Column::NodeIdx.is_in(indexes.to_vec()))
+        .filter(cl_items::Column::Tree.eq(tree))
+        .order_by_desc(cl_items::Column::NodeIdx)
+        .order_by_desc(cl_items::Column::Id)
+        .order_by_desc(cl_items::Column::Seq)
+        .build(DbBackend::Postgres);
+    query.sql = query
+        .sql
+        .replace("SELECT", "SELECT DISTINCT ON (cl_items.node_idx)");
+    db.query_all(query).await.map(|qr| {
+        qr.iter()
+            .map(|q| SimpleChangeLog::from_query_result(q, "").unwrap())
+            .collect()
     })
 }
 
+fn to_node(hash: &[u8]) -> Result<Node, DbErr> {
+    Node::try_from(hash).map_err(|_| DbErr::Custom("Malformed tree node".to_string()))
+}
+
 fn make_empty_node(lvl: i64, node_index: i64) -> SimpleChangeLog {
     SimpleChangeLog {
         node_idx: node_index,
@@ -1,13 +1,13 @@
 use crate::dao::sea_orm_active_enums::{SpecificationAssetClass, SpecificationVersions};
 use crate::dao::Pagination;
-use crate::dao::{asset, asset_authority, asset_creators, asset_data, asset_grouping};
-use crate::dao::{FullAsset, FullAssetList};
+use crate::dao::{asset, asset_authority, asset_creators, asset_data, asset_grouping, tokens};
+use crate::dao::{FullAsset, FullAssetList, FullCollection};
 
 use crate::rpc::filter::{AssetSortBy, AssetSortDirection, AssetSorting};
 use crate::rpc::response::{AssetError, AssetList};
 use crate::rpc::{
-    Asset as RpcAsset, Authority, Compression, Content, Creator, File, Group, Interface,
-    MetadataMap, Ownership, Royalty, Scope, Supply, Uses,
+    Asset as RpcAsset, Authority, CollectionMetadata, Compression, Content, Creator, File, Group,
+    Interface, MetadataMap, Ownership, Royalty, Scope, Supply, TokenInfo, Uses,
 };
 
 use jsonpath_lib::JsonPathError;
@@ -231,16 +231,59 @@ pub fn to_creators(creators: Vec<asset_creators::Model>) -> Vec<Creator> {
         .collect()
 }
 
-pub fn to_grouping(groups: Vec<asset_grouping::Model>) -> Vec<Group> {
+pub fn to_collection_metadata(collection: &FullCollection) -> CollectionMetadata {
+    let select = |v: &Value, key: &str| {
+        v.get(key)
+            .and_then(|v| v.as_str())
+            .map(|s| s.trim().to_string())
+    };
+    let data = collection.data.as_ref();
+    CollectionMetadata {
+        name: data.and_then(|d| select(&d.chain_data, "name")),
+        symbol: data.and_then(|d| select(&d.chain_data, "symbol")),
+        image: data.and_then(|d| select(&d.metadata, "image")),
+        size: collection
+            .details
+            .as_ref()
+            .and_then(|d| d.size)
+            .map(|s| s as u64),
+    }
+}
+
+pub fn to_grouping(
+    groups: Vec<asset_grouping::Model>,
+    collections: Vec<FullCollection>,
+) -> Vec<Group> {
     groups
         .iter()
         .map(|a| Group {
             group_key: a.group_key.clone(),
             group_value: a.group_value.clone(),
+            verified: a.verified,
+            collection_metadata: match a.group_key.as_str() {
+                "collection" => collections
+                    .iter()
+                    .find(|c| bs58::encode(&c.id).into_string() == a.group_value)
+                    .map(to_collection_metadata),
+                _ => None,
+            },
         })
         .collect()
 }
 
+pub fn to_token_info(token: tokens::Model, balance: Option<i64>) -> TokenInfo {
+    TokenInfo {
+        supply: token.supply as u64,
+        decimals: token.decimals as u8,
+        token_program: bs58::encode(token.token_program).into_string(),
+        mint_authority: token.mint_authority.map(|s| bs58::encode(s).into_string()),
+        freeze_authority: token
+            .freeze_authority
+            .map(|s| bs58::encode(s).into_string()),
+        balance: balance.map(|b| b as u64),
+    }
+}
+
 pub fn get_interface(asset: &asset::Model) -> Interface {
     Interface::from((
         &asset.specification_version,
@@ -256,10 +299,13 @@ pub fn asset_to_rpc(asset: FullAsset) -> Result<RpcAsset, DbErr> {
         authorities,
         creators,
         groups,
+        token,
+        token_balance,
+        collections,
     } = asset;
     let rpc_authorities = to_authority(authorities);
     let rpc_creators = to_creators(creators);
-    let rpc_groups = to_grouping(groups);
+    let rpc_groups = to_grouping(groups, collections);
     let interface = get_interface(&asset);
     let content = get_content(&asset, &data)?;
     let mut chain_data_selector_fn = jsonpath_lib::selector(&data.chain_data);
@@ -276,6 +322,7 @@ pub fn asset_to_rpc(asset: FullAsset) -> Result<RpcAsset, DbErr> {
         content: Some(content),
         authorities: Some(rpc_authorities),
         mutable: data.chain_data_mutability.into(),
+        burnt: asset.burnt,
         compression: Some(Compression {
             eligible: asset.compressible,
             compressed: asset.compressed,
@@ -317,6 +364,9 @@ pub fn asset_to_rpc(asset: FullAsset) -> Result<RpcAsset, DbErr> {
                 .owner
                 .map(|o| bs58::encode(o).into_string())
                 .unwrap_or("".to_string()),
+            beneficial_owner: asset
+                .beneficial_owner
+                .map(|o| bs58::encode(o).into_string()),
         },
         supply: match interface {
             Interface::V1NFT => Some(Supply {
@@ -326,6 +376,7 @@ pub fn asset_to_rpc(asset: FullAsset) -> Result<RpcAsset, DbErr> {
             }),
             _ => None,
         },
+        token_info: token.map(|t| to_token_info(t, token_balance)),
         uses: data.chain_data.get("uses").map(|u| Uses {
             use_method: u
                 .get("use_method")
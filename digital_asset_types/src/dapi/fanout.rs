@@ -0,0 +1,49 @@
+use sea_orm::{entity::*, query::*, DatabaseConnection, DbErr};
+
+use crate::{
+    dao::{fanout, fanout_membership},
+    rpc::{Fanout, FanoutMember},
+};
+
+/// Looks a fanout up by its own address or by the address of its wallet, which is what assets
+/// paying royalties to the fanout report as their royalty target.
+pub async fn get_fanout(db: &DatabaseConnection, id: Vec<u8>) -> Result<Fanout, DbErr> {
+    let fanout = fanout::Entity::find()
+        .filter(
+            Condition::any()
+                .add(fanout::Column::Id.eq(id.clone()))
+                .add(fanout::Column::AccountKey.eq(id)),
+        )
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("Fanout Not Found".to_string()))?;
+    let members = fanout_membership::Entity::find()
+        .filter(fanout_membership::Column::Fanout.eq(fanout.id.clone()))
+        .order_by_desc(fanout_membership::Column::Shares)
+        .all(db)
+        .await?;
+
+    Ok(Fanout {
+        id: bs58::encode(fanout.id).into_string(),
+        authority: bs58::encode(fanout.authority).into_string(),
+        name: fanout.name,
+        wallet: bs58::encode(fanout.account_key).into_string(),
+        membership_model: fanout.membership_model,
+        membership_mint: fanout
+            .membership_mint
+            .map(|m| bs58::encode(m).into_string()),
+        total_shares: fanout.total_shares as u64,
+        total_members: fanout.total_members as u64,
+        total_available_shares: fanout.total_available_shares as u64,
+        total_inflow: fanout.total_inflow as u64,
+        members: members
+            .into_iter()
+            .map(|m| FanoutMember {
+                voucher: bs58::encode(m.id).into_string(),
+                membership_key: bs58::encode(m.membership_key).into_string(),
+                shares: m.shares as u64,
+                total_inflow: m.total_inflow as u64,
+            })
+            .collect(),
+    })
+}
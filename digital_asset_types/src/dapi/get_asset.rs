@@ -1,10 +1,71 @@
-use sea_orm::{DatabaseConnection, DbErr};
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait};
 
-use crate::{dao::scopes, rpc::Asset};
+use crate::{
+    dao::{
+        closed_accounts,
+        scopes::{
+            self,
+            commitment::{asset_at_slot, collection_at_slot},
+        },
+        sea_orm_active_enums::SlotStatus,
+    },
+    rpc::{filter::Commitment, Asset},
+};
 
 use super::common::asset_to_rpc;
 
-pub async fn get_asset(db: &DatabaseConnection, id: Vec<u8>) -> Result<Asset, DbErr> {
-    let asset = scopes::asset::get_by_id(db, id).await?;
+/// The newest slot whose state is visible at `commitment`, none if everything is.
+pub async fn commitment_slot(
+    db: &DatabaseConnection,
+    commitment: &Commitment,
+) -> Result<Option<i64>, DbErr> {
+    let statuses = match commitment {
+        Commitment::Processed => return Ok(None),
+        Commitment::Confirmed => vec![SlotStatus::Confirmed, SlotStatus::Rooted],
+        Commitment::Finalized => vec![SlotStatus::Rooted],
+    };
+    scopes::commitment::last_slot(db, statuses).await
+}
+
+pub async fn get_asset(
+    db: &DatabaseConnection,
+    id: Vec<u8>,
+    show_collection_metadata: bool,
+    commitment: &Commitment,
+) -> Result<Asset, DbErr> {
+    let asset = match scopes::asset::get_by_id(db, id.clone()).await {
+        // Tell apart ids that were never indexed from accounts that have been closed.
+        Err(DbErr::RecordNotFound(e)) => {
+            return match closed_accounts::Entity::find_by_id(id).one(db).await? {
+                Some(closed) => Err(DbErr::RecordNotFound(format!(
+                    "Asset Closed At Slot {}",
+                    closed.slot_closed
+                ))),
+                None => Err(DbErr::RecordNotFound(e)),
+            };
+        }
+        asset => asset?,
+    };
+    let slot = commitment_slot(db, commitment).await?;
+    let asset = match slot {
+        Some(slot) => asset_at_slot(db, asset, slot)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(format!("Asset Not Found At Slot {}", slot)))?,
+        None => asset,
+    };
+    if !show_collection_metadata {
+        return asset_to_rpc(asset);
+    }
+    let mut asset = scopes::asset::get_collections_for_assets(db, vec![asset])
+        .await?
+        .pop()
+        .ok_or_else(|| DbErr::RecordNotFound("Asset Not Found".to_string()))?;
+    if let Some(slot) = slot {
+        let mut collections = Vec::new();
+        for collection in asset.collections {
+            collections.push(collection_at_slot(db, collection, slot).await?);
+        }
+        asset.collections = collections;
+    }
     asset_to_rpc(asset)
 }
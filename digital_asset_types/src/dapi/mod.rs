@@ -2,14 +2,20 @@ mod assets_by_authority;
 mod assets_by_creator;
 mod assets_by_group;
 mod assets_by_owner;
+mod auction_house;
+mod candy_machine;
 mod change_logs;
 pub mod common;
+mod fanout;
 mod get_asset;
 mod search_assets;
 pub use assets_by_authority::*;
 pub use assets_by_creator::*;
 pub use assets_by_group::*;
 pub use assets_by_owner::*;
+pub use auction_house::*;
+pub use candy_machine::*;
 pub use change_logs::*;
+pub use fanout::*;
 pub use get_asset::*;
 pub use search_assets::*;
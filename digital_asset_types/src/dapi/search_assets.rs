@@ -8,6 +8,7 @@ use sea_orm::{DatabaseConnection, DbErr};
 pub async fn search_assets(
     db: &DatabaseConnection,
     search_assets_query: SearchAssetsQuery,
+    show_collection_metadata: bool,
     sorting: AssetSorting,
     limit: u64,
     page: Option<u64>,
@@ -27,5 +28,10 @@ pub async fn search_assets(
         limit,
     )
     .await?;
+    let assets = if show_collection_metadata {
+        scopes::asset::get_collections_for_assets(db, assets).await?
+    } else {
+        assets
+    };
     Ok(build_asset_response(assets, limit, &pagination))
 }
@@ -18,6 +18,17 @@ pub struct AssetProof {
     pub node_index: i64,
     pub leaf: String,
     pub tree_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tree_config: Option<TreeConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TreeConfig {
+    pub tree_creator: String,
+    pub tree_delegate: String,
+    pub total_mint_capacity: u64,
+    pub num_minted: u64,
+    pub is_public: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
@@ -32,6 +43,8 @@ pub enum Interface {
     Nft,
     #[serde(rename = "FungibleAsset")]
     FungibleAsset,
+    #[serde(rename = "FungibleToken")]
+    FungibleToken,
     #[serde(rename = "Custom")]
     Custom,
     #[serde(rename = "Identity")]
@@ -51,6 +64,12 @@ impl From<(&SpecificationVersions, &SpecificationAssetClass)> for Interface {
             (SpecificationVersions::V1, SpecificationAssetClass::ProgrammableNft) => {
                 Interface::ProgrammableNFT
             }
+            (SpecificationVersions::V1, SpecificationAssetClass::FungibleAsset) => {
+                Interface::FungibleAsset
+            }
+            (SpecificationVersions::V1, SpecificationAssetClass::FungibleToken) => {
+                Interface::FungibleToken
+            }
             _ => Interface::Custom,
         }
     }
@@ -70,6 +89,10 @@ impl Into<(SpecificationVersions, SpecificationAssetClass)> for Interface {
                 SpecificationVersions::V1,
                 SpecificationAssetClass::FungibleAsset,
             ),
+            Interface::FungibleToken => (
+                SpecificationVersions::V1,
+                SpecificationAssetClass::FungibleToken,
+            ),
             _ => (SpecificationVersions::V1, SpecificationAssetClass::Unknown),
         }
     }
@@ -196,6 +219,21 @@ pub type GroupValue = String;
 pub struct Group {
     pub group_key: String,
     pub group_value: String,
+    pub verified: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection_metadata: Option<CollectionMetadata>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct CollectionMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
@@ -288,6 +326,9 @@ pub struct Ownership {
     pub delegate: Option<String>,
     pub ownership_model: OwnershipModel,
     pub owner: String,
+    /// The wallet an asset held in escrow or staked by its owner belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub beneficial_owner: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -334,6 +375,20 @@ pub struct Supply {
     pub edition_nonce: u64,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenInfo {
+    pub supply: u64,
+    pub decimals: u8,
+    pub token_program: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mint_authority: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub freeze_authority: Option<String>,
+    /// Sum of the owner's token account balances, only set when querying by owner.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<u64>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Asset {
     pub interface: Interface,
@@ -354,5 +409,8 @@ pub struct Asset {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub uses: Option<Uses>,
     pub supply: Option<Supply>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_info: Option<TokenInfo>,
     pub mutable: bool,
+    pub burnt: bool,
 }
@@ -0,0 +1,40 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// An active Auction House listing.  The price is in the treasury currency of the auction
+/// house, per `token_size` tokens of the asset.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Listing {
+    pub trade_state: String,
+    pub auction_house: String,
+    pub asset_id: String,
+    pub seller: String,
+    pub token_account: String,
+    pub price: u64,
+    pub token_size: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, JsonSchema)]
+pub struct ListingList {
+    pub total: u32,
+    pub limit: u32,
+    pub page: u32,
+    pub items: Vec<Listing>,
+}
+
+/// Something that happened to an asset, such as a `sale`.  `data` depends on the event.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AssetEvent {
+    pub event: String,
+    pub signature: String,
+    pub slot: u64,
+    pub data: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, JsonSchema)]
+pub struct AssetHistory {
+    pub total: u32,
+    pub limit: u32,
+    pub page: u32,
+    pub items: Vec<AssetEvent>,
+}
@@ -0,0 +1,34 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CandyMachine {
+    pub id: String,
+    pub authority: String,
+    pub mint_authority: String,
+    pub collection_mint: String,
+    pub items_available: u64,
+    pub items_redeemed: u64,
+    pub symbol: String,
+    pub seller_fee_basis_points: u16,
+    pub max_supply: u64,
+    pub is_mutable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub candy_guard: Option<CandyGuard>,
+}
+
+/// The guard wrapping a candy machine, it is the machine's mint authority.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CandyGuard {
+    pub id: String,
+    pub base: String,
+    pub authority: String,
+    pub guards: serde_json::Value,
+    pub groups: Vec<CandyGuardGroup>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CandyGuardGroup {
+    pub label: String,
+    pub guards: serde_json::Value,
+}
@@ -0,0 +1,28 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A Hydra fanout wallet and its members, the members split whatever the wallet receives by
+/// their shares of the total.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Fanout {
+    pub id: String,
+    pub authority: String,
+    pub name: String,
+    pub wallet: String,
+    pub membership_model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub membership_mint: Option<String>,
+    pub total_shares: u64,
+    pub total_members: u64,
+    pub total_available_shares: u64,
+    pub total_inflow: u64,
+    pub members: Vec<FanoutMember>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct FanoutMember {
+    pub voucher: String,
+    pub membership_key: String,
+    pub shares: u64,
+    pub total_inflow: u64,
+}
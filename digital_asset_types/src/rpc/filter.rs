@@ -44,3 +44,21 @@ pub enum SearchConditionType {
     #[serde(rename = "any")]
     Any,
 }
+
+/// How final the state returned is, like the commitment of the Solana RPC.  State written by
+/// slots that haven't reached the commitment is left out.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub enum Commitment {
+    #[serde(rename = "processed")]
+    Processed,
+    #[serde(rename = "confirmed")]
+    Confirmed,
+    #[serde(rename = "finalized")]
+    Finalized,
+}
+
+impl Default for Commitment {
+    fn default() -> Commitment {
+        Commitment::Processed
+    }
+}
@@ -1,6 +1,12 @@
 mod asset;
+mod auction_house;
+mod candy_machine;
+mod fanout;
 
 pub mod filter;
 pub mod response;
 
 pub use asset::*;
+pub use auction_house::*;
+pub use candy_machine::*;
+pub use fanout::*;
@@ -82,6 +82,7 @@ pub fn create_asset_data(
             metadata_mutability: Mutability::Mutable,
             metadata: JsonValue::String("processing".to_string()),
             slot_updated: 0,
+            write_version: 0,
         },
     )
 }
@@ -150,6 +151,11 @@ pub fn create_asset(
             data_hash: None,
             alt_id: None,
             creator_hash: None,
+            metadata_address: None,
+            beneficial_owner: None,
+            write_version: 0,
+            owner_slot_updated: 0,
+            owner_write_version: 0,
         },
     )
 }
@@ -214,6 +220,7 @@ pub fn create_asset_grouping(
             asset_id: Set(asset_id.clone()),
             group_key: Set(String::from("collection")),
             group_value: Set(bs58::encode(collection).into_string()),
+            verified: Set(true),
             ..Default::default()
         },
         asset_grouping::Model {
@@ -223,6 +230,7 @@ pub fn create_asset_grouping(
             id: row_num,
             group_key: "collection".to_string(),
             slot_updated: 0,
+            verified: true,
         },
     )
 }
@@ -0,0 +1,175 @@
+#[cfg(test)]
+mod common;
+
+use blockbuster::token_metadata::state::*;
+use common::*;
+use digital_asset_types::dao::{
+    scopes::commitment::{asset_at_slot, row_key},
+    sea_orm_active_enums::{OwnerType, RoyaltyTargetType, SpecificationVersions},
+    unconfirmed_writes, FullAsset,
+};
+use sea_orm::{DatabaseBackend, DbErr, JsonValue, MockDatabase};
+use solana_sdk::{signature::Keypair, signer::Signer};
+
+fn full_asset(owner: Vec<u8>) -> FullAsset {
+    let id = Keypair::new().pubkey().to_bytes().to_vec();
+    let metadata = MockMetadataArgs {
+        name: String::from("Test #1"),
+        symbol: String::from("BUBBLE"),
+        uri: Keypair::new().pubkey().to_string(),
+        primary_sale_happened: true,
+        is_mutable: true,
+        edition_nonce: None,
+        token_standard: Some(TokenStandard::NonFungible),
+        collection: None,
+        uses: None,
+        creators: vec![],
+        seller_fee_basis_points: 100,
+    };
+    let (_, data) = create_asset_data(metadata, id.clone());
+    let (_, asset) = create_asset(
+        id,
+        owner,
+        OwnerType::Single,
+        None,
+        false,
+        1,
+        None,
+        true,
+        false,
+        None,
+        SpecificationVersions::V1,
+        0,
+        None,
+        RoyaltyTargetType::Creators,
+        None,
+        100,
+    );
+    FullAsset {
+        asset,
+        data,
+        authorities: vec![],
+        creators: vec![],
+        groups: vec![],
+        token: None,
+        token_balance: None,
+        collections: vec![],
+    }
+}
+
+fn write(
+    table: &str,
+    key: &[u8],
+    slot: i64,
+    previous: Option<JsonValue>,
+) -> unconfirmed_writes::Model {
+    unconfirmed_writes::Model {
+        id: 1,
+        slot,
+        table_name: table.to_string(),
+        row_key: row_key(key),
+        previous,
+        written: None,
+    }
+}
+
+#[test]
+fn row_keys_match_postgres_json() {
+    assert_eq!(
+        row_key(&[0, 1, 171]),
+        JsonValue::String("\\x0001ab".to_string())
+    );
+}
+
+#[tokio::test]
+async fn assets_without_newer_writes_are_unchanged() -> Result<(), DbErr> {
+    let asset = full_asset(Keypair::new().pubkey().to_bytes().to_vec());
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![Vec::<unconfirmed_writes::Model>::new(); 6])
+        .into_connection();
+
+    assert_eq!(asset_at_slot(&db, asset.clone(), 100).await?, Some(asset));
+    Ok(())
+}
+
+#[tokio::test]
+async fn assets_minted_after_the_slot_are_hidden() -> Result<(), DbErr> {
+    let asset = full_asset(Keypair::new().pubkey().to_bytes().to_vec());
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![vec![write("asset", &asset.asset.id, 101, None)]])
+        .into_connection();
+
+    assert_eq!(asset_at_slot(&db, asset, 100).await?, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn assets_transferred_after_the_slot_keep_their_owner() -> Result<(), DbErr> {
+    let (owner, new_owner) = (
+        Keypair::new().pubkey().to_bytes().to_vec(),
+        Keypair::new().pubkey().to_bytes().to_vec(),
+    );
+    let before = full_asset(owner);
+    let mut after = before.clone();
+    after.asset.owner = Some(new_owner);
+    let image = JsonValue::String("image".to_string());
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![vec![write(
+            "asset",
+            &after.asset.id,
+            101,
+            Some(image),
+        )]])
+        .append_query_results(vec![vec![before.asset.clone()]])
+        .append_query_results(vec![Vec::<unconfirmed_writes::Model>::new(); 5])
+        .into_connection();
+
+    assert_eq!(asset_at_slot(&db, after, 100).await?, Some(before));
+    let log = format!("{:?}", db.into_transaction_log());
+    assert!(log.contains(r#"FROM jsonb_populate_record(NULL::\"asset\", $1) AS \"asset\""#));
+    Ok(())
+}
+
+#[tokio::test]
+async fn creators_are_taken_back_with_the_asset() -> Result<(), DbErr> {
+    let mut before = full_asset(Keypair::new().pubkey().to_bytes().to_vec());
+    let (_, removed) = create_asset_creator(
+        before.asset.id.clone(),
+        Keypair::new().pubkey().to_bytes().to_vec(),
+        100,
+        true,
+        1,
+    );
+    let (_, added) = create_asset_creator(
+        before.asset.id.clone(),
+        Keypair::new().pubkey().to_bytes().to_vec(),
+        100,
+        true,
+        2,
+    );
+    before.creators = vec![removed.clone()];
+    let mut after = before.clone();
+    after.creators = vec![added];
+    let creator_write = |id: i64, previous: Option<JsonValue>| unconfirmed_writes::Model {
+        row_key: JsonValue::from(id),
+        ..write("asset_creators", &[], 101, previous)
+    };
+    let image = JsonValue::String("image".to_string());
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![Vec::<unconfirmed_writes::Model>::new(); 3])
+        .append_query_results(vec![vec![
+            creator_write(2, None),
+            creator_write(1, Some(image)),
+        ]])
+        .append_query_results(vec![vec![removed]])
+        .append_query_results(vec![Vec::<unconfirmed_writes::Model>::new(); 2])
+        .into_connection();
+
+    assert_eq!(asset_at_slot(&db, after, 100).await?, Some(before));
+    let log = format!("{:?}", db.into_transaction_log());
+    assert!(log.contains("OR unconfirmed_writes.written -> 'asset_id' = "));
+    assert!(log.contains(
+        r#"FROM jsonb_populate_record(NULL::\"asset_creators\", $1) AS \"asset_creators\""#
+    ));
+    Ok(())
+}
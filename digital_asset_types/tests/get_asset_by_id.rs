@@ -5,8 +5,13 @@ use blockbuster::token_metadata::state::*;
 use common::*;
 use digital_asset_types::dao::sea_orm_active_enums::*;
 use digital_asset_types::dao::{
-    asset, asset_authority, asset_creators, asset_data,
+    asset, asset_authority, asset_creators, asset_data, collection,
     sea_orm_active_enums::{OwnerType, RoyaltyTargetType},
+    tokens,
+};
+use digital_asset_types::{
+    dapi::get_asset,
+    rpc::{filter::Commitment, Asset, CollectionMetadata},
 };
 use sea_orm::{entity::prelude::*, DatabaseBackend, MockDatabase};
 use solana_sdk::{signature::Keypair, signer::Signer};
@@ -108,3 +113,108 @@ async fn get_asset_by_id() -> Result<(), DbErr> {
 
     Ok(())
 }
+
+/// `getAsset` of an asset in a sized collection, the collection's metadata being read only if
+/// `show_collection_metadata` is set.
+async fn get_asset_in_collection(show_collection_metadata: bool) -> Result<Asset, DbErr> {
+    let id = Keypair::new().pubkey();
+    let collection_id = Keypair::new().pubkey();
+
+    let metadata = MockMetadataArgs {
+        name: String::from("Test #1"),
+        symbol: String::from("BUBBLE"),
+        uri: Keypair::new().pubkey().to_string(),
+        primary_sale_happened: true,
+        is_mutable: true,
+        edition_nonce: None,
+        token_standard: Some(TokenStandard::NonFungible),
+        collection: Some(Collection {
+            verified: true,
+            key: collection_id,
+        }),
+        uses: None,
+        creators: vec![],
+        seller_fee_basis_points: 100,
+    };
+    let asset_data = create_asset_data(metadata.clone(), id.to_bytes().to_vec());
+    let asset = create_asset(
+        id.to_bytes().to_vec(),
+        Keypair::new().pubkey().to_bytes().to_vec(),
+        OwnerType::Single,
+        None,
+        false,
+        1,
+        None,
+        true,
+        false,
+        None,
+        SpecificationVersions::V1,
+        0 as i64,
+        None,
+        RoyaltyTargetType::Creators,
+        None,
+        metadata.seller_fee_basis_points as i32,
+    );
+    let asset_grouping = create_asset_grouping(id.to_bytes().to_vec(), collection_id, 1);
+    let collection_data = asset_data::Model {
+        id: collection_id.to_bytes().to_vec(),
+        chain_data_mutability: ChainMutability::Mutable,
+        chain_data: serde_json::json!({ "name": "Collection", "symbol": "COL" }),
+        metadata_url: Keypair::new().pubkey().to_string(),
+        metadata_mutability: Mutability::Mutable,
+        metadata: serde_json::json!({ "image": "https://example.com/collection.png" }),
+        slot_updated: 0,
+        write_version: 0,
+    };
+    let collection_details = collection::Model {
+        id: collection_id.to_bytes().to_vec(),
+        size: Some(42),
+        slot_updated: 0,
+    };
+
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![vec![(asset.1, asset_data.1)]])
+        .append_query_results(vec![Vec::<asset_authority::Model>::new()])
+        .append_query_results(vec![Vec::<asset_creators::Model>::new()])
+        .append_query_results(vec![vec![asset_grouping.1]])
+        .append_query_results(vec![Vec::<tokens::Model>::new()])
+        .append_query_results(vec![vec![collection_data]])
+        .append_query_results(vec![vec![collection_details]])
+        .into_connection();
+    get_asset(
+        &db,
+        id.to_bytes().to_vec(),
+        show_collection_metadata,
+        &Commitment::Processed,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn get_asset_embeds_collection_metadata_when_asked_to() -> Result<(), DbErr> {
+    let asset = get_asset_in_collection(true).await?;
+    let grouping = asset.grouping.unwrap();
+    assert_eq!(grouping.len(), 1);
+    assert_eq!(
+        grouping[0].collection_metadata,
+        Some(CollectionMetadata {
+            name: Some(String::from("Collection")),
+            symbol: Some(String::from("COL")),
+            image: Some(String::from("https://example.com/collection.png")),
+            size: Some(42),
+        })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn get_asset_omits_collection_metadata_by_default() -> Result<(), DbErr> {
+    let asset = get_asset_in_collection(false).await?;
+    let grouping = asset.grouping.as_ref().unwrap();
+    assert_eq!(grouping.len(), 1);
+    assert_eq!(grouping[0].collection_metadata, None);
+    assert!(!serde_json::to_string(&asset)
+        .unwrap()
+        .contains("collection_metadata"));
+    Ok(())
+}
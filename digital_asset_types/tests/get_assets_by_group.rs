@@ -13,6 +13,7 @@ use digital_asset_types::dao::{
     asset, asset_authority, asset_creators, asset_data, asset_grouping,
     sea_orm_active_enums::{OwnerType, RoyaltyTargetType},
 };
+use digital_asset_types::{dapi::get_assets_by_group, rpc::filter::AssetSorting};
 
 #[tokio::test]
 async fn get_assets_by_group() -> Result<(), DbErr> {
@@ -325,3 +326,41 @@ async fn get_assets_by_group() -> Result<(), DbErr> {
 
     Ok(())
 }
+
+/// The SQL of `getAssetsByGroup` for a collection without assets.
+async fn assets_by_collection_sql(show_unverified: bool) -> Result<String, DbErr> {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results((0..5).map(|_| Vec::<asset::Model>::new()).collect())
+        .into_connection();
+    let list = get_assets_by_group(
+        &db,
+        "collection".to_string(),
+        Keypair::new().pubkey().to_string(),
+        show_unverified,
+        false,
+        AssetSorting::default(),
+        10,
+        Some(1),
+        None,
+        None,
+    )
+    .await?;
+    assert!(list.items.is_empty());
+    Ok(format!("{:?}", db.into_transaction_log()))
+}
+
+#[tokio::test]
+async fn get_assets_by_group_hides_unverified_members() -> Result<(), DbErr> {
+    let sql = assets_by_collection_sql(false).await?;
+    assert!(sql.contains(r#"\"asset_grouping\".\"group_value\" = $2"#));
+    assert!(sql.contains(r#"AND \"asset_grouping\".\"verified\" = $3"#));
+    Ok(())
+}
+
+#[tokio::test]
+async fn get_assets_by_group_shows_unverified_members_when_asked_to() -> Result<(), DbErr> {
+    let sql = assets_by_collection_sql(true).await?;
+    assert!(sql.contains(r#"\"asset_grouping\".\"group_value\" = $2"#));
+    assert!(!sql.contains(r#"\"asset_grouping\".\"verified\""#));
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use digital_asset_types::{
+    dao::{candy_guard, candy_guard_group, candy_machine},
+    dapi::get_candy_machine,
+};
+use sea_orm::{entity::prelude::*, DatabaseBackend, MockDatabase};
+use serde_json::json;
+use solana_sdk::{signature::Keypair, signer::Signer};
+
+fn candy_machine_model(id: Vec<u8>, mint_authority: Vec<u8>) -> candy_machine::Model {
+    candy_machine::Model {
+        id,
+        features: 0,
+        authority: Keypair::new().pubkey().to_bytes().to_vec(),
+        mint_authority,
+        collection_mint: Keypair::new().pubkey().to_bytes().to_vec(),
+        authority_pda: Keypair::new().pubkey().to_bytes().to_vec(),
+        items_available: 100,
+        items_redeemed: 42,
+        symbol: "CNDY".to_string(),
+        seller_fee_basis_points: 500,
+        max_supply: 0,
+        is_mutable: true,
+        slot_updated: 1,
+    }
+}
+
+#[tokio::test]
+async fn get_candy_machine_with_guard() -> Result<(), DbErr> {
+    let id = Keypair::new().pubkey();
+    let guard_id = Keypair::new().pubkey();
+    let guard = candy_guard::Model {
+        id: guard_id.to_bytes().to_vec(),
+        base: Keypair::new().pubkey().to_bytes().to_vec(),
+        bump: 255,
+        authority: Keypair::new().pubkey().to_bytes().to_vec(),
+        slot_updated: 1,
+    };
+    let group =
+        |position: i16, label: Option<&str>, guards: serde_json::Value| candy_guard_group::Model {
+            candy_guard_id: guard_id.to_bytes().to_vec(),
+            position,
+            label: label.map(str::to_string),
+            guards,
+            slot_updated: 1,
+        };
+    let default_guards = json!({ "botTax": { "lamports": 10000000, "lastInstruction": true } });
+    let public_guards = json!({ "startDate": { "date": 1673913600 } });
+
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![vec![candy_machine_model(
+            id.to_bytes().to_vec(),
+            guard_id.to_bytes().to_vec(),
+        )]])
+        .append_query_results(vec![vec![guard]])
+        .append_query_results(vec![vec![
+            group(0, None, default_guards.clone()),
+            group(1, Some("public"), public_guards.clone()),
+        ]])
+        .into_connection();
+
+    let candy_machine = get_candy_machine(&db, id.to_bytes().to_vec()).await?;
+    assert_eq!(candy_machine.id, id.to_string());
+    assert_eq!(candy_machine.items_available, 100);
+    assert_eq!(candy_machine.items_redeemed, 42);
+    let candy_guard = candy_machine.candy_guard.unwrap();
+    assert_eq!(candy_guard.id, guard_id.to_string());
+    assert_eq!(candy_guard.guards, default_guards);
+    assert_eq!(candy_guard.groups.len(), 1);
+    assert_eq!(candy_guard.groups[0].label, "public");
+    assert_eq!(candy_guard.groups[0].guards, public_guards);
+    Ok(())
+}
+
+#[tokio::test]
+async fn get_candy_machine_without_guard() -> Result<(), DbErr> {
+    let id = Keypair::new().pubkey();
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![vec![candy_machine_model(
+            id.to_bytes().to_vec(),
+            Keypair::new().pubkey().to_bytes().to_vec(),
+        )]])
+        .append_query_results(vec![Vec::<candy_guard::Model>::new()])
+        .into_connection();
+
+    let candy_machine = get_candy_machine(&db, id.to_bytes().to_vec()).await?;
+    assert_eq!(candy_machine.candy_guard, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn get_missing_candy_machine() {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![Vec::<candy_machine::Model>::new()])
+        .into_connection();
+
+    let result = get_candy_machine(&db, Keypair::new().pubkey().to_bytes().to_vec()).await;
+    assert!(matches!(result, Err(DbErr::RecordNotFound(_))));
+}
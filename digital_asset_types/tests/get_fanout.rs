@@ -0,0 +1,58 @@
+use digital_asset_types::{
+    dao::{fanout, fanout_membership},
+    dapi::get_fanout,
+};
+use sea_orm::{entity::prelude::*, DatabaseBackend, MockDatabase};
+use solana_sdk::{signature::Keypair, signer::Signer};
+
+#[tokio::test]
+async fn get_fanout_by_wallet() -> Result<(), DbErr> {
+    let id = Keypair::new().pubkey();
+    let wallet = Keypair::new().pubkey();
+    let member = |shares: i64| fanout_membership::Model {
+        id: Keypair::new().pubkey().to_bytes().to_vec(),
+        fanout: id.to_bytes().to_vec(),
+        membership_key: Keypair::new().pubkey().to_bytes().to_vec(),
+        shares,
+        total_inflow: 0,
+        slot_updated: 1,
+    };
+    let model = fanout::Model {
+        id: id.to_bytes().to_vec(),
+        authority: Keypair::new().pubkey().to_bytes().to_vec(),
+        name: "royalties".to_string(),
+        account_key: wallet.to_bytes().to_vec(),
+        total_shares: 100,
+        total_members: 2,
+        total_available_shares: 0,
+        total_inflow: 0,
+        membership_model: "wallet".to_string(),
+        membership_mint: None,
+        slot_updated: 1,
+    };
+
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![vec![model]])
+        .append_query_results(vec![vec![member(60), member(40)]])
+        .into_connection();
+
+    let fanout = get_fanout(&db, wallet.to_bytes().to_vec()).await?;
+    assert_eq!(fanout.id, id.to_string());
+    assert_eq!(fanout.wallet, wallet.to_string());
+    assert_eq!(fanout.membership_mint, None);
+    assert_eq!(
+        fanout.members.iter().map(|m| m.shares).collect::<Vec<_>>(),
+        vec![60, 40]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn get_missing_fanout() {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![Vec::<fanout::Model>::new()])
+        .into_connection();
+
+    let result = get_fanout(&db, Keypair::new().pubkey().to_bytes().to_vec()).await;
+    assert!(matches!(result, Err(DbErr::RecordNotFound(_))));
+}
@@ -0,0 +1,84 @@
+use digital_asset_types::{
+    dao::{asset_history, listings},
+    dapi::{get_asset_history, get_listings_by_collection, get_listings_by_seller},
+};
+use sea_orm::{entity::prelude::*, DatabaseBackend, MockDatabase};
+use serde_json::json;
+use solana_sdk::{signature::Keypair, signer::Signer};
+
+fn listing(seller: &[u8], price: i64) -> listings::Model {
+    listings::Model {
+        trade_state: Keypair::new().pubkey().to_bytes().to_vec(),
+        auction_house: Keypair::new().pubkey().to_bytes().to_vec(),
+        asset_id: Some(Keypair::new().pubkey().to_bytes().to_vec()),
+        metadata: Keypair::new().pubkey().to_bytes().to_vec(),
+        seller: seller.to_vec(),
+        token_account: Keypair::new().pubkey().to_bytes().to_vec(),
+        price,
+        token_size: 1,
+        active: true,
+        slot_updated: 1,
+    }
+}
+
+#[tokio::test]
+async fn get_listings_of_a_seller() -> Result<(), DbErr> {
+    let seller = Keypair::new().pubkey();
+    let cheap = listing(seller.as_ref(), 1_000);
+    let expensive = listing(seller.as_ref(), 5_000);
+
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![vec![cheap.clone(), expensive]])
+        .into_connection();
+
+    let list = get_listings_by_seller(&db, seller.to_bytes().to_vec(), 10, 1).await?;
+    assert_eq!(list.total, 2);
+    assert_eq!(list.page, 1);
+    assert_eq!(
+        list.items.iter().map(|l| l.price).collect::<Vec<_>>(),
+        vec![1_000, 5_000]
+    );
+    assert_eq!(list.items[0].seller, seller.to_string());
+    assert_eq!(
+        list.items[0].asset_id,
+        bs58::encode(cheap.asset_id.unwrap()).into_string()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn get_listings_of_an_empty_collection() -> Result<(), DbErr> {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![Vec::<listings::Model>::new()])
+        .into_connection();
+
+    let list = get_listings_by_collection(&db, Keypair::new().pubkey().to_string(), 10, 1).await?;
+    assert_eq!(list.total, 0);
+    assert!(list.items.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn get_sales_in_asset_history() -> Result<(), DbErr> {
+    let asset_id = Keypair::new().pubkey().to_bytes().to_vec();
+    let sale = asset_history::Model {
+        id: 1,
+        asset_id: asset_id.clone(),
+        event: "sale".to_string(),
+        txn_id: "5ig".to_string(),
+        slot: 42,
+        data: json!({ "price": 1_000 }),
+    };
+
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![vec![sale]])
+        .into_connection();
+
+    let history = get_asset_history(&db, asset_id, 10, 1).await?;
+    assert_eq!(history.total, 1);
+    assert_eq!(history.items[0].event, "sale");
+    assert_eq!(history.items[0].signature, "5ig");
+    assert_eq!(history.items[0].slot, 42);
+    assert_eq!(history.items[0].data["price"], 1_000);
+    Ok(())
+}
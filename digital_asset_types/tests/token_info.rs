@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod common;
+
+use blockbuster::token_metadata::state::*;
+use common::*;
+use digital_asset_types::dao::sea_orm_active_enums::*;
+use digital_asset_types::dao::{tokens, FullAsset};
+use digital_asset_types::dapi::common::asset_to_rpc;
+use digital_asset_types::rpc::{Interface, TokenInfo};
+use solana_sdk::{signature::Keypair, signer::Signer};
+
+#[test]
+fn fungible_asset_token_info() {
+    let id = Keypair::new().pubkey();
+    let mint_authority = Keypair::new().pubkey();
+    let metadata = MockMetadataArgs {
+        name: String::from("Test Token"),
+        symbol: String::from("TEST"),
+        uri: Keypair::new().pubkey().to_string(),
+        primary_sale_happened: true,
+        is_mutable: true,
+        edition_nonce: None,
+        collection: None,
+        uses: None,
+        token_standard: Some(TokenStandard::Fungible),
+        creators: vec![],
+        seller_fee_basis_points: 0,
+    };
+
+    let asset_data = create_asset_data(metadata, id.to_bytes().to_vec());
+    let (_, mut asset) = create_asset(
+        id.to_bytes().to_vec(),
+        vec![],
+        OwnerType::Token,
+        None,
+        false,
+        1_000_000,
+        Some(id.to_bytes().to_vec()),
+        false,
+        false,
+        None,
+        SpecificationVersions::V1,
+        0,
+        None,
+        RoyaltyTargetType::Creators,
+        None,
+        0,
+    );
+    asset.owner = None;
+    asset.specification_asset_class = SpecificationAssetClass::FungibleToken;
+
+    let token = tokens::Model {
+        mint: id.to_bytes().to_vec(),
+        supply: 1_000_000,
+        decimals: 6,
+        token_program: spl_token_program(),
+        mint_authority: Some(mint_authority.to_bytes().to_vec()),
+        freeze_authority: None,
+        close_authority: None,
+        extension_data: None,
+        slot_updated: 0,
+        write_version: 0,
+    };
+
+    let rpc_asset = asset_to_rpc(FullAsset {
+        asset,
+        data: asset_data.1,
+        authorities: vec![],
+        creators: vec![],
+        groups: vec![],
+        token: Some(token),
+        token_balance: Some(2_500),
+        collections: vec![],
+    })
+    .unwrap();
+
+    assert_eq!(rpc_asset.interface, Interface::FungibleToken);
+    assert_eq!(
+        rpc_asset.token_info,
+        Some(TokenInfo {
+            supply: 1_000_000,
+            decimals: 6,
+            token_program: bs58::encode(spl_token_program()).into_string(),
+            mint_authority: Some(mint_authority.to_string()),
+            freeze_authority: None,
+            balance: Some(2_500),
+        })
+    );
+}
+
+fn spl_token_program() -> Vec<u8> {
+    bs58::decode("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA")
+        .into_vec()
+        .unwrap()
+}
@@ -11,6 +11,19 @@ mod m20221115_165700_add_backfiller_locked;
 mod m20221116_110500_add_backfiller_failed_and_locked_indeces;
 mod m20230105_160722_drop_collection_info;
 mod m20230106_051135_unique_groupings;
+mod m20230110_142205_add_closed_accounts;
+mod m20230111_093012_add_asset_grouping_verified;
+mod m20230112_181544_add_collection;
+mod m20230113_104517_add_tree_config;
+mod m20230116_152030_add_candy_machine;
+mod m20230117_091522_add_fanout;
+mod m20230118_103412_add_auction_house;
+mod m20230119_142630_add_beneficial_owner;
+mod m20230120_093247_add_dead_letters;
+mod m20230123_101755_add_write_version;
+mod m20230125_143310_add_slots;
+mod m20230126_101422_add_signature_crawls;
+mod m20230127_112035_add_asset_owner_versions;
 
 pub struct Migrator;
 
@@ -29,6 +42,19 @@ impl MigratorTrait for Migrator {
             Box::new(m20221116_110500_add_backfiller_failed_and_locked_indeces::Migration),
             Box::new(m20230105_160722_drop_collection_info::Migration),
             Box::new(m20230106_051135_unique_groupings::Migration),
+            Box::new(m20230110_142205_add_closed_accounts::Migration),
+            Box::new(m20230111_093012_add_asset_grouping_verified::Migration),
+            Box::new(m20230112_181544_add_collection::Migration),
+            Box::new(m20230113_104517_add_tree_config::Migration),
+            Box::new(m20230116_152030_add_candy_machine::Migration),
+            Box::new(m20230117_091522_add_fanout::Migration),
+            Box::new(m20230118_103412_add_auction_house::Migration),
+            Box::new(m20230119_142630_add_beneficial_owner::Migration),
+            Box::new(m20230120_093247_add_dead_letters::Migration),
+            Box::new(m20230123_101755_add_write_version::Migration),
+            Box::new(m20230125_143310_add_slots::Migration),
+            Box::new(m20230126_101422_add_signature_crawls::Migration),
+            Box::new(m20230127_112035_add_asset_owner_versions::Migration),
         ]
     }
 }
@@ -0,0 +1,79 @@
+use digital_asset_types::dao::asset;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ClosedAccounts::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ClosedAccounts::Pubkey)
+                            .binary()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ClosedAccounts::Owner).binary().not_null())
+                    .col(
+                        ColumnDef::new(ClosedAccounts::SlotClosed)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(asset::Entity)
+                    .add_column(ColumnDef::new(Alias::new("metadata_address")).binary())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("asset_metadata_address_idx")
+                    .col(Alias::new("metadata_address"))
+                    .table(asset::Entity)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("asset_metadata_address_idx")
+                    .table(asset::Entity)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(asset::Entity)
+                    .drop_column(Alias::new("metadata_address"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(ClosedAccounts::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ClosedAccounts {
+    Table,
+    Pubkey,
+    Owner,
+    SlotClosed,
+}
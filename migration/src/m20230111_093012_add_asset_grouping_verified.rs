@@ -0,0 +1,42 @@
+use digital_asset_types::dao::asset_grouping;
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(asset_grouping::Entity)
+                    .add_column(
+                        ColumnDef::new(Alias::new("verified"))
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        // Only verified collections were stored so far.
+        let stmt = Statement::from_string(
+            manager.get_database_backend(),
+            "UPDATE asset_grouping SET verified = true".to_string(),
+        );
+        manager.get_connection().execute(stmt).await.map(|_| ())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(asset_grouping::Entity)
+                    .drop_column(Alias::new("verified"))
+                    .to_owned(),
+            )
+            .await
+    }
+}
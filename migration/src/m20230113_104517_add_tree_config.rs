@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TreeConfig::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TreeConfig::Id)
+                            .binary()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(TreeConfig::TreeCreator).binary().not_null())
+                    .col(ColumnDef::new(TreeConfig::TreeDelegate).binary().not_null())
+                    .col(
+                        ColumnDef::new(TreeConfig::TotalMintCapacity)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TreeConfig::NumMinted)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(TreeConfig::IsPublic).boolean().not_null())
+                    .col(
+                        ColumnDef::new(TreeConfig::SlotUpdated)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TreeConfig::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum TreeConfig {
+    Table,
+    Id,
+    TreeCreator,
+    TreeDelegate,
+    TotalMintCapacity,
+    NumMinted,
+    IsPublic,
+    SlotUpdated,
+}
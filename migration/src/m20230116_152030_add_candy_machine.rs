@@ -0,0 +1,196 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CandyMachine::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CandyMachine::Id)
+                            .binary()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CandyMachine::Features)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CandyMachine::Authority).binary().not_null())
+                    .col(
+                        ColumnDef::new(CandyMachine::MintAuthority)
+                            .binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CandyMachine::CollectionMint)
+                            .binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CandyMachine::AuthorityPda)
+                            .binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CandyMachine::ItemsAvailable)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CandyMachine::ItemsRedeemed)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CandyMachine::Symbol).string().not_null())
+                    .col(
+                        ColumnDef::new(CandyMachine::SellerFeeBasisPoints)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CandyMachine::MaxSupply)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CandyMachine::IsMutable).boolean().not_null())
+                    .col(
+                        ColumnDef::new(CandyMachine::SlotUpdated)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("candy_machine_authority_pda")
+                    .col(CandyMachine::AuthorityPda)
+                    .table(CandyMachine::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("candy_machine_mint_authority")
+                    .col(CandyMachine::MintAuthority)
+                    .table(CandyMachine::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(CandyGuard::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CandyGuard::Id)
+                            .binary()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CandyGuard::Base).binary().not_null())
+                    .col(ColumnDef::new(CandyGuard::Bump).small_integer().not_null())
+                    .col(ColumnDef::new(CandyGuard::Authority).binary().not_null())
+                    .col(
+                        ColumnDef::new(CandyGuard::SlotUpdated)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(CandyGuardGroup::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CandyGuardGroup::CandyGuardId)
+                            .binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CandyGuardGroup::Position)
+                            .small_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CandyGuardGroup::Label).string())
+                    .col(
+                        ColumnDef::new(CandyGuardGroup::Guards)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CandyGuardGroup::SlotUpdated)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(CandyGuardGroup::CandyGuardId)
+                            .col(CandyGuardGroup::Position),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CandyGuardGroup::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(CandyGuard::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(CandyMachine::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum CandyMachine {
+    Table,
+    Id,
+    Features,
+    Authority,
+    MintAuthority,
+    CollectionMint,
+    AuthorityPda,
+    ItemsAvailable,
+    ItemsRedeemed,
+    Symbol,
+    SellerFeeBasisPoints,
+    MaxSupply,
+    IsMutable,
+    SlotUpdated,
+}
+
+#[derive(Iden)]
+enum CandyGuard {
+    Table,
+    Id,
+    Base,
+    Bump,
+    Authority,
+    SlotUpdated,
+}
+
+#[derive(Iden)]
+enum CandyGuardGroup {
+    Table,
+    CandyGuardId,
+    Position,
+    Label,
+    Guards,
+    SlotUpdated,
+}
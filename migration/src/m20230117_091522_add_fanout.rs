@@ -0,0 +1,127 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Fanout::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Fanout::Id).binary().not_null().primary_key())
+                    .col(ColumnDef::new(Fanout::Authority).binary().not_null())
+                    .col(ColumnDef::new(Fanout::Name).string().not_null())
+                    .col(ColumnDef::new(Fanout::AccountKey).binary().not_null())
+                    .col(ColumnDef::new(Fanout::TotalShares).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(Fanout::TotalMembers)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Fanout::TotalAvailableShares)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Fanout::TotalInflow).big_integer().not_null())
+                    .col(ColumnDef::new(Fanout::MembershipModel).string().not_null())
+                    .col(ColumnDef::new(Fanout::MembershipMint).binary())
+                    .col(ColumnDef::new(Fanout::SlotUpdated).big_integer().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("fanout_account_key")
+                    .col(Fanout::AccountKey)
+                    .table(Fanout::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(FanoutMembership::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FanoutMembership::Id)
+                            .binary()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(FanoutMembership::Fanout).binary().not_null())
+                    .col(
+                        ColumnDef::new(FanoutMembership::MembershipKey)
+                            .binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FanoutMembership::Shares)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FanoutMembership::TotalInflow)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(FanoutMembership::SlotUpdated)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("fanout_membership_fanout")
+                    .col(FanoutMembership::Fanout)
+                    .table(FanoutMembership::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FanoutMembership::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Fanout::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Fanout {
+    Table,
+    Id,
+    Authority,
+    Name,
+    AccountKey,
+    TotalShares,
+    TotalMembers,
+    TotalAvailableShares,
+    TotalInflow,
+    MembershipModel,
+    MembershipMint,
+    SlotUpdated,
+}
+
+#[derive(Iden)]
+enum FanoutMembership {
+    Table,
+    Id,
+    Fanout,
+    MembershipKey,
+    Shares,
+    TotalInflow,
+    SlotUpdated,
+}
@@ -0,0 +1,178 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Listings::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Listings::TradeState)
+                            .binary()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Listings::AuctionHouse).binary().not_null())
+                    .col(ColumnDef::new(Listings::AssetId).binary())
+                    .col(ColumnDef::new(Listings::Metadata).binary().not_null())
+                    .col(ColumnDef::new(Listings::Seller).binary().not_null())
+                    .col(ColumnDef::new(Listings::TokenAccount).binary().not_null())
+                    .col(ColumnDef::new(Listings::Price).big_integer().not_null())
+                    .col(ColumnDef::new(Listings::TokenSize).big_integer().not_null())
+                    .col(ColumnDef::new(Listings::Active).boolean().not_null())
+                    .col(
+                        ColumnDef::new(Listings::SlotUpdated)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        for (name, col) in [
+            ("listings_asset_id", Listings::AssetId),
+            ("listings_metadata", Listings::Metadata),
+            ("listings_seller", Listings::Seller),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .col(col)
+                        .table(Listings::Table)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        manager
+            .create_table(
+                Table::create()
+                    .table(Bids::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Bids::TradeState)
+                            .binary()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Bids::AuctionHouse).binary().not_null())
+                    .col(ColumnDef::new(Bids::AssetId).binary())
+                    .col(ColumnDef::new(Bids::Metadata).binary().not_null())
+                    .col(ColumnDef::new(Bids::Buyer).binary().not_null())
+                    .col(ColumnDef::new(Bids::TokenAccount).binary().not_null())
+                    .col(ColumnDef::new(Bids::TreasuryMint).binary().not_null())
+                    .col(ColumnDef::new(Bids::Price).big_integer().not_null())
+                    .col(ColumnDef::new(Bids::TokenSize).big_integer().not_null())
+                    .col(ColumnDef::new(Bids::Active).boolean().not_null())
+                    .col(ColumnDef::new(Bids::SlotUpdated).big_integer().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        for (name, col) in [
+            ("bids_asset_id", Bids::AssetId),
+            ("bids_metadata", Bids::Metadata),
+            ("bids_buyer", Bids::Buyer),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .col(col)
+                        .table(Bids::Table)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        manager
+            .create_table(
+                Table::create()
+                    .table(AssetHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AssetHistory::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AssetHistory::AssetId).binary().not_null())
+                    .col(ColumnDef::new(AssetHistory::Event).string().not_null())
+                    .col(ColumnDef::new(AssetHistory::TxnId).string().not_null())
+                    .col(ColumnDef::new(AssetHistory::Slot).big_integer().not_null())
+                    .col(ColumnDef::new(AssetHistory::Data).json_binary().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        // Replayed transactions record their events once.
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("asset_history_asset_id_event_txn_id")
+                    .col(AssetHistory::AssetId)
+                    .col(AssetHistory::Event)
+                    .col(AssetHistory::TxnId)
+                    .table(AssetHistory::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AssetHistory::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Bids::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Listings::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Listings {
+    Table,
+    TradeState,
+    AuctionHouse,
+    AssetId,
+    Metadata,
+    Seller,
+    TokenAccount,
+    Price,
+    TokenSize,
+    Active,
+    SlotUpdated,
+}
+
+#[derive(Iden)]
+enum Bids {
+    Table,
+    TradeState,
+    AuctionHouse,
+    AssetId,
+    Metadata,
+    Buyer,
+    TokenAccount,
+    TreasuryMint,
+    Price,
+    TokenSize,
+    Active,
+    SlotUpdated,
+}
+
+#[derive(Iden)]
+enum AssetHistory {
+    Table,
+    Id,
+    AssetId,
+    Event,
+    TxnId,
+    Slot,
+    Data,
+}
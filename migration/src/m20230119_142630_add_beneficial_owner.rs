@@ -0,0 +1,94 @@
+use digital_asset_types::dao::asset;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(asset::Entity)
+                    .add_column(ColumnDef::new(Alias::new("beneficial_owner")).binary())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("asset_beneficial_owner_idx")
+                    .col(Alias::new("beneficial_owner"))
+                    .table(asset::Entity)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(TokenOwnedEscrow::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TokenOwnedEscrow::Id)
+                            .binary()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(TokenOwnedEscrow::BaseToken)
+                            .binary()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(TokenOwnedEscrow::Creator).binary())
+                    .col(
+                        ColumnDef::new(TokenOwnedEscrow::SlotUpdated)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("token_owned_escrow_base_token")
+                    .col(TokenOwnedEscrow::BaseToken)
+                    .table(TokenOwnedEscrow::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TokenOwnedEscrow::Table).to_owned())
+            .await?;
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("asset_beneficial_owner_idx")
+                    .table(asset::Entity)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(asset::Entity)
+                    .drop_column(Alias::new("beneficial_owner"))
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum TokenOwnedEscrow {
+    Table,
+    Id,
+    BaseToken,
+    // Set when the escrow belongs to a creator rather than to the holder of the base token.
+    Creator,
+    SlotUpdated,
+}
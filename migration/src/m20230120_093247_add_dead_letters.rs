@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DeadLetters::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DeadLetters::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(DeadLetters::Stream).string().not_null())
+                    .col(ColumnDef::new(DeadLetters::MessageId).string().not_null())
+                    .col(ColumnDef::new(DeadLetters::Payload).binary().not_null())
+                    .col(ColumnDef::new(DeadLetters::ProgramId).binary())
+                    .col(ColumnDef::new(DeadLetters::Signature).string())
+                    .col(ColumnDef::new(DeadLetters::Account).binary())
+                    .col(ColumnDef::new(DeadLetters::Error).string().not_null())
+                    .col(ColumnDef::new(DeadLetters::Deliveries).integer().not_null())
+                    .col(
+                        ColumnDef::new(DeadLetters::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("dead_letters_stream")
+                    .col(DeadLetters::Stream)
+                    .table(DeadLetters::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DeadLetters::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum DeadLetters {
+    Table,
+    Id,
+    Stream,
+    MessageId,
+    Payload,
+    ProgramId,
+    Signature,
+    Account,
+    Error,
+    Deliveries,
+    CreatedAt,
+}
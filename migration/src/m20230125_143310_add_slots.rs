@@ -0,0 +1,266 @@
+use enum_iterator::{all, Sequence};
+use sea_orm::Statement;
+use sea_orm_migration::prelude::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Tables whose writes are recorded while their slot isn't rooted, with their primary key.
+const TRACKED_TABLES: [(&str, &str); 8] = [
+    ("asset", "id"),
+    ("asset_data", "id"),
+    ("asset_authority", "id"),
+    ("asset_creators", "id"),
+    ("asset_grouping", "id"),
+    ("collection", "id"),
+    ("tokens", "mint"),
+    ("token_accounts", "pubkey"),
+];
+
+// Records the row written, what it was before and what the write left, when the write belongs to a slot newer than
+// the last rooted one.  The slot is the one the ingester sets for the transaction in
+// `das.write_slot`, or else the `slot_updated` of the row.  The last rooted slot is read from
+// the one row of `last_rooted_slot`, which the ingester moves forward as slots are rooted.
+// Nothing is recorded before the first slot is rooted, that is while slots aren't tracked, nor
+// while writes are rolled back.
+const RECORD_UNCONFIRMED_WRITE: &str = r#"
+CREATE OR REPLACE FUNCTION record_unconfirmed_write()
+    RETURNS trigger
+    LANGUAGE 'plpgsql'
+AS
+$BODY$
+declare
+    row_image   jsonb;
+    write_slot  bigint;
+    rooted_slot bigint;
+begin
+    if current_setting('das.rolling_back', true) = 'on' then
+        return null;
+    end if;
+    if (tg_op = 'DELETE') then
+        row_image := to_jsonb(OLD);
+    else
+        row_image := to_jsonb(NEW);
+    end if;
+    write_slot := coalesce(
+        nullif(current_setting('das.write_slot', true), '')::bigint,
+        (row_image ->> 'slot_updated')::bigint
+    );
+    select slot into rooted_slot from last_rooted_slot;
+    if (write_slot is null or rooted_slot is null or write_slot <= rooted_slot) then
+        return null;
+    end if;
+    insert into unconfirmed_writes (slot, table_name, row_key, previous, written)
+    values (
+        write_slot,
+        tg_table_name,
+        row_image -> tg_argv[0],
+        case when tg_op = 'INSERT' then null else to_jsonb(OLD) end,
+        case when tg_op = 'DELETE' then null else to_jsonb(NEW) end
+    );
+    return null;
+end
+$BODY$;
+"#;
+
+async fn execute(manager: &SchemaManager<'_>, sql: String) -> Result<(), DbErr> {
+    let stmt = Statement::from_string(manager.get_database_backend(), sql);
+    manager.get_connection().execute(stmt).await.map(|_| ())
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(Slots::SlotStatus)
+                    .values(all::<SlotStatus>().collect::<Vec<_>>())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Slots::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Slots::Slot)
+                            .big_integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Slots::Parent).big_integer())
+                    .col(
+                        ColumnDef::new(Slots::Status)
+                            .enumeration(Slots::SlotStatus, all::<SlotStatus>().collect::<Vec<_>>())
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Slots::UpdatedAt).date_time().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("slots_status_slot")
+                    .col(Slots::Status)
+                    .col(Slots::Slot)
+                    .table(Slots::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(UnconfirmedWrites::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UnconfirmedWrites::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(UnconfirmedWrites::Slot)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UnconfirmedWrites::TableName)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UnconfirmedWrites::RowKey)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(UnconfirmedWrites::Previous).json_binary())
+                    .col(ColumnDef::new(UnconfirmedWrites::Written).json_binary())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("unconfirmed_writes_slot")
+                    .col(UnconfirmedWrites::Slot)
+                    .table(UnconfirmedWrites::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("unconfirmed_writes_row")
+                    .col(UnconfirmedWrites::TableName)
+                    .col(UnconfirmedWrites::RowKey)
+                    .table(UnconfirmedWrites::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(LastRootedSlot::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LastRootedSlot::Id)
+                            .boolean()
+                            .not_null()
+                            .default(true)
+                            .primary_key()
+                            .extra("CHECK (id)".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(LastRootedSlot::Slot)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        execute(manager, RECORD_UNCONFIRMED_WRITE.to_string()).await?;
+        for (table, key) in TRACKED_TABLES {
+            execute(
+                manager,
+                format!(
+                    "CREATE TRIGGER {0}_unconfirmed_write AFTER INSERT OR UPDATE OR DELETE ON {0} \
+                     FOR EACH ROW EXECUTE PROCEDURE record_unconfirmed_write('{1}')",
+                    table, key
+                ),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (table, _) in TRACKED_TABLES {
+            execute(
+                manager,
+                format!("DROP TRIGGER IF EXISTS {0}_unconfirmed_write ON {0}", table),
+            )
+            .await?;
+        }
+        execute(
+            manager,
+            "DROP FUNCTION IF EXISTS record_unconfirmed_write()".to_string(),
+        )
+        .await?;
+        manager
+            .drop_table(Table::drop().table(LastRootedSlot::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(UnconfirmedWrites::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Slots::Table).to_owned())
+            .await?;
+        manager
+            .drop_type(Type::drop().name(Slots::SlotStatus).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden, Debug, PartialEq, Sequence)]
+enum SlotStatus {
+    Processed,
+    Confirmed,
+    Rooted,
+    Dead,
+}
+
+#[derive(Iden)]
+enum Slots {
+    Table,
+    Slot,
+    Parent,
+    Status,
+    SlotStatus,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+enum UnconfirmedWrites {
+    Table,
+    Id,
+    Slot,
+    TableName,
+    RowKey,
+    Previous,
+    Written,
+}
+
+#[derive(Iden)]
+enum LastRootedSlot {
+    Table,
+    Id,
+    Slot,
+}
@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SignatureCrawls::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SignatureCrawls::Tree)
+                            .binary()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SignatureCrawls::LastSignature).string())
+                    .col(
+                        ColumnDef::new(SignatureCrawls::UpdatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(SignatureCrawlSlots::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SignatureCrawlSlots::Tree)
+                            .binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SignatureCrawlSlots::Slot)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(SignatureCrawlSlots::Tree)
+                            .col(SignatureCrawlSlots::Slot),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SignatureCrawlSlots::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(SignatureCrawls::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum SignatureCrawls {
+    Table,
+    Tree,
+    LastSignature,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+enum SignatureCrawlSlots {
+    Table,
+    Tree,
+    Slot,
+}
@@ -0,0 +1,351 @@
+//! Auditor that periodically compares the root of every indexed tree, as recomputed from
+//! `cl_items`, against the root held in the tree account on chain.  Trees that disagree are
+//! flagged with `force_chk` so the backfiller replays them from the start.
+use crate::{error::IngesterError, IngesterConfig, RPC_COMMITMENT_KEY, RPC_URL_KEY};
+use borsh::BorshDeserialize;
+use cadence_macros::statsd_count;
+use digital_asset_types::dao::backfill_items;
+use digital_asset_types::dapi::recompute_tree_root;
+use sea_orm::{
+    entity::*, query::*, sea_query::Expr, DatabaseConnection, DbBackend, FromQueryResult,
+    SqlxPostgresConnector,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    pubkey::Pubkey,
+};
+use spl_account_compression::state::{
+    ConcurrentMerkleTreeHeader, CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1,
+};
+use sqlx::{Pool, Postgres};
+use std::str::FromStr;
+use tokio::time::{self, Duration};
+
+const DEFAULT_AUDIT_INTERVAL: u64 = 600;
+// Size of the `sequence_number`, `active_index` and `buffer_size` fields preceding the change
+// log buffer of a `ConcurrentMerkleTree`.
+const TREE_PREAMBLE_SIZE: usize = 24;
+const NODE_SIZE: usize = 32;
+
+/// Main public entry point for the auditor task.
+pub async fn auditor(pool: Pool<Postgres>, config: IngesterConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        println!("Auditor task running");
+        let auditor = Auditor::new(pool, &config);
+        let interval = config.auditor_interval.unwrap_or(DEFAULT_AUDIT_INTERVAL);
+        let mut interval = time::interval(Duration::from_secs(interval));
+        loop {
+            interval.tick().await;
+            auditor.audit_trees().await;
+        }
+    })
+}
+
+/// Struct used when querying for unique trees.
+#[derive(Debug, FromQueryResult)]
+struct UniqueTree {
+    tree: Vec<u8>,
+}
+
+/// Result of auditing a single tree.
+#[derive(Debug, PartialEq, Eq)]
+enum TreeAudit {
+    Consistent,
+    Mismatch,
+    // The index is ahead of the chain, or so far behind that the root at its sequence number has
+    // left the change log buffer, so the roots can't be compared.
+    Unsynced,
+    // Nothing has been indexed for the tree.
+    Empty,
+}
+
+/// Roots held in the change log buffer of a tree account, the newest first, along with the
+/// sequence number of the newest.
+#[derive(Debug, PartialEq, Eq)]
+struct OnChainRoots {
+    roots: Vec<Vec<u8>>,
+    seq: u64,
+}
+
+impl OnChainRoots {
+    /// The root of the tree at `seq`, none if it isn't in the buffer.
+    fn root_at(&self, seq: u64) -> Option<&[u8]> {
+        let age = self.seq.checked_sub(seq)?;
+        self.roots.get(age as usize).map(Vec::as_slice)
+    }
+}
+
+struct Auditor {
+    db: DatabaseConnection,
+    rpc_client: RpcClient,
+}
+
+impl Auditor {
+    fn new(pool: Pool<Postgres>, config: &IngesterConfig) -> Self {
+        let db = SqlxPostgresConnector::from_sqlx_postgres_pool(pool);
+
+        let rpc_url = config
+            .rpc_config
+            .get(RPC_URL_KEY)
+            .and_then(|u| u.clone().into_string())
+            .ok_or(IngesterError::ConfigurationError {
+                msg: format!("RPC URL missing: {}", RPC_URL_KEY),
+            })
+            .unwrap();
+        let rpc_commitment_level = config
+            .rpc_config
+            .get(RPC_COMMITMENT_KEY)
+            .and_then(|v| v.as_str())
+            .ok_or(IngesterError::ConfigurationError {
+                msg: format!("RPC commitment level missing: {}", RPC_COMMITMENT_KEY),
+            })
+            .unwrap();
+        let rpc_commitment = CommitmentConfig {
+            commitment: CommitmentLevel::from_str(rpc_commitment_level)
+                .map_err(|_| IngesterError::ConfigurationError {
+                    msg: format!("Invalid RPC commitment level: {}", rpc_commitment_level),
+                })
+                .unwrap(),
+        };
+
+        Self {
+            db,
+            rpc_client: RpcClient::new_with_commitment(rpc_url, rpc_commitment),
+        }
+    }
+
+    async fn audit_trees(&self) {
+        let trees = match self.get_trees().await {
+            Ok(trees) => trees,
+            Err(err) => {
+                println!("Auditor could not get trees from db: {err}");
+                return;
+            }
+        };
+        println!("Auditing {} trees", trees.len());
+        for tree in trees {
+            let tree_string = bs58::encode(&tree.tree).into_string();
+            match self.audit_tree(&tree.tree).await {
+                Ok(TreeAudit::Mismatch) => {
+                    println!("Root mismatch for tree {tree_string}, forcing a backfill");
+                    statsd_count!("ingester.auditor.root_mismatch", 1);
+                    if let Err(err) = self.force_check(&tree.tree).await {
+                        println!("Error setting force check on tree {tree_string}: {err}");
+                    }
+                }
+                Ok(TreeAudit::Consistent) => {
+                    statsd_count!("ingester.auditor.root_match", 1);
+                }
+                Ok(TreeAudit::Unsynced) => {
+                    statsd_count!("ingester.auditor.tree_unsynced", 1);
+                }
+                Ok(TreeAudit::Empty) => {}
+                Err(err) => {
+                    println!("Error auditing tree {tree_string}: {err}");
+                    statsd_count!("ingester.auditor.error", 1);
+                }
+            }
+        }
+    }
+
+    async fn get_trees(&self) -> Result<Vec<UniqueTree>, IngesterError> {
+        let query = backfill_items::Entity::find()
+            .select_only()
+            .column(backfill_items::Column::Tree)
+            .distinct()
+            .filter(backfill_items::Column::Failed.eq(false))
+            .build(DbBackend::Postgres);
+        Ok(UniqueTree::find_by_statement(query).all(&self.db).await?)
+    }
+
+    async fn audit_tree(&self, tree: &[u8]) -> Result<TreeAudit, IngesterError> {
+        let indexed = match recompute_tree_root(&self.db, tree).await? {
+            Some(indexed) => indexed,
+            None => return Ok(TreeAudit::Empty),
+        };
+        let account = self
+            .rpc_client
+            .get_account(&Pubkey::new(tree))
+            .await
+            .map_err(|e| IngesterError::RpcGetDataError(e.to_string()))?;
+        let on_chain = parse_on_chain_roots(&account.data)?;
+        Ok(compare_roots(&indexed.root, indexed.seq as u64, &on_chain))
+    }
+
+    async fn force_check(&self, tree: &[u8]) -> Result<(), IngesterError> {
+        backfill_items::Entity::update_many()
+            .col_expr(backfill_items::Column::ForceChk, Expr::value(true))
+            .filter(backfill_items::Column::Tree.eq(tree))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Compares the indexed root with the root the tree had on chain at the same sequence number, so
+/// that a tree written to while it is audited is still audited.
+fn compare_roots(indexed_root: &[u8], indexed_seq: u64, on_chain: &OnChainRoots) -> TreeAudit {
+    match on_chain.root_at(indexed_seq) {
+        None => TreeAudit::Unsynced,
+        Some(root) if root == indexed_root => TreeAudit::Consistent,
+        Some(_) => TreeAudit::Mismatch,
+    }
+}
+
+/// Reads the roots of the change log buffer out of the data of a concurrent merkle tree
+/// account.  The tree follows the header and starts with its sequence number, the index of the
+/// active change log and the number of change logs in the buffer.  Change logs start with their
+/// root, the active one holds the current root and the ones before it, wrapping around, the
+/// previous ones.
+fn parse_on_chain_roots(data: &[u8]) -> Result<OnChainRoots, IngesterError> {
+    let mut header_data = data;
+    let header = ConcurrentMerkleTreeHeader::deserialize(&mut header_data)
+        .map_err(|e| IngesterError::DeserializationError(e.to_string()))?;
+    let max_depth = header.get_max_depth() as usize;
+    let max_buffer_size = header.get_max_buffer_size() as usize;
+
+    let tree = data
+        .get(CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1..)
+        .ok_or_else(|| IngesterError::DeserializationError("Tree data missing".to_string()))?;
+    let read_u64 = |offset: usize| -> Result<u64, IngesterError> {
+        tree.get(offset..offset + 8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+            .ok_or_else(|| IngesterError::DeserializationError("Tree data too short".to_string()))
+    };
+    let seq = read_u64(0)?;
+    let active_index = read_u64(8)? as usize;
+    let buffer_size = read_u64(16)? as usize;
+    if active_index >= max_buffer_size || buffer_size > max_buffer_size {
+        return Err(IngesterError::DeserializationError(
+            "Change log buffer out of bounds".to_string(),
+        ));
+    }
+    // A change log holds the root, the path of `max_depth` nodes and a padded `u32` index.
+    let change_log_size = NODE_SIZE + NODE_SIZE * max_depth + 8;
+    // Only as many roots as there were changes are in the buffer.
+    let roots = (0..buffer_size.min(seq as usize + 1))
+        .map(|age| {
+            let index = (active_index + max_buffer_size - age) % max_buffer_size;
+            let root_offset = TREE_PREAMBLE_SIZE + index * change_log_size;
+            tree.get(root_offset..root_offset + NODE_SIZE)
+                .map(<[u8]>::to_vec)
+                .ok_or_else(|| {
+                    IngesterError::DeserializationError("Tree data too short".to_string())
+                })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(OnChainRoots { roots, seq })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_concurrent_merkle_tree::concurrent_merkle_tree::ConcurrentMerkleTree;
+
+    const MAX_DEPTH: usize = 3;
+    const MAX_BUFFER_SIZE: usize = 8;
+
+    /// Account data of a tree as it is laid out on chain, a V1 header followed by the tree.
+    fn account_data(tree: &ConcurrentMerkleTree<MAX_DEPTH, MAX_BUFFER_SIZE>) -> Vec<u8> {
+        let mut data = vec![1, 0];
+        data.extend_from_slice(&(MAX_BUFFER_SIZE as u32).to_le_bytes());
+        data.extend_from_slice(&(MAX_DEPTH as u32).to_le_bytes());
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&1u64.to_le_bytes());
+        data.extend_from_slice(&[0; 6]);
+        assert_eq!(data.len(), CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1);
+        // SAFETY: the tree is `repr(C)` plain old data, exactly as stored in the account.
+        let tree_data = unsafe {
+            std::slice::from_raw_parts(
+                tree as *const _ as *const u8,
+                std::mem::size_of::<ConcurrentMerkleTree<MAX_DEPTH, MAX_BUFFER_SIZE>>(),
+            )
+        };
+        data.extend_from_slice(tree_data);
+        data
+    }
+
+    fn tree_with_leaves(leaves: u8) -> ConcurrentMerkleTree<MAX_DEPTH, MAX_BUFFER_SIZE> {
+        let mut tree = ConcurrentMerkleTree::<MAX_DEPTH, MAX_BUFFER_SIZE>::new();
+        tree.initialize().unwrap();
+        for leaf in 1..=leaves {
+            tree.append([leaf; 32]).unwrap();
+        }
+        tree
+    }
+
+    #[test]
+    fn reads_the_active_root() {
+        // More appends than the buffer holds so the active index wraps around.
+        for leaves in [0, 1, 5, 8] {
+            let tree = tree_with_leaves(leaves);
+            let on_chain = parse_on_chain_roots(&account_data(&tree)).unwrap();
+            assert_eq!(on_chain.seq, tree.sequence_number);
+            assert_eq!(on_chain.roots[0], tree.get_root().to_vec());
+        }
+    }
+
+    #[test]
+    fn reads_the_previous_roots_of_the_buffer() {
+        let tree = tree_with_leaves(10);
+        let on_chain = parse_on_chain_roots(&account_data(&tree)).unwrap();
+        assert_eq!(on_chain.roots.len(), MAX_BUFFER_SIZE);
+        for leaves in 3..=10 {
+            assert_eq!(
+                on_chain.root_at(leaves as u64),
+                Some(&tree_with_leaves(leaves).get_root()[..])
+            );
+        }
+        assert_eq!(on_chain.root_at(2), None);
+        assert_eq!(on_chain.root_at(11), None);
+    }
+
+    #[test]
+    fn rejects_truncated_accounts() {
+        let data = account_data(&tree_with_leaves(2));
+        assert!(parse_on_chain_roots(&data[..CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1 + 16]).is_err());
+    }
+
+    #[test]
+    fn compares_roots_at_the_same_sequence_number() {
+        let tree = tree_with_leaves(3);
+        let on_chain = parse_on_chain_roots(&account_data(&tree)).unwrap();
+        let root = tree.get_root();
+        assert_eq!(
+            compare_roots(&root, tree.sequence_number, &on_chain),
+            TreeAudit::Consistent
+        );
+        assert_eq!(
+            compare_roots(&[0; 32], tree.sequence_number, &on_chain),
+            TreeAudit::Mismatch
+        );
+        assert_eq!(
+            compare_roots(&[0; 32], tree.sequence_number + 1, &on_chain),
+            TreeAudit::Unsynced
+        );
+    }
+
+    #[test]
+    fn compares_roots_of_busy_trees_against_the_buffer() {
+        // The tree took more changes on chain while it was being indexed.
+        let tree = tree_with_leaves(6);
+        let on_chain = parse_on_chain_roots(&account_data(&tree)).unwrap();
+        let indexed = tree_with_leaves(4);
+        assert_eq!(
+            compare_roots(&indexed.get_root(), indexed.sequence_number, &on_chain),
+            TreeAudit::Consistent
+        );
+        assert_eq!(
+            compare_roots(&[0; 32], indexed.sequence_number, &on_chain),
+            TreeAudit::Mismatch
+        );
+
+        let tree = tree_with_leaves(12);
+        let on_chain = parse_on_chain_roots(&account_data(&tree)).unwrap();
+        assert_eq!(
+            compare_roots(&indexed.get_root(), indexed.sequence_number, &on_chain),
+            TreeAudit::Unsynced
+        );
+    }
+}
@@ -1,8 +1,10 @@
 //! Backfiller that fills gaps in trees by detecting gaps in sequence numbers
 //! in the `backfill_items` table.  Inspired by backfiller.ts/backfill.ts.
 use crate::{
-    error::IngesterError, IngesterConfig, DATABASE_LISTENER_CHANNEL_KEY, RPC_COMMITMENT_KEY,
-    RPC_URL_KEY,
+    block_source::{block_source, BlockSource, CachedBlockSource},
+    error::IngesterError,
+    signature_crawl::{unlock_abandoned_crawls, SignatureCrawl},
+    IngesterConfig, DATABASE_LISTENER_CHANNEL_KEY, RPC_COMMITMENT_KEY, RPC_URL_KEY,
 };
 use borsh::BorshDeserialize;
 use cadence_macros::statsd_count;
@@ -22,28 +24,24 @@ use sea_orm::{
 use solana_account_decoder::UiAccountEncoding;
 use solana_client::{
     nonblocking::rpc_client::RpcClient,
-    rpc_client::GetConfirmedSignaturesForAddress2Config,
-    rpc_config::{RpcAccountInfoConfig, RpcBlockConfig, RpcProgramAccountsConfig},
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
     rpc_filter::{Memcmp, RpcFilterType},
 };
 use solana_sdk::{
     account::Account,
     commitment_config::{CommitmentConfig, CommitmentLevel},
     pubkey::Pubkey,
-    signature::Signature,
-    slot_history::Slot,
 };
 use solana_transaction_status::{
-    option_serializer::OptionSerializer, EncodedConfirmedBlock, UiInstruction::Compiled,
-    UiRawMessage, UiTransactionEncoding, UiTransactionStatusMeta,
+    option_serializer::OptionSerializer, UiInstruction::Compiled, UiRawMessage,
+    UiTransactionStatusMeta,
 };
 use spl_account_compression::state::ConcurrentMerkleTreeHeader;
 use sqlx::{self, postgres::PgListener, Pool, Postgres};
 use std::cmp;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
-use stretto::{AsyncCache, AsyncCacheBuilder};
 use tokio::{
     sync::Semaphore,
     time::{self, sleep, Duration},
@@ -55,9 +53,6 @@ const MAX_BACKFILL_CHECK_WAIT: u64 = 5000;
 // Constants used for varying delays when failures occur.
 const INITIAL_FAILURE_DELAY: u64 = 100;
 const MAX_FAILURE_DELAY_MS: u64 = 10_000;
-const BLOCK_CACHE_SIZE: usize = 300_000;
-const MAX_CACHE_COST: i64 = 32;
-const BLOCK_CACHE_DURATION: u64 = 172800;
 // Account key used to determine if transaction is a simple vote.
 const VOTE: &str = "Vote111111111111111111111111111111111111111";
 
@@ -71,27 +66,24 @@ pub async fn backfiller<T: Messenger>(
             let pool_cloned = pool.clone();
             let config_cloned = config.clone();
             let tasks = FuturesUnordered::new();
-            let block_cache = Arc::new(
-                AsyncCacheBuilder::new(BLOCK_CACHE_SIZE, MAX_CACHE_COST)
-                    .set_ignore_internal_cost(true)
-                    .finalize(tokio::spawn)
-                    .expect("failed to create cache"),
-            );
-            let bc = Arc::clone(&block_cache);
+            let source: Arc<dyn BlockSource> = Arc::new(CachedBlockSource::new(
+                block_source(&config.rpc_config).unwrap(),
+            ));
+            let bs = Arc::clone(&source);
             tasks.push(tokio::spawn(async move {
                 println!("Backfiller task running");
 
-                let mut backfiller = Backfiller::<T>::new(pool_cloned, config_cloned, &bc).await;
+                let mut backfiller = Backfiller::<T>::new(pool_cloned, config_cloned, bs).await;
                 backfiller.run_filler().await;
             }));
 
             let pool_cloned = pool.clone();
             let config_cloned = config.clone();
-            let bc = Arc::clone(&block_cache);
+            let bs = Arc::clone(&source);
             tasks.push(tokio::spawn(async move {
                 println!("Backfiller task running");
 
-                let mut backfiller = Backfiller::<T>::new(pool_cloned, config_cloned, &bc).await;
+                let mut backfiller = Backfiller::<T>::new(pool_cloned, config_cloned, bs).await;
                 backfiller.run_finder().await;
             }));
 
@@ -175,23 +167,22 @@ impl GapInfo {
 }
 
 /// Main struct used for backfiller task.
-struct Backfiller<'a, T: Messenger> {
+struct Backfiller<T: Messenger> {
     db: DatabaseConnection,
     listener: PgListener,
     rpc_client: RpcClient,
-    rpc_block_config: RpcBlockConfig,
+    block_source: Arc<dyn BlockSource>,
     messenger: T,
     failure_delay: u64,
-    cache: &'a AsyncCache<String, EncodedConfirmedBlock>,
 }
 
-impl<'a, T: Messenger> Backfiller<'a, T> {
+impl<T: Messenger> Backfiller<T> {
     /// Create a new `Backfiller` struct.
     async fn new(
         pool: Pool<Postgres>,
         config: IngesterConfig,
-        cache: &'a AsyncCache<String, EncodedConfirmedBlock>,
-    ) -> Backfiller<'a, T> {
+        block_source: Arc<dyn BlockSource>,
+    ) -> Backfiller<T> {
         // Create Sea ORM database connection used later for queries.
         let db = SqlxPostgresConnector::from_sqlx_postgres_pool(pool.clone());
 
@@ -254,14 +245,6 @@ impl<'a, T: Messenger> Backfiller<'a, T> {
                 .unwrap(),
         };
 
-        // Create `RpcBlockConfig` used when getting blocks from RPC provider.
-        let rpc_block_config = RpcBlockConfig {
-            encoding: Some(UiTransactionEncoding::Json),
-            commitment: Some(rpc_commitment),
-            max_supported_transaction_version: Some(0),
-            ..RpcBlockConfig::default()
-        };
-
         // Instantiate RPC client.
         let rpc_client = RpcClient::new_with_commitment(rpc_url, rpc_commitment);
 
@@ -274,10 +257,9 @@ impl<'a, T: Messenger> Backfiller<'a, T> {
             db,
             listener,
             rpc_client,
-            rpc_block_config,
+            block_source,
             messenger,
             failure_delay: INITIAL_FAILURE_DELAY,
-            cache,
         }
     }
 
@@ -330,14 +312,11 @@ impl<'a, T: Messenger> Backfiller<'a, T> {
                             }
                         }
                     } else {
-                        // First just check if we can talk to an RPC provider.
-                        match self.rpc_client.get_version().await {
-                            Ok(version) => println!("RPC client version {version}"),
-                            Err(err) => {
-                                println!("RPC client error {err}");
-                                self.sleep_and_increase_delay().await;
-                                continue;
-                            }
+                        // First just check if we can get blocks.
+                        if let Err(err) = self.block_source.check().await {
+                            println!("Block source error {err}");
+                            self.sleep_and_increase_delay().await;
+                            continue;
                         }
 
                         for backfill_tree in backfill_trees {
@@ -521,6 +500,9 @@ impl<'a, T: Messenger> Backfiller<'a, T> {
         ))
         .await?;
 
+        // Unlock the trees of abandoned crawls.
+        unlock_abandoned_crawls(&txn, Utc::now().naive_utc()).await?;
+
         // Get trees with the `force_chk` flag set to true (that have not failed and are not locked).
         let force_chk_trees = Statement::from_string(
             DbBackend::Postgres,
@@ -624,68 +606,31 @@ impl<'a, T: Messenger> Backfiller<'a, T> {
         btree: &BackfillTree,
     ) -> Result<Option<i64>, IngesterError> {
         let address = Pubkey::new(btree.unique_tree.tree.as_slice());
-        let slots = self.find_slots_via_address(&address).await?;
-        let address = btree.unique_tree.tree.clone();
-        for slot in slots {
-            let gap = GapInfo {
-                prev: SimpleBackfillItem {
-                    seq: 0,
-                    slot: slot as i64,
-                },
-                curr: SimpleBackfillItem {
-                    seq: 0,
-                    slot: slot as i64,
-                },
-            };
-            self.plug_gap(&gap, &address).await?;
-        }
-        Ok(Some(0))
-    }
-
-    async fn find_slots_via_address(&self, address: &Pubkey) -> Result<Vec<Slot>, IngesterError> {
-        let mut last_sig = None;
-        let mut slots = HashSet::new();
-        // TODO: Any log running function like this should actually be run in a way that supports re-entry,
-        // usually we woudl break the tasks into smaller parralel tasks and we woudl not worry about it, but in this we have several linearally dpendent async tasks
-        // and if they fail, it causes a chain reaction of failures since the dependant nature of it affects the next task. Right now you are just naivley looping and
-        // hoping for the best what needs to happen is to start saving the state opf each task with the last signature that was retuned iun durable storage.
-        // Then if the task fails, you can restart it from the last signature that was returned.
-        loop {
-            let before = last_sig;
-            let sigs = self
-                .rpc_client
-                .get_signatures_for_address_with_config(
-                    address,
-                    GetConfirmedSignaturesForAddress2Config {
-                        before,
-                        until: None,
-                        ..GetConfirmedSignaturesForAddress2Config::default()
+        let tree = btree.unique_tree.tree.clone();
+        let mut crawl = SignatureCrawl::resume(&self.db, address).await?;
+        while let Some(page) = crawl.next_page(&self.rpc_client, &self.db).await? {
+            for slot in page.new_slots.iter() {
+                let gap = GapInfo {
+                    prev: SimpleBackfillItem {
+                        seq: 0,
+                        slot: *slot as i64,
                     },
-                )
-                .await
-                .map_err(|e| {
-                    IngesterError::RpcGetDataError(format!(
-                        "GetSignaturesForAddressWithConfig failed {}",
-                        e
-                    ))
-                })?;
-            for sig in sigs.iter() {
-                let slot = sig.slot;
-                let sig = Signature::from_str(&sig.signature).map_err(|e| {
-                    IngesterError::RpcDataUnsupportedFormat(format!(
-                        "Failed to parse signature {}",
-                        e
-                    ))
-                })?;
-
-                slots.insert(slot);
-                last_sig = Some(sig);
-            }
-            if sigs.is_empty() || sigs.len() < 1000 {
-                break;
+                    curr: SimpleBackfillItem {
+                        seq: 0,
+                        slot: *slot as i64,
+                    },
+                };
+                self.plug_gap(&gap, &tree).await?;
+                crawl.touch(&self.db).await?;
             }
+            statsd_count!(
+                "ingester.backfiller.crawled_slots",
+                page.new_slots.len() as i64
+            );
+            crawl.checkpoint(&self.db, page).await?;
         }
-        Ok(Vec::from_iter(slots))
+        crawl.finish(&self.db).await?;
+        Ok(Some(0))
     }
 
     async fn get_max_seq(&self, tree: &[u8]) -> Result<Option<i64>, DbErr> {
@@ -821,13 +766,10 @@ impl<'a, T: Messenger> Backfiller<'a, T> {
             num_iter = 1;
         }
         for _ in 0..num_iter {
-            get_confirmed_slot_tasks.push(self.rpc_client.get_blocks_with_commitment(
-                start_slot as u64,
-                Some(end_slot as u64),
-                CommitmentConfig {
-                    commitment: CommitmentLevel::Confirmed,
-                },
-            ));
+            get_confirmed_slot_tasks.push(
+                self.block_source
+                    .get_blocks(start_slot as u64, end_slot as u64),
+            );
             start_slot = end_slot;
             end_slot = cmp::min(end_slot + 500_000, gap.curr.slot);
         }
@@ -838,44 +780,7 @@ impl<'a, T: Messenger> Backfiller<'a, T> {
             .filter_map(|x| x.ok())
             .flatten();
         for slot in result_slots {
-            let key = format!("block{}", slot);
-            let mut cached_block = self.cache.get(&key);
-            if cached_block.is_none() {
-                println!("Fetching block {} from RPC", slot);
-                let block = EncodedConfirmedBlock::from(
-                    self.rpc_client
-                        .get_block_with_config(slot as u64, self.rpc_block_config)
-                        .await
-                        .map_err(|e| IngesterError::RpcGetDataError(e.to_string()))?,
-                );
-                let cost = cmp::min(32, block.transactions.len() as i64);
-                let write = self
-                    .cache
-                    .try_insert_with_ttl(
-                        key.clone(),
-                        block,
-                        cost,
-                        Duration::from_secs(BLOCK_CACHE_DURATION),
-                    )
-                    .await?;
-
-                if !write {
-                    return Err(IngesterError::CacheStorageWriteError(format!(
-                        "Cache Write Failed on {} is missing.",
-                        &key
-                    )));
-                }
-                self.cache.wait().await?;
-                cached_block = self.cache.get(&key);
-            }
-            if cached_block.is_none() {
-                return Err(IngesterError::CacheStorageWriteError(format!(
-                    "Cache Procedure Failed {} is missing.",
-                    &key
-                )));
-            }
-            let block_ref = cached_block.unwrap();
-            let block_data = block_ref.value();
+            let block_data = self.block_source.get_block(slot).await?;
 
             for tx in block_data.transactions.iter() {
                 // See if transaction has an error.
@@ -943,7 +848,6 @@ impl<'a, T: Messenger> Backfiller<'a, T> {
                     .send(TRANSACTION_STREAM, builder.finished_data())
                     .await?;
             }
-            drop(block_ref);
         }
 
         Ok(())
@@ -0,0 +1,642 @@
+//! Sources of the blocks the backfiller ingests, chosen through `rpc_config`:
+//!
+//! * `rpc`, the default, fetches them from the RPC node at `url`.
+//! * `dir` reads them from the files of `block_dir`, for when no RPC node is reachable.
+//! * `pool` fetches them from `block_endpoints`, each with its own rate limit, failing over to
+//!   the next endpoint when one fails.
+//!
+//! Whichever source is chosen sits behind the block cache.
+use crate::{
+    error::IngesterError, metrics::safe_metric, RpcConfig, RPC_COMMITMENT_KEY, RPC_URL_KEY,
+};
+use async_trait::async_trait;
+use cadence_macros::statsd_count;
+use serde::{Deserialize, Serialize};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::RpcBlockConfig,
+};
+use solana_sdk::{
+    clock::UnixTimestamp,
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    slot_history::Slot,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use solana_transaction_status::{
+    EncodedConfirmedBlock, InnerInstructions, TransactionStatusMeta, UiConfirmedBlock,
+    UiTransactionEncoding, VersionedTransactionWithStatusMeta,
+};
+use std::{
+    cmp,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use stretto::{AsyncCache, AsyncCacheBuilder};
+use tokio::{
+    sync::Mutex,
+    time::{self, Duration, Instant},
+};
+use url::Url;
+
+pub const BLOCK_SOURCE_KEY: &str = "block_source";
+pub const BLOCK_DIR_KEY: &str = "block_dir";
+pub const BLOCK_ENDPOINTS_KEY: &str = "block_endpoints";
+const BLOCK_CACHE_SIZE: usize = 300_000;
+const MAX_CACHE_COST: i64 = 32;
+const BLOCK_CACHE_DURATION: u64 = 172800;
+
+#[async_trait]
+pub trait BlockSource: Send + Sync {
+    /// Checks that blocks can be fetched.
+    async fn check(&self) -> Result<(), IngesterError>;
+    /// The slots between `start_slot` and `end_slot`, both included, that have a block.
+    async fn get_blocks(
+        &self,
+        start_slot: Slot,
+        end_slot: Slot,
+    ) -> Result<Vec<Slot>, IngesterError>;
+    async fn get_block(&self, slot: Slot) -> Result<Arc<EncodedConfirmedBlock>, IngesterError>;
+}
+
+/// The block source configured in `rpc_config`.
+pub fn block_source(rpc_config: &RpcConfig) -> Result<Box<dyn BlockSource>, IngesterError> {
+    let get = |key: &str| rpc_config.get(key).and_then(|v| v.clone().into_string());
+    let missing = |key: &str| IngesterError::ConfigurationError {
+        msg: format!("Block source setting missing: {}", key),
+    };
+    match get(BLOCK_SOURCE_KEY).as_deref().unwrap_or("rpc") {
+        "rpc" => {
+            let url = get(RPC_URL_KEY).ok_or_else(|| missing(RPC_URL_KEY))?;
+            Ok(Box::new(RpcBlockSource::new(
+                url,
+                commitment(rpc_config)?,
+                None,
+            )))
+        }
+        "dir" => {
+            let dir = get(BLOCK_DIR_KEY).ok_or_else(|| missing(BLOCK_DIR_KEY))?;
+            Ok(Box::new(DirBlockSource::new(dir)?))
+        }
+        "pool" => {
+            let endpoints: Vec<EndpointConfig> = rpc_config
+                .get(BLOCK_ENDPOINTS_KEY)
+                .ok_or_else(|| missing(BLOCK_ENDPOINTS_KEY))?
+                .deserialize()
+                .map_err(|e| IngesterError::ConfigurationError {
+                    msg: format!("Invalid {}: {}", BLOCK_ENDPOINTS_KEY, e),
+                })?;
+            if endpoints.is_empty() {
+                return Err(missing(BLOCK_ENDPOINTS_KEY));
+            }
+            let commitment = commitment(rpc_config)?;
+            Ok(Box::new(RpcPoolBlockSource::new(
+                endpoints
+                    .into_iter()
+                    .map(|e| RpcBlockSource::new(e.url, commitment, e.requests_per_second))
+                    .collect(),
+            )))
+        }
+        other => Err(IngesterError::ConfigurationError {
+            msg: format!("Unknown {}: {}", BLOCK_SOURCE_KEY, other),
+        }),
+    }
+}
+
+fn commitment(rpc_config: &RpcConfig) -> Result<CommitmentConfig, IngesterError> {
+    let level = rpc_config
+        .get(RPC_COMMITMENT_KEY)
+        .and_then(|v| v.as_str())
+        .ok_or(IngesterError::ConfigurationError {
+            msg: format!("RPC commitment level missing: {}", RPC_COMMITMENT_KEY),
+        })?;
+    Ok(CommitmentConfig {
+        commitment: CommitmentLevel::from_str(level).map_err(|_| {
+            IngesterError::ConfigurationError {
+                msg: format!("Invalid RPC commitment level: {}", level),
+            }
+        })?,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct EndpointConfig {
+    url: String,
+    requests_per_second: Option<u32>,
+}
+
+/// Spaces out requests so that at most `requests_per_second` are made.
+struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(requests_per_second: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / cmp::max(requests_per_second, 1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    async fn wait(&self) {
+        let at = {
+            let mut next = self.next.lock().await;
+            let at = cmp::max(*next, Instant::now());
+            *next = at + self.interval;
+            at
+        };
+        time::sleep_until(at).await;
+    }
+}
+
+pub struct RpcBlockSource {
+    // Only the host of the URL is logged, the rest may hold an API key.
+    host: String,
+    rpc_client: RpcClient,
+    rpc_block_config: RpcBlockConfig,
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl RpcBlockSource {
+    pub fn new(
+        url: String,
+        commitment: CommitmentConfig,
+        requests_per_second: Option<u32>,
+    ) -> Self {
+        Self {
+            host: Url::parse(&url)
+                .ok()
+                .and_then(|u| u.host_str().map(str::to_string))
+                .unwrap_or_default(),
+            rpc_client: RpcClient::new_with_commitment(url, commitment),
+            rpc_block_config: RpcBlockConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(commitment),
+                max_supported_transaction_version: Some(0),
+                ..RpcBlockConfig::default()
+            },
+            rate_limiter: requests_per_second.map(RateLimiter::new),
+        }
+    }
+
+    async fn wait(&self) {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.wait().await;
+        }
+    }
+}
+
+/// The error of a request to an endpoint, without the URL the HTTP client puts in its errors.
+fn request_error(err: ClientError) -> IngesterError {
+    let msg = match err.kind {
+        ClientErrorKind::Reqwest(err) => err.without_url().to_string(),
+        kind => kind.to_string(),
+    };
+    IngesterError::RpcGetDataError(msg)
+}
+
+#[async_trait]
+impl BlockSource for RpcBlockSource {
+    async fn check(&self) -> Result<(), IngesterError> {
+        self.wait().await;
+        let version = self.rpc_client.get_version().await.map_err(request_error)?;
+        println!("RPC client version {version}");
+        Ok(())
+    }
+
+    async fn get_blocks(
+        &self,
+        start_slot: Slot,
+        end_slot: Slot,
+    ) -> Result<Vec<Slot>, IngesterError> {
+        self.wait().await;
+        self.rpc_client
+            .get_blocks_with_commitment(
+                start_slot,
+                Some(end_slot),
+                CommitmentConfig {
+                    commitment: CommitmentLevel::Confirmed,
+                },
+            )
+            .await
+            .map_err(request_error)
+    }
+
+    async fn get_block(&self, slot: Slot) -> Result<Arc<EncodedConfirmedBlock>, IngesterError> {
+        self.wait().await;
+        println!("Fetching block {} from {}", slot, self.host);
+        let block = self
+            .rpc_client
+            .get_block_with_config(slot, self.rpc_block_config)
+            .await
+            .map_err(request_error)?;
+        Ok(Arc::new(EncodedConfirmedBlock::from(block)))
+    }
+}
+
+/// RPC endpoints taking turns, a request failing on one endpoint is retried on the next ones.
+pub struct RpcPoolBlockSource {
+    endpoints: Vec<RpcBlockSource>,
+    next: AtomicUsize,
+}
+
+impl RpcPoolBlockSource {
+    pub fn new(endpoints: Vec<RpcBlockSource>) -> Self {
+        Self {
+            endpoints,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// The endpoints in the order a request tries them, with their index in the pool.
+    fn endpoints(&self) -> impl Iterator<Item = (usize, &RpcBlockSource)> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % self.endpoints.len();
+        self.endpoints
+            .iter()
+            .enumerate()
+            .cycle()
+            .skip(start)
+            .take(self.endpoints.len())
+    }
+}
+
+fn failed_over(index: usize, endpoint: &RpcBlockSource, err: &IngesterError) {
+    println!(
+        "Block source {} ({}) failed, failing over: {}",
+        index, endpoint.host, err
+    );
+    safe_metric(|| {
+        statsd_count!("ingester.backfiller.block_source_failover", 1, "endpoint" => &index.to_string());
+    });
+}
+
+#[async_trait]
+impl BlockSource for RpcPoolBlockSource {
+    async fn check(&self) -> Result<(), IngesterError> {
+        let mut result = Ok(());
+        for (index, endpoint) in self.endpoints() {
+            result = endpoint.check().await;
+            match &result {
+                Ok(_) => break,
+                Err(err) => failed_over(index, endpoint, err),
+            }
+        }
+        result
+    }
+
+    async fn get_blocks(
+        &self,
+        start_slot: Slot,
+        end_slot: Slot,
+    ) -> Result<Vec<Slot>, IngesterError> {
+        let mut result = Ok(Vec::new());
+        for (index, endpoint) in self.endpoints() {
+            result = endpoint.get_blocks(start_slot, end_slot).await;
+            match &result {
+                Ok(_) => break,
+                Err(err) => failed_over(index, endpoint, err),
+            }
+        }
+        result
+    }
+
+    async fn get_block(&self, slot: Slot) -> Result<Arc<EncodedConfirmedBlock>, IngesterError> {
+        let mut result = Err(IngesterError::RpcGetDataError(format!(
+            "No endpoint to get block {} from",
+            slot
+        )));
+        for (index, endpoint) in self.endpoints() {
+            result = endpoint.get_block(slot).await;
+            match &result {
+                Ok(_) => break,
+                Err(err) => failed_over(index, endpoint, err),
+            }
+        }
+        result
+    }
+}
+
+/// A block stored with bincode, which can't read the encoded blocks of the RPC API back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredBlock {
+    pub previous_blockhash: String,
+    pub blockhash: String,
+    pub parent_slot: Slot,
+    pub transactions: Vec<StoredTransaction>,
+    pub block_time: Option<UnixTimestamp>,
+    pub block_height: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredTransaction {
+    pub transaction: VersionedTransaction,
+    pub err: Option<TransactionError>,
+    pub inner_instructions: Option<Vec<InnerInstructions>>,
+}
+
+impl StoredBlock {
+    fn encode(self) -> Result<EncodedConfirmedBlock, IngesterError> {
+        let transactions = self
+            .transactions
+            .into_iter()
+            .map(|tx| {
+                VersionedTransactionWithStatusMeta {
+                    transaction: tx.transaction,
+                    meta: TransactionStatusMeta {
+                        status: tx.err.map_or(Ok(()), Err),
+                        inner_instructions: tx.inner_instructions,
+                        ..TransactionStatusMeta::default()
+                    },
+                }
+                .encode(UiTransactionEncoding::Json, Some(0), false)
+                .map_err(|e| IngesterError::DeserializationError(e.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(EncodedConfirmedBlock {
+            previous_blockhash: self.previous_blockhash,
+            blockhash: self.blockhash,
+            parent_slot: self.parent_slot,
+            transactions,
+            rewards: Vec::new(),
+            block_time: self.block_time,
+            block_height: self.block_height,
+        })
+    }
+}
+
+/// Blocks stored in a directory, as `<slot>.json` files holding the JSON encoded block returned
+/// by `getBlock`, or as `<slot>.bin` files holding a bincode `StoredBlock`.  The directory is
+/// listed once when the source is built, blocks added later aren't seen.
+pub struct DirBlockSource {
+    dir: PathBuf,
+    // The slots with a block, in order.
+    slots: Vec<Slot>,
+}
+
+impl DirBlockSource {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, IngesterError> {
+        let dir = dir.into();
+        let mut slots = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            if let Some(slot) = block_slot(&entry?.path()) {
+                slots.push(slot);
+            }
+        }
+        slots.sort_unstable();
+        slots.dedup();
+        Ok(Self { dir, slots })
+    }
+}
+
+fn block_slot(path: &Path) -> Option<Slot> {
+    match path.extension()?.to_str()? {
+        "json" | "bin" => path.file_stem()?.to_str()?.parse().ok(),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl BlockSource for DirBlockSource {
+    async fn check(&self) -> Result<(), IngesterError> {
+        tokio::fs::read_dir(&self.dir).await?;
+        Ok(())
+    }
+
+    async fn get_blocks(
+        &self,
+        start_slot: Slot,
+        end_slot: Slot,
+    ) -> Result<Vec<Slot>, IngesterError> {
+        let start = self.slots.partition_point(|s| *s < start_slot);
+        let end = self.slots.partition_point(|s| *s <= end_slot).max(start);
+        Ok(self.slots[start..end].to_vec())
+    }
+
+    async fn get_block(&self, slot: Slot) -> Result<Arc<EncodedConfirmedBlock>, IngesterError> {
+        let json = self.dir.join(format!("{}.json", slot));
+        let block = if json.exists() {
+            let block: UiConfirmedBlock = serde_json::from_slice(&tokio::fs::read(json).await?)?;
+            EncodedConfirmedBlock::from(block)
+        } else {
+            let bin = tokio::fs::read(self.dir.join(format!("{}.bin", slot))).await?;
+            bincode::deserialize::<StoredBlock>(&bin)
+                .map_err(|e| IngesterError::DeserializationError(e.to_string()))?
+                .encode()?
+        };
+        Ok(Arc::new(block))
+    }
+}
+
+/// A block source whose blocks are kept in the block cache.
+pub struct CachedBlockSource {
+    source: Box<dyn BlockSource>,
+    cache: AsyncCache<String, Arc<EncodedConfirmedBlock>>,
+}
+
+impl CachedBlockSource {
+    pub fn new(source: Box<dyn BlockSource>) -> Self {
+        let cache = AsyncCacheBuilder::new(BLOCK_CACHE_SIZE, MAX_CACHE_COST)
+            .set_ignore_internal_cost(true)
+            .finalize(tokio::spawn)
+            .expect("failed to create cache");
+        Self { source, cache }
+    }
+}
+
+#[async_trait]
+impl BlockSource for CachedBlockSource {
+    async fn check(&self) -> Result<(), IngesterError> {
+        self.source.check().await
+    }
+
+    async fn get_blocks(
+        &self,
+        start_slot: Slot,
+        end_slot: Slot,
+    ) -> Result<Vec<Slot>, IngesterError> {
+        self.source.get_blocks(start_slot, end_slot).await
+    }
+
+    async fn get_block(&self, slot: Slot) -> Result<Arc<EncodedConfirmedBlock>, IngesterError> {
+        let key = format!("block{}", slot);
+        if let Some(cached) = self.cache.get(&key) {
+            return Ok(cached.value().clone());
+        }
+        let block = self.source.get_block(slot).await?;
+        let cost = cmp::min(MAX_CACHE_COST, block.transactions.len() as i64);
+        // A block the cache turns down is still ingested, it is only fetched again next time.
+        self.cache
+            .try_insert_with_ttl(
+                key,
+                block.clone(),
+                cost,
+                Duration::from_secs(BLOCK_CACHE_DURATION),
+            )
+            .await?;
+        self.cache.wait().await?;
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock_rpc::mock_rpc, rand_string};
+    use serde_json::json;
+    use solana_sdk::{
+        hash::Hash, pubkey::Pubkey, signature::Keypair, signer::Signer, system_transaction,
+    };
+    use solana_transaction_status::EncodedTransaction;
+    use std::sync::atomic::AtomicU64;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("das-blocks-{}", rand_string()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn stored_block(transaction: VersionedTransaction) -> StoredBlock {
+        StoredBlock {
+            previous_blockhash: Hash::new_unique().to_string(),
+            blockhash: Hash::new_unique().to_string(),
+            parent_slot: 11,
+            transactions: vec![StoredTransaction {
+                transaction,
+                err: None,
+                inner_instructions: Some(Vec::new()),
+            }],
+            block_time: None,
+            block_height: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_json_and_bincode_blocks_from_a_directory() {
+        let dir = temp_dir();
+        let json_block = json!({
+            "previousBlockhash": Hash::new_unique().to_string(),
+            "blockhash": Hash::new_unique().to_string(),
+            "parentSlot": 9,
+            "transactions": [],
+            "blockTime": null,
+            "blockHeight": null,
+        });
+        std::fs::write(dir.join("10.json"), json_block.to_string()).unwrap();
+        let transaction = VersionedTransaction::from(system_transaction::transfer(
+            &Keypair::new(),
+            &Pubkey::new_unique(),
+            1,
+            Hash::default(),
+        ));
+        let signature = transaction.signatures[0].to_string();
+        let bin = bincode::serialize(&stored_block(transaction)).unwrap();
+        std::fs::write(dir.join("12.bin"), bin).unwrap();
+        std::fs::write(dir.join("README"), "").unwrap();
+        let source = DirBlockSource::new(&dir).unwrap();
+
+        assert_eq!(source.get_blocks(10, 12).await.unwrap(), vec![10, 12]);
+        assert_eq!(source.get_blocks(11, 20).await.unwrap(), vec![12]);
+        assert!(source.get_blocks(13, 11).await.unwrap().is_empty());
+        assert_eq!(source.get_block(10).await.unwrap().parent_slot, 9);
+        let block = source.get_block(12).await.unwrap();
+        match &block.transactions[0].transaction {
+            EncodedTransaction::Json(tx) => assert_eq!(tx.signatures, vec![signature]),
+            other => panic!("Unexpected encoding {:?}", other),
+        }
+        assert!(source.get_block(11).await.is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn fails_over_to_the_next_endpoint() {
+        // Nothing listens on the port of a dropped listener.
+        let down = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let down_url = format!("http://{}", down.local_addr().unwrap());
+        drop(down);
+        let up_url = mock_rpc(|method, params| {
+            assert_eq!(method, "getBlocks");
+            json!([params[0], params[1]])
+        });
+        let pool = RpcPoolBlockSource::new(vec![
+            RpcBlockSource::new(down_url, CommitmentConfig::finalized(), None),
+            RpcBlockSource::new(up_url, CommitmentConfig::finalized(), Some(100)),
+        ]);
+
+        // Each request starts with the next endpoint and tries the other one if it fails.
+        assert_eq!(pool.get_blocks(5, 6).await.unwrap(), vec![5, 6]);
+        assert_eq!(pool.get_blocks(7, 8).await.unwrap(), vec![7, 8]);
+    }
+
+    #[tokio::test]
+    async fn endpoint_urls_are_kept_out_of_errors() {
+        // Nothing listens on the port of a dropped listener.
+        let down = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/?api-key=secret", down.local_addr().unwrap());
+        drop(down);
+        let endpoint = RpcBlockSource::new(url, CommitmentConfig::finalized(), None);
+
+        assert_eq!(endpoint.host, "127.0.0.1");
+        let err = endpoint.get_block(5).await.unwrap_err();
+        assert!(!err.to_string().contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn rate_limited_endpoints_space_out_requests() {
+        let limiter = RateLimiter::new(20);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.wait().await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    /// A source counting the blocks fetched from it.
+    struct CountingSource {
+        fetched: Arc<AtomicU64>,
+    }
+
+    #[async_trait]
+    impl BlockSource for CountingSource {
+        async fn check(&self) -> Result<(), IngesterError> {
+            Ok(())
+        }
+
+        async fn get_blocks(
+            &self,
+            start_slot: Slot,
+            end_slot: Slot,
+        ) -> Result<Vec<Slot>, IngesterError> {
+            Ok((start_slot..=end_slot).collect())
+        }
+
+        async fn get_block(&self, slot: Slot) -> Result<Arc<EncodedConfirmedBlock>, IngesterError> {
+            self.fetched.fetch_add(1, Ordering::SeqCst);
+            Ok(Arc::new(EncodedConfirmedBlock {
+                previous_blockhash: Hash::default().to_string(),
+                blockhash: Hash::default().to_string(),
+                parent_slot: slot - 1,
+                transactions: Vec::new(),
+                rewards: Vec::new(),
+                block_time: None,
+                block_height: None,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn cached_blocks_are_fetched_once() {
+        let fetched = Arc::new(AtomicU64::new(0));
+        let source = CachedBlockSource::new(Box::new(CountingSource {
+            fetched: fetched.clone(),
+        }));
+
+        assert_eq!(source.get_block(7).await.unwrap().parent_slot, 6);
+        assert_eq!(source.get_block(7).await.unwrap().parent_slot, 6);
+        assert_eq!(fetched.load(Ordering::SeqCst), 1);
+    }
+}
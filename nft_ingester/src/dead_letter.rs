@@ -0,0 +1,240 @@
+//! Messages that can't be processed.  A message that fails on its last delivery, or whose
+//! flatbuffer can't be read at all, is moved to `dead_letters` and acknowledged instead of
+//! being delivered again.  `nft_ingester dead-letters` lets an operator look at them, send
+//! them back to their stream once the cause is fixed, or drop them.
+use crate::{error::IngesterError, metrics::safe_metric};
+use cadence_macros::statsd_count;
+use chrono::Utc;
+use digital_asset_types::dao::dead_letters;
+use plerkle_messenger::{Messenger, MessengerConfig, ACCOUNT_STREAM, TRANSACTION_STREAM};
+use sea_orm::{entity::*, query::*, DatabaseConnection, EntityTrait};
+
+pub const DEFAULT_MAX_DELIVERIES: usize = 10;
+
+/// A message that failed, with what is known of the cause.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeadLetter {
+    pub stream: String,
+    pub message_id: String,
+    pub payload: Vec<u8>,
+    pub program_id: Option<Vec<u8>>,
+    pub signature: Option<String>,
+    pub account: Option<Vec<u8>>,
+    pub error: String,
+    pub deliveries: usize,
+}
+
+pub struct DeadLetterQueue {
+    db: DatabaseConnection,
+    max_deliveries: usize,
+}
+
+impl DeadLetterQueue {
+    pub fn new(db: DatabaseConnection, max_deliveries: usize) -> Self {
+        DeadLetterQueue { db, max_deliveries }
+    }
+
+    /// Whether a message delivered `tries` times before is delivered for the last time.
+    pub fn is_last_delivery(&self, tries: usize) -> bool {
+        tries + 1 >= self.max_deliveries
+    }
+
+    pub async fn push(&self, letter: DeadLetter) -> Result<(), IngesterError> {
+        let stream = letter.stream.clone();
+        let model = dead_letters::ActiveModel {
+            stream: Set(letter.stream),
+            message_id: Set(letter.message_id),
+            payload: Set(letter.payload),
+            program_id: Set(letter.program_id),
+            signature: Set(letter.signature),
+            account: Set(letter.account),
+            error: Set(letter.error),
+            deliveries: Set(letter.deliveries as i32),
+            created_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        };
+        model.insert(&self.db).await?;
+        safe_metric(|| {
+            statsd_count!("ingester.dead_letter", 1, "stream" => &stream);
+        });
+        Ok(())
+    }
+}
+
+fn stream_key(stream: &str) -> Option<&'static str> {
+    match stream {
+        TRANSACTION_STREAM => Some(TRANSACTION_STREAM),
+        ACCOUNT_STREAM => Some(ACCOUNT_STREAM),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Selection {
+    ids: Vec<i64>,
+    stream: Option<String>,
+    all: bool,
+}
+
+fn parse_selection(args: &[String]) -> Result<Selection, IngesterError> {
+    let mut selection = Selection::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--all" => selection.all = true,
+            "--stream" => {
+                let stream = args.next().ok_or(IngesterError::ConfigurationError {
+                    msg: "--stream needs a stream".to_string(),
+                })?;
+                selection.stream = Some(stream.clone());
+            }
+            id => {
+                selection
+                    .ids
+                    .push(id.parse().map_err(|_| IngesterError::ConfigurationError {
+                        msg: format!("Invalid dead letter id: {}", id),
+                    })?)
+            }
+        }
+    }
+    Ok(selection)
+}
+
+impl Selection {
+    fn is_empty(&self) -> bool {
+        self.ids.is_empty() && self.stream.is_none() && !self.all
+    }
+
+    fn condition(&self) -> Condition {
+        let mut condition = Condition::all();
+        if !self.ids.is_empty() {
+            condition = condition.add(dead_letters::Column::Id.is_in(self.ids.clone()));
+        }
+        if let Some(stream) = &self.stream {
+            condition = condition.add(dead_letters::Column::Stream.eq(stream.clone()));
+        }
+        condition
+    }
+}
+
+/// `dead-letters list|replay|purge [ID...] [--stream STREAM] [--all]`.  Listing shows every
+/// dead letter by default, replaying and purging need the letters to be selected.
+pub async fn run_dead_letter_command<T: Messenger>(
+    db: &DatabaseConnection,
+    messenger_config: MessengerConfig,
+    args: &[String],
+) -> Result<(), IngesterError> {
+    let command = args.first().map(String::as_str).unwrap_or("list");
+    if !["list", "replay", "purge"].contains(&command) {
+        return Err(IngesterError::ConfigurationError {
+            msg: format!("Unknown dead letter command: {}", command),
+        });
+    }
+    let selection = parse_selection(args.get(1..).unwrap_or_default())?;
+    if command != "list" && selection.is_empty() {
+        return Err(IngesterError::ConfigurationError {
+            msg: format!("Select the dead letters to {} or pass --all", command),
+        });
+    }
+    let letters = dead_letters::Entity::find()
+        .filter(selection.condition())
+        .order_by_asc(dead_letters::Column::Id)
+        .all(db)
+        .await?;
+
+    match command {
+        "list" => {
+            for letter in letters.iter() {
+                println!(
+                    "{} {} {} deliveries={} program={} signature={} account={} error={}",
+                    letter.id,
+                    letter.created_at,
+                    letter.stream,
+                    letter.deliveries,
+                    letter
+                        .program_id
+                        .as_ref()
+                        .map(|p| bs58::encode(p).into_string())
+                        .unwrap_or_default(),
+                    letter.signature.clone().unwrap_or_default(),
+                    letter
+                        .account
+                        .as_ref()
+                        .map(|a| bs58::encode(a).into_string())
+                        .unwrap_or_default(),
+                    letter.error,
+                );
+            }
+            println!("{} dead letters", letters.len());
+        }
+        "replay" => {
+            let mut messenger = T::new(messenger_config).await?;
+            messenger.add_stream(TRANSACTION_STREAM).await?;
+            messenger.add_stream(ACCOUNT_STREAM).await?;
+            let mut replayed = 0;
+            for letter in letters.iter() {
+                let stream = match stream_key(&letter.stream) {
+                    Some(stream) => stream,
+                    None => {
+                        println!(
+                            "Dead letter {} has unknown stream {}",
+                            letter.id, letter.stream
+                        );
+                        continue;
+                    }
+                };
+                messenger.send(stream, &letter.payload).await?;
+                dead_letters::Entity::delete_by_id(letter.id)
+                    .exec(db)
+                    .await?;
+                replayed += 1;
+            }
+            println!("Replayed {} dead letters", replayed);
+        }
+        // purge
+        _ => {
+            let ids: Vec<i64> = letters.iter().map(|l| l.id).collect();
+            let res = dead_letters::Entity::delete_many()
+                .filter(dead_letters::Column::Id.is_in(ids))
+                .exec(db)
+                .await?;
+            println!("Purged {} dead letters", res.rows_affected);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DatabaseBackend, MockDatabase};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn counts_deliveries() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let queue = DeadLetterQueue::new(db, 3);
+        assert!(!queue.is_last_delivery(0));
+        assert!(!queue.is_last_delivery(1));
+        assert!(queue.is_last_delivery(2));
+    }
+
+    #[test]
+    fn parses_selections() {
+        assert_eq!(
+            parse_selection(&args(&["4", "--stream", "TXN", "7"])).unwrap(),
+            Selection {
+                ids: vec![4, 7],
+                stream: Some("TXN".to_string()),
+                all: false,
+            }
+        );
+        assert!(parse_selection(&args(&[])).unwrap().is_empty());
+        assert!(!parse_selection(&args(&["--all"])).unwrap().is_empty());
+        assert!(parse_selection(&args(&["--stream"])).is_err());
+        assert!(parse_selection(&args(&["four"])).is_err());
+    }
+}
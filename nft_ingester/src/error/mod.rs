@@ -102,3 +102,9 @@ impl From<MessengerError> for IngesterError {
         IngesterError::MessengerError(e.to_string())
     }
 }
+
+impl From<redis::RedisError> for IngesterError {
+    fn from(e: redis::RedisError) -> Self {
+        IngesterError::MessengerError(e.to_string())
+    }
+}
@@ -0,0 +1,703 @@
+//! A Geyser gRPC stream as the source of the ingester, in place of the Redis streams the
+//! validator plugin writes to.  `GrpcMessenger` subscribes to the accounts and transactions of
+//! the programs the ingester handles, and to slot notifications, and hands them over as the
+//! same flatbuffers the plugin sends.  The stream has no acknowledgements, updates that fail to
+//! be processed aren't delivered again.
+use crate::{error::IngesterError, metrics::safe_metric};
+use async_trait::async_trait;
+use cadence_macros::statsd_count;
+use chrono::Utc;
+use flatbuffers::{FlatBufferBuilder, WIPOffset};
+use futures::{channel::mpsc, lock::Mutex, FutureExt};
+use plerkle_messenger::{
+    Messenger, MessengerConfig, MessengerError, MessengerType, RecvData, ACCOUNT_STREAM,
+    SLOT_STREAM, TRANSACTION_STREAM,
+};
+use plerkle_serialization::{
+    AccountInfo, AccountInfoArgs, CompiledInstruction, CompiledInstructionArgs, InnerInstructions,
+    InnerInstructionsArgs, Pubkey as FBPubkey, SlotStatusInfo, SlotStatusInfoArgs, Status,
+    TransactionInfo, TransactionInfoArgs,
+};
+use std::{collections::HashMap, time::Duration};
+use tokio::time::sleep;
+use tonic::{
+    metadata::{Ascii, MetadataValue},
+    transport::{ClientTlsConfig, Endpoint},
+    Request, Streaming,
+};
+use yellowstone_grpc_proto::prelude::{
+    self as geyser, geyser_client::GeyserClient, subscribe_update::UpdateOneof,
+};
+
+pub const GRPC_ENDPOINT_KEY: &str = "grpc_endpoint";
+pub const GRPC_X_TOKEN_KEY: &str = "grpc_x_token";
+// Comma separated program ids, set from the enabled program handlers.
+pub const GRPC_PROGRAMS_KEY: &str = "grpc_programs";
+
+const GRPC_BATCH_SIZE: usize = 100;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const FILTER_NAME: &str = "das";
+
+struct Subscription {
+    // The subscription lasts as long as its request stream is open.
+    _requests: mpsc::UnboundedSender<geyser::SubscribeRequest>,
+    // Messengers are shared between threads and the stream isn't `Sync`, it is only ever read
+    // through `get_mut` though.
+    updates: Mutex<Streaming<geyser::SubscribeUpdate>>,
+}
+
+/// Reads the updates of the Geyser gRPC endpoint under `grpc_endpoint` in the connection
+/// config, authenticated with `grpc_x_token` if set.  Each stream is a subscription of its
+/// own, made on its first read and made again after it fails.
+pub struct GrpcMessenger {
+    endpoint: String,
+    x_token: Option<String>,
+    programs: Vec<String>,
+    subscriptions: HashMap<&'static str, Subscription>,
+    received: u64,
+}
+
+impl GrpcMessenger {
+    fn subscribe_request(
+        &self,
+        stream_key: &str,
+    ) -> Result<geyser::SubscribeRequest, MessengerError> {
+        let mut request = geyser::SubscribeRequest::default();
+        match stream_key {
+            ACCOUNT_STREAM => {
+                let filter = geyser::SubscribeRequestFilterAccounts {
+                    owner: self.programs.clone(),
+                    ..Default::default()
+                };
+                request.accounts.insert(FILTER_NAME.to_string(), filter);
+            }
+            TRANSACTION_STREAM => {
+                let filter = geyser::SubscribeRequestFilterTransactions {
+                    vote: Some(false),
+                    failed: Some(false),
+                    account_include: self.programs.clone(),
+                    ..Default::default()
+                };
+                request.transactions.insert(FILTER_NAME.to_string(), filter);
+            }
+            SLOT_STREAM => {
+                request.slots.insert(
+                    FILTER_NAME.to_string(),
+                    geyser::SubscribeRequestFilterSlots::default(),
+                );
+            }
+            _ => {
+                return Err(MessengerError::ConfigurationError {
+                    msg: format!("Unknown stream: {}", stream_key),
+                })
+            }
+        }
+        Ok(request)
+    }
+
+    // The interceptor has to fail with a `tonic::Status`.
+    #[allow(clippy::result_large_err)]
+    async fn subscribe(&self, stream_key: &str) -> Result<Subscription, MessengerError> {
+        let connection_error = |err: String| MessengerError::ConnectionError { msg: err };
+        let request = self.subscribe_request(stream_key)?;
+        let mut endpoint = Endpoint::from_shared(self.endpoint.clone())
+            .map_err(|e| connection_error(e.to_string()))?;
+        if self.endpoint.starts_with("https") {
+            endpoint = endpoint
+                .tls_config(ClientTlsConfig::new())
+                .map_err(|e| connection_error(e.to_string()))?;
+        }
+        let channel = endpoint
+            .connect()
+            .await
+            .map_err(|e| connection_error(e.to_string()))?;
+        let x_token: Option<MetadataValue<Ascii>> = self
+            .x_token
+            .as_ref()
+            .map(|t| t.parse())
+            .transpose()
+            .map_err(|_| MessengerError::ConfigurationError {
+                msg: format!("Invalid {}", GRPC_X_TOKEN_KEY),
+            })?;
+        let mut client = GeyserClient::with_interceptor(channel, move |mut req: Request<()>| {
+            if let Some(x_token) = x_token.clone() {
+                req.metadata_mut().insert("x-token", x_token);
+            }
+            Ok(req)
+        });
+
+        let (requests, receiver) = mpsc::unbounded();
+        requests
+            .unbounded_send(request)
+            .map_err(|e| connection_error(e.to_string()))?;
+        let updates = client
+            .subscribe(receiver)
+            .await
+            .map_err(|e| connection_error(e.to_string()))?
+            .into_inner();
+        Ok(Subscription {
+            _requests: requests,
+            updates: Mutex::new(updates),
+        })
+    }
+
+    fn recv_data(&mut self, update: geyser::SubscribeUpdate) -> Option<RecvData> {
+        let data = match update.update_oneof? {
+            UpdateOneof::Account(account) => serialize_account(&account),
+            UpdateOneof::Transaction(transaction) => serialize_transaction(&transaction),
+            UpdateOneof::Slot(slot) => serialize_slot(&slot),
+            // Pings and the updates of other filters.
+            _ => return None,
+        };
+        match data {
+            Ok(data) => {
+                self.received += 1;
+                Some(RecvData {
+                    id: self.received.to_string(),
+                    tries: 0,
+                    data,
+                })
+            }
+            Err(err) => {
+                println!("Error serializing gRPC update: {}", err);
+                safe_metric(|| {
+                    statsd_count!("ingester.grpc_serialization_error", 1);
+                });
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Messenger for GrpcMessenger {
+    async fn new(config: MessengerConfig) -> Result<Self, MessengerError> {
+        let get = |key: &str| {
+            config
+                .connection_config
+                .get(key)
+                .and_then(|v| v.clone().into_string())
+        };
+        let endpoint = get(GRPC_ENDPOINT_KEY).ok_or(MessengerError::ConfigurationError {
+            msg: format!("gRPC endpoint missing: {}", GRPC_ENDPOINT_KEY),
+        })?;
+        let programs = get(GRPC_PROGRAMS_KEY)
+            .map(|p| p.split(',').map(str::to_string).collect())
+            .unwrap_or_default();
+        Ok(GrpcMessenger {
+            endpoint,
+            x_token: get(GRPC_X_TOKEN_KEY),
+            programs,
+            subscriptions: HashMap::new(),
+            received: 0,
+        })
+    }
+
+    fn messenger_type(&self) -> MessengerType {
+        MessengerType::Invalid
+    }
+
+    async fn add_stream(&mut self, _stream_key: &'static str) -> Result<(), MessengerError> {
+        Ok(())
+    }
+
+    async fn set_buffer_size(&mut self, _stream_key: &'static str, _max_buffer_size: usize) {}
+
+    async fn send(
+        &mut self,
+        _stream_key: &'static str,
+        _bytes: &[u8],
+    ) -> Result<(), MessengerError> {
+        Err(MessengerError::SendError {
+            msg: "Geyser gRPC streams are read only".to_string(),
+        })
+    }
+
+    async fn recv(&mut self, stream_key: &'static str) -> Result<Vec<RecvData>, MessengerError> {
+        if !self.subscriptions.contains_key(stream_key) {
+            match self.subscribe(stream_key).await {
+                Ok(subscription) => {
+                    self.subscriptions.insert(stream_key, subscription);
+                }
+                Err(err) => {
+                    sleep(RECONNECT_DELAY).await;
+                    return Err(err);
+                }
+            }
+        }
+        let stream = self
+            .subscriptions
+            .get_mut(stream_key)
+            .unwrap()
+            .updates
+            .get_mut();
+        let mut updates = Vec::new();
+        let ended = match stream.message().await {
+            Ok(Some(update)) => {
+                updates.push(update);
+                None
+            }
+            Ok(None) => Some("gRPC subscription ended".to_string()),
+            Err(status) => Some(status.to_string()),
+        };
+        if let Some(msg) = ended {
+            self.subscriptions.remove(stream_key);
+            return Err(MessengerError::ReceiveError { msg });
+        }
+        // Batch the updates that already arrived, a failure shows on the next read.
+        while updates.len() < GRPC_BATCH_SIZE {
+            match stream.message().now_or_never() {
+                Some(Ok(Some(update))) => updates.push(update),
+                _ => break,
+            }
+        }
+        Ok(updates
+            .into_iter()
+            .filter_map(|u| self.recv_data(u))
+            .collect())
+    }
+
+    async fn stream_size(&mut self, _stream_key: &'static str) -> Result<u64, MessengerError> {
+        Ok(0)
+    }
+
+    async fn ack_msg(
+        &mut self,
+        _stream_key: &'static str,
+        _ids: &[String],
+    ) -> Result<(), MessengerError> {
+        Ok(())
+    }
+}
+
+fn fb_pubkey(key: &[u8]) -> Result<FBPubkey, IngesterError> {
+    <[u8; 32]>::try_from(key)
+        .map(FBPubkey)
+        .map_err(|_| IngesterError::SerializatonError(format!("Invalid pubkey {:?}", key)))
+}
+
+fn missing(field: &str) -> IngesterError {
+    IngesterError::SerializatonError(format!("gRPC update without {}", field))
+}
+
+pub fn serialize_account(
+    update: &geyser::SubscribeUpdateAccount,
+) -> Result<Vec<u8>, IngesterError> {
+    let account = update.account.as_ref().ok_or_else(|| missing("account"))?;
+    let pubkey = fb_pubkey(&account.pubkey)?;
+    let owner = fb_pubkey(&account.owner)?;
+
+    let mut builder = FlatBufferBuilder::new();
+    let data = Some(builder.create_vector(&account.data));
+    let account_info = AccountInfo::create(
+        &mut builder,
+        &AccountInfoArgs {
+            pubkey: Some(&pubkey),
+            lamports: account.lamports,
+            owner: Some(&owner),
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            data,
+            write_version: account.write_version,
+            slot: update.slot,
+            is_startup: update.is_startup,
+            seen_at: Utc::now().timestamp_millis(),
+        },
+    );
+    builder.finish(account_info, None);
+    Ok(builder.finished_data().to_vec())
+}
+
+/// A slot notification, the finalized commitment being the rooted status of the plugin.
+pub fn serialize_slot(update: &geyser::SubscribeUpdateSlot) -> Result<Vec<u8>, IngesterError> {
+    let status = match geyser::CommitmentLevel::from_i32(update.status) {
+        Some(geyser::CommitmentLevel::Processed) => Status::Processed,
+        Some(geyser::CommitmentLevel::Confirmed) => Status::Confirmed,
+        Some(geyser::CommitmentLevel::Finalized) => Status::Rooted,
+        None => {
+            return Err(IngesterError::SerializatonError(format!(
+                "Unknown slot status {}",
+                update.status
+            )))
+        }
+    };
+    let mut builder = FlatBufferBuilder::new();
+    let slot_status = SlotStatusInfo::create(
+        &mut builder,
+        &SlotStatusInfoArgs {
+            slot: update.slot,
+            parent: update.parent,
+            status,
+            seen_at: Utc::now().timestamp_millis(),
+        },
+    );
+    builder.finish(slot_status, None);
+    Ok(builder.finished_data().to_vec())
+}
+
+fn compiled_instruction<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    program_id_index: u32,
+    accounts: &[u8],
+    data: &[u8],
+) -> WIPOffset<CompiledInstruction<'a>> {
+    let accounts = Some(builder.create_vector(accounts));
+    let data = Some(builder.create_vector(data));
+    CompiledInstruction::create(
+        builder,
+        &CompiledInstructionArgs {
+            program_id_index: program_id_index as u8,
+            accounts,
+            data,
+        },
+    )
+}
+
+pub fn serialize_transaction(
+    update: &geyser::SubscribeUpdateTransaction,
+) -> Result<Vec<u8>, IngesterError> {
+    let info = update
+        .transaction
+        .as_ref()
+        .ok_or_else(|| missing("transaction"))?;
+    let message = info
+        .transaction
+        .as_ref()
+        .and_then(|t| t.message.as_ref())
+        .ok_or_else(|| missing("message"))?;
+    let meta = info.meta.as_ref().ok_or_else(|| missing("meta"))?;
+    let mut builder = FlatBufferBuilder::new();
+
+    // Keys loaded from address lookup tables follow the keys of the message.
+    let account_keys = message
+        .account_keys
+        .iter()
+        .chain(meta.loaded_writable_addresses.iter())
+        .chain(meta.loaded_readonly_addresses.iter())
+        .map(|k| fb_pubkey(k))
+        .collect::<Result<Vec<_>, _>>()?;
+    let account_keys = Some(builder.create_vector(&account_keys));
+
+    let mut outer_instructions = Vec::with_capacity(message.instructions.len());
+    for ix in message.instructions.iter() {
+        outer_instructions.push(compiled_instruction(
+            &mut builder,
+            ix.program_id_index,
+            &ix.accounts,
+            &ix.data,
+        ));
+    }
+    let outer_instructions = Some(builder.create_vector(&outer_instructions));
+
+    let inner_instructions = if meta.inner_instructions_none {
+        None
+    } else {
+        let mut overall_fb_vec = Vec::with_capacity(meta.inner_instructions.len());
+        for inner_instructions in meta.inner_instructions.iter() {
+            let mut instructions_fb_vec = Vec::with_capacity(inner_instructions.instructions.len());
+            for ix in inner_instructions.instructions.iter() {
+                instructions_fb_vec.push(compiled_instruction(
+                    &mut builder,
+                    ix.program_id_index,
+                    &ix.accounts,
+                    &ix.data,
+                ));
+            }
+            let instructions = Some(builder.create_vector(&instructions_fb_vec));
+            overall_fb_vec.push(InnerInstructions::create(
+                &mut builder,
+                &InnerInstructionsArgs {
+                    index: inner_instructions.index as u8,
+                    instructions,
+                },
+            ));
+        }
+        Some(builder.create_vector(&overall_fb_vec))
+    };
+
+    let signature = builder.create_string(&bs58::encode(&info.signature).into_string());
+    let slot_index = builder.create_string(&format!("{}-{}", update.slot, info.index));
+    let transaction_info = TransactionInfo::create(
+        &mut builder,
+        &TransactionInfoArgs {
+            is_vote: info.is_vote,
+            account_keys,
+            // We dont use them for now.
+            log_messages: None,
+            inner_instructions,
+            outer_instructions,
+            slot: update.slot,
+            seen_at: Utc::now().timestamp_millis(),
+            slot_index: Some(slot_index),
+            signature: Some(signature),
+        },
+    );
+    builder.finish(transaction_info, None);
+    Ok(builder.finished_data().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use figment::value::{Dict, Value};
+    use futures::{stream, Stream, StreamExt};
+    use plerkle_serialization::{
+        root_as_account_info, root_as_slot_status_info, root_as_transaction_info,
+    };
+    use solana_sdk::pubkey::Pubkey;
+    use std::{
+        pin::Pin,
+        sync::{Arc, Mutex},
+    };
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{transport::Server, Response, Status};
+    use yellowstone_grpc_proto::prelude::geyser_server::{Geyser, GeyserServer};
+
+    /// Answers every subscription with `updates` and keeps it open.
+    #[derive(Clone, Default)]
+    struct MockGeyser {
+        requests: Arc<Mutex<Vec<geyser::SubscribeRequest>>>,
+        updates: Vec<geyser::SubscribeUpdate>,
+    }
+
+    #[tonic::async_trait]
+    impl Geyser for MockGeyser {
+        type SubscribeStream =
+            Pin<Box<dyn Stream<Item = Result<geyser::SubscribeUpdate, Status>> + Send>>;
+
+        async fn subscribe(
+            &self,
+            request: Request<Streaming<geyser::SubscribeRequest>>,
+        ) -> Result<Response<Self::SubscribeStream>, Status> {
+            let mut requests = request.into_inner();
+            if let Some(request) = requests.message().await? {
+                self.requests.lock().unwrap().push(request);
+            }
+            let updates = stream::iter(self.updates.clone().into_iter().map(Ok));
+            Ok(Response::new(Box::pin(updates.chain(stream::pending()))))
+        }
+
+        async fn ping(
+            &self,
+            request: Request<geyser::PingRequest>,
+        ) -> Result<Response<geyser::PongResponse>, Status> {
+            Ok(Response::new(geyser::PongResponse {
+                count: request.into_inner().count,
+            }))
+        }
+
+        async fn get_latest_blockhash(
+            &self,
+            _request: Request<geyser::GetLatestBlockhashRequest>,
+        ) -> Result<Response<geyser::GetLatestBlockhashResponse>, Status> {
+            Err(Status::unimplemented("get_latest_blockhash"))
+        }
+
+        async fn get_block_height(
+            &self,
+            _request: Request<geyser::GetBlockHeightRequest>,
+        ) -> Result<Response<geyser::GetBlockHeightResponse>, Status> {
+            Err(Status::unimplemented("get_block_height"))
+        }
+
+        async fn get_slot(
+            &self,
+            _request: Request<geyser::GetSlotRequest>,
+        ) -> Result<Response<geyser::GetSlotResponse>, Status> {
+            Err(Status::unimplemented("get_slot"))
+        }
+
+        async fn is_blockhash_valid(
+            &self,
+            _request: Request<geyser::IsBlockhashValidRequest>,
+        ) -> Result<Response<geyser::IsBlockhashValidResponse>, Status> {
+            Err(Status::unimplemented("is_blockhash_valid"))
+        }
+
+        async fn get_version(
+            &self,
+            _request: Request<geyser::GetVersionRequest>,
+        ) -> Result<Response<geyser::GetVersionResponse>, Status> {
+            Err(Status::unimplemented("get_version"))
+        }
+    }
+
+    async fn connect(geyser: MockGeyser, programs: &[Pubkey]) -> GrpcMessenger {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            Server::builder()
+                .add_service(GeyserServer::new(geyser))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let programs: Vec<String> = programs.iter().map(|p| p.to_string()).collect();
+        let mut connection_config = Dict::new();
+        connection_config.insert(GRPC_ENDPOINT_KEY.to_string(), Value::from(endpoint));
+        connection_config.insert(
+            GRPC_PROGRAMS_KEY.to_string(),
+            Value::from(programs.join(",")),
+        );
+        GrpcMessenger::new(MessengerConfig {
+            messenger_type: MessengerType::Invalid,
+            connection_config,
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn streams_the_accounts_of_the_programs() {
+        let programs = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let key = Pubkey::new_unique();
+        let account = geyser::SubscribeUpdateAccount {
+            account: Some(geyser::SubscribeUpdateAccountInfo {
+                pubkey: key.to_bytes().to_vec(),
+                lamports: 42,
+                owner: programs[0].to_bytes().to_vec(),
+                data: vec![1, 2, 3],
+                write_version: 7,
+                ..Default::default()
+            }),
+            slot: 100,
+            is_startup: false,
+        };
+        let geyser = MockGeyser {
+            updates: vec![
+                geyser::SubscribeUpdate {
+                    filters: vec![FILTER_NAME.to_string()],
+                    update_oneof: Some(UpdateOneof::Account(account)),
+                },
+                geyser::SubscribeUpdate {
+                    filters: vec![],
+                    update_oneof: Some(UpdateOneof::Ping(geyser::SubscribeUpdatePing {})),
+                },
+            ],
+            ..Default::default()
+        };
+        let requests = geyser.requests.clone();
+        let mut messenger = connect(geyser, &programs).await;
+
+        let data = messenger.recv(ACCOUNT_STREAM).await.unwrap();
+        assert_eq!(data.len(), 1);
+        let account = root_as_account_info(&data[0].data).unwrap();
+        assert_eq!(account.pubkey().unwrap().0, key.to_bytes());
+        assert_eq!(account.owner().unwrap().0, programs[0].to_bytes());
+        assert_eq!(account.lamports(), 42);
+        assert_eq!(account.data().unwrap().bytes(), &[1, 2, 3]);
+        assert_eq!(account.slot(), 100);
+
+        let request = requests.lock().unwrap()[0].clone();
+        let filter = &request.accounts[FILTER_NAME];
+        assert_eq!(
+            filter.owner,
+            vec![programs[0].to_string(), programs[1].to_string()]
+        );
+        assert!(request.transactions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn streams_the_transactions_of_the_programs() {
+        let program = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let looked_up = Pubkey::new_unique();
+        let transaction = geyser::SubscribeUpdateTransaction {
+            transaction: Some(geyser::SubscribeUpdateTransactionInfo {
+                signature: vec![9; 64],
+                is_vote: false,
+                transaction: Some(geyser::Transaction {
+                    signatures: vec![vec![9; 64]],
+                    message: Some(geyser::Message {
+                        account_keys: vec![payer.to_bytes().to_vec(), program.to_bytes().to_vec()],
+                        instructions: vec![geyser::CompiledInstruction {
+                            program_id_index: 1,
+                            accounts: vec![0, 2],
+                            data: vec![5],
+                        }],
+                        ..Default::default()
+                    }),
+                }),
+                meta: Some(geyser::TransactionStatusMeta {
+                    inner_instructions: vec![geyser::InnerInstructions {
+                        index: 0,
+                        instructions: vec![geyser::InnerInstruction {
+                            program_id_index: 1,
+                            accounts: vec![2],
+                            data: vec![6],
+                            ..Default::default()
+                        }],
+                    }],
+                    loaded_readonly_addresses: vec![looked_up.to_bytes().to_vec()],
+                    ..Default::default()
+                }),
+                index: 3,
+            }),
+            slot: 200,
+        };
+        let geyser = MockGeyser {
+            updates: vec![geyser::SubscribeUpdate {
+                filters: vec![FILTER_NAME.to_string()],
+                update_oneof: Some(UpdateOneof::Transaction(transaction)),
+            }],
+            ..Default::default()
+        };
+        let requests = geyser.requests.clone();
+        let mut messenger = connect(geyser, &[program]).await;
+
+        let data = messenger.recv(TRANSACTION_STREAM).await.unwrap();
+        let tx = root_as_transaction_info(&data[0].data).unwrap();
+        let keys: Vec<[u8; 32]> = tx.account_keys().unwrap().iter().map(|k| k.0).collect();
+        assert_eq!(
+            keys,
+            vec![payer.to_bytes(), program.to_bytes(), looked_up.to_bytes()]
+        );
+        assert_eq!(
+            tx.signature(),
+            Some(bs58::encode([9; 64]).into_string().as_str())
+        );
+        assert_eq!(tx.slot(), 200);
+        assert_eq!(tx.slot_index(), Some("200-3"));
+        let outer = tx.outer_instructions().unwrap().get(0);
+        assert_eq!(outer.program_id_index(), 1);
+        assert_eq!(outer.accounts().unwrap().bytes(), &[0, 2]);
+        let inner = tx.inner_instructions().unwrap().get(0);
+        assert_eq!(
+            inner.instructions().unwrap().get(0).data().unwrap().bytes(),
+            &[6]
+        );
+
+        let request = requests.lock().unwrap()[0].clone();
+        let filter = &request.transactions[FILTER_NAME];
+        assert_eq!(filter.account_include, vec![program.to_string()]);
+        assert_eq!(filter.vote, Some(false));
+        assert!(request.accounts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn streams_slot_notifications() {
+        let geyser = MockGeyser {
+            updates: vec![geyser::SubscribeUpdate {
+                filters: vec![FILTER_NAME.to_string()],
+                update_oneof: Some(UpdateOneof::Slot(geyser::SubscribeUpdateSlot {
+                    slot: 201,
+                    parent: Some(200),
+                    status: geyser::CommitmentLevel::Finalized as i32,
+                })),
+            }],
+            ..Default::default()
+        };
+        let requests = geyser.requests.clone();
+        let mut messenger = connect(geyser, &[Pubkey::new_unique()]).await;
+
+        let data = messenger.recv(SLOT_STREAM).await.unwrap();
+        let slot = root_as_slot_status_info(&data[0].data).unwrap();
+        assert_eq!(slot.slot(), 201);
+        assert_eq!(slot.parent(), Some(200));
+        assert_eq!(slot.status(), plerkle_serialization::Status::Rooted);
+
+        let request = requests.lock().unwrap()[0].clone();
+        assert!(request.slots.contains_key(FILTER_NAME));
+        assert!(request.accounts.is_empty());
+    }
+}
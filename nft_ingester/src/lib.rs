@@ -0,0 +1,1327 @@
+//! The DAS ingester.  `start` runs it with the program handlers built into this crate plus any
+//! handlers registered by the crate embedding it.
+mod auditor;
+mod backfiller;
+mod block_source;
+pub mod dead_letter;
+pub mod error;
+pub mod geyser_grpc;
+mod metrics;
+#[cfg(test)]
+mod mock_rpc;
+pub mod pending;
+pub mod program_transformers;
+pub mod recording;
+mod scheduler;
+mod signature_crawl;
+mod slots;
+pub mod tasks;
+use crate::{
+    auditor::auditor,
+    backfiller::backfiller,
+    dead_letter::{run_dead_letter_command, DeadLetter, DeadLetterQueue, DEFAULT_MAX_DELIVERIES},
+    error::IngesterError,
+    geyser_grpc::{GrpcMessenger, GRPC_ENDPOINT_KEY, GRPC_PROGRAMS_KEY},
+    metrics::safe_metric,
+    pending::{pending_by_consumer, PendingClaimer, CLAIM_INTERVAL_KEY, CLAIM_MIN_IDLE_KEY},
+    program_transformers::{
+        default_handlers, AccountBatch, BeneficialOwnerResolver, ProgramHandler,
+        ProgramTransformer, DEFAULT_BATCH_LATENCY, DEFAULT_BATCH_SIZE,
+    },
+    recording::{
+        FileMessenger, RecordingMessenger, RECORD_DIR_KEY, REPLAY_DIR_KEY,
+        REPLAY_ORIGINAL_PACING_KEY,
+    },
+    scheduler::{run_partitioned, DEFAULT_PARALLELISM},
+    slots::{save_slot_status, slot_status},
+    tasks::{
+        common::{
+            leaf_check::{
+                check_leaves, decode_tree, CheckLeaves, CheckLeavesTask, LeafCheckConfig,
+                LeafCheckReport,
+            },
+            task::DownloadMetadataTask,
+        },
+        BgTask, IntoTaskData, TaskData, TaskManager,
+    },
+};
+use blockbuster::instruction::{order_instructions, InstructionBundle, IxPair};
+use cadence::{BufferedUdpMetricSink, QueuingMetricSink, StatsdClient};
+use cadence_macros::{set_global_default, statsd_count, statsd_gauge, statsd_time};
+use chrono::Utc;
+use figment::{providers::Env, value::Value, Figment};
+use futures::{future::BoxFuture, FutureExt};
+use plerkle_messenger::{
+    redis_messenger::RedisMessenger, Messenger, MessengerConfig, MessengerType, RecvData,
+    ACCOUNT_STREAM, SLOT_STREAM, TRANSACTION_STREAM,
+};
+use plerkle_serialization::{
+    root_as_account_info, root_as_slot_status_info, root_as_transaction_info, Pubkey as FBPubkey,
+};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use sea_orm::{DatabaseConnection, SqlxPostgresConnector};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use sqlx::{self, postgres::PgPoolOptions, Pool, Postgres};
+use std::fmt::{Display, Formatter};
+use std::net::UdpSocket;
+use tokio::{
+    sync::mpsc::{self, UnboundedSender},
+    task::{JoinHandle, JoinSet},
+    time,
+};
+
+// Types and constants used for Figment configuration items.
+pub type DatabaseConfig = figment::value::Dict;
+
+pub const DATABASE_URL_KEY: &str = "url";
+pub const DATABASE_LISTENER_CHANNEL_KEY: &str = "listener_channel";
+
+pub type RpcConfig = figment::value::Dict;
+
+pub const RPC_URL_KEY: &str = "url";
+pub const RPC_COMMITMENT_KEY: &str = "commitment";
+
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+pub enum IngesterRole {
+    All,
+    Auditor,
+    Backfiller,
+    BackgroundTaskRunner,
+    Ingester,
+}
+
+impl Display for IngesterRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IngesterRole::All => write!(f, "all"),
+            IngesterRole::Auditor => write!(f, "auditor"),
+            IngesterRole::Backfiller => write!(f, "backfiller"),
+            IngesterRole::BackgroundTaskRunner => write!(f, "background_task_runner"),
+            IngesterRole::Ingester => write!(f, "ingester"),
+        }
+    }
+}
+
+// Struct used for Figment configuration items.
+#[derive(Deserialize, PartialEq, Debug, Clone)]
+pub struct IngesterConfig {
+    pub database_config: DatabaseConfig,
+    pub messenger_config: MessengerConfig,
+    pub env: Option<String>,
+    pub rpc_config: RpcConfig,
+    pub metrics_port: Option<u16>,
+    pub metrics_host: Option<String>,
+    pub backfiller: Option<bool>,
+    pub role: Option<IngesterRole>,
+    pub max_postgres_connections: Option<u32>,
+    // Seconds between two audits of the indexed trees.
+    pub auditor_interval: Option<u64>,
+    // Names of the program handlers this deployment runs, all of them when unset.
+    pub program_handlers: Option<Vec<String>>,
+    // Escrow and staking programs whose vaults hold assets on behalf of the depositing wallet.
+    pub escrow_programs: Option<Vec<EscrowProgramConfig>>,
+    // Directory the messages read from the streams are recorded to.
+    pub record_dir: Option<String>,
+    // Recording ingested instead of the messenger streams.
+    pub replay: Option<ReplayConfig>,
+    // Deliveries of a message that keeps failing before it is dead lettered.
+    pub max_deliveries: Option<usize>,
+    // Consumer id in the Redis consumer groups, the host name by default.
+    pub consumer_id: Option<String>,
+    // Seconds a message stays pending before another consumer claims it.
+    pub claim_min_idle: Option<u64>,
+    // Seconds between two claims of pending messages.
+    pub claim_interval: Option<u64>,
+    // Partitions of a batch, trees or assets, processed at once.
+    pub parallelism: Option<usize>,
+    // Account updates written in bulk, each update is written on its own when unset.
+    pub account_batch: Option<AccountBatchConfig>,
+    // Slot notifications followed to take back the writes of abandoned forks.
+    pub track_slots: Option<bool>,
+    // Leaf checks of every tree enqueued as background tasks, none are when unset.
+    pub leaf_check: Option<LeafCheckConfig>,
+}
+
+/// An escrow or staking program holding deposited tokens in token accounts owned by a PDA.
+/// `seeds` derive the PDA, `$wallet` and `$mint` stand for the depositing wallet and the
+/// deposited mint, any other seed is taken as a literal string.
+#[derive(Deserialize, PartialEq, Debug, Clone)]
+pub struct EscrowProgramConfig {
+    pub program: String,
+    pub seeds: Vec<String>,
+}
+
+/// Token account and mint updates buffered and written with a multi-row upsert per table, once
+/// `max_size` messages are buffered or `max_latency_ms` after the first one.
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct AccountBatchConfig {
+    pub max_size: Option<usize>,
+    pub max_latency_ms: Option<u64>,
+}
+
+/// A recording made with `record_dir`, replayed as fast as it is ingested or, with
+/// `original_pacing`, at the pace it was recorded at.
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct ReplayConfig {
+    pub dir: String,
+    pub original_pacing: Option<bool>,
+}
+
+fn setup_metrics(config: &IngesterConfig) {
+    let uri = config.metrics_host.clone();
+    let port = config.metrics_port;
+    let env = config.env.clone().unwrap_or("dev".to_string());
+    if uri.is_some() || port.is_some() {
+        let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+        socket.set_nonblocking(true).unwrap();
+        let host = (uri.unwrap(), port.unwrap());
+        let udp_sink = BufferedUdpMetricSink::from(host, socket).unwrap();
+        let queuing_sink = QueuingMetricSink::from(udp_sink);
+
+        let builder = StatsdClient::builder("das_ingester", queuing_sink);
+        let client = builder.with_tag("env", env).build();
+        set_global_default(client);
+    }
+}
+
+fn rand_string() -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(30)
+        .map(char::from)
+        .collect()
+}
+
+/// Consumer id of this ingester in the Redis consumer groups.  A stable id keeps the pending
+/// entries of a restarted ingester under the same consumer, the pod name on Kubernetes.
+fn consumer_id(config: &IngesterConfig) -> String {
+    config
+        .consumer_id
+        .clone()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .unwrap_or_else(rand_string)
+}
+
+/// Handlers enabled by the configuration, out of the built in ones and `custom_handlers`.
+fn enabled_handlers(
+    config: &IngesterConfig,
+    custom_handlers: Vec<Arc<dyn ProgramHandler>>,
+) -> Result<Vec<Arc<dyn ProgramHandler>>, IngesterError> {
+    let resolver =
+        BeneficialOwnerResolver::new(config.escrow_programs.as_deref().unwrap_or_default())?;
+    let handlers: Vec<Arc<dyn ProgramHandler>> = default_handlers(Arc::new(resolver))
+        .into_iter()
+        .chain(custom_handlers)
+        .collect();
+    let enabled = match &config.program_handlers {
+        Some(enabled) => enabled,
+        None => return Ok(handlers),
+    };
+    if let Some(unknown) = enabled
+        .iter()
+        .find(|name| !handlers.iter().any(|h| h.name() == name.as_str()))
+    {
+        return Err(IngesterError::ConfigurationError {
+            msg: format!("Unknown program handler: {}", unknown),
+        });
+    }
+    Ok(handlers
+        .into_iter()
+        .filter(|h| enabled.iter().any(|name| name == h.name()))
+        .collect())
+}
+
+/// Runs the ingester until ctrl-c, with `custom_handlers` registered alongside the built in
+/// program handlers.
+pub async fn start(custom_handlers: Vec<Arc<dyn ProgramHandler>>) {
+    // Read config.
+    println!("Starting DASgester");
+    let mut config: IngesterConfig = Figment::new()
+        .join(Env::prefixed("INGESTER_"))
+        .extract()
+        .map_err(|config_error| IngesterError::ConfigurationError {
+            msg: format!("{}", config_error),
+        })
+        .unwrap();
+    let consumer_id = consumer_id(&config);
+    let connection_config = &mut config.messenger_config.connection_config;
+    connection_config.insert("consumer_id".to_string(), Value::from(consumer_id));
+    if let Some(min_idle) = config.claim_min_idle {
+        connection_config.insert(CLAIM_MIN_IDLE_KEY.to_string(), Value::from(min_idle));
+    }
+    if let Some(interval) = config.claim_interval {
+        connection_config.insert(CLAIM_INTERVAL_KEY.to_string(), Value::from(interval));
+    }
+    if let Some(record_dir) = &config.record_dir {
+        config
+            .messenger_config
+            .connection_config
+            .insert(RECORD_DIR_KEY.to_string(), Value::from(record_dir.clone()));
+    }
+    if let Some(replay) = &config.replay {
+        let connection_config = &mut config.messenger_config.connection_config;
+        connection_config.insert(REPLAY_DIR_KEY.to_string(), Value::from(replay.dir.clone()));
+        connection_config.insert(
+            REPLAY_ORIGINAL_PACING_KEY.to_string(),
+            Value::from(replay.original_pacing.unwrap_or(false)),
+        );
+    }
+
+    setup_metrics(&config);
+
+    let handlers = enabled_handlers(&config, custom_handlers).unwrap();
+    let handler_names: Vec<&str> = handlers.iter().map(|h| h.name()).collect();
+    println!("Program handlers: {}", handler_names.join(", "));
+
+    let url = config
+        .database_config
+        .get(DATABASE_URL_KEY)
+        .and_then(|u| u.clone().into_string())
+        .ok_or(IngesterError::ConfigurationError {
+            msg: format!("Database connection string missing: {}", DATABASE_URL_KEY),
+        })
+        .unwrap();
+
+    let pool = PgPoolOptions::new()
+        .max_connections(config.max_postgres_connections.unwrap_or(100))
+        .connect(&url)
+        .await
+        .unwrap();
+
+    // `nft_ingester check-leaves [TREE] [--repair]` checks the leaves of compressed assets once
+    // and exits instead of starting the ingester.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("check-leaves") {
+        match run_leaf_check(pool, &args[1..]).await {
+            Ok(report) => println!("Leaf check {}", report),
+            Err(err) => println!("Leaf check failed: {}", err),
+        }
+        return;
+    }
+    // `nft_ingester dead-letters list|replay|purge [ID...] [--stream STREAM] [--all]`.
+    if args.first().map(String::as_str) == Some("dead-letters") {
+        let db = SqlxPostgresConnector::from_sqlx_postgres_pool(pool);
+        let res = run_dead_letter_command::<RedisMessenger>(
+            &db,
+            config.messenger_config.clone(),
+            &args[1..],
+        )
+        .await;
+        if let Err(err) = res {
+            println!("Dead letter command failed: {}", err);
+        }
+        return;
+    }
+
+    let backfiller = backfiller::<RedisMessenger>(pool.clone(), config.clone());
+    let auditor = auditor(pool.clone(), config.clone());
+
+    let bg_task_definitions: Vec<Box<dyn BgTask>> = vec![
+        Box::new(DownloadMetadataTask {}),
+        Box::new(CheckLeavesTask { pool: pool.clone() }),
+    ];
+    let mut background_task_manager =
+        TaskManager::new(rand_string(), pool.clone(), bg_task_definitions);
+    let background_task_manager_handle = background_task_manager.start_listener();
+    let backgroun_task_sender = background_task_manager.get_sender().unwrap();
+
+    // The listeners read from a recording, a Geyser gRPC stream or Redis.
+    let grpc = config
+        .messenger_config
+        .connection_config
+        .contains_key(GRPC_ENDPOINT_KEY);
+    if grpc {
+        let programs: Vec<String> = handlers.iter().map(|h| h.key().to_string()).collect();
+        config.messenger_config.connection_config.insert(
+            GRPC_PROGRAMS_KEY.to_string(),
+            Value::from(programs.join(",")),
+        );
+    }
+    let leaf_check_timer = config
+        .leaf_check
+        .clone()
+        .map(|leaf_check| schedule_leaf_checks(backgroun_task_sender.clone(), leaf_check));
+    let messenger_config = config.messenger_config.clone();
+    let sender = backgroun_task_sender; // This is allowed because we must
+    let dead_letters = Arc::new(DeadLetterQueue::new(
+        SqlxPostgresConnector::from_sqlx_postgres_pool(pool.clone()),
+        config.max_deliveries.unwrap_or(DEFAULT_MAX_DELIVERIES),
+    ));
+    let parallelism = config.parallelism.unwrap_or(DEFAULT_PARALLELISM);
+    let track_slots = config.track_slots.unwrap_or(false);
+    let (txn_stream, account_stream, slot_stream) = match (&config.replay, &config.record_dir, grpc)
+    {
+        (Some(_), _, _) => listeners::<FileMessenger>(
+            pool.clone(),
+            sender,
+            messenger_config,
+            handlers,
+            dead_letters,
+            parallelism,
+            config.account_batch.clone(),
+            track_slots,
+        ),
+        (None, Some(_), true) => listeners::<RecordingMessenger<GrpcMessenger>>(
+            pool.clone(),
+            sender,
+            messenger_config,
+            handlers,
+            dead_letters,
+            parallelism,
+            config.account_batch.clone(),
+            track_slots,
+        ),
+        (None, Some(_), false) => listeners::<RecordingMessenger<RedisMessenger>>(
+            pool.clone(),
+            sender,
+            messenger_config,
+            handlers,
+            dead_letters,
+            parallelism,
+            config.account_batch.clone(),
+            track_slots,
+        ),
+        (None, None, true) => listeners::<GrpcMessenger>(
+            pool.clone(),
+            sender,
+            messenger_config,
+            handlers,
+            dead_letters,
+            parallelism,
+            config.account_batch.clone(),
+            track_slots,
+        ),
+        (None, None, false) => listeners::<RedisMessenger>(
+            pool.clone(),
+            sender,
+            messenger_config,
+            handlers,
+            dead_letters,
+            parallelism,
+            config.account_batch.clone(),
+            track_slots,
+        ),
+    };
+    // Only the Redis streams have a size.
+    let reads_redis = config.replay.is_none() && !grpc;
+
+    let mut tasks = JoinSet::new();
+
+    let role = config.role.unwrap_or(IngesterRole::All);
+
+    let stream_size_timer = async move {
+        let mut interval = time::interval(tokio::time::Duration::from_secs(10));
+        let mut messenger = RedisMessenger::new(config.messenger_config.clone())
+            .await
+            .unwrap();
+        loop {
+            interval.tick().await;
+
+            let tx_size = messenger.stream_size(TRANSACTION_STREAM).await;
+            let acc_size = messenger.stream_size(ACCOUNT_STREAM).await;
+            if tx_size.is_err() {
+                safe_metric(|| {
+                    statsd_count!("ingester.transaction_stream_size_error", 1);
+                });
+            }
+            if acc_size.is_err() {
+                safe_metric(|| {
+                    statsd_count!("ingester.account_stream_size_error", 1);
+                });
+            }
+            let tx_size = tx_size.unwrap_or(0);
+            let acc_size = acc_size.unwrap_or(0);
+            safe_metric(move || {
+                statsd_gauge!("ingester.transaction_stream_size", tx_size);
+                statsd_gauge!("ingester.account_stream_size", acc_size);
+            });
+
+            for stream in [TRANSACTION_STREAM, ACCOUNT_STREAM] {
+                match pending_by_consumer(&config.messenger_config, stream).await {
+                    Ok(pending) => {
+                        for (consumer, count) in pending {
+                            safe_metric(|| {
+                                statsd_gauge!("ingester.pending_entries", count as u64, "stream" => stream, "consumer" => &consumer);
+                            });
+                        }
+                    }
+                    Err(_) => {
+                        safe_metric(|| {
+                            statsd_count!("ingester.pending_entries_error", 1, "stream" => stream);
+                        });
+                    }
+                }
+            }
+        }
+    };
+
+    match role {
+        IngesterRole::All => {
+            tasks.spawn(backfiller.await);
+            tasks.spawn(auditor.await);
+            tasks.spawn(txn_stream.await);
+            tasks.spawn(account_stream.await);
+            if let Some(slot_stream) = slot_stream {
+                tasks.spawn(slot_stream.await);
+            }
+            tasks.spawn(background_task_manager_handle);
+            tasks.spawn(background_task_manager.start_runner());
+            if let Some(leaf_check_timer) = leaf_check_timer {
+                tasks.spawn(leaf_check_timer);
+            }
+            if reads_redis {
+                tasks.spawn(stream_size_timer);
+            }
+        }
+        IngesterRole::Auditor => {
+            tasks.spawn(auditor.await);
+        }
+        IngesterRole::Backfiller => {
+            tasks.spawn(backfiller.await);
+        }
+        IngesterRole::BackgroundTaskRunner => {
+            tasks.spawn(background_task_manager.start_runner());
+        }
+        IngesterRole::Ingester => {
+            tasks.spawn(background_task_manager_handle);
+            tasks.spawn(txn_stream.await);
+            tasks.spawn(account_stream.await);
+            if let Some(slot_stream) = slot_stream {
+                tasks.spawn(slot_stream.await);
+            }
+            tasks.spawn(background_task_manager.start_runner());
+            if let Some(leaf_check_timer) = leaf_check_timer {
+                tasks.spawn(leaf_check_timer);
+            }
+            if reads_redis {
+                tasks.spawn(stream_size_timer);
+            }
+        }
+    }
+    let roles_str = role.to_string();
+    safe_metric(|| {
+        statsd_count!("ingester.startup", 1, "role" => &roles_str);
+    });
+
+    // Wait for ctrl-c.
+    match tokio::signal::ctrl_c().await {
+        Ok(()) => {}
+        Err(err) => {
+            println!("Unable to listen for shutdown signal: {}", err);
+        }
+    }
+
+    tasks.shutdown().await;
+}
+
+async fn run_leaf_check(
+    pool: Pool<Postgres>,
+    args: &[String],
+) -> Result<LeafCheckReport, IngesterError> {
+    let repair = args.iter().any(|a| a == "--repair");
+    let tree = args
+        .iter()
+        .find(|a| !a.starts_with("--"))
+        .map(|t| decode_tree(t))
+        .transpose()?;
+    let db = SqlxPostgresConnector::from_sqlx_postgres_pool(pool);
+    check_leaves(&db, tree, repair).await
+}
+
+/// Enqueues a check of the leaves of every tree every `interval` seconds, the first one an
+/// interval after startup.
+async fn schedule_leaf_checks(tasks: UnboundedSender<TaskData>, config: LeafCheckConfig) {
+    let period = tokio::time::Duration::from_secs(config.interval);
+    let mut interval = time::interval_at(time::Instant::now() + period, period);
+    loop {
+        interval.tick().await;
+        let check = CheckLeaves {
+            tree: None,
+            repair: config.repair.unwrap_or(false),
+        };
+        match check.into_task_data() {
+            Ok(task) => {
+                if tasks.send(task).is_err() {
+                    break;
+                }
+            }
+            Err(err) => println!("Failed to schedule a leaf check: {}", err),
+        }
+    }
+}
+
+type Listener = BoxFuture<'static, JoinHandle<()>>;
+
+/// The transaction and account stream listeners, and the slot stream listener if slots are
+/// tracked, reading from `T`.
+fn listeners<T: Messenger + 'static>(
+    pool: Pool<Postgres>,
+    tasks: UnboundedSender<TaskData>,
+    messenger_config: MessengerConfig,
+    handlers: Vec<Arc<dyn ProgramHandler>>,
+    dead_letters: Arc<DeadLetterQueue>,
+    parallelism: usize,
+    account_batch: Option<AccountBatchConfig>,
+    track_slots: bool,
+) -> (Listener, Listener, Option<Listener>) {
+    let txn_stream = service_transaction_stream::<T>(
+        pool.clone(),
+        tasks.clone(),
+        messenger_config.clone(),
+        handlers.clone(),
+        dead_letters.clone(),
+        parallelism,
+    );
+    let slot_stream = track_slots
+        .then(|| service_slot_stream::<T>(pool.clone(), messenger_config.clone()).boxed());
+    let account_stream = service_account_stream::<T>(
+        pool,
+        tasks,
+        messenger_config,
+        handlers,
+        dead_letters,
+        parallelism,
+        account_batch,
+    );
+    (txn_stream.boxed(), account_stream.boxed(), slot_stream)
+}
+
+/// Records the slot notifications, taking back the writes of the forks a rooted slot abandoned.
+/// Notifications are acknowledged even if they fail, the next root covers the slots of one that
+/// is lost.
+async fn service_slot_stream<T: Messenger>(
+    pool: Pool<Postgres>,
+    messenger_config: MessengerConfig,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let pool_cloned = pool.clone();
+            let messenger_config_cloned = messenger_config.clone();
+
+            let result = tokio::spawn(async move {
+                let db = SqlxPostgresConnector::from_sqlx_postgres_pool(pool_cloned);
+                let mut messenger = T::new(messenger_config_cloned).await.unwrap();
+                println!("Setting up slot listener");
+
+                loop {
+                    let data = messenger.recv(SLOT_STREAM).await.unwrap_or_default();
+                    if data.is_empty() {
+                        continue;
+                    }
+                    let mut ids = Vec::with_capacity(data.len());
+                    for item in data {
+                        handle_slot(&db, &item.data).await;
+                        ids.push(item.id);
+                    }
+                    if let Err(e) = messenger.ack_msg(SLOT_STREAM, &ids).await {
+                        println!("Error ACK-ing messages {:?}", e);
+                    }
+                }
+            })
+            .await;
+
+            match result {
+                Ok(_) => break,
+                Err(err) if err.is_panic() => {
+                    statsd_count!("ingester.service_slot_stream.task_panic", 1);
+                }
+                Err(err) => {
+                    let err = err.to_string();
+                    statsd_count!("ingester.service_slot_stream.task_error", 1, "error" => &err);
+                }
+            }
+        }
+    })
+}
+
+async fn handle_slot(db: &DatabaseConnection, data: &[u8]) {
+    let info = match root_as_slot_status_info(data) {
+        Ok(info) => info,
+        Err(err) => {
+            println!("Error parsing slot notification: {:?}", err);
+            safe_metric(|| {
+                statsd_count!("ingester.slot_parse_error", 1);
+            });
+            return;
+        }
+    };
+    match save_slot_status(db, info.slot(), info.parent(), slot_status(&info)).await {
+        Ok(abandoned) if !abandoned.is_empty() => {
+            println!(
+                "Slot {} rooted, took back the writes of slots {:?}",
+                info.slot(),
+                abandoned
+            );
+            safe_metric(|| {
+                statsd_count!("ingester.abandoned_slots", abandoned.len() as i64);
+            });
+        }
+        Ok(_) => {}
+        Err(err) => {
+            println!("Error saving slot {}: {}", info.slot(), err);
+            safe_metric(|| {
+                statsd_count!("ingester.slot_error", 1);
+            });
+        }
+    }
+}
+
+async fn service_transaction_stream<T: Messenger>(
+    pool: Pool<Postgres>,
+    tasks: UnboundedSender<TaskData>,
+    messenger_config: MessengerConfig,
+    handlers: Vec<Arc<dyn ProgramHandler>>,
+    dead_letters: Arc<DeadLetterQueue>,
+    parallelism: usize,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let pool_cloned = pool.clone();
+            let tasks_cloned = tasks.clone();
+            let messenger_config_cloned = messenger_config.clone();
+            let handlers_cloned = handlers.clone();
+            let dead_letters_cloned = dead_letters.clone();
+
+            let result = tokio::spawn(async move {
+                let dead_letters = dead_letters_cloned;
+                let mut manager = ProgramTransformer::new(pool_cloned, tasks_cloned);
+                for handler in handlers_cloned {
+                    manager.register(handler);
+                }
+                let manager = Arc::new(manager);
+                let mut messenger = T::new(messenger_config_cloned.clone()).await.unwrap();
+                let mut claimer = match messenger.messenger_type() {
+                    MessengerType::Redis => PendingClaimer::new(&messenger_config_cloned)
+                        .await
+                        .map_err(|e| println!("Not claiming pending messages {:?}", e))
+                        .ok(),
+                    _ => None,
+                };
+                println!("Setting up transaction listener");
+
+                loop {
+                    let mut data = messenger.recv(TRANSACTION_STREAM).await.unwrap_or_default();
+                    if let Some(claimer) = claimer.as_mut() {
+                        match claimer.claim(TRANSACTION_STREAM).await {
+                            Ok(claimed) => data.extend(claimed),
+                            Err(e) => println!("Error claiming pending messages {:?}", e),
+                        }
+                    }
+                    if data.is_empty() {
+                        continue;
+                    }
+                    let ids = handle_transaction(&manager, &dead_letters, parallelism, data).await;
+                    if !ids.is_empty() {
+                        if let Err(e) = messenger.ack_msg(TRANSACTION_STREAM, &ids).await {
+                            println!("Error ACK-ing messages {:?}", e);
+                        }
+                    }
+                }
+            })
+            .await;
+
+            match result {
+                Ok(_) => break,
+                Err(err) if err.is_panic() => {
+                    statsd_count!("ingester.service_transaction_stream.task_panic", 1);
+                }
+                Err(err) => {
+                    let err = err.to_string();
+                    statsd_count!("ingester.service_transaction_stream.task_error", 1, "error" => &err);
+                }
+            }
+        }
+    })
+}
+
+/// Reads `stream_key` with `messenger` in a task of its own, one batch ahead of the receiver of
+/// the batches.  A read is never cancelled: cancelling one loses the messages already read, or
+/// leaves the connection waiting for a reply that is never read.
+fn read_stream<T: Messenger>(
+    mut messenger: T,
+    stream_key: &'static str,
+) -> mpsc::Receiver<Vec<RecvData>> {
+    let (sender, receiver) = mpsc::channel(1);
+    tokio::spawn(async move {
+        loop {
+            let data = messenger.recv(stream_key).await.unwrap_or_default();
+            if sender.send(data).await.is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+async fn service_account_stream<T: Messenger>(
+    pool: Pool<Postgres>,
+    tasks: UnboundedSender<TaskData>,
+    messenger_config: MessengerConfig,
+    handlers: Vec<Arc<dyn ProgramHandler>>,
+    dead_letters: Arc<DeadLetterQueue>,
+    parallelism: usize,
+    account_batch: Option<AccountBatchConfig>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let pool_cloned = pool.clone();
+            let tasks_cloned = tasks.clone();
+            let messenger_config_cloned = messenger_config.clone();
+            let handlers_cloned = handlers.clone();
+            let dead_letters_cloned = dead_letters.clone();
+            let account_batch_cloned = account_batch.clone();
+
+            let result = tokio::spawn(async move {
+                let dead_letters = dead_letters_cloned;
+                let mut manager = ProgramTransformer::new(pool_cloned, tasks_cloned);
+                for handler in handlers_cloned {
+                    manager.register(handler);
+                }
+                let manager = Arc::new(manager);
+                let mut messenger = T::new(messenger_config_cloned.clone()).await.unwrap();
+                let mut reader = read_stream(
+                    T::new(messenger_config_cloned.clone()).await.unwrap(),
+                    ACCOUNT_STREAM,
+                );
+                let mut claimer = match messenger.messenger_type() {
+                    MessengerType::Redis => PendingClaimer::new(&messenger_config_cloned)
+                        .await
+                        .map_err(|e| println!("Not claiming pending messages {:?}", e))
+                        .ok(),
+                    _ => None,
+                };
+                let mut batch = account_batch_cloned.map(|config| {
+                    AccountBatch::new(
+                        config.max_size.unwrap_or(DEFAULT_BATCH_SIZE),
+                        config
+                            .max_latency_ms
+                            .map(time::Duration::from_millis)
+                            .unwrap_or(DEFAULT_BATCH_LATENCY),
+                    )
+                });
+                println!("Setting up account listener");
+
+                loop {
+                    // A buffered batch bounds the wait for new messages.  Only the wait is cut
+                    // short, the read itself carries on in the reader task.
+                    let received = match batch.as_ref().and_then(|b| b.time_left()) {
+                        Some(time_left) => time::timeout(time_left, reader.recv())
+                            .await
+                            .unwrap_or_else(|_| Some(Vec::new())),
+                        None => reader.recv().await,
+                    };
+                    let mut data = received.expect("The account stream reader stopped");
+                    if let Some(claimer) = claimer.as_mut() {
+                        match claimer.claim(ACCOUNT_STREAM).await {
+                            Ok(claimed) => data.extend(claimed),
+                            Err(e) => println!("Error claiming pending messages {:?}", e),
+                        }
+                    }
+                    let mut ids = Vec::new();
+                    if !data.is_empty() {
+                        ids = handle_account(
+                            &manager,
+                            &dead_letters,
+                            parallelism,
+                            batch.as_mut(),
+                            data,
+                        )
+                        .await;
+                    }
+                    if let Some(batch) = batch.as_mut().filter(|b| b.is_due()) {
+                        ids.extend(
+                            flush_account_batch(&manager, &dead_letters, parallelism, batch).await,
+                        );
+                    }
+                    if !ids.is_empty() {
+                        if let Err(e) = messenger.ack_msg(ACCOUNT_STREAM, &ids).await {
+                            println!("Error ACK-ing messages {:?}", e);
+                        }
+                    }
+                }
+            })
+            .await;
+
+            match result {
+                Ok(_) => break,
+                Err(err) if err.is_panic() => {
+                    statsd_count!("ingester.service_account_stream.task_panic", 1);
+                }
+                Err(err) => {
+                    let err = err.to_string();
+                    statsd_count!("ingester.service_account_stream.task_error", 1, "error" => &err);
+                }
+            }
+        }
+    })
+}
+
+async fn handle_account(
+    manager: &Arc<ProgramTransformer>,
+    dead_letters: &Arc<DeadLetterQueue>,
+    parallelism: usize,
+    batch: Option<&mut AccountBatch>,
+    data: Vec<RecvData>,
+) -> Vec<String> {
+    safe_metric(|| {
+        statsd_gauge!("ingester.account_batch_size", data.len() as u64);
+    });
+
+    // The updates written in bulk wait in the batch, they are acknowledged once it is flushed.
+    let data = match batch {
+        Some(batch) => data
+            .into_iter()
+            .filter_map(|item| {
+                let row = root_as_account_info(&item.data)
+                    .ok()
+                    .and_then(|account_update| manager.batch_row(&account_update));
+                match row {
+                    Some((program, row)) => {
+                        batch.push(program, row, item);
+                        None
+                    }
+                    None => Some(item),
+                }
+            })
+            .collect(),
+        None => data,
+    };
+
+    // Updates of the same asset are processed in order.
+    let mut jobs = Vec::with_capacity(data.len());
+    for item in data.into_iter() {
+        let manager = Arc::clone(manager);
+        let dead_letters = Arc::clone(dead_letters);
+        let keys: Vec<Vec<u8>> = root_as_account_info(&item.data)
+            .ok()
+            .and_then(|account_update| manager.account_ordering_key(&account_update))
+            .into_iter()
+            .collect();
+
+        jobs.push((keys, async move {
+            let id = item.id;
+            let mut ids = Vec::new();
+            if item.tries > 0 {
+                safe_metric(|| {
+                    statsd_count!("ingester.account_stream_redelivery", 1);
+                });
+            }
+
+            let data = item.data;
+            // Get root of account info flatbuffers object.
+            let account_update = match root_as_account_info(&data) {
+                Ok(account_update) => account_update,
+                // A message that can't be read never will be.
+                Err(err) => {
+                    let letter = DeadLetter {
+                        stream: ACCOUNT_STREAM.to_string(),
+                        message_id: id.clone(),
+                        payload: data.clone(),
+                        error: err.to_string(),
+                        deliveries: item.tries + 1,
+                        ..Default::default()
+                    };
+                    if dead_letter(&dead_letters, letter).await {
+                        ids.push(id);
+                    }
+                    return ids;
+                }
+            };
+
+            let seen_at = Utc::now();
+            let str_program_id =
+                bs58::encode(account_update.owner().unwrap().0.as_slice()).into_string();
+            safe_metric(|| {
+                statsd_count!("ingester.account_update_seen", 1, "owner" => &str_program_id);
+            });
+            safe_metric(|| {
+                statsd_time!(
+                    "ingester.account_bus_ingest_time",
+                    (seen_at.timestamp_millis() - account_update.seen_at()) as u64,
+                    "owner" => &str_program_id
+                );
+            });
+            let begin_processing = Utc::now();
+            let res = manager.handle_account_update(account_update).await;
+            let finish_processing = Utc::now();
+            match res {
+                Ok(_) => {
+                    if item.tries == 0 {
+                        safe_metric(|| {
+                            let proc_time = (finish_processing.timestamp_millis()
+                                - begin_processing.timestamp_millis())
+                                as u64;
+                            statsd_time!("ingester.account_proc_time", proc_time, "owner" => &str_program_id);
+                        });
+                        safe_metric(|| {
+                            statsd_count!("ingester.account_update_success", 1, "owner" => &str_program_id);
+                        });
+                    }
+                    ids.push(id);
+                }
+                Err(err) if err == IngesterError::NotImplemented => {
+                    safe_metric(|| {
+                        statsd_count!("ingester.account_not_implemented", 1, "owner" => &str_program_id);
+                    });
+                    ids.push(id);
+                }
+                Err(err) => {
+                    println!("Error handling account update: {:?}", err);
+                    safe_metric(|| {
+                        statsd_count!("ingester.account_update_error", 1, "owner" => &str_program_id);
+                    });
+                    if dead_letters.is_last_delivery(item.tries) {
+                        let letter = DeadLetter {
+                            stream: ACCOUNT_STREAM.to_string(),
+                            message_id: id.clone(),
+                            payload: data.clone(),
+                            program_id: account_update.owner().map(|o| o.0.to_vec()),
+                            account: account_update.pubkey().map(|k| k.0.to_vec()),
+                            error: err.to_string(),
+                            deliveries: item.tries + 1,
+                            ..Default::default()
+                        };
+                        if dead_letter(&dead_letters, letter).await {
+                            ids.push(id);
+                        }
+                    }
+                }
+            }
+            ids
+        }));
+    }
+    run_partitioned(jobs, parallelism)
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Writes the buffered account updates, returning the ids of their messages.  A batch that
+/// can't be written is processed message by message, so that the update failing is retried or
+/// dead lettered on its own.
+async fn flush_account_batch(
+    manager: &Arc<ProgramTransformer>,
+    dead_letters: &Arc<DeadLetterQueue>,
+    parallelism: usize,
+    batch: &mut AccountBatch,
+) -> Vec<String> {
+    let (rows, messages) = batch.take();
+    let begin_processing = Utc::now();
+    match manager.save_batch(rows).await {
+        Ok(_) => {
+            let finish_processing = Utc::now();
+            safe_metric(|| {
+                statsd_gauge!("ingester.account_batch_flush_size", messages.len() as u64);
+                statsd_time!(
+                    "ingester.account_batch_flush_time",
+                    (finish_processing.timestamp_millis() - begin_processing.timestamp_millis())
+                        as u64
+                );
+            });
+            messages.into_iter().map(|m| m.id).collect()
+        }
+        Err(err) => {
+            println!("Error flushing account batch: {:?}", err);
+            safe_metric(|| {
+                statsd_count!("ingester.account_batch_flush_error", 1);
+            });
+            handle_account(manager, dead_letters, parallelism, None, messages).await
+        }
+    }
+}
+
+/// Moves a message that failed to the dead letters, whether it can be acknowledged.
+async fn dead_letter(dead_letters: &DeadLetterQueue, letter: DeadLetter) -> bool {
+    match dead_letters.push(letter).await {
+        Ok(_) => true,
+        Err(err) => {
+            println!("Error dead lettering message: {:?}", err);
+            false
+        }
+    }
+}
+
+async fn process_instruction<'i>(
+    manager: Arc<ProgramTransformer>,
+    signature: &str,
+    slot: u64,
+    keys: &[FBPubkey],
+    outer_ix: IxPair<'i>,
+    inner_ix: Option<Vec<IxPair<'i>>>,
+) -> Result<(), IngesterError> {
+    let (program, instruction) = outer_ix;
+    let ix_accounts = instruction.accounts().unwrap().iter().collect::<Vec<_>>();
+    let ix_account_len = ix_accounts.len();
+    let max = ix_accounts.iter().max().copied().unwrap_or(0) as usize;
+    if keys.len() < max {
+        return Err(IngesterError::DeserializationError(
+            "Missing Accounts in Serialized Ixn/Txn".to_string(),
+        ));
+    }
+    let ix_accounts = ix_accounts
+        .iter()
+        .fold(Vec::with_capacity(ix_account_len), |mut acc, a| {
+            if let Some(key) = keys.get(*a as usize) {
+                acc.push(*key);
+            }
+            //else case here is handled on 272
+            acc
+        });
+    let bundle = InstructionBundle {
+        txn_id: signature,
+        program,
+        instruction: Some(instruction),
+        inner_ix,
+        keys: ix_accounts.as_slice(),
+        slot,
+    };
+    manager.handle_instruction(&bundle).await
+}
+
+async fn handle_transaction(
+    manager: &Arc<ProgramTransformer>,
+    dead_letters: &Arc<DeadLetterQueue>,
+    parallelism: usize,
+    data: Vec<RecvData>,
+) -> Vec<String> {
+    safe_metric(|| {
+        statsd_gauge!("ingester.txn_batch_size", data.len() as u64);
+    });
+
+    // Transactions updating the same tree are processed in order.
+    let mut jobs = Vec::with_capacity(data.len());
+    for item in data {
+        let manager = Arc::clone(manager);
+        let dead_letters = Arc::clone(dead_letters);
+        let keys = root_as_transaction_info(&item.data)
+            .map(|tx| manager.transaction_ordering_keys(&tx))
+            .unwrap_or_default();
+
+        jobs.push((keys, async move {
+            let mut ids = Vec::new();
+            if item.tries > 0 {
+                safe_metric(|| {
+                    statsd_count!("ingester.tx_stream_redelivery", 1);
+                });
+            }
+            let id = item.id.to_string();
+            let tx_data = item.data;
+            let tx = match root_as_transaction_info(&tx_data) {
+                Ok(tx) => tx,
+                // A message that can't be read never will be.
+                Err(err) => {
+                    let letter = DeadLetter {
+                        stream: TRANSACTION_STREAM.to_string(),
+                        message_id: id.clone(),
+                        payload: tx_data.clone(),
+                        error: err.to_string(),
+                        deliveries: item.tries + 1,
+                        ..Default::default()
+                    };
+                    if dead_letter(&dead_letters, letter).await {
+                        ids.push(id);
+                    }
+                    return ids;
+                }
+            };
+            let instructions = manager.break_transaction(&tx);
+            let accounts = tx.account_keys().unwrap_or_default();
+            let mut va: Vec<FBPubkey> = Vec::with_capacity(accounts.len());
+            for k in accounts.into_iter() {
+                va.push(*k);
+            }
+
+            let signature = tx.signature().unwrap_or("NO SIG");
+            if let Some(si) = tx.slot_index() {
+                let slt_idx = format!("{}-{}", tx.slot(), si);
+                safe_metric(|| {
+                    statsd_count!("ingester.transaction_event_seen", 1, "slot-idx" => &slt_idx);
+                });
+            }
+            let seen_at = Utc::now();
+            safe_metric(|| {
+                statsd_time!(
+                    "ingester.bus_ingest_time",
+                    (seen_at.timestamp_millis() - tx.seen_at()) as u64
+                );
+            });
+            // The first instruction that failed, the transaction is delivered again unless it
+            // is dead lettered.
+            let mut failure = None;
+            for (outer_ix, inner_ix) in instructions {
+                let manager = Arc::clone(&manager);
+                let (program, _) = &outer_ix;
+                let program_id = program.0.to_vec();
+                let str_program_id = bs58::encode(&program_id).into_string();
+                let begin_processing = Utc::now();
+                let res =
+                    process_instruction(manager, signature, tx.slot(), &va, outer_ix, inner_ix)
+                        .await;
+                let finish_processing = Utc::now();
+                match res {
+                    Ok(_) => {
+                        if item.tries == 0 {
+                            safe_metric(|| {
+                                let proc_time = (finish_processing.timestamp_millis()
+                                    - begin_processing.timestamp_millis())
+                                    as u64;
+                                statsd_time!("ingester.tx_proc_time", proc_time);
+                            });
+                            safe_metric(|| {
+                                statsd_count!("ingester.tx_ingest_success", 1, "owner" => &str_program_id);
+                            });
+                        } else {
+                            safe_metric(|| {
+                                statsd_count!("ingester.tx_ingest_redeliver_success", 1, "owner" => &str_program_id);
+                            });
+                        }
+                    }
+                    Err(err) if err == IngesterError::NotImplemented => {
+                        safe_metric(|| {
+                            statsd_count!("ingester.tx_not_implemented", 1, "owner" => &str_program_id);
+                        });
+                    }
+                    Err(err) => {
+                        println!("ERROR:txn: {:?} {:?}", signature, err);
+                        safe_metric(|| {
+                            statsd_count!("ingester.tx_ingest_error", 1, "owner" => &str_program_id);
+                        });
+                        if failure.is_none() {
+                            failure = Some((program_id, err));
+                        }
+                    }
+                };
+            }
+            match failure {
+                None => {
+                    println!("SUCCESS:txn: {:?} yay", signature);
+                    ids.push(id);
+                }
+                Some((program_id, err)) if dead_letters.is_last_delivery(item.tries) => {
+                    let letter = DeadLetter {
+                        stream: TRANSACTION_STREAM.to_string(),
+                        message_id: id.clone(),
+                        payload: tx_data.clone(),
+                        program_id: Some(program_id),
+                        signature: Some(signature.to_string()),
+                        error: err.to_string(),
+                        deliveries: item.tries + 1,
+                        ..Default::default()
+                    };
+                    if dead_letter(&dead_letters, letter).await {
+                        ids.push(id);
+                    }
+                }
+                Some(_) => {}
+            }
+            ids
+        }));
+    }
+    run_partitioned(jobs, parallelism)
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use figment::value::Dict;
+    use solana_sdk::pubkey::Pubkey;
+
+    struct CustomHandler;
+
+    #[async_trait]
+    impl ProgramHandler for CustomHandler {
+        fn name(&self) -> &'static str {
+            "custom"
+        }
+
+        fn key(&self) -> Pubkey {
+            Pubkey::new_from_array([7; 32])
+        }
+    }
+
+    fn config(program_handlers: Option<&[&str]>) -> IngesterConfig {
+        IngesterConfig {
+            database_config: Dict::new(),
+            messenger_config: MessengerConfig {
+                messenger_type: MessengerType::Invalid,
+                connection_config: Dict::new(),
+            },
+            env: None,
+            rpc_config: Dict::new(),
+            metrics_port: None,
+            metrics_host: None,
+            backfiller: None,
+            role: None,
+            max_postgres_connections: None,
+            auditor_interval: None,
+            program_handlers: program_handlers
+                .map(|names| names.iter().map(|name| name.to_string()).collect()),
+            escrow_programs: None,
+            record_dir: None,
+            replay: None,
+            max_deliveries: None,
+            consumer_id: None,
+            claim_min_idle: None,
+            claim_interval: None,
+            parallelism: None,
+            account_batch: None,
+            track_slots: None,
+            leaf_check: None,
+        }
+    }
+
+    fn enabled_names(program_handlers: Option<&[&str]>) -> Vec<&'static str> {
+        enabled_handlers(&config(program_handlers), vec![Arc::new(CustomHandler)])
+            .unwrap()
+            .iter()
+            .map(|h| h.name())
+            .collect()
+    }
+
+    #[test]
+    fn every_handler_runs_when_none_are_named() {
+        assert_eq!(
+            enabled_names(None),
+            vec![
+                "bubblegum",
+                "token_metadata",
+                "token",
+                "candy_machine",
+                "candy_guard",
+                "hydra",
+                "auction_house",
+                "custom",
+            ]
+        );
+    }
+
+    #[test]
+    fn handlers_that_are_not_named_are_skipped() {
+        assert_eq!(
+            enabled_names(Some(&["custom", "token_metadata"])),
+            vec!["token_metadata", "custom"]
+        );
+        assert!(enabled_names(Some(&[])).is_empty());
+    }
+
+    #[test]
+    fn unknown_handler_names_are_rejected() {
+        let res = enabled_handlers(&config(Some(&["bubblegum", "bubblegm"])), vec![]);
+        match res {
+            Err(IngesterError::ConfigurationError { msg }) => {
+                assert_eq!(msg, "Unknown program handler: bubblegm")
+            }
+            _ => panic!("expected a configuration error"),
+        }
+        // Custom handlers are only known once registered.
+        assert!(enabled_handlers(&config(Some(&["custom"])), vec![]).is_err());
+    }
+}
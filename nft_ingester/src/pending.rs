@@ -0,0 +1,211 @@
+//! Messages left pending in the Redis streams.  A message read by a consumer stays in the
+//! consumer group's pending entries list until it is acknowledged, so the messages of an
+//! ingester that died before acknowledging them are never delivered again unless another
+//! consumer claims them.
+use crate::error::IngesterError;
+use plerkle_messenger::{MessengerConfig, RecvData};
+use redis::{
+    aio::Connection,
+    streams::{StreamPendingCountReply, StreamPendingReply},
+    AsyncCommands, Value,
+};
+use std::{collections::HashMap, time::Duration};
+use tokio::time::Instant;
+
+// Consumer group and entry field `RedisMessenger` uses.
+const GROUP_NAME: &str = "plerkle";
+const DATA_KEY: &str = "data";
+const REDIS_CONNECTION_KEY: &str = "redis_connection_str";
+
+pub const CLAIM_MIN_IDLE_KEY: &str = "claim_min_idle";
+pub const CLAIM_INTERVAL_KEY: &str = "claim_interval";
+
+pub const DEFAULT_CLAIM_MIN_IDLE: Duration = Duration::from_secs(300);
+pub const DEFAULT_CLAIM_INTERVAL: Duration = Duration::from_secs(30);
+const CLAIM_BATCH_SIZE: usize = 100;
+
+async fn connect(config: &MessengerConfig) -> Result<Connection, IngesterError> {
+    let url = config
+        .connection_config
+        .get(REDIS_CONNECTION_KEY)
+        .and_then(|u| u.clone().into_string())
+        .ok_or(IngesterError::ConfigurationError {
+            msg: format!("Redis connection string missing: {}", REDIS_CONNECTION_KEY),
+        })?;
+    let client = redis::Client::open(url)?;
+    Ok(client.get_async_connection().await?)
+}
+
+fn config_secs(config: &MessengerConfig, key: &str, default: Duration) -> Duration {
+    config
+        .connection_config
+        .get(key)
+        .and_then(|v| v.to_u128())
+        .map(|secs| Duration::from_secs(secs as u64))
+        .unwrap_or(default)
+}
+
+/// Takes over the messages of a stream that were delivered to a consumer and left
+/// unacknowledged for longer than `claim_min_idle` seconds, like `XAUTOCLAIM` does.  Live
+/// consumers acknowledge their messages long before that, so the messages claimed are those
+/// of dead consumers, and of messages that keep failing until they are dead lettered.
+pub struct PendingClaimer {
+    connection: Connection,
+    consumer: String,
+    min_idle: Duration,
+    interval: Duration,
+    last_claim: Option<Instant>,
+    // Where the next claim resumes the scan of the pending entries list.
+    cursor: String,
+}
+
+impl PendingClaimer {
+    pub async fn new(config: &MessengerConfig) -> Result<Self, IngesterError> {
+        let consumer = config
+            .connection_config
+            .get("consumer_id")
+            .and_then(|c| c.clone().into_string())
+            .ok_or(IngesterError::ConfigurationError {
+                msg: "Consumer id missing: consumer_id".to_string(),
+            })?;
+        Ok(PendingClaimer {
+            connection: connect(config).await?,
+            consumer,
+            min_idle: config_secs(config, CLAIM_MIN_IDLE_KEY, DEFAULT_CLAIM_MIN_IDLE),
+            interval: config_secs(config, CLAIM_INTERVAL_KEY, DEFAULT_CLAIM_INTERVAL),
+            last_claim: None,
+            cursor: "0-0".to_string(),
+        })
+    }
+
+    /// Messages claimed from other consumers, none if the last claim is too recent.  `tries`
+    /// counts their earlier deliveries so that poison messages still get dead lettered.
+    pub async fn claim(&mut self, stream: &str) -> Result<Vec<RecvData>, IngesterError> {
+        if matches!(self.last_claim, Some(last) if last.elapsed() < self.interval) {
+            return Ok(Vec::new());
+        }
+        self.last_claim = Some(Instant::now());
+
+        let reply: Value = redis::cmd("XAUTOCLAIM")
+            .arg(stream)
+            .arg(GROUP_NAME)
+            .arg(&self.consumer)
+            .arg(self.min_idle.as_millis() as u64)
+            .arg(&self.cursor)
+            .arg("COUNT")
+            .arg(CLAIM_BATCH_SIZE)
+            .query_async(&mut self.connection)
+            .await?;
+        let (cursor, entries) = parse_autoclaim_reply(reply)?;
+        self.cursor = cursor;
+        let (first, last) = match (entries.first(), entries.last()) {
+            (Some((first, _)), Some((last, _))) => (first.clone(), last.clone()),
+            _ => return Ok(Vec::new()),
+        };
+
+        let pending: StreamPendingCountReply = self
+            .connection
+            .xpending_consumer_count(
+                stream,
+                GROUP_NAME,
+                first,
+                last,
+                entries.len(),
+                &self.consumer,
+            )
+            .await?;
+        let deliveries: HashMap<String, usize> = pending
+            .ids
+            .into_iter()
+            .map(|p| (p.id, p.times_delivered))
+            .collect();
+        Ok(entries
+            .into_iter()
+            .map(|(id, data)| RecvData {
+                tries: deliveries.get(&id).copied().unwrap_or(1).saturating_sub(1),
+                id,
+                data,
+            })
+            .collect())
+    }
+}
+
+/// The cursor and the entries of an `XAUTOCLAIM` reply.  Entries deleted from the stream while
+/// they were pending have no fields and are left out.
+fn parse_autoclaim_reply(reply: Value) -> Result<(String, Vec<(String, Vec<u8>)>), IngesterError> {
+    let invalid = || IngesterError::MessengerError("Invalid XAUTOCLAIM reply".to_string());
+    let mut reply = match reply {
+        Value::Bulk(reply) => reply.into_iter(),
+        _ => return Err(invalid()),
+    };
+    let cursor: String = redis::from_redis_value(&reply.next().ok_or_else(invalid)?)?;
+    let entries = match reply.next() {
+        Some(Value::Bulk(entries)) => entries,
+        _ => return Err(invalid()),
+    };
+    let mut claimed = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let (id, fields): (String, Option<Vec<(String, Vec<u8>)>>) =
+            redis::from_redis_value(&entry)?;
+        let data = fields
+            .unwrap_or_default()
+            .into_iter()
+            .find(|(field, _)| field == DATA_KEY)
+            .map(|(_, data)| data);
+        if let Some(data) = data {
+            claimed.push((id, data));
+        }
+    }
+    Ok((cursor, claimed))
+}
+
+/// Number of pending messages of each consumer of a stream.
+pub async fn pending_by_consumer(
+    config: &MessengerConfig,
+    stream: &str,
+) -> Result<Vec<(String, usize)>, IngesterError> {
+    let mut connection = connect(config).await?;
+    let reply: StreamPendingReply = connection.xpending(stream, GROUP_NAME).await?;
+    Ok(match reply {
+        StreamPendingReply::Data(data) => data
+            .consumers
+            .into_iter()
+            .map(|c| (c.name, c.pending))
+            .collect(),
+        StreamPendingReply::Empty => Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(s: &str) -> Value {
+        Value::Data(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn parses_autoclaim_replies() {
+        let reply = Value::Bulk(vec![
+            data("1674000000000-3"),
+            Value::Bulk(vec![
+                Value::Bulk(vec![
+                    data("1674000000000-1"),
+                    Value::Bulk(vec![data(DATA_KEY), Value::Data(vec![1, 2, 3])]),
+                ]),
+                // Deleted while pending.
+                Value::Bulk(vec![data("1674000000000-2"), Value::Nil]),
+            ]),
+            Value::Bulk(vec![]),
+        ]);
+        let (cursor, entries) = parse_autoclaim_reply(reply).unwrap();
+        assert_eq!(cursor, "1674000000000-3");
+        assert_eq!(
+            entries,
+            vec![("1674000000000-1".to_string(), vec![1, 2, 3])]
+        );
+
+        assert!(parse_autoclaim_reply(Value::Okay).is_err());
+        assert!(parse_autoclaim_reply(Value::Bulk(vec![data("0-0")])).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! Instructions of the Auction House program the ingester indexes.  Auction House is not a
+//! dependency of the workspace, so the anchor instruction arguments are decoded here.
+use anchor_lang::solana_program::hash::hash;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::{pubkey, pubkey::Pubkey};
+
+pub const AUCTION_HOUSE_PROGRAM_ID: Pubkey = pubkey!("hausS13jsjafwWwGqZTUQRmWyvyxn9EQpqMwV1PBBmk");
+
+// Trade state accounts only hold their bump, the terms of the trade are part of the seeds.
+pub const TRADE_STATE_SIZE: usize = 1;
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SellArgs {
+    pub trade_state_bump: u8,
+    pub free_trade_state_bump: u8,
+    pub program_as_signer_bump: u8,
+    pub buyer_price: u64,
+    pub token_size: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BuyArgs {
+    pub trade_state_bump: u8,
+    pub escrow_payment_bump: u8,
+    pub buyer_price: u64,
+    pub token_size: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CancelArgs {
+    pub buyer_price: u64,
+    pub token_size: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ExecuteSaleArgs {
+    pub escrow_payment_bump: u8,
+    pub free_trade_state_bump: u8,
+    pub program_as_signer_bump: u8,
+    pub buyer_price: u64,
+    pub token_size: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AuctionHouseInstruction {
+    Sell(SellArgs),
+    Buy(BuyArgs),
+    PublicBuy(BuyArgs),
+    Cancel(CancelArgs),
+    ExecuteSale(ExecuteSaleArgs),
+}
+
+fn instruction_discriminator(name: &str) -> [u8; 8] {
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&hash(format!("global:{}", name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+/// Decodes the instructions that open or close trades, other instructions (including the
+/// auctioneer variants) are not indexed.
+pub fn parse_auction_house_instruction(data: &[u8]) -> Option<AuctionHouseInstruction> {
+    if data.len() < 8 {
+        return None;
+    }
+    let (discriminator, mut rest) = data.split_at(8);
+    if discriminator == instruction_discriminator("sell") {
+        SellArgs::deserialize(&mut rest)
+            .ok()
+            .map(AuctionHouseInstruction::Sell)
+    } else if discriminator == instruction_discriminator("buy") {
+        BuyArgs::deserialize(&mut rest)
+            .ok()
+            .map(AuctionHouseInstruction::Buy)
+    } else if discriminator == instruction_discriminator("public_buy") {
+        BuyArgs::deserialize(&mut rest)
+            .ok()
+            .map(AuctionHouseInstruction::PublicBuy)
+    } else if discriminator == instruction_discriminator("cancel") {
+        CancelArgs::deserialize(&mut rest)
+            .ok()
+            .map(AuctionHouseInstruction::Cancel)
+    } else if discriminator == instruction_discriminator("execute_sale") {
+        ExecuteSaleArgs::deserialize(&mut rest)
+            .ok()
+            .map(AuctionHouseInstruction::ExecuteSale)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction_data(name: &str, args: impl BorshSerialize) -> Vec<u8> {
+        let mut data = instruction_discriminator(name).to_vec();
+        data.extend(args.try_to_vec().unwrap());
+        data
+    }
+
+    #[test]
+    fn parses_trade_instructions() {
+        let sell = SellArgs {
+            trade_state_bump: 255,
+            free_trade_state_bump: 254,
+            program_as_signer_bump: 253,
+            buyer_price: 1_000_000_000,
+            token_size: 1,
+        };
+        let buy = BuyArgs {
+            trade_state_bump: 255,
+            escrow_payment_bump: 252,
+            buyer_price: 900_000_000,
+            token_size: 1,
+        };
+        let cancel = CancelArgs {
+            buyer_price: 1_000_000_000,
+            token_size: 1,
+        };
+        let execute_sale = ExecuteSaleArgs {
+            escrow_payment_bump: 252,
+            free_trade_state_bump: 254,
+            program_as_signer_bump: 253,
+            buyer_price: 1_000_000_000,
+            token_size: 1,
+        };
+        assert_eq!(
+            parse_auction_house_instruction(&instruction_data("sell", sell.clone())),
+            Some(AuctionHouseInstruction::Sell(sell))
+        );
+        assert_eq!(
+            parse_auction_house_instruction(&instruction_data("buy", buy.clone())),
+            Some(AuctionHouseInstruction::Buy(buy.clone()))
+        );
+        assert_eq!(
+            parse_auction_house_instruction(&instruction_data("public_buy", buy.clone())),
+            Some(AuctionHouseInstruction::PublicBuy(buy))
+        );
+        assert_eq!(
+            parse_auction_house_instruction(&instruction_data("cancel", cancel.clone())),
+            Some(AuctionHouseInstruction::Cancel(cancel))
+        );
+        assert_eq!(
+            parse_auction_house_instruction(&instruction_data(
+                "execute_sale",
+                execute_sale.clone()
+            )),
+            Some(AuctionHouseInstruction::ExecuteSale(execute_sale))
+        );
+    }
+
+    #[test]
+    fn ignores_other_instructions() {
+        assert_eq!(parse_auction_house_instruction(&[1, 2, 3]), None);
+        assert_eq!(
+            parse_auction_house_instruction(&instruction_data("deposit", 42u64)),
+            None
+        );
+        // Arguments that are cut short are not decoded.
+        assert_eq!(
+            parse_auction_house_instruction(&instruction_data("cancel", 42u64)),
+            None
+        );
+    }
+}
@@ -0,0 +1,253 @@
+mod instruction;
+
+pub use instruction::*;
+
+use crate::IngesterError;
+use blockbuster::instruction::InstructionBundle;
+use digital_asset_types::dao::{asset, asset_history, bids, listings, token_accounts};
+use plerkle_serialization::{AccountInfo, Pubkey as FBPubkey};
+use sea_orm::{
+    entity::*, query::*, sea_query::Expr, sea_query::OnConflict, ConnectionTrait,
+    DatabaseConnection, DatabaseTransaction, DbBackend, EntityTrait, TransactionTrait,
+};
+use serde_json::json;
+
+/// Trade state accounts are opened by sells and bids and closed once the trade is cancelled or
+/// executed.  The terms of the trade only appear in the instructions, so an account update
+/// can only reopen a trade indexed from the instruction that opened it.
+pub async fn handle_auction_house_account<'a>(
+    account_update: &'a AccountInfo<'a>,
+    db: &DatabaseConnection,
+) -> Result<(), IngesterError> {
+    let len = account_update.data().map(|d| d.len()).unwrap_or_default();
+    if len != TRADE_STATE_SIZE {
+        return Err(IngesterError::NotImplemented);
+    }
+    let key = account_update.pubkey().unwrap().0.to_vec();
+    let slot = account_update.slot() as i64;
+    let txn = db.begin().await?;
+    listings::Entity::update_many()
+        .col_expr(listings::Column::Active, Expr::value(true))
+        .col_expr(listings::Column::SlotUpdated, Expr::value(slot))
+        .filter(listings::Column::TradeState.eq(key.clone()))
+        .filter(listings::Column::SlotUpdated.lt(slot))
+        .exec(&txn)
+        .await?;
+    bids::Entity::update_many()
+        .col_expr(bids::Column::Active, Expr::value(true))
+        .col_expr(bids::Column::SlotUpdated, Expr::value(slot))
+        .filter(bids::Column::TradeState.eq(key))
+        .filter(bids::Column::SlotUpdated.lt(slot))
+        .exec(&txn)
+        .await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+pub async fn handle_auction_house_account_closure(
+    key: FBPubkey,
+    slot: u64,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    close_trade_state(key.0.to_vec(), slot as i64, txn).await
+}
+
+pub async fn handle_auction_house_instruction<'a>(
+    bundle: &'a InstructionBundle<'a>,
+    db: &DatabaseConnection,
+) -> Result<(), IngesterError> {
+    let data = bundle
+        .instruction
+        .and_then(|ix| ix.data())
+        .map(|d| d.bytes())
+        .unwrap_or_default();
+    let ix = parse_auction_house_instruction(data).ok_or(IngesterError::NotImplemented)?;
+    let key = |i: usize| -> Result<Vec<u8>, IngesterError> {
+        bundle.keys.get(i).map(|k| k.0.to_vec()).ok_or_else(|| {
+            IngesterError::DeserializationError("Missing Auction House account".to_string())
+        })
+    };
+    let slot = bundle.slot as i64;
+    let txn = db.begin().await?;
+    match ix {
+        AuctionHouseInstruction::Sell(args) => {
+            let metadata = key(2)?;
+            let token_account = key(1)?;
+            let model = listings::ActiveModel {
+                trade_state: Set(key(6)?),
+                auction_house: Set(key(4)?),
+                asset_id: Set(find_asset(&metadata, &token_account, &txn).await?),
+                metadata: Set(metadata),
+                seller: Set(key(0)?),
+                token_account: Set(token_account),
+                price: Set(args.buyer_price as i64),
+                token_size: Set(args.token_size as i64),
+                active: Set(true),
+                slot_updated: Set(slot),
+            };
+            let mut query = listings::Entity::insert(model)
+                .on_conflict(
+                    OnConflict::columns([listings::Column::TradeState])
+                        .update_columns([
+                            listings::Column::AuctionHouse,
+                            listings::Column::AssetId,
+                            listings::Column::Metadata,
+                            listings::Column::Seller,
+                            listings::Column::TokenAccount,
+                            listings::Column::Price,
+                            listings::Column::TokenSize,
+                            listings::Column::Active,
+                            listings::Column::SlotUpdated,
+                        ])
+                        .to_owned(),
+                )
+                .build(DbBackend::Postgres);
+            // A trade state closed and reopened within a slot is active again.
+            query.sql = format!(
+                "{} WHERE excluded.slot_updated >= listings.slot_updated",
+                query.sql
+            );
+            txn.execute(query).await?;
+        }
+        AuctionHouseInstruction::Buy(args) | AuctionHouseInstruction::PublicBuy(args) => {
+            let metadata = key(5)?;
+            let token_account = key(4)?;
+            let model = bids::ActiveModel {
+                trade_state: Set(key(10)?),
+                auction_house: Set(key(8)?),
+                asset_id: Set(find_asset(&metadata, &token_account, &txn).await?),
+                metadata: Set(metadata),
+                buyer: Set(key(0)?),
+                token_account: Set(token_account),
+                treasury_mint: Set(key(3)?),
+                price: Set(args.buyer_price as i64),
+                token_size: Set(args.token_size as i64),
+                active: Set(true),
+                slot_updated: Set(slot),
+            };
+            let mut query = bids::Entity::insert(model)
+                .on_conflict(
+                    OnConflict::columns([bids::Column::TradeState])
+                        .update_columns([
+                            bids::Column::AuctionHouse,
+                            bids::Column::AssetId,
+                            bids::Column::Metadata,
+                            bids::Column::Buyer,
+                            bids::Column::TokenAccount,
+                            bids::Column::TreasuryMint,
+                            bids::Column::Price,
+                            bids::Column::TokenSize,
+                            bids::Column::Active,
+                            bids::Column::SlotUpdated,
+                        ])
+                        .to_owned(),
+                )
+                .build(DbBackend::Postgres);
+            query.sql = format!(
+                "{} WHERE excluded.slot_updated >= bids.slot_updated",
+                query.sql
+            );
+            txn.execute(query).await?;
+        }
+        AuctionHouseInstruction::Cancel(_) => {
+            close_trade_state(key(6)?, slot, &txn).await?;
+        }
+        AuctionHouseInstruction::ExecuteSale(args) => {
+            let mint = key(3)?;
+            close_trade_state(key(13)?, slot, &txn).await?;
+            close_trade_state(key(14)?, slot, &txn).await?;
+            let model = asset_history::ActiveModel {
+                asset_id: Set(mint),
+                event: Set("sale".to_string()),
+                txn_id: Set(bundle.txn_id.to_string()),
+                slot: Set(slot),
+                data: Set(json!({
+                    "seller": bs58::encode(key(1)?).into_string(),
+                    "buyer": bs58::encode(key(0)?).into_string(),
+                    "price": args.buyer_price,
+                    "tokenSize": args.token_size,
+                    "treasuryMint": bs58::encode(key(5)?).into_string(),
+                    "auctionHouse": bs58::encode(key(10)?).into_string(),
+                })),
+                ..Default::default()
+            };
+            let query = asset_history::Entity::insert(model)
+                .on_conflict(
+                    OnConflict::columns([
+                        asset_history::Column::AssetId,
+                        asset_history::Column::Event,
+                        asset_history::Column::TxnId,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                )
+                .build(DbBackend::Postgres);
+            txn.execute(query).await?;
+        }
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Sells and bids name the metadata and token accounts of the asset, not its mint.  Trades of
+/// assets that aren't indexed yet are linked once their metadata is.
+async fn find_asset(
+    metadata: &[u8],
+    token_account: &[u8],
+    txn: &DatabaseTransaction,
+) -> Result<Option<Vec<u8>>, IngesterError> {
+    let by_metadata = asset::Entity::find()
+        .filter(asset::Column::MetadataAddress.eq(metadata.to_vec()))
+        .one(txn)
+        .await?;
+    if let Some(asset) = by_metadata {
+        return Ok(Some(asset.id));
+    }
+    let by_token_account = token_accounts::Entity::find_by_id(token_account.to_vec())
+        .one(txn)
+        .await?;
+    Ok(by_token_account.map(|t| t.mint))
+}
+
+async fn close_trade_state(
+    trade_state: Vec<u8>,
+    slot: i64,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    listings::Entity::update_many()
+        .col_expr(listings::Column::Active, Expr::value(false))
+        .col_expr(listings::Column::SlotUpdated, Expr::value(slot))
+        .filter(listings::Column::TradeState.eq(trade_state.clone()))
+        .filter(listings::Column::SlotUpdated.lte(slot))
+        .exec(txn)
+        .await?;
+    bids::Entity::update_many()
+        .col_expr(bids::Column::Active, Expr::value(false))
+        .col_expr(bids::Column::SlotUpdated, Expr::value(slot))
+        .filter(bids::Column::TradeState.eq(trade_state))
+        .filter(bids::Column::SlotUpdated.lte(slot))
+        .exec(txn)
+        .await?;
+    Ok(())
+}
+
+/// Links the trades of an asset that were indexed before its metadata.
+pub async fn link_trades(
+    asset_id: &[u8],
+    metadata: &[u8],
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    listings::Entity::update_many()
+        .col_expr(listings::Column::AssetId, Expr::value(asset_id.to_vec()))
+        .filter(listings::Column::Metadata.eq(metadata.to_vec()))
+        .filter(listings::Column::AssetId.is_null())
+        .exec(txn)
+        .await?;
+    bids::Entity::update_many()
+        .col_expr(bids::Column::AssetId, Expr::value(asset_id.to_vec()))
+        .filter(bids::Column::Metadata.eq(metadata.to_vec()))
+        .filter(bids::Column::AssetId.is_null())
+        .exec(txn)
+        .await?;
+    Ok(())
+}
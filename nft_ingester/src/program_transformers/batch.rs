@@ -0,0 +1,163 @@
+//! Account updates written in bulk.  A handler that can write an account update as a plain row
+//! hands it over through `ProgramHandler::batch_row`, the rows buffered in an `AccountBatch` are
+//! then written with a multi-row upsert per table, all of them in one transaction.
+use digital_asset_types::dao::{token_accounts, tokens};
+use plerkle_messenger::RecvData;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashMap, time::Duration};
+use tokio::time::Instant;
+
+pub const DEFAULT_BATCH_SIZE: usize = 1000;
+pub const DEFAULT_BATCH_LATENCY: Duration = Duration::from_millis(100);
+
+/// The row an account update writes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchRow {
+    TokenAccount(token_accounts::Model),
+    Mint(tokens::Model),
+}
+
+impl BatchRow {
+    pub fn key(&self) -> &[u8] {
+        match self {
+            BatchRow::TokenAccount(ta) => &ta.pubkey,
+            BatchRow::Mint(m) => &m.mint,
+        }
+    }
+
+    fn version(&self) -> (i64, i64) {
+        match self {
+            BatchRow::TokenAccount(ta) => (ta.slot_updated, ta.write_version),
+            BatchRow::Mint(m) => (m.slot_updated, m.write_version),
+        }
+    }
+}
+
+/// Account updates buffered until `max_size` messages are buffered or `max_latency` after the
+/// first one.  Only the newest update of each account is written, the messages of all of them
+/// are acknowledged once the batch is.
+pub struct AccountBatch {
+    rows: HashMap<(Pubkey, Vec<u8>), BatchRow>,
+    messages: Vec<RecvData>,
+    first: Option<Instant>,
+    max_size: usize,
+    max_latency: Duration,
+}
+
+impl AccountBatch {
+    pub fn new(max_size: usize, max_latency: Duration) -> Self {
+        AccountBatch {
+            rows: HashMap::new(),
+            messages: Vec::new(),
+            first: None,
+            max_size,
+            max_latency,
+        }
+    }
+
+    /// Buffers the row written by `message` for the handler of `program`.
+    pub fn push(&mut self, program: Pubkey, row: BatchRow, message: RecvData) {
+        self.first.get_or_insert_with(Instant::now);
+        self.messages.push(message);
+        let key = (program, row.key().to_vec());
+        match self.rows.get(&key) {
+            Some(buffered) if buffered.version() >= row.version() => {}
+            _ => {
+                self.rows.insert(key, row);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.messages.len() >= self.max_size || self.time_left() == Some(Duration::ZERO)
+    }
+
+    /// Time until the batch is due, none while it is empty.
+    pub fn time_left(&self) -> Option<Duration> {
+        self.first
+            .map(|first| self.max_latency.saturating_sub(first.elapsed()))
+    }
+
+    /// Empties the batch, returning the rows of each handler and the messages they came from.
+    pub fn take(&mut self) -> (HashMap<Pubkey, Vec<BatchRow>>, Vec<RecvData>) {
+        self.first = None;
+        let mut rows: HashMap<Pubkey, Vec<BatchRow>> = HashMap::new();
+        for ((program, _), row) in self.rows.drain() {
+            rows.entry(program).or_default().push(row);
+        }
+        (rows, std::mem::take(&mut self.messages))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mint(key: &Pubkey, slot: i64, write_version: i64, supply: i64) -> BatchRow {
+        BatchRow::Mint(tokens::Model {
+            mint: key.to_bytes().to_vec(),
+            supply,
+            decimals: 0,
+            token_program: spl_token::id().to_bytes().to_vec(),
+            mint_authority: None,
+            freeze_authority: None,
+            close_authority: None,
+            extension_data: None,
+            slot_updated: slot,
+            write_version,
+        })
+    }
+
+    fn message(id: &str) -> RecvData {
+        RecvData {
+            id: id.to_string(),
+            tries: 0,
+            data: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn keeps_the_newest_update_of_each_account() {
+        let program = spl_token::id();
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut batch = AccountBatch::new(10, Duration::from_secs(60));
+        assert!(batch.is_empty());
+        assert_eq!(batch.time_left(), None);
+
+        batch.push(program, mint(&a, 100, 2, 20), message("1"));
+        batch.push(program, mint(&a, 100, 3, 30), message("2"));
+        batch.push(program, mint(&a, 100, 1, 10), message("3"));
+        batch.push(program, mint(&b, 99, 7, 70), message("4"));
+        batch.push(program, mint(&b, 101, 0, 80), message("5"));
+        assert!(!batch.is_due());
+
+        let (rows, messages) = batch.take();
+        let mut rows = rows.get(&program).unwrap().clone();
+        rows.sort_by_key(|r| r.key().to_vec());
+        let mut expected = vec![mint(&a, 100, 3, 30), mint(&b, 101, 0, 80)];
+        expected.sort_by_key(|r| r.key().to_vec());
+        assert_eq!(rows, expected);
+        let ids: Vec<&str> = messages.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "2", "3", "4", "5"]);
+        assert!(batch.is_empty());
+        assert_eq!(batch.time_left(), None);
+    }
+
+    #[test]
+    fn is_due_when_full_or_late() {
+        let program = spl_token::id();
+        let mut batch = AccountBatch::new(2, Duration::from_secs(60));
+        batch.push(program, mint(&Pubkey::new_unique(), 1, 0, 1), message("1"));
+        assert!(!batch.is_due());
+        batch.push(program, mint(&Pubkey::new_unique(), 1, 0, 1), message("2"));
+        assert!(batch.is_due());
+
+        let mut batch = AccountBatch::new(2, Duration::ZERO);
+        batch.push(program, mint(&Pubkey::new_unique(), 1, 0, 1), message("1"));
+        assert!(batch.is_due());
+    }
+}
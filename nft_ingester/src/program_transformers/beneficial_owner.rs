@@ -0,0 +1,281 @@
+//! Resolution of the wallet an escrowed or staked asset belongs to.  Such an asset is owned on
+//! chain by a PDA, `asset.beneficial_owner` records the wallet behind it.
+use crate::{slots::set_write_slot, EscrowProgramConfig, IngesterError};
+use borsh::BorshDeserialize;
+use digital_asset_types::dao::{asset, token_owned_escrow};
+use plerkle_serialization::AccountInfo;
+use sea_orm::{
+    entity::*, query::*, sea_query::Expr, sea_query::OnConflict, ConnectionTrait,
+    DatabaseConnection, DatabaseTransaction, DbBackend, EntityTrait, TransactionTrait,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+// `Key::TokenOwnedEscrow` of the token metadata program.
+const TOKEN_OWNED_ESCROW_KEY: u8 = 10;
+
+#[derive(BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum EscrowAuthority {
+    TokenOwner,
+    Creator(Pubkey),
+}
+
+/// A token owned escrow of the token metadata program, an account that holds tokens on behalf
+/// of whoever holds `base_token`, or of a creator.
+#[derive(BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TokenOwnedEscrow {
+    pub key: u8,
+    pub base_token: Pubkey,
+    pub authority: EscrowAuthority,
+    pub bump: u8,
+}
+
+pub fn parse_token_owned_escrow(data: &[u8]) -> Option<TokenOwnedEscrow> {
+    if data.first() != Some(&TOKEN_OWNED_ESCROW_KEY) {
+        return None;
+    }
+    TokenOwnedEscrow::deserialize(&mut &data[..]).ok()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Seed {
+    Literal(Vec<u8>),
+    Wallet,
+    Mint,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct EscrowProgram {
+    program: Pubkey,
+    seeds: Vec<Seed>,
+}
+
+/// Recognises the vaults of the configured escrow and staking programs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BeneficialOwnerResolver {
+    programs: Vec<EscrowProgram>,
+}
+
+impl BeneficialOwnerResolver {
+    pub fn new(config: &[EscrowProgramConfig]) -> Result<Self, IngesterError> {
+        let programs = config
+            .iter()
+            .map(|c| {
+                let program = Pubkey::from_str(&c.program).map_err(|_| {
+                    IngesterError::ConfigurationError {
+                        msg: format!("Invalid escrow program: {}", c.program),
+                    }
+                })?;
+                let seeds = c
+                    .seeds
+                    .iter()
+                    .map(|s| match s.as_str() {
+                        "$wallet" => Seed::Wallet,
+                        "$mint" => Seed::Mint,
+                        literal => Seed::Literal(literal.as_bytes().to_vec()),
+                    })
+                    .collect();
+                Ok(EscrowProgram { program, seeds })
+            })
+            .collect::<Result<_, IngesterError>>()?;
+        Ok(BeneficialOwnerResolver { programs })
+    }
+
+    /// Whether `vault` is the PDA of a configured program holding `mint` deposited by `wallet`.
+    pub fn is_vault_of(&self, vault: &Pubkey, wallet: &Pubkey, mint: &Pubkey) -> bool {
+        self.programs.iter().any(|p| {
+            let seeds: Vec<&[u8]> = p
+                .seeds
+                .iter()
+                .map(|s| match s {
+                    Seed::Literal(bytes) => bytes.as_slice(),
+                    Seed::Wallet => wallet.as_ref(),
+                    Seed::Mint => mint.as_ref(),
+                })
+                .collect();
+            Pubkey::try_find_program_address(&seeds, &p.program)
+                .map(|(pda, _)| pda == *vault)
+                .unwrap_or(false)
+        })
+    }
+
+    /// Beneficial owner of `mint` once it is held by `owner`, `previous` being the asset as it
+    /// was indexed before.  A token moved into a vault belongs to the wallet it came from, and
+    /// keeps its beneficial owner for as long as it stays there.
+    pub async fn resolve(
+        &self,
+        mint: &[u8],
+        owner: &[u8],
+        previous: Option<&asset::Model>,
+        txn: &DatabaseTransaction,
+    ) -> Result<Option<Vec<u8>>, IngesterError> {
+        if let Some(beneficial_owner) = escrow_beneficial_owner(owner, txn).await? {
+            return Ok(Some(beneficial_owner));
+        }
+        let previous_owner = match previous.and_then(|a| a.owner.as_ref()) {
+            Some(previous_owner) => previous_owner,
+            None => return Ok(None),
+        };
+        if previous_owner == owner {
+            return Ok(previous.and_then(|a| a.beneficial_owner.clone()));
+        }
+        let is_vault = self.is_vault_of(
+            &Pubkey::new(owner),
+            &Pubkey::new(previous_owner),
+            &Pubkey::new(mint),
+        );
+        Ok(is_vault.then(|| previous_owner.clone()))
+    }
+}
+
+/// Beneficial owner of whatever a token owned escrow holds, the creator it was created for or
+/// the holder of its base token.
+pub async fn escrow_beneficial_owner(
+    escrow: &[u8],
+    txn: &DatabaseTransaction,
+) -> Result<Option<Vec<u8>>, IngesterError> {
+    let escrow = match token_owned_escrow::Entity::find_by_id(escrow.to_vec())
+        .one(txn)
+        .await?
+    {
+        Some(escrow) => escrow,
+        None => return Ok(None),
+    };
+    if escrow.creator.is_some() {
+        return Ok(escrow.creator);
+    }
+    let base = asset::Entity::find_by_id(escrow.base_token)
+        .one(txn)
+        .await?;
+    Ok(base.and_then(|a| a.beneficial_owner.or(a.owner)))
+}
+
+/// Hands the tokens held by the escrows of `base_token` to its new holder.
+pub async fn update_escrowed_assets(
+    base_token: &[u8],
+    holder: Option<Vec<u8>>,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    let escrows = token_owned_escrow::Entity::find()
+        .select_only()
+        .column(token_owned_escrow::Column::Id)
+        .filter(token_owned_escrow::Column::BaseToken.eq(base_token.to_vec()))
+        .filter(token_owned_escrow::Column::Creator.is_null())
+        .into_query();
+    asset::Entity::update_many()
+        .col_expr(asset::Column::BeneficialOwner, Expr::value(holder))
+        .filter(asset::Column::Owner.in_subquery(escrows))
+        .exec(txn)
+        .await?;
+    Ok(())
+}
+
+pub async fn handle_token_owned_escrow_account<'a>(
+    account_update: &'a AccountInfo<'a>,
+    escrow: TokenOwnedEscrow,
+    db: &DatabaseConnection,
+) -> Result<(), IngesterError> {
+    let key = account_update.pubkey().unwrap().0.to_vec();
+    let model = token_owned_escrow::ActiveModel {
+        id: Set(key.clone()),
+        base_token: Set(escrow.base_token.to_bytes().to_vec()),
+        creator: Set(match escrow.authority {
+            EscrowAuthority::TokenOwner => None,
+            EscrowAuthority::Creator(creator) => Some(creator.to_bytes().to_vec()),
+        }),
+        slot_updated: Set(account_update.slot() as i64),
+    };
+    let mut query = token_owned_escrow::Entity::insert(model)
+        .on_conflict(
+            OnConflict::columns([token_owned_escrow::Column::Id])
+                .update_columns([
+                    token_owned_escrow::Column::BaseToken,
+                    token_owned_escrow::Column::Creator,
+                    token_owned_escrow::Column::SlotUpdated,
+                ])
+                .to_owned(),
+        )
+        .build(DbBackend::Postgres);
+    query.sql = format!(
+        "{} WHERE excluded.slot_updated > token_owned_escrow.slot_updated",
+        query.sql
+    );
+
+    let txn = db.begin().await?;
+    set_write_slot(&txn, Some(account_update.slot())).await?;
+    txn.execute(query).await?;
+    // Tokens may have been sent to the escrow before it was indexed.
+    let beneficial_owner = escrow_beneficial_owner(&key, &txn).await?;
+    asset::Entity::update_many()
+        .col_expr(
+            asset::Column::BeneficialOwner,
+            Expr::value(beneficial_owner),
+        )
+        .filter(asset::Column::Owner.eq(key))
+        .exec(&txn)
+        .await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshSerialize;
+
+    #[test]
+    fn recognises_configured_vaults() {
+        let program = Pubkey::new_unique();
+        let resolver = BeneficialOwnerResolver::new(&[EscrowProgramConfig {
+            program: program.to_string(),
+            seeds: vec![
+                "vault".to_string(),
+                "$wallet".to_string(),
+                "$mint".to_string(),
+            ],
+        }])
+        .unwrap();
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (vault, _) =
+            Pubkey::find_program_address(&[b"vault", wallet.as_ref(), mint.as_ref()], &program);
+
+        assert!(resolver.is_vault_of(&vault, &wallet, &mint));
+        assert!(!resolver.is_vault_of(&vault, &Pubkey::new_unique(), &mint));
+        assert!(!resolver.is_vault_of(&Pubkey::new_unique(), &wallet, &mint));
+        assert!(!BeneficialOwnerResolver::default().is_vault_of(&vault, &wallet, &mint));
+    }
+
+    #[test]
+    fn rejects_invalid_programs() {
+        let config = EscrowProgramConfig {
+            program: "not a pubkey".to_string(),
+            seeds: vec![],
+        };
+        assert!(BeneficialOwnerResolver::new(&[config]).is_err());
+    }
+
+    #[test]
+    fn parses_token_owned_escrows() {
+        let base_token = Pubkey::new_unique();
+        let creator = Pubkey::new_unique();
+        let mut data = vec![TOKEN_OWNED_ESCROW_KEY];
+        data.extend(base_token.try_to_vec().unwrap());
+        data.extend([1]);
+        data.extend(creator.try_to_vec().unwrap());
+        data.extend([254]);
+
+        assert_eq!(
+            parse_token_owned_escrow(&data),
+            Some(TokenOwnedEscrow {
+                key: TOKEN_OWNED_ESCROW_KEY,
+                base_token,
+                authority: EscrowAuthority::Creator(creator),
+                bump: 254,
+            })
+        );
+        // Metadata accounts and other token metadata accounts aren't escrows.
+        data[0] = 4;
+        assert_eq!(parse_token_owned_escrow(&data), None);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    program_transformers::bubblegum::db::update_asset, tasks::common::save_changelog_event,
+    program_transformers::bubblegum::{db::update_asset, Outcome},
     IngesterError,
 };
 use blockbuster::{
@@ -7,15 +7,15 @@ use blockbuster::{
     programs::bubblegum::{BubblegumInstruction, LeafSchema},
 };
 use digital_asset_types::dao::asset;
-use sea_orm::{entity::*, DatabaseTransaction};
+use sea_orm::entity::*;
 
-pub async fn burn<'c>(
-    parsing_result: &BubblegumInstruction,
-    bundle: &InstructionBundle<'c>,
-    txn: &'c DatabaseTransaction,
-) -> Result<(), IngesterError> {
+pub fn burn<'c>(
+    parsing_result: &'c BubblegumInstruction,
+    bundle: &InstructionBundle,
+) -> Result<Outcome<'c>, IngesterError> {
     if let (Some(le), Some(cl)) = (&parsing_result.leaf_update, &parsing_result.tree_update) {
-        let seq = save_changelog_event(cl, bundle.slot, txn).await?;
+        let mut outcome = Outcome::default();
+        let seq = outcome.change_log(cl, bundle.slot);
         return match le.schema {
             LeafSchema::V1 { id, .. } => {
                 let id_bytes = id.to_bytes().to_vec();
@@ -27,7 +27,8 @@ pub async fn burn<'c>(
                 };
                 // Don't send sequence number with this update, because we will always
                 // run this update even if it's from a backfill/replay.
-                update_asset(txn, id_bytes, None, asset_to_update).await
+                outcome.push(update_asset(id_bytes, None, asset_to_update));
+                Ok(outcome)
             }
             _ => Err(IngesterError::NotImplemented),
         };
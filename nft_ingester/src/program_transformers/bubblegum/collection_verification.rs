@@ -1,24 +1,27 @@
 use blockbuster::instruction::InstructionBundle;
 use blockbuster::programs::bubblegum::{BubblegumInstruction, LeafSchema, Payload};
 use digital_asset_types::dao::{asset, asset_grouping};
+use mpl_bubblegum::state::metaplex_adapter::Collection;
 use sea_orm::{
     entity::*, query::*, sea_query::OnConflict, DatabaseTransaction, DbBackend, Set, Unchanged,
 };
 
-use crate::program_transformers::bubblegum::update_asset;
-use crate::tasks::common::save_changelog_event;
+use crate::program_transformers::bubblegum::{
+    instruction_metadata, update_asset, update_metadata, Outcome,
+};
 use crate::IngesterError;
 pub async fn process<'c>(
     parsing_result: &'c BubblegumInstruction,
     bundle: &'c InstructionBundle<'c>,
-    txn: &'c DatabaseTransaction,
     verify: bool,
-) -> Result<(), IngesterError> {
+    txn: &'c DatabaseTransaction,
+) -> Result<Outcome<'c>, IngesterError> {
     if let (Some(le), Some(cl)) = (&parsing_result.leaf_update, &parsing_result.tree_update) {
         // Do we need to update the `slot_updated` field as well as part of the table
         // updates below?
-        let seq = save_changelog_event(cl, bundle.slot, txn).await?;
-        match le.schema {
+        let mut outcome = Outcome::default();
+        let seq = outcome.change_log(cl, bundle.slot);
+        return match le.schema {
             LeafSchema::V1 { id, .. } => {
                 let id_bytes = id.to_bytes().to_vec();
 
@@ -28,48 +31,58 @@ pub async fn process<'c>(
                     seq: Set(seq as i64),
                     ..Default::default()
                 };
-                update_asset(txn, id_bytes.clone(), Some(seq), asset_to_update).await?;
+                outcome.push(update_asset(id_bytes.clone(), Some(seq), asset_to_update));
 
-                if verify {
-                    if let Some(Payload::SetAndVerifyCollection { collection }) =
-                        parsing_result.payload
-                    {
-                        let grouping = asset_grouping::ActiveModel {
-                            asset_id: Set(id_bytes.clone()),
-                            group_key: Set("collection".to_string()),
-                            group_value: Set(collection.to_string()),
-                            seq: Set(seq as i64),
-                            slot_updated: Set(bundle.slot as i64),
-                            ..Default::default()
-                        };
-                        let mut query = asset_grouping::Entity::insert(grouping)
-                            .on_conflict(
-                                OnConflict::columns([
-                                    asset_grouping::Column::AssetId,
-                                    asset_grouping::Column::GroupKey,
-                                ])
-                                .update_columns([
-                                    asset_grouping::Column::GroupKey,
-                                    asset_grouping::Column::GroupValue,
-                                    asset_grouping::Column::Seq,
-                                    asset_grouping::Column::SlotUpdated,
-                                ])
-                                .to_owned(),
-                            )
-                            .build(DbBackend::Postgres);
-                        query.sql = format!(
-                    "{} WHERE excluded.slot_updated > asset_grouping.slot_updated AND excluded.seq >= asset_grouping.seq",
-                    query.sql
-                );
-                        txn.execute(query).await?;
+                let mut metadata = instruction_metadata(&parsing_result.instruction, bundle)?;
+                match (&parsing_result.payload, metadata.collection.as_mut()) {
+                    (Some(Payload::SetAndVerifyCollection { collection }), _) => {
+                        metadata.collection = Some(Collection {
+                            verified: true,
+                            key: *collection,
+                        });
                     }
+                    (_, Some(c)) => c.verified = verify,
+                    _ => {}
                 }
-                id_bytes
+
+                if let Some(c) = &metadata.collection {
+                    let grouping = asset_grouping::ActiveModel {
+                        asset_id: Set(id_bytes.clone()),
+                        group_key: Set("collection".to_string()),
+                        group_value: Set(c.key.to_string()),
+                        seq: Set(seq as i64),
+                        slot_updated: Set(bundle.slot as i64),
+                        verified: Set(c.verified),
+                        ..Default::default()
+                    };
+                    let mut query = asset_grouping::Entity::insert(grouping)
+                        .on_conflict(
+                            OnConflict::columns([
+                                asset_grouping::Column::AssetId,
+                                asset_grouping::Column::GroupKey,
+                            ])
+                            .update_columns([
+                                asset_grouping::Column::GroupKey,
+                                asset_grouping::Column::GroupValue,
+                                asset_grouping::Column::Seq,
+                                asset_grouping::Column::SlotUpdated,
+                                asset_grouping::Column::Verified,
+                            ])
+                            .to_owned(),
+                        )
+                        .build(DbBackend::Postgres);
+                    query.sql = format!(
+                        "{} WHERE excluded.slot_updated > asset_grouping.slot_updated AND excluded.seq >= asset_grouping.seq",
+                        query.sql
+                    );
+                    outcome.push(query);
+                }
+
+                update_metadata(&mut outcome, id_bytes, seq, bundle.slot, &metadata, txn).await?;
+                Ok(outcome)
             }
-            _ => return Err(IngesterError::NotImplemented),
+            _ => Err(IngesterError::NotImplemented),
         };
-
-        return Ok(());
     };
     Err(IngesterError::ParsingError(
         "Ix not parsed correctly".to_string(),
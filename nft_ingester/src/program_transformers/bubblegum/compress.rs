@@ -0,0 +1,174 @@
+use crate::{
+    program_transformers::bubblegum::{account_key, Outcome},
+    IngesterError,
+};
+use blockbuster::{
+    instruction::InstructionBundle,
+    programs::bubblegum::{BubblegumInstruction, LeafSchema},
+};
+use digital_asset_types::dao::{
+    asset, asset_authority, asset_creators, asset_grouping,
+    sea_orm_active_enums::{
+        OwnerType, RoyaltyTargetType, SpecificationAssetClass, SpecificationVersions,
+    },
+};
+use sea_orm::{
+    entity::*, query::*, sea_query::OnConflict, DatabaseTransaction, DbBackend, EntityTrait,
+};
+
+pub async fn compress<'c>(
+    parsing_result: &'c BubblegumInstruction,
+    bundle: &InstructionBundle<'c>,
+    txn: &'c DatabaseTransaction,
+) -> Result<Outcome<'c>, IngesterError> {
+    if let (Some(le), Some(cl)) = (&parsing_result.leaf_update, &parsing_result.tree_update) {
+        let mut outcome = Outcome::default();
+        let seq = outcome.change_log(cl, bundle.slot);
+        return match le.schema {
+            LeafSchema::V1 {
+                id,
+                delegate,
+                owner,
+                nonce,
+                data_hash,
+                creator_hash,
+            } => {
+                let id_bytes = id.to_bytes().to_vec();
+                let mint = account_key(bundle, 5)?;
+                let slot_i = bundle.slot as i64;
+
+                // The compressed leaf is a new asset.  Its data stays with the mint it was
+                // compressed from, which is burnt by the token program.
+                let decompressed = asset::Entity::find_by_id(mint.clone()).one(txn).await?;
+                let delegate = if owner == delegate {
+                    None
+                } else {
+                    Some(delegate.to_bytes().to_vec())
+                };
+                let model = asset::ActiveModel {
+                    id: Set(id_bytes.clone()),
+                    owner: Set(Some(owner.to_bytes().to_vec())),
+                    owner_type: Set(OwnerType::Single),
+                    delegate: Set(delegate),
+                    frozen: Set(false),
+                    supply: Set(1),
+                    supply_mint: Set(None),
+                    compressed: Set(true),
+                    tree_id: Set(Some(account_key(bundle, 3)?)),
+                    specification_version: Set(SpecificationVersions::V1),
+                    specification_asset_class: Set(decompressed
+                        .as_ref()
+                        .map(|a| a.specification_asset_class.clone())
+                        .unwrap_or(SpecificationAssetClass::Nft)),
+                    nonce: Set(nonce as i64),
+                    leaf: Set(Some(le.leaf_hash.to_vec())),
+                    royalty_target_type: Set(RoyaltyTargetType::Creators),
+                    royalty_target: Set(None),
+                    royalty_amount: Set(decompressed
+                        .as_ref()
+                        .map(|a| a.royalty_amount)
+                        .unwrap_or_default()),
+                    asset_data: Set(decompressed.and_then(|a| a.asset_data)),
+                    seq: Set(seq as i64),
+                    slot_updated: Set(slot_i),
+                    data_hash: Set(Some(bs58::encode(data_hash).into_string())),
+                    creator_hash: Set(Some(bs58::encode(creator_hash).into_string())),
+                    ..Default::default()
+                };
+
+                // Do not attempt to modify any existing values:
+                // `ON CONFLICT ('id') DO NOTHING`.
+                let query = asset::Entity::insert(model)
+                    .on_conflict(
+                        OnConflict::columns([asset::Column::Id])
+                            .do_nothing()
+                            .to_owned(),
+                    )
+                    .build(DbBackend::Postgres);
+                outcome.push(query);
+
+                let creators = asset_creators::Entity::find()
+                    .filter(asset_creators::Column::AssetId.eq(mint.clone()))
+                    .all(txn)
+                    .await?;
+                if !creators.is_empty() {
+                    let creators = creators.into_iter().map(|c| asset_creators::ActiveModel {
+                        asset_id: Set(id_bytes.clone()),
+                        creator: Set(c.creator),
+                        share: Set(c.share),
+                        verified: Set(c.verified),
+                        seq: Set(seq as i64),
+                        slot_updated: Set(slot_i),
+                        position: Set(c.position),
+                        ..Default::default()
+                    });
+                    let query = asset_creators::Entity::insert_many(creators)
+                        .on_conflict(
+                            OnConflict::columns([
+                                asset_creators::Column::AssetId,
+                                asset_creators::Column::Position,
+                            ])
+                            .do_nothing()
+                            .to_owned(),
+                        )
+                        .build(DbBackend::Postgres);
+                    outcome.push(query);
+                }
+
+                let authority = asset_authority::Entity::find()
+                    .filter(asset_authority::Column::AssetId.eq(mint.clone()))
+                    .one(txn)
+                    .await?;
+                if let Some(a) = authority {
+                    let model = asset_authority::ActiveModel {
+                        asset_id: Set(id_bytes.clone()),
+                        authority: Set(a.authority),
+                        seq: Set(seq as i64),
+                        slot_updated: Set(slot_i),
+                        ..Default::default()
+                    };
+                    let query = asset_authority::Entity::insert(model)
+                        .on_conflict(
+                            OnConflict::columns([asset_authority::Column::AssetId])
+                                .do_nothing()
+                                .to_owned(),
+                        )
+                        .build(DbBackend::Postgres);
+                    outcome.push(query);
+                }
+
+                let groups = asset_grouping::Entity::find()
+                    .filter(asset_grouping::Column::AssetId.eq(mint))
+                    .all(txn)
+                    .await?;
+                if !groups.is_empty() {
+                    let groups = groups.into_iter().map(|g| asset_grouping::ActiveModel {
+                        asset_id: Set(id_bytes.clone()),
+                        group_key: Set(g.group_key),
+                        group_value: Set(g.group_value),
+                        seq: Set(seq as i64),
+                        slot_updated: Set(slot_i),
+                        verified: Set(g.verified),
+                        ..Default::default()
+                    });
+                    let query = asset_grouping::Entity::insert_many(groups)
+                        .on_conflict(
+                            OnConflict::columns([
+                                asset_grouping::Column::AssetId,
+                                asset_grouping::Column::GroupKey,
+                            ])
+                            .do_nothing()
+                            .to_owned(),
+                        )
+                        .build(DbBackend::Postgres);
+                    outcome.push(query);
+                }
+                Ok(outcome)
+            }
+            _ => Err(IngesterError::NotImplemented),
+        };
+    }
+    Err(IngesterError::ParsingError(
+        "Ix not parsed correctly".to_string(),
+    ))
+}
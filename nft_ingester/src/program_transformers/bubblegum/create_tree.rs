@@ -0,0 +1,23 @@
+use crate::{
+    program_transformers::bubblegum::{account_key, Outcome},
+    IngesterError,
+};
+use blockbuster::instruction::InstructionBundle;
+use sea_orm::{DbBackend, Statement};
+
+pub fn create_tree<'c>(bundle: &InstructionBundle) -> Result<Outcome<'c>, IngesterError> {
+    let tree_id = account_key(bundle, 1)?;
+
+    // Seed the tree at sequence number 0 so the backfiller knows the slot the tree was created
+    // in and any gap up to the first indexed change can be detected.
+    let query = Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "INSERT INTO backfill_items (tree, seq, slot, force_chk, backfilled, failed) \
+         SELECT $1, 0, $2, false, false, false \
+         WHERE NOT EXISTS (SELECT 1 FROM backfill_items WHERE tree = $1)",
+        vec![tree_id.into(), (bundle.slot as i64).into()],
+    );
+    let mut outcome = Outcome::default();
+    outcome.push(query);
+    Ok(outcome)
+}
@@ -3,16 +3,17 @@ use blockbuster::programs::bubblegum::{BubblegumInstruction, LeafSchema, Payload
 use digital_asset_types::dao::{asset, asset_creators};
 use sea_orm::{DatabaseTransaction, Set, Unchanged};
 
-use crate::program_transformers::bubblegum::{update_asset, update_creator};
-use crate::tasks::common::save_changelog_event;
+use crate::program_transformers::bubblegum::{
+    instruction_metadata, update_asset, update_creator, update_metadata, Outcome,
+};
 use crate::IngesterError;
 
 pub async fn process<'c>(
     parsing_result: &'c BubblegumInstruction,
     bundle: &'c InstructionBundle<'c>,
-    txn: &'c DatabaseTransaction,
     value: bool,
-) -> Result<(), IngesterError> {
+    txn: &'c DatabaseTransaction,
+) -> Result<Outcome<'c>, IngesterError> {
     let maybe_creator = match parsing_result.payload {
         Some(Payload::VerifyCreator { creator }) => Some(creator),
         Some(Payload::UnverifyCreator { creator }) => Some(creator),
@@ -27,7 +28,8 @@ pub async fn process<'c>(
         // Do we need to update the `slot_updated` field as well as part of the table
         // updates below?
 
-        let seq = save_changelog_event(cl, bundle.slot, txn).await?;
+        let mut outcome = Outcome::default();
+        let seq = outcome.change_log(cl, bundle.slot);
         let asset_id_bytes = match le.schema {
             LeafSchema::V1 { id, .. } => {
                 let id_bytes = id.to_bytes().to_vec();
@@ -38,7 +40,7 @@ pub async fn process<'c>(
                     ..Default::default()
                 };
 
-                update_asset(txn, id_bytes.clone(), Some(seq), asset_to_update).await?;
+                outcome.push(update_asset(id_bytes.clone(), Some(seq), asset_to_update));
                 id_bytes
             }
             _ => return Err(IngesterError::NotImplemented),
@@ -53,16 +55,29 @@ pub async fn process<'c>(
             ..Default::default()
         };
 
-        update_creator(
-            txn,
-            asset_id_bytes,
+        outcome.push(update_creator(
+            asset_id_bytes.clone(),
             creator.to_bytes().to_vec(),
             seq,
             creator_to_update,
+        ));
+
+        let mut metadata = instruction_metadata(&parsing_result.instruction, bundle)?;
+        for c in metadata.creators.iter_mut() {
+            if c.address == creator {
+                c.verified = value;
+            }
+        }
+        update_metadata(
+            &mut outcome,
+            asset_id_bytes,
+            seq,
+            bundle.slot,
+            &metadata,
+            txn,
         )
         .await?;
-
-        return Ok(());
+        return Ok(outcome);
     }
     Err(IngesterError::ParsingError(
         "Ix not parsed correctly".to_string(),
@@ -1,54 +1,31 @@
-use crate::IngesterError;
 use digital_asset_types::dao::{asset, asset_creators};
-use sea_orm::{entity::*, query::*, ColumnTrait, DatabaseTransaction, DbErr, EntityTrait};
+use sea_orm::{entity::*, query::*, ColumnTrait, DbBackend, EntityTrait, Statement};
 
-pub async fn update_asset(
-    txn: &DatabaseTransaction,
-    id: Vec<u8>,
-    seq: Option<u64>,
-    model: asset::ActiveModel,
-) -> Result<(), IngesterError> {
-    let update_one = if let Some(seq) = seq {
-        asset::Entity::update(model)
-            .filter(asset::Column::Id.eq(id))
-            .filter(asset::Column::Seq.lte(seq))
+pub fn update_asset(id: Vec<u8>, seq: Option<u64>, model: asset::ActiveModel) -> Statement {
+    let update = asset::Entity::update_many()
+        .set(model)
+        .filter(asset::Column::Id.eq(id));
+    let update = if let Some(seq) = seq {
+        update.filter(asset::Column::Seq.lte(seq))
     } else {
-        asset::Entity::update(model).filter(asset::Column::Id.eq(id))
+        update
     };
-
-    match update_one.exec(txn).await {
-        Ok(_) => Ok(()),
-        Err(err) => match err {
-            DbErr::RecordNotFound(ref s) => {
-                if s.contains("None of the database rows are affected") {
-                    Ok(())
-                } else {
-                    Err(IngesterError::from(err))
-                }
-            }
-            _ => Err(IngesterError::from(err)),
-        },
-    }
+    update.build(DbBackend::Postgres)
 }
 
-pub async fn update_creator(
-    txn: &DatabaseTransaction,
+pub fn update_creator(
     asset_id: Vec<u8>,
     creator: Vec<u8>,
     seq: u64,
     model: asset_creators::ActiveModel,
-) -> Result<(), IngesterError> {
+) -> Statement {
     // Using `update_many` to avoid having to supply the primary key as well within `model`.
     // We still effectively end up updating a single row at most, which is uniquely identified
-    // by the `(asset_id, creator)` pair. Is there any reason why we should not use
-    // `update_many` here?
-    let update = asset_creators::Entity::update_many()
+    // by the `(asset_id, creator)` pair.
+    asset_creators::Entity::update_many()
         .filter(asset_creators::Column::AssetId.eq(asset_id))
         .filter(asset_creators::Column::Creator.eq(creator))
         .filter(asset_creators::Column::Seq.lte(seq))
-        .set(model);
-
-    update.exec(txn).await.map_err(IngesterError::from)?;
-
-    Ok(())
+        .set(model)
+        .build(DbBackend::Postgres)
 }
@@ -1,16 +1,16 @@
-use crate::IngesterError;
+use crate::{
+    program_transformers::bubblegum::{account_key, Outcome},
+    IngesterError,
+};
 use blockbuster::{instruction::InstructionBundle, programs::bubblegum::BubblegumInstruction};
 use digital_asset_types::dao::asset;
-use sea_orm::{
-    entity::*, query::*, ColumnTrait, ConnectionTrait, DatabaseTransaction, DbBackend, EntityTrait,
-};
+use sea_orm::{entity::*, query::*, ColumnTrait, DbBackend, EntityTrait};
 
-pub async fn decompress<'c>(
-    _parsing_result: &BubblegumInstruction,
-    bundle: &InstructionBundle<'c>,
-    txn: &'c DatabaseTransaction,
-) -> Result<(), IngesterError> {
-    let id_bytes = bundle.keys.get(3).unwrap().0.as_slice().to_vec();
+pub fn decompress<'c>(
+    _parsing_result: &'c BubblegumInstruction,
+    bundle: &InstructionBundle,
+) -> Result<Outcome<'c>, IngesterError> {
+    let id_bytes = account_key(bundle, 3)?;
 
     let model = asset::ActiveModel {
         id: Unchanged(id_bytes.clone()),
@@ -33,5 +33,7 @@ pub async fn decompress<'c>(
         .filter(asset::Column::Compressed.eq(true))
         .build(DbBackend::Postgres);
 
-    txn.execute(query).await.map(|_| ()).map_err(Into::into)
+    let mut outcome = Outcome::default();
+    outcome.push(query);
+    Ok(outcome)
 }
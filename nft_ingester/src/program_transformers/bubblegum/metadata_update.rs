@@ -0,0 +1,147 @@
+use crate::{
+    program_transformers::bubblegum::{instruction_data, update_asset, update_metadata, Outcome},
+    tasks::{common::task::DownloadMetadata, IntoTaskData},
+    IngesterError,
+};
+use anchor_lang::{AnchorDeserialize, AnchorSerialize};
+use blockbuster::{
+    instruction::InstructionBundle,
+    programs::bubblegum::{BubblegumInstruction, LeafSchema},
+};
+use chrono::Utc;
+use digital_asset_types::dao::{asset, asset_data};
+use mpl_bubblegum::state::metaplex_adapter::MetadataArgs;
+use mpl_bubblegum_sdk::{
+    get_instruction_type, instructions::UpdateMetadataInstructionArgs, InstructionName,
+};
+use sea_orm::{entity::*, query::*, DatabaseTransaction, DbBackend, EntityTrait, JsonValue};
+
+/// Whether the instruction is Bubblegum's `update_metadata`, which the parser reports as
+/// unknown.
+pub fn is_metadata_update(bundle: &InstructionBundle) -> bool {
+    match instruction_data(bundle) {
+        Ok(data) => matches!(get_instruction_type(&data), InstructionName::UpdateMetadata),
+        Err(_) => false,
+    }
+}
+
+/// The metadata of the leaf once `update_metadata` applied its changes.
+fn updated_metadata(bundle: &InstructionBundle) -> Result<MetadataArgs, IngesterError> {
+    let data = instruction_data(bundle)?;
+    // Skip the anchor discriminator.
+    let args = UpdateMetadataInstructionArgs::deserialize(&mut &data[8..])
+        .map_err(|e| IngesterError::DeserializationError(e.to_string()))?;
+    let (mut metadata, update) = (args.current_metadata, args.update_args);
+    if let Some(name) = update.name {
+        metadata.name = name;
+    }
+    if let Some(symbol) = update.symbol {
+        metadata.symbol = symbol;
+    }
+    if let Some(uri) = update.uri {
+        metadata.uri = uri;
+    }
+    if let Some(creators) = update.creators {
+        metadata.creators = creators;
+    }
+    if let Some(seller_fee_basis_points) = update.seller_fee_basis_points {
+        metadata.seller_fee_basis_points = seller_fee_basis_points;
+    }
+    if let Some(primary_sale_happened) = update.primary_sale_happened {
+        metadata.primary_sale_happened = primary_sale_happened;
+    }
+    if let Some(is_mutable) = update.is_mutable {
+        metadata.is_mutable = is_mutable;
+    }
+    // The SDK encodes the metadata the way the program does, as the adapter of the other
+    // instructions does.
+    let bytes = metadata
+        .try_to_vec()
+        .map_err(|e| IngesterError::SerializatonError(e.to_string()))?;
+    MetadataArgs::deserialize(&mut bytes.as_slice())
+        .map_err(|e| IngesterError::DeserializationError(e.to_string()))
+}
+
+/// Points the data of the asset at `uri` if it was pointing elsewhere, and downloads the
+/// metadata found there once the change is committed.
+async fn update_uri(
+    outcome: &mut Outcome<'_>,
+    id: Vec<u8>,
+    slot: u64,
+    uri: &str,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    let slot_i = slot as i64;
+    let uri = uri.trim().replace('\0', "");
+    if uri.is_empty() {
+        return Err(IngesterError::DeserializationError(
+            "URI is empty".to_string(),
+        ));
+    }
+    let current = asset_data::Entity::find_by_id(id.clone())
+        .filter(asset_data::Column::SlotUpdated.lte(slot_i))
+        .one(txn)
+        .await?;
+    match current {
+        Some(data) if data.metadata_url != uri => {}
+        _ => return Ok(()),
+    }
+
+    let data = asset_data::ActiveModel {
+        id: Unchanged(id.clone()),
+        metadata_url: Set(uri.clone()),
+        metadata: Set(JsonValue::String("processing".to_string())),
+        ..Default::default()
+    };
+    outcome.push(
+        asset_data::Entity::update(data)
+            .filter(asset_data::Column::SlotUpdated.lte(slot_i))
+            .build(DbBackend::Postgres),
+    );
+    let mut task = DownloadMetadata {
+        asset_data_id: id,
+        uri,
+        created_at: Some(Utc::now().naive_utc()),
+    };
+    task.sanitize();
+    outcome.enqueue(task.into_task_data()?);
+    Ok(())
+}
+
+pub async fn metadata_update<'c>(
+    parsing_result: &'c BubblegumInstruction,
+    bundle: &'c InstructionBundle<'c>,
+    txn: &'c DatabaseTransaction,
+) -> Result<Outcome<'c>, IngesterError> {
+    if let (Some(le), Some(cl)) = (&parsing_result.leaf_update, &parsing_result.tree_update) {
+        let mut outcome = Outcome::default();
+        let seq = outcome.change_log(cl, bundle.slot);
+        let id_bytes = match le.schema {
+            LeafSchema::V1 { id, .. } => id.to_bytes().to_vec(),
+            _ => return Err(IngesterError::NotImplemented),
+        };
+        let asset_to_update = asset::ActiveModel {
+            id: Unchanged(id_bytes.clone()),
+            leaf: Set(Some(le.leaf_hash.to_vec())),
+            seq: Set(seq as i64),
+            ..Default::default()
+        };
+        outcome.push(update_asset(id_bytes.clone(), Some(seq), asset_to_update));
+
+        let metadata = updated_metadata(bundle)?;
+        update_metadata(
+            &mut outcome,
+            id_bytes.clone(),
+            seq,
+            bundle.slot,
+            &metadata,
+            txn,
+        )
+        .await?;
+        update_uri(&mut outcome, id_bytes, bundle.slot, &metadata.uri, txn).await?;
+        return Ok(outcome);
+    }
+    Err(IngesterError::ParsingError(
+        "Ix not parsed correctly".to_string(),
+    ))
+}
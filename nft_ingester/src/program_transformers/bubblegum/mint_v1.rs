@@ -1,4 +1,10 @@
-use crate::{IngesterError, TaskData};
+use crate::{
+    program_transformers::{
+        bubblegum::{account_key, Outcome},
+        hydra::fanout_royalty,
+    },
+    IngesterError,
+};
 use blockbuster::{
     instruction::InstructionBundle,
     programs::bubblegum::{BubblegumInstruction, LeafSchema, Payload},
@@ -8,20 +14,18 @@ use digital_asset_types::{
         asset, asset_authority, asset_creators, asset_data, asset_grouping,
         asset_v1_account_attachments,
         sea_orm_active_enums::{ChainMutability, Mutability, OwnerType, RoyaltyTargetType},
+        tree_config,
     },
     json::ChainDataV1,
 };
 use num_traits::FromPrimitive;
 use sea_orm::{
-    entity::*, query::*, sea_query::OnConflict, ConnectionTrait, DatabaseTransaction, DbBackend,
-    EntityTrait, JsonValue,
+    entity::*, query::*, sea_query::OnConflict, DatabaseTransaction, DbBackend, EntityTrait,
+    JsonValue,
 };
 use std::collections::HashSet;
 
-use crate::tasks::{
-    common::{save_changelog_event, task::DownloadMetadata},
-    IntoTaskData,
-};
+use crate::tasks::{common::task::DownloadMetadata, IntoTaskData};
 use blockbuster::token_metadata::{
     pda::find_master_edition_account,
     state::{TokenStandard, UseMethod, Uses},
@@ -36,16 +40,17 @@ use mpl_bubblegum::{hash_creators, hash_metadata};
 // TODO -> consider moving structs into these functions to avoid clone
 
 pub async fn mint_v1<'c>(
-    parsing_result: &BubblegumInstruction,
+    parsing_result: &'c BubblegumInstruction,
     bundle: &InstructionBundle<'c>,
     txn: &'c DatabaseTransaction,
-) -> Result<TaskData, IngesterError> {
+) -> Result<Outcome<'c>, IngesterError> {
     if let (Some(le), Some(cl), Some(Payload::MintV1 { args })) = (
         &parsing_result.leaf_update,
         &parsing_result.tree_update,
         &parsing_result.payload,
     ) {
-        let seq = save_changelog_event(cl, bundle.slot, txn).await?;
+        let mut outcome = Outcome::default();
+        let seq = outcome.change_log(cl, bundle.slot);
         let metadata = args;
         return match le.schema {
             LeafSchema::V1 {
@@ -94,7 +99,7 @@ pub async fn mint_v1<'c>(
                     ..Default::default()
                 };
 
-                let mut query = asset_data::Entity::insert(data)
+                let query = asset_data::Entity::insert(data)
                     .on_conflict(
                         OnConflict::columns([asset_data::Column::Id])
                             .update_columns([
@@ -108,11 +113,7 @@ pub async fn mint_v1<'c>(
                             .to_owned(),
                     )
                     .build(DbBackend::Postgres);
-                query.sql = format!(
-                    "{} WHERE excluded.slot_updated > asset_data.slot_updated",
-                    query.sql
-                );
-                txn.execute(query).await?;
+                outcome.push_slot_guarded(query, "asset_data");
                 // Insert into `asset` table.
                 let delegate = if owner == delegate {
                     None
@@ -163,7 +164,7 @@ pub async fn mint_v1<'c>(
                             .to_owned(),
                     )
                     .build(DbBackend::Postgres);
-                txn.execute(query).await?;
+                outcome.push(query);
 
                 let attachment = asset_v1_account_attachments::ActiveModel {
                     id: Set(edition_attachment_address.to_bytes().to_vec()),
@@ -179,7 +180,7 @@ pub async fn mint_v1<'c>(
                             .to_owned(),
                     )
                     .build(DbBackend::Postgres);
-                txn.execute(query).await?;
+                outcome.push(query);
 
                 // Insert into `asset_creators` table.
                 let creators = &metadata.creators;
@@ -219,12 +220,23 @@ pub async fn mint_v1<'c>(
                             .to_owned(),
                         )
                         .build(DbBackend::Postgres);
-                    txn.execute(query).await?;
+                    outcome.push(query);
                 }
-                // Insert into `asset_authority` table.
+                outcome.push(fanout_royalty(id_bytes.to_vec()));
+                // Insert into `asset_authority` table.  The authority of a compressed asset is
+                // the creator of its tree, which is only known once the `TreeConfig` account
+                // (the first account of the instruction) has been indexed.  Until then the config
+                // address stands in and is replaced when the account arrives.  The tree delegate
+                // only mints on the creator's behalf and can be swapped by it at any time, so it
+                // is not an authority of what it mints.
+                let tree_config_key = account_key(bundle, 0)?;
+                let authority = tree_config::Entity::find_by_id(tree_config_key.clone())
+                    .one(txn)
+                    .await?
+                    .map_or(tree_config_key, |config| config.tree_creator);
                 let model = asset_authority::ActiveModel {
                     asset_id: Set(id_bytes.to_vec()),
-                    authority: Set(bundle.keys.get(0).unwrap().0.to_vec()), //TODO - we need to rem,ove the optional bubblegum signer logic
+                    authority: Set(authority),
                     seq: Set(seq as i64),
                     slot_updated: Set(slot_i),
                     ..Default::default()
@@ -239,7 +251,7 @@ pub async fn mint_v1<'c>(
                             .to_owned(),
                     )
                     .build(DbBackend::Postgres);
-                txn.execute(query).await?;
+                outcome.push(query);
 
                 // Insert into `asset_grouping` table.
                 if let Some(c) = &metadata.collection {
@@ -250,6 +262,7 @@ pub async fn mint_v1<'c>(
                             group_value: Set(c.key.to_string()),
                             seq: Set(seq as i64), // gummyroll seq
                             slot_updated: Set(slot_i),
+                            verified: Set(true),
                             ..Default::default()
                         };
 
@@ -262,7 +275,7 @@ pub async fn mint_v1<'c>(
                                     .to_owned(),
                             )
                             .build(DbBackend::Postgres);
-                        txn.execute(query).await?;
+                        outcome.push(query);
                     }
                 }
                 let mut task = DownloadMetadata {
@@ -271,10 +284,11 @@ pub async fn mint_v1<'c>(
                     created_at: Some(Utc::now().naive_utc()),
                 };
                 task.sanitize();
-                return task.into_task_data();
+                outcome.enqueue(task.into_task_data()?);
+                Ok(outcome)
             }
             _ => Err(IngesterError::NotImplemented),
-        }?;
+        };
     }
     Err(IngesterError::ParsingError(
         "Ix not parsed correctly".to_string(),
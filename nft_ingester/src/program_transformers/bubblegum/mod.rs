@@ -3,23 +3,35 @@ use blockbuster::{
     instruction::InstructionBundle,
     programs::bubblegum::{BubblegumInstruction, InstructionName},
 };
-use sea_orm::{DatabaseConnection, TransactionTrait};
+use sea_orm::{DatabaseConnection, DatabaseTransaction, TransactionTrait};
 use tokio::sync::mpsc::UnboundedSender;
 
 mod burn;
 mod cancel_redeem;
 mod collection_verification;
+mod compress;
+mod create_tree;
 mod creator_verification;
 mod db;
 mod decompress;
 mod delegate;
+mod metadata_update;
 mod mint_v1;
+mod outcome;
 mod redeem;
 mod transfer;
+mod tree_config;
+mod update_metadata;
+
+#[cfg(test)]
+mod tests;
 
 pub use db::*;
+pub use outcome::*;
+pub use tree_config::*;
+pub use update_metadata::*;
 
-use crate::{IngesterError, TaskData};
+use crate::{slots::set_write_slot, IngesterError, TaskData};
 
 pub async fn handle_bubblegum_instruction<'c>(
     parsing_result: &'c BubblegumInstruction,
@@ -28,7 +40,6 @@ pub async fn handle_bubblegum_instruction<'c>(
     task_manager: &UnboundedSender<TaskData>,
 ) -> Result<(), IngesterError> {
     let ix_type = &parsing_result.instruction;
-    let txn = db.begin().await?;
     match ix_type {
         InstructionName::Unknown => {
             println!("Unknown instruction:");
@@ -80,53 +91,70 @@ pub async fn handle_bubblegum_instruction<'c>(
         }
     }
 
-    match ix_type {
-        InstructionName::Transfer => {
-            transfer::transfer(parsing_result, bundle, &txn).await?;
-            txn.commit().await?;
-        }
-        InstructionName::Burn => {
-            burn::burn(parsing_result, bundle, &txn).await?;
-            txn.commit().await?;
-        }
-        InstructionName::Delegate => {
-            delegate::delegate(parsing_result, bundle, &txn).await?;
-        }
+    // Every instruction is applied within a single transaction, the tasks it produced are only
+    // enqueued once the writes they depend on have been committed.
+    let txn = db.begin().await?;
+    let outcome = match instruction_outcome(parsing_result, bundle, &txn).await? {
+        Some(outcome) => outcome,
+        None => {
+            println!("Bubblegum: Not Implemented Instruction");
+            return Ok(());
+        }
+    };
+    // Leaf changes leave `slot_updated` alone, the writes are made for the slot of the
+    // instruction.
+    set_write_slot(&txn, Some(bundle.slot)).await?;
+    let tasks = outcome.apply(&txn).await?;
+    txn.commit().await?;
+    for task in tasks {
+        task_manager.send(task)?;
+    }
+    Ok(())
+}
+
+/// Describes what the instruction changes.  `txn` is only read from, nothing is written until
+/// the outcome is applied.
+pub async fn instruction_outcome<'c>(
+    parsing_result: &'c BubblegumInstruction,
+    bundle: &'c InstructionBundle<'c>,
+    txn: &'c DatabaseTransaction,
+) -> Result<Option<Outcome<'c>>, IngesterError> {
+    let outcome = match parsing_result.instruction {
+        InstructionName::Transfer => transfer::transfer(parsing_result, bundle)?,
+        InstructionName::Burn => burn::burn(parsing_result, bundle)?,
+        InstructionName::Delegate => delegate::delegate(parsing_result, bundle)?,
         InstructionName::MintV1 | InstructionName::MintToCollectionV1 => {
-            let task = mint_v1::mint_v1(parsing_result, bundle, &txn).await?;
-            txn.commit().await?;
-            task_manager.send(task)?;
-        }
-        InstructionName::Redeem => {
-            redeem::redeem(parsing_result, bundle, &txn).await?;
-            txn.commit().await?;
-        }
-        InstructionName::CancelRedeem => {
-            cancel_redeem::cancel_redeem(parsing_result, bundle, &txn).await?;
-            txn.commit().await?;
-        }
-        InstructionName::DecompressV1 => {
-            decompress::decompress(parsing_result, bundle, &txn).await?;
-            txn.commit().await?;
+            mint_v1::mint_v1(parsing_result, bundle, txn).await?
         }
+        InstructionName::Redeem => redeem::redeem(parsing_result, bundle)?,
+        InstructionName::CancelRedeem => cancel_redeem::cancel_redeem(parsing_result, bundle)?,
+        InstructionName::DecompressV1 => decompress::decompress(parsing_result, bundle)?,
+        InstructionName::Compress => compress::compress(parsing_result, bundle, txn).await?,
+        InstructionName::CreateTree => create_tree::create_tree(bundle)?,
         InstructionName::VerifyCreator => {
-            creator_verification::process(parsing_result, bundle, &txn, true).await?;
-            txn.commit().await?;
+            creator_verification::process(parsing_result, bundle, true, txn).await?
         }
         InstructionName::UnverifyCreator => {
-            creator_verification::process(parsing_result, bundle, &txn, false).await?;
-            txn.commit().await?;
+            creator_verification::process(parsing_result, bundle, false, txn).await?
         }
-        InstructionName::VerifyCollection => {
-            collection_verification::process(parsing_result, bundle, &txn, true).await?;
+        InstructionName::VerifyCollection | InstructionName::SetAndVerifyCollection => {
+            collection_verification::process(parsing_result, bundle, true, txn).await?
         }
         InstructionName::UnverifyCollection => {
-            collection_verification::process(parsing_result, bundle, &txn, false).await?;
+            collection_verification::process(parsing_result, bundle, false, txn).await?
         }
-        InstructionName::SetAndVerifyCollection => {
-            collection_verification::process(parsing_result, bundle, &txn, true).await?;
+        // The parser doesn't know of `update_metadata` yet.
+        InstructionName::Unknown if metadata_update::is_metadata_update(bundle) => {
+            metadata_update::metadata_update(parsing_result, bundle, txn).await?
         }
-        _ => println!("Bubblegum: Not Implemented Instruction"),
-    }
-    Ok(())
+        InstructionName::Unknown => return Ok(None),
+    };
+    Ok(Some(outcome))
+}
+
+/// Key of the account the instruction was given at `index`.
+fn account_key(bundle: &InstructionBundle, index: usize) -> Result<Vec<u8>, IngesterError> {
+    bundle.keys.get(index).map(|k| k.0.to_vec()).ok_or_else(|| {
+        IngesterError::DeserializationError(format!("Missing Bubblegum account {}", index))
+    })
 }
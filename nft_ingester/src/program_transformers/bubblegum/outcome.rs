@@ -0,0 +1,71 @@
+use crate::{tasks::common::save_changelog_event, IngesterError, TaskData};
+use blockbuster::programs::bubblegum::ChangeLogEventV1;
+use sea_orm::{ConnectionTrait, DatabaseTransaction, Statement};
+
+/// Everything a Bubblegum instruction changes.  Handlers only describe the change, the
+/// executor in `handle_bubblegum_instruction` applies it atomically and enqueues the tasks once
+/// the transaction has been committed.
+#[derive(Default)]
+pub struct Outcome<'a> {
+    change_log: Option<(&'a ChangeLogEventV1, u64)>,
+    statements: Vec<Statement>,
+    tasks: Vec<TaskData>,
+}
+
+impl<'a> Outcome<'a> {
+    /// Records the tree change made by the instruction and returns its sequence number.
+    pub fn change_log(&mut self, change_log_event: &'a ChangeLogEventV1, slot: u64) -> u64 {
+        self.change_log = Some((change_log_event, slot));
+        change_log_event.seq
+    }
+
+    pub fn push(&mut self, statement: Statement) {
+        self.statements.push(statement);
+    }
+
+    /// Pushes an upsert that only overwrites rows written at an earlier slot.
+    pub fn push_slot_guarded(&mut self, mut statement: Statement, table: &str) {
+        statement.sql = format!(
+            "{} WHERE excluded.slot_updated > {}.slot_updated",
+            statement.sql, table
+        );
+        self.statements.push(statement);
+    }
+
+    /// Pushes an upsert that only overwrites rows written at the same or an earlier sequence
+    /// number.
+    pub fn push_seq_guarded(&mut self, mut statement: Statement, table: &str) {
+        statement.sql = format!("{} WHERE excluded.seq >= {}.seq", statement.sql, table);
+        self.statements.push(statement);
+    }
+
+    pub fn enqueue(&mut self, task: TaskData) {
+        self.tasks.push(task);
+    }
+
+    #[cfg(test)]
+    pub fn tasks(&self) -> &[TaskData] {
+        &self.tasks
+    }
+
+    #[cfg(test)]
+    pub fn statements(&self) -> &[Statement] {
+        &self.statements
+    }
+
+    #[cfg(test)]
+    pub fn seq(&self) -> Option<u64> {
+        self.change_log.map(|(cl, _)| cl.seq)
+    }
+
+    /// Writes the outcome within `txn` and hands back the tasks to enqueue after commit.
+    pub async fn apply(self, txn: &DatabaseTransaction) -> Result<Vec<TaskData>, IngesterError> {
+        if let Some((change_log_event, slot)) = self.change_log {
+            save_changelog_event(change_log_event, slot, txn).await?;
+        }
+        for statement in self.statements {
+            txn.execute(statement).await?;
+        }
+        Ok(self.tasks)
+    }
+}
@@ -0,0 +1,618 @@
+use super::{handle_bubblegum_instruction, instruction_outcome};
+use crate::TaskData;
+use anchor_lang::{AnchorDeserialize, AnchorSerialize, InstructionData};
+use blockbuster::{
+    instruction::InstructionBundle,
+    programs::bubblegum::{
+        BubblegumInstruction, ChangeLogEventV1, InstructionName, LeafSchema, Payload,
+    },
+};
+use digital_asset_types::dao::{
+    asset, asset_authority, asset_creators, asset_data, asset_grouping, backfill_items,
+    sea_orm_active_enums::{ChainMutability, Mutability},
+    tree_config,
+};
+use flatbuffers::FlatBufferBuilder;
+use mpl_bubblegum::{
+    instruction as bubblegum_ix,
+    state::{
+        leaf_schema::LeafSchemaEvent,
+        metaplex_adapter::{Collection, Creator, MetadataArgs, TokenProgramVersion, TokenStandard},
+    },
+};
+use mpl_bubblegum_sdk::{instructions::UpdateMetadataInstructionArgs, types::UpdateArgs};
+use plerkle_serialization::{CompiledInstruction, CompiledInstructionArgs, Pubkey as FBPubkey};
+use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult, Transaction, TransactionTrait};
+use solana_sdk::{hash::hash, pubkey::Pubkey};
+use spl_account_compression::state::PathNode;
+use tokio::sync::mpsc::unbounded_channel;
+
+const SLOT: u64 = 100;
+const SEQ: u64 = 7;
+
+/// Every instruction the Bubblegum parser can produce.
+fn instruction_names() -> Vec<InstructionName> {
+    vec![
+        InstructionName::Unknown,
+        InstructionName::MintV1,
+        InstructionName::MintToCollectionV1,
+        InstructionName::Redeem,
+        InstructionName::CancelRedeem,
+        InstructionName::Transfer,
+        InstructionName::Delegate,
+        InstructionName::DecompressV1,
+        InstructionName::Compress,
+        InstructionName::Burn,
+        InstructionName::CreateTree,
+        InstructionName::VerifyCreator,
+        InstructionName::UnverifyCreator,
+        InstructionName::VerifyCollection,
+        InstructionName::UnverifyCollection,
+        InstructionName::SetAndVerifyCollection,
+    ]
+}
+
+fn metadata(creator: Pubkey, collection: Pubkey) -> MetadataArgs {
+    MetadataArgs {
+        name: "Test".to_string(),
+        symbol: "TST".to_string(),
+        uri: "https://example.com/test.json".to_string(),
+        seller_fee_basis_points: 500,
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+        token_standard: Some(TokenStandard::NonFungible),
+        collection: Some(Collection {
+            verified: false,
+            key: collection,
+        }),
+        uses: None,
+        token_program_version: TokenProgramVersion::Original,
+        creators: vec![Creator {
+            address: creator,
+            verified: false,
+            share: 100,
+        }],
+    }
+}
+
+/// The anchor encoded instruction data the handlers decode the metadata from.
+fn instruction_data(name: &InstructionName, message: MetadataArgs, collection: Pubkey) -> Vec<u8> {
+    let (root, data_hash, creator_hash, nonce, index) = ([0; 32], [0; 32], [0; 32], 0, 0);
+    match name {
+        InstructionName::VerifyCreator => bubblegum_ix::VerifyCreator {
+            root,
+            data_hash,
+            creator_hash,
+            nonce,
+            index,
+            message,
+        }
+        .data(),
+        InstructionName::UnverifyCreator => bubblegum_ix::UnverifyCreator {
+            root,
+            data_hash,
+            creator_hash,
+            nonce,
+            index,
+            message,
+        }
+        .data(),
+        InstructionName::VerifyCollection => bubblegum_ix::VerifyCollection {
+            root,
+            data_hash,
+            creator_hash,
+            nonce,
+            index,
+            message,
+        }
+        .data(),
+        InstructionName::UnverifyCollection => bubblegum_ix::UnverifyCollection {
+            root,
+            data_hash,
+            creator_hash,
+            nonce,
+            index,
+            message,
+        }
+        .data(),
+        InstructionName::SetAndVerifyCollection => bubblegum_ix::SetAndVerifyCollection {
+            root,
+            data_hash,
+            creator_hash,
+            nonce,
+            index,
+            message,
+            collection,
+        }
+        .data(),
+        _ => vec![0; 8],
+    }
+}
+
+/// The data of Bubblegum's `update_metadata`, changing the URI of `current` to `uri` if any.
+fn metadata_update_data(current: MetadataArgs, uri: Option<String>) -> Vec<u8> {
+    let current = current.try_to_vec().unwrap();
+    let args = UpdateMetadataInstructionArgs {
+        root: [0; 32],
+        nonce: 0,
+        index: 0,
+        current_metadata: AnchorDeserialize::try_from_slice(&current).unwrap(),
+        update_args: UpdateArgs {
+            name: None,
+            symbol: None,
+            uri,
+            creators: None,
+            seller_fee_basis_points: None,
+            primary_sale_happened: None,
+            is_mutable: None,
+        },
+    };
+    [
+        &hash(b"global:update_metadata").to_bytes()[..8],
+        &args.try_to_vec().unwrap()[..],
+    ]
+    .concat()
+}
+
+fn compiled_instruction(data: &[u8]) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::new();
+    let data = builder.create_vector(data);
+    let ix = CompiledInstruction::create(
+        &mut builder,
+        &CompiledInstructionArgs {
+            program_id_index: 0,
+            accounts: None,
+            data: Some(data),
+        },
+    );
+    builder.finish(ix, None);
+    builder.finished_data().to_vec()
+}
+
+fn fixture_instruction(
+    name: InstructionName,
+    creator: Pubkey,
+    collection: Pubkey,
+) -> BubblegumInstruction {
+    let owner = Pubkey::new_unique();
+    let schema = LeafSchema::new_v0(Pubkey::new_unique(), owner, owner, 0, [1; 32], [2; 32]);
+    let leaf_update = LeafSchemaEvent::new(schema.version(), schema.clone(), schema.to_node());
+    let tree_update = ChangeLogEventV1 {
+        id: Pubkey::new_unique(),
+        path: vec![PathNode {
+            node: schema.to_node(),
+            index: 1,
+        }],
+        seq: SEQ,
+        index: 0,
+    };
+    let payload = match name {
+        InstructionName::MintV1 | InstructionName::MintToCollectionV1 => Some(Payload::MintV1 {
+            args: metadata(creator, collection),
+        }),
+        InstructionName::VerifyCreator => Some(Payload::VerifyCreator { creator }),
+        InstructionName::UnverifyCreator => Some(Payload::UnverifyCreator { creator }),
+        InstructionName::SetAndVerifyCollection => {
+            Some(Payload::SetAndVerifyCollection { collection })
+        }
+        _ => None,
+    };
+    let tree_changing = !matches!(
+        name,
+        InstructionName::Unknown | InstructionName::CreateTree | InstructionName::DecompressV1
+    );
+    BubblegumInstruction {
+        instruction: name,
+        tree_update: tree_changing.then_some(tree_update),
+        leaf_update: tree_changing.then_some(leaf_update),
+        payload,
+    }
+}
+
+fn fixture_keys() -> Vec<FBPubkey> {
+    (0..8)
+        .map(|_| FBPubkey::new(&Pubkey::new_unique().to_bytes()))
+        .collect()
+}
+
+fn fixture_bundle<'a>(keys: &'a [FBPubkey], instruction: &'a [u8]) -> InstructionBundle<'a> {
+    InstructionBundle {
+        txn_id: "",
+        program: FBPubkey::new(&mpl_bubblegum::id().to_bytes()),
+        instruction: Some(flatbuffers::root::<CompiledInstruction>(instruction).unwrap()),
+        inner_ix: None,
+        keys,
+        slot: SLOT,
+    }
+}
+
+fn backfill_item() -> backfill_items::Model {
+    backfill_items::Model {
+        id: 1,
+        tree: vec![],
+        seq: 0,
+        slot: 0,
+        force_chk: false,
+        backfilled: false,
+        failed: false,
+        locked: false,
+    }
+}
+
+/// A database answering the reads each instruction makes before and while its outcome is
+/// applied.
+fn mock_database(name: &InstructionName) -> MockDatabase {
+    let db = MockDatabase::new(DatabaseBackend::Postgres);
+    let db = match name {
+        // Mints look the creator of the tree up, which isn't indexed yet.
+        InstructionName::MintV1 | InstructionName::MintToCollectionV1 => {
+            db.append_query_results(vec![Vec::<tree_config::Model>::new()])
+        }
+        InstructionName::Compress => db
+            .append_query_results(vec![Vec::<asset::Model>::new()])
+            .append_query_results(vec![Vec::<asset_creators::Model>::new()])
+            .append_query_results(vec![Vec::<asset_authority::Model>::new()])
+            .append_query_results(vec![Vec::<asset_grouping::Model>::new()]),
+        // Changing the metadata looks for creators written by a later instruction.
+        InstructionName::VerifyCreator
+        | InstructionName::UnverifyCreator
+        | InstructionName::VerifyCollection
+        | InstructionName::UnverifyCollection
+        | InstructionName::SetAndVerifyCollection => {
+            db.append_query_results(vec![Vec::<asset_creators::Model>::new()])
+        }
+        _ => db,
+    };
+    // Saving the change log looks the tree up in `backfill_items` and inserts a row for it.
+    db.append_query_results(vec![vec![backfill_item()], vec![backfill_item()]])
+        .append_exec_results(
+            (0..32)
+                .map(|_| MockExecResult {
+                    last_insert_id: 0,
+                    rows_affected: 1,
+                })
+                .collect(),
+        )
+}
+
+async fn replay(
+    parsing_result: &BubblegumInstruction,
+    bundle: &InstructionBundle<'_>,
+) -> (Vec<Transaction>, Vec<TaskData>) {
+    let db = mock_database(&parsing_result.instruction).into_connection();
+    let (sender, mut receiver) = unbounded_channel();
+    handle_bubblegum_instruction(parsing_result, bundle, &db, &sender)
+        .await
+        .unwrap();
+    drop(sender);
+    let mut tasks = Vec::new();
+    while let Some(task) = receiver.recv().await {
+        tasks.push(task);
+    }
+    (db.into_transaction_log(), tasks)
+}
+
+/// The SQL of the statements of the one transaction the instruction was applied in, which
+/// must have been committed.
+fn committed_sql(log: &[Transaction]) -> Vec<String> {
+    assert_eq!(log.len(), 1);
+    let sql: Vec<String> = log[0].statements().iter().map(|s| s.sql.clone()).collect();
+    assert_eq!(sql.first().map(String::as_str), Some("BEGIN"));
+    assert_eq!(sql.last().map(String::as_str), Some("COMMIT"));
+    sql
+}
+
+/// The SQL of the statements the outcome of the instruction is made of.
+async fn outcome_sql(
+    parsing_result: &BubblegumInstruction,
+    bundle: &InstructionBundle<'_>,
+) -> Vec<String> {
+    let db = mock_database(&parsing_result.instruction).into_connection();
+    let txn = db.begin().await.unwrap();
+    let outcome = instruction_outcome(parsing_result, bundle, &txn)
+        .await
+        .unwrap()
+        .unwrap();
+    outcome.statements().iter().map(|s| s.sql.clone()).collect()
+}
+
+#[tokio::test]
+async fn every_instruction_is_committed() {
+    let (creator, collection) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let keys = fixture_keys();
+    for name in instruction_names() {
+        let unknown = matches!(name, InstructionName::Unknown);
+        let updates_asset = !matches!(name, InstructionName::Unknown | InstructionName::CreateTree);
+        let data = instruction_data(&name, metadata(creator, collection), collection);
+        let instruction = compiled_instruction(&data);
+        let parsing_result = fixture_instruction(name, creator, collection);
+        let bundle = fixture_bundle(&keys, &instruction);
+
+        let (log, _) = replay(&parsing_result, &bundle).await;
+        if unknown {
+            assert!(log
+                .iter()
+                .flat_map(|t| t.statements())
+                .all(|s| s.sql == "BEGIN" || s.sql == "ROLLBACK"));
+            continue;
+        }
+        // The write slot is set, the change log saved, then the outcome's statements are run
+        // in order, and nothing else.
+        let sql = committed_sql(&log);
+        let expected = outcome_sql(&parsing_result, &bundle).await;
+        let write_slot = sql
+            .iter()
+            .position(|s| s.contains("das.write_slot"))
+            .unwrap();
+        assert_eq!(
+            &sql[sql.len() - 1 - expected.len()..sql.len() - 1],
+            expected
+        );
+        assert!(write_slot < sql.len() - 1 - expected.len());
+        assert!(sql[write_slot + 1..sql.len() - 1 - expected.len()]
+            .iter()
+            .all(|s| s.starts_with("INSERT INTO \"cl_items\"") || s.contains("backfill_items")));
+        assert_eq!(
+            expected
+                .iter()
+                .any(|s| s.starts_with(r#"UPDATE "asset" "#)
+                    || s.starts_with(r#"INSERT INTO "asset" "#)),
+            updates_asset
+        );
+    }
+}
+
+#[tokio::test]
+async fn mints_enqueue_metadata_download_after_commit() {
+    let (creator, collection) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let keys = fixture_keys();
+    for name in [InstructionName::MintV1, InstructionName::MintToCollectionV1] {
+        let instruction = compiled_instruction(&[0; 8]);
+        let parsing_result = fixture_instruction(name, creator, collection);
+        let bundle = fixture_bundle(&keys, &instruction);
+
+        let (log, tasks) = replay(&parsing_result, &bundle).await;
+        committed_sql(&log);
+        assert_eq!(tasks.len(), 1);
+    }
+}
+
+/// The authority the mint of `keys` is given, `config` being the indexed `TreeConfig` of its
+/// tree if any.
+async fn mint_authority(keys: &[FBPubkey], config: Option<tree_config::Model>) -> String {
+    let instruction = compiled_instruction(&[0; 8]);
+    let parsing_result = fixture_instruction(
+        InstructionName::MintV1,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let bundle = fixture_bundle(keys, &instruction);
+
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results(vec![config.into_iter().collect::<Vec<_>>()])
+        .into_connection();
+    let txn = db.begin().await.unwrap();
+    let outcome = instruction_outcome(&parsing_result, &bundle, &txn)
+        .await
+        .unwrap()
+        .unwrap();
+    let authority = outcome
+        .statements()
+        .iter()
+        .find(|s| s.sql.starts_with(r#"INSERT INTO "asset_authority""#))
+        .unwrap();
+    assert!(authority
+        .sql
+        .contains("ON CONFLICT (\"asset_id\") DO NOTHING"));
+    format!("{:?}", authority.values)
+}
+
+#[tokio::test]
+async fn mints_are_authored_by_the_tree_creator() {
+    let keys = fixture_keys();
+    let (creator, delegate) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let config = tree_config::Model {
+        id: keys[0].0.to_vec(),
+        tree_creator: creator.to_bytes().to_vec(),
+        tree_delegate: delegate.to_bytes().to_vec(),
+        total_mint_capacity: 16,
+        num_minted: 1,
+        is_public: false,
+        slot_updated: 0,
+    };
+
+    let values = mint_authority(&keys, Some(config)).await;
+    assert!(values.contains(&format!("{:?}", creator.to_bytes())));
+    assert!(!values.contains(&format!("{:?}", delegate.to_bytes())));
+    assert!(!values.contains(&format!("{:?}", keys[0].0)));
+}
+
+#[tokio::test]
+async fn mints_of_unindexed_trees_are_authored_by_the_tree_config() {
+    let keys = fixture_keys();
+
+    let values = mint_authority(&keys, None).await;
+    assert!(values.contains(&format!("{:?}", keys[0].0)));
+}
+
+#[tokio::test]
+async fn leaf_changes_are_guarded_by_seq() {
+    let (creator, collection) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let keys = fixture_keys();
+    for name in [
+        InstructionName::Transfer,
+        InstructionName::Burn,
+        InstructionName::Delegate,
+        InstructionName::Redeem,
+        InstructionName::CancelRedeem,
+        InstructionName::VerifyCreator,
+        InstructionName::VerifyCollection,
+    ] {
+        let data = instruction_data(&name, metadata(creator, collection), collection);
+        let instruction = compiled_instruction(&data);
+        let parsing_result = fixture_instruction(name, creator, collection);
+        let bundle = fixture_bundle(&keys, &instruction);
+
+        let db = mock_database(&parsing_result.instruction).into_connection();
+        let txn = db.begin().await.unwrap();
+        let outcome = instruction_outcome(&parsing_result, &bundle, &txn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome.seq(), Some(SEQ));
+        assert!(outcome.tasks().is_empty());
+        let asset_update = &outcome.statements()[0];
+        assert!(asset_update.sql.starts_with(r#"UPDATE "asset""#));
+        assert!(asset_update.sql.contains(r#""seq" <= "#));
+    }
+}
+
+#[tokio::test]
+async fn tree_creation_has_no_change_log() {
+    let keys = fixture_keys();
+    let instruction = compiled_instruction(&[0; 8]);
+    let parsing_result = fixture_instruction(
+        InstructionName::CreateTree,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let bundle = fixture_bundle(&keys, &instruction);
+
+    let db = mock_database(&parsing_result.instruction).into_connection();
+    let txn = db.begin().await.unwrap();
+    let outcome = instruction_outcome(&parsing_result, &bundle, &txn)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(outcome.seq(), None);
+    assert_eq!(outcome.statements().len(), 1);
+    assert!(outcome.statements()[0]
+        .sql
+        .starts_with("INSERT INTO backfill_items"));
+}
+
+#[tokio::test]
+async fn metadata_changes_keep_the_token_standard() {
+    let (creator, collection) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let keys = fixture_keys();
+    for name in [
+        InstructionName::VerifyCreator,
+        InstructionName::VerifyCollection,
+    ] {
+        let mut message = metadata(creator, collection);
+        message.token_standard = Some(TokenStandard::NonFungibleEdition);
+        let data = instruction_data(&name, message, collection);
+        let instruction = compiled_instruction(&data);
+        let parsing_result = fixture_instruction(name, creator, collection);
+        let bundle = fixture_bundle(&keys, &instruction);
+
+        let db = mock_database(&parsing_result.instruction).into_connection();
+        let txn = db.begin().await.unwrap();
+        let outcome = instruction_outcome(&parsing_result, &bundle, &txn)
+            .await
+            .unwrap()
+            .unwrap();
+        let chain_data = outcome
+            .statements()
+            .iter()
+            .find(|s| s.sql.starts_with(r#"UPDATE "asset_data""#))
+            .unwrap();
+        assert!(format!("{:?}", chain_data.values).contains(r#"String("NonFungibleEdition")"#));
+    }
+}
+
+#[tokio::test]
+async fn metadata_changes_replace_the_creators() {
+    let (creator, collection) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let keys = fixture_keys();
+    for name in [
+        InstructionName::VerifyCreator,
+        InstructionName::UnverifyCreator,
+        InstructionName::VerifyCollection,
+    ] {
+        let data = instruction_data(&name, metadata(creator, collection), collection);
+        let instruction = compiled_instruction(&data);
+        let parsing_result = fixture_instruction(name, creator, collection);
+        let bundle = fixture_bundle(&keys, &instruction);
+
+        let sql = outcome_sql(&parsing_result, &bundle).await;
+        let deleted = sql
+            .iter()
+            .position(|s| s.starts_with(r#"DELETE FROM "asset_creators""#))
+            .unwrap();
+        assert!(!sql[deleted].contains("position"));
+        let inserted = &sql[deleted + 1];
+        assert!(inserted.starts_with(r#"INSERT INTO "asset_creators""#));
+        assert!(!inserted.contains("ON CONFLICT"));
+    }
+}
+
+#[tokio::test]
+async fn metadata_updates_download_changed_uris() {
+    let (creator, collection) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let keys = fixture_keys();
+    let current_uri = metadata(creator, collection).uri;
+    for (uri, downloads) in [
+        (None, false),
+        (Some(current_uri.clone()), false),
+        (Some("https://example.com/changed.json".to_string()), true),
+    ] {
+        let instruction =
+            compiled_instruction(&metadata_update_data(metadata(creator, collection), uri));
+        // The parser reports the instruction as unknown, with its leaf and change log.
+        let mut parsing_result =
+            fixture_instruction(InstructionName::Transfer, creator, collection);
+        parsing_result.instruction = InstructionName::Unknown;
+        let bundle = fixture_bundle(&keys, &instruction);
+
+        let data = asset_data::Model {
+            id: vec![],
+            chain_data_mutability: ChainMutability::Mutable,
+            chain_data: serde_json::Value::Null,
+            metadata_url: current_uri.clone(),
+            metadata_mutability: Mutability::Mutable,
+            metadata: serde_json::Value::Null,
+            slot_updated: 0,
+            write_version: 0,
+        };
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<asset_creators::Model>::new()])
+            .append_query_results(vec![vec![data]])
+            .into_connection();
+        let txn = db.begin().await.unwrap();
+        let outcome = instruction_outcome(&parsing_result, &bundle, &txn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome.seq(), Some(SEQ));
+        assert_eq!(outcome.tasks().len(), downloads as usize);
+        assert_eq!(
+            outcome
+                .statements()
+                .iter()
+                .any(|s| s.sql.starts_with(r#"UPDATE "asset_data""#)
+                    && s.sql.contains(r#""metadata_url" = "#)),
+            downloads
+        );
+    }
+}
+
+#[tokio::test]
+async fn missing_accounts_are_errors() {
+    let keys = fixture_keys();
+    let instruction = compiled_instruction(&[0; 8]);
+    for name in [
+        InstructionName::Compress,
+        InstructionName::DecompressV1,
+        InstructionName::CreateTree,
+    ] {
+        let parsing_result = fixture_instruction(name, Pubkey::new_unique(), Pubkey::new_unique());
+        let bundle = fixture_bundle(&keys[..1], &instruction);
+
+        let db = mock_database(&parsing_result.instruction).into_connection();
+        let txn = db.begin().await.unwrap();
+        let outcome = instruction_outcome(&parsing_result, &bundle, &txn).await;
+        assert!(outcome.is_err());
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    program_transformers::bubblegum::db::update_asset, tasks::common::save_changelog_event,
+    program_transformers::bubblegum::{db::update_asset, Outcome},
     IngesterError,
 };
 use blockbuster::{
@@ -7,15 +7,15 @@ use blockbuster::{
     programs::bubblegum::{BubblegumInstruction, LeafSchema},
 };
 use digital_asset_types::dao::asset;
-use sea_orm::{entity::*, DatabaseTransaction};
+use sea_orm::entity::*;
 
-pub async fn transfer<'c>(
+pub fn transfer<'c>(
     parsing_result: &'c BubblegumInstruction,
-    bundle: &'c InstructionBundle<'c>,
-    txn: &'c DatabaseTransaction,
-) -> Result<(), IngesterError> {
+    bundle: &InstructionBundle,
+) -> Result<Outcome<'c>, IngesterError> {
     if let (Some(le), Some(cl)) = (&parsing_result.leaf_update, &parsing_result.tree_update) {
-        let seq = save_changelog_event(cl, bundle.slot, txn).await?;
+        let mut outcome = Outcome::default();
+        let seq = outcome.change_log(cl, bundle.slot);
         return match le.schema {
             LeafSchema::V1 {
                 id,
@@ -38,7 +38,8 @@ pub async fn transfer<'c>(
                     seq: Set(seq as i64), // gummyroll seq
                     ..Default::default()
                 };
-                update_asset(txn, id_bytes, Some(seq), asset_to_update).await
+                outcome.push(update_asset(id_bytes, Some(seq), asset_to_update));
+                Ok(outcome)
             }
             _ => Err(IngesterError::NotImplemented),
         };
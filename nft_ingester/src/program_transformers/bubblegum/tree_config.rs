@@ -0,0 +1,195 @@
+use crate::IngesterError;
+use anchor_lang::AccountDeserialize;
+use digital_asset_types::dao::{asset_authority, tree_config};
+use mpl_bubblegum::state::TreeConfig;
+use plerkle_serialization::AccountInfo;
+use sea_orm::{
+    entity::*,
+    query::*,
+    sea_query::{Expr, OnConflict},
+    ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait, TransactionTrait,
+};
+
+/// Stores the `TreeConfig` account of a tree.  Vouchers and any other Bubblegum accounts are
+/// not indexed.  The delegate is stored for proofs to report, but the creator alone is the
+/// authority of the assets of the tree: the delegate only mints on its behalf and can be
+/// replaced by it at any time.
+pub async fn handle_bubblegum_account<'a>(
+    account_update: &'a AccountInfo<'a>,
+    db: &DatabaseConnection,
+) -> Result<(), IngesterError> {
+    let data = account_update
+        .data()
+        .map(|d| d.iter().collect::<Vec<u8>>())
+        .unwrap_or_default();
+    let config = match TreeConfig::try_deserialize(&mut data.as_slice()) {
+        Ok(config) => config,
+        Err(_) => return Err(IngesterError::NotImplemented),
+    };
+    let key = account_update.pubkey().unwrap().0.to_vec();
+    let slot = account_update.slot() as i64;
+    let creator = config.tree_creator.to_bytes().to_vec();
+
+    let model = tree_config::ActiveModel {
+        id: Set(key.clone()),
+        tree_creator: Set(creator.clone()),
+        tree_delegate: Set(config.tree_delegate.to_bytes().to_vec()),
+        total_mint_capacity: Set(config.total_mint_capacity as i64),
+        num_minted: Set(config.num_minted as i64),
+        is_public: Set(config.is_public),
+        slot_updated: Set(slot),
+    };
+    let mut query = tree_config::Entity::insert(model)
+        .on_conflict(
+            OnConflict::columns([tree_config::Column::Id])
+                .update_columns([
+                    tree_config::Column::TreeCreator,
+                    tree_config::Column::TreeDelegate,
+                    tree_config::Column::TotalMintCapacity,
+                    tree_config::Column::NumMinted,
+                    tree_config::Column::IsPublic,
+                    tree_config::Column::SlotUpdated,
+                ])
+                .to_owned(),
+        )
+        .build(DbBackend::Postgres);
+    query.sql = format!(
+        "{} WHERE excluded.slot_updated > tree_config.slot_updated",
+        query.sql
+    );
+
+    let txn = db.begin().await?;
+    txn.execute(query).await?;
+
+    // Assets minted before the config was indexed fell back to the config address as their
+    // authority, point them at the tree creator now that it is known.
+    asset_authority::Entity::update_many()
+        .col_expr(asset_authority::Column::Authority, Expr::value(creator))
+        .filter(asset_authority::Column::Authority.eq(key))
+        .exec(&txn)
+        .await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AccountSerialize;
+    use flatbuffers::FlatBufferBuilder;
+    use plerkle_serialization::{root_as_account_info, AccountInfoArgs, Pubkey as FBPubkey};
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult};
+    use solana_sdk::pubkey::Pubkey;
+
+    fn serialize_update(key: &Pubkey, slot: u64, data: &[u8]) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::new();
+        let pubkey = FBPubkey(key.to_bytes());
+        let owner = FBPubkey(mpl_bubblegum::id().to_bytes());
+        let data = Some(builder.create_vector(data));
+        let account_info = AccountInfo::create(
+            &mut builder,
+            &AccountInfoArgs {
+                pubkey: Some(&pubkey),
+                lamports: 1,
+                owner: Some(&owner),
+                executable: false,
+                rent_epoch: 0,
+                data,
+                write_version: 1,
+                slot,
+                is_startup: false,
+                seen_at: 0,
+            },
+        );
+        builder.finish(account_info, None);
+        builder.finished_data().to_vec()
+    }
+
+    fn mock_database() -> DatabaseConnection {
+        MockDatabase::new(DatabaseBackend::Postgres)
+            .append_exec_results(
+                (0..2)
+                    .map(|_| MockExecResult {
+                        last_insert_id: 0,
+                        rows_affected: 1,
+                    })
+                    .collect(),
+            )
+            .into_connection()
+    }
+
+    #[tokio::test]
+    async fn tree_configs_are_upserted_by_slot() {
+        let key = Pubkey::new_unique();
+        let config = TreeConfig {
+            tree_creator: Pubkey::new_unique(),
+            tree_delegate: Pubkey::new_unique(),
+            total_mint_capacity: 16384,
+            num_minted: 3,
+            is_public: true,
+        };
+        let mut data = Vec::new();
+        config.try_serialize(&mut data).unwrap();
+        let bytes = serialize_update(&key, 100, &data);
+        let account_update = root_as_account_info(&bytes).unwrap();
+
+        let db = mock_database();
+        handle_bubblegum_account(&account_update, &db)
+            .await
+            .unwrap();
+
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(log.contains(r#"INSERT INTO \"tree_config\""#));
+        assert!(log.contains(r#"ON CONFLICT (\"id\") DO UPDATE SET"#));
+        assert!(log.contains("WHERE excluded.slot_updated > tree_config.slot_updated"));
+        assert!(log.contains(&format!("{:?}", config.tree_creator.to_bytes())));
+        assert!(log.contains(&format!("{:?}", config.tree_delegate.to_bytes())));
+        assert!(log.contains("BigInt(Some(16384))"));
+        assert!(log.contains("BigInt(Some(3))"));
+        assert!(log.contains("Bool(Some(true))"));
+        assert!(log.contains("BigInt(Some(100))"));
+    }
+
+    #[tokio::test]
+    async fn mints_authored_by_the_config_are_handed_to_the_tree_creator() {
+        let key = Pubkey::new_unique();
+        let config = TreeConfig {
+            tree_creator: Pubkey::new_unique(),
+            tree_delegate: Pubkey::new_unique(),
+            total_mint_capacity: 16384,
+            num_minted: 3,
+            is_public: false,
+        };
+        let mut data = Vec::new();
+        config.try_serialize(&mut data).unwrap();
+        let bytes = serialize_update(&key, 100, &data);
+        let account_update = root_as_account_info(&bytes).unwrap();
+
+        let db = mock_database();
+        handle_bubblegum_account(&account_update, &db)
+            .await
+            .unwrap();
+
+        let log = format!("{:?}", db.into_transaction_log());
+        let update = &log[log
+            .find(r#"UPDATE \"asset_authority\" SET \"authority\" = $1"#)
+            .unwrap()..];
+        assert!(update.contains(r#"WHERE \"asset_authority\".\"authority\" = $2"#));
+        let creator_at = update
+            .find(&format!("{:?}", config.tree_creator.to_bytes()))
+            .unwrap();
+        let key_at = update.find(&format!("{:?}", key.to_bytes())).unwrap();
+        assert!(creator_at < key_at);
+    }
+
+    #[tokio::test]
+    async fn other_accounts_are_not_indexed() {
+        let bytes = serialize_update(&Pubkey::new_unique(), 100, &[0; 64]);
+        let account_update = root_as_account_info(&bytes).unwrap();
+
+        let db = mock_database();
+        let result = handle_bubblegum_account(&account_update, &db).await;
+        assert!(matches!(result, Err(IngesterError::NotImplemented)));
+        assert!(db.into_transaction_log().is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{
+    program_transformers::{
+        bubblegum::{update_asset, Outcome},
+        creators::{replace_creators, Creator},
+    },
+    IngesterError,
+};
+use anchor_lang::AnchorDeserialize;
+use blockbuster::{
+    instruction::InstructionBundle,
+    programs::bubblegum::InstructionName,
+    token_metadata::state::{TokenStandard, UseMethod, Uses},
+};
+use digital_asset_types::{
+    dao::{asset, asset_data, sea_orm_active_enums::ChainMutability},
+    json::ChainDataV1,
+};
+use mpl_bubblegum::{
+    hash_creators, hash_metadata, instruction as bubblegum_ix,
+    state::metaplex_adapter::{self, MetadataArgs},
+};
+use num_traits::FromPrimitive;
+use sea_orm::{entity::*, query::*, DatabaseTransaction, DbBackend, EntityTrait};
+
+/// The data of the instruction, starting with its anchor discriminator.
+pub fn instruction_data(bundle: &InstructionBundle) -> Result<Vec<u8>, IngesterError> {
+    let data = bundle
+        .instruction
+        .and_then(|ix| ix.data())
+        .map(|d| d.iter().collect::<Vec<u8>>())
+        .ok_or_else(|| IngesterError::ParsingError("Ix data missing".to_string()))?;
+    if data.len() < 8 {
+        return Err(IngesterError::ParsingError("Ix data too short".to_string()));
+    }
+    Ok(data)
+}
+
+/// Decodes the metadata the instruction was invoked with.  Bubblegum instructions that change
+/// the metadata of a leaf receive the full metadata as it was before the change, so that the
+/// program can verify the leaf.
+pub fn instruction_metadata(
+    instruction: &InstructionName,
+    bundle: &InstructionBundle,
+) -> Result<MetadataArgs, IngesterError> {
+    let data = instruction_data(bundle)?;
+    // Skip the anchor discriminator.
+    let mut args = &data[8..];
+    let metadata = match instruction {
+        InstructionName::VerifyCreator => {
+            bubblegum_ix::VerifyCreator::deserialize(&mut args).map(|ix| ix.message)
+        }
+        InstructionName::UnverifyCreator => {
+            bubblegum_ix::UnverifyCreator::deserialize(&mut args).map(|ix| ix.message)
+        }
+        InstructionName::VerifyCollection => {
+            bubblegum_ix::VerifyCollection::deserialize(&mut args).map(|ix| ix.message)
+        }
+        InstructionName::UnverifyCollection => {
+            bubblegum_ix::UnverifyCollection::deserialize(&mut args).map(|ix| ix.message)
+        }
+        InstructionName::SetAndVerifyCollection => {
+            bubblegum_ix::SetAndVerifyCollection::deserialize(&mut args).map(|ix| ix.message)
+        }
+        _ => return Err(IngesterError::NotImplemented),
+    };
+    metadata.map_err(|e| IngesterError::DeserializationError(e.to_string()))
+}
+
+fn token_standard(token_standard: &metaplex_adapter::TokenStandard) -> TokenStandard {
+    match token_standard {
+        metaplex_adapter::TokenStandard::NonFungible => TokenStandard::NonFungible,
+        metaplex_adapter::TokenStandard::FungibleAsset => TokenStandard::FungibleAsset,
+        metaplex_adapter::TokenStandard::Fungible => TokenStandard::Fungible,
+        metaplex_adapter::TokenStandard::NonFungibleEdition => TokenStandard::NonFungibleEdition,
+    }
+}
+
+/// The chain data of a compressed asset with `metadata`.
+pub fn chain_data(metadata: &MetadataArgs) -> Result<ChainDataV1, IngesterError> {
+    let uses = match &metadata.uses {
+        Some(u) => Some(Uses {
+            use_method: UseMethod::from_u8(u.use_method.clone() as u8).ok_or_else(|| {
+                IngesterError::DeserializationError("Invalid use method".to_string())
+            })?,
+            remaining: u.remaining,
+            total: u.total,
+        }),
+        None => None,
+    };
+    let mut chain_data = ChainDataV1 {
+        name: metadata.name.clone(),
+        symbol: metadata.symbol.clone(),
+        edition_nonce: metadata.edition_nonce,
+        primary_sale_happened: metadata.primary_sale_happened,
+        token_standard: metadata.token_standard.as_ref().map(token_standard),
+        uses,
+    };
+    chain_data.sanitize();
+    Ok(chain_data)
+}
+
+/// Rewrites the data of a compressed asset from its changed metadata.  The data and creator
+/// hashes are recomputed so they keep matching the leaf.  The URI is left alone, only
+/// Bubblegum's `update_metadata` instruction changes it, see `metadata_update`.
+pub async fn update_metadata(
+    outcome: &mut Outcome<'_>,
+    id: Vec<u8>,
+    seq: u64,
+    slot: u64,
+    metadata: &MetadataArgs,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    let slot_i = slot as i64;
+    let chain_data = chain_data(metadata)?;
+    let chain_data_json = serde_json::to_value(chain_data)
+        .map_err(|e| IngesterError::DeserializationError(e.to_string()))?;
+    let chain_mutability = match metadata.is_mutable {
+        true => ChainMutability::Mutable,
+        false => ChainMutability::Immutable,
+    };
+
+    let data = asset_data::ActiveModel {
+        id: Unchanged(id.clone()),
+        chain_data_mutability: Set(chain_mutability),
+        chain_data: Set(chain_data_json),
+        slot_updated: Set(slot_i),
+        ..Default::default()
+    };
+    outcome.push(
+        asset_data::Entity::update(data)
+            .filter(asset_data::Column::SlotUpdated.lte(slot_i))
+            .build(DbBackend::Postgres),
+    );
+
+    let data_hash = hash_metadata(metadata)
+        .map(|e| bs58::encode(e).into_string())
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    let creator_hash = hash_creators(&metadata.creators)
+        .map(|e| bs58::encode(e).into_string())
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    let asset_to_update = asset::ActiveModel {
+        id: Unchanged(id.clone()),
+        royalty_amount: Set(metadata.seller_fee_basis_points as i32),
+        data_hash: Set(Some(data_hash)),
+        creator_hash: Set(Some(creator_hash)),
+        ..Default::default()
+    };
+    outcome.push(update_asset(id.clone(), Some(seq), asset_to_update));
+
+    let creators = metadata
+        .creators
+        .iter()
+        .map(|c| Creator {
+            address: c.address.to_bytes().to_vec(),
+            share: c.share,
+            verified: c.verified,
+        })
+        .collect();
+    for query in replace_creators(&id, creators, seq as i64, slot_i, txn).await? {
+        outcome.push(query);
+    }
+    Ok(())
+}
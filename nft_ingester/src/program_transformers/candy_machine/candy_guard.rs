@@ -0,0 +1,108 @@
+use crate::{program_transformers::candy_machine::guards::guard_set_to_json, IngesterError};
+use anchor_lang::AccountDeserialize;
+use digital_asset_types::dao::{candy_guard, candy_guard_group};
+use mpl_candy_guard::state::{CandyGuard, CandyGuardData, DATA_OFFSET};
+use plerkle_serialization::{AccountInfo, Pubkey as FBPubkey};
+use sea_orm::{
+    entity::*, query::*, sea_query::OnConflict, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, DbBackend, EntityTrait, TransactionTrait,
+};
+
+pub async fn handle_candy_guard_account<'a>(
+    account_update: &'a AccountInfo<'a>,
+    db: &DatabaseConnection,
+) -> Result<(), IngesterError> {
+    let data = account_update
+        .data()
+        .map(|d| d.iter().collect::<Vec<u8>>())
+        .unwrap_or_default();
+    let guard = match CandyGuard::try_deserialize(&mut data.as_slice()) {
+        Ok(guard) => guard,
+        Err(_) => return Err(IngesterError::NotImplemented),
+    };
+    // The guard sets are serialized after the account struct.
+    let guard_data = data
+        .get(DATA_OFFSET..)
+        .ok_or_else(|| IngesterError::DeserializationError("Guard data missing".to_string()))
+        .and_then(|d| {
+            CandyGuardData::load(d).map_err(|e| IngesterError::DeserializationError(e.to_string()))
+        })?;
+    let key = account_update.pubkey().unwrap().0.to_vec();
+    let slot = account_update.slot() as i64;
+
+    let model = candy_guard::ActiveModel {
+        id: Set(key.clone()),
+        base: Set(guard.base.to_bytes().to_vec()),
+        bump: Set(guard.bump as i16),
+        authority: Set(guard.authority.to_bytes().to_vec()),
+        slot_updated: Set(slot),
+    };
+    let mut query = candy_guard::Entity::insert(model)
+        .on_conflict(
+            OnConflict::columns([candy_guard::Column::Id])
+                .update_columns([
+                    candy_guard::Column::Base,
+                    candy_guard::Column::Bump,
+                    candy_guard::Column::Authority,
+                    candy_guard::Column::SlotUpdated,
+                ])
+                .to_owned(),
+        )
+        .build(DbBackend::Postgres);
+    query.sql = format!(
+        "{} WHERE excluded.slot_updated > candy_guard.slot_updated",
+        query.sql
+    );
+
+    let txn = db.begin().await?;
+    let updated = txn.execute(query).await?.rows_affected() > 0;
+    // Groups are replaced as a whole, unless the update was older than the indexed guard.
+    if updated {
+        // The default guard set is stored unlabelled at position 0, followed by the groups.
+        let groups = std::iter::once((None, &guard_data.default)).chain(
+            guard_data
+                .groups
+                .iter()
+                .flatten()
+                .map(|g| (Some(g.label.clone()), &g.guards)),
+        );
+        let models: Vec<candy_guard_group::ActiveModel> = groups
+            .enumerate()
+            .map(|(i, (label, guards))| candy_guard_group::ActiveModel {
+                candy_guard_id: Set(key.clone()),
+                position: Set(i as i16),
+                label: Set(label),
+                guards: Set(guard_set_to_json(guards)),
+                slot_updated: Set(slot),
+            })
+            .collect();
+        candy_guard_group::Entity::delete_many()
+            .filter(candy_guard_group::Column::CandyGuardId.eq(key.clone()))
+            .exec(&txn)
+            .await?;
+        candy_guard_group::Entity::insert_many(models)
+            .exec(&txn)
+            .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+pub async fn handle_candy_guard_account_closure(
+    key: FBPubkey,
+    slot: u64,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    let slot_i = slot as i64;
+    candy_guard_group::Entity::delete_many()
+        .filter(candy_guard_group::Column::CandyGuardId.eq(key.0.to_vec()))
+        .filter(candy_guard_group::Column::SlotUpdated.lte(slot_i))
+        .exec(txn)
+        .await?;
+    candy_guard::Entity::delete_many()
+        .filter(candy_guard::Column::Id.eq(key.0.to_vec()))
+        .filter(candy_guard::Column::SlotUpdated.lte(slot_i))
+        .exec(txn)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use crate::IngesterError;
+use anchor_lang::AccountDeserialize;
+use digital_asset_types::dao::candy_machine;
+use mpl_candy_machine_core::CandyMachine;
+use plerkle_serialization::{AccountInfo, Pubkey as FBPubkey};
+use sea_orm::{
+    entity::*, query::*, sea_query::OnConflict, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, DbBackend, EntityTrait, Statement, TransactionTrait,
+};
+use solana_sdk::pubkey::Pubkey;
+
+// Seed of the PDA a candy machine mints through.  It signs as the verified first creator of
+// every asset the machine mints, which is how minted assets are tied back to their machine.
+const AUTHORITY_SEED: &[u8] = b"candy_machine";
+
+pub fn find_candy_machine_authority_pda(candy_machine: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[AUTHORITY_SEED, candy_machine.as_ref()],
+        &mpl_candy_machine_core::id(),
+    )
+    .0
+}
+
+pub async fn handle_candy_machine_account<'a>(
+    account_update: &'a AccountInfo<'a>,
+    db: &DatabaseConnection,
+) -> Result<(), IngesterError> {
+    let data = account_update
+        .data()
+        .map(|d| d.iter().collect::<Vec<u8>>())
+        .unwrap_or_default();
+    // The config lines following the candy machine are not indexed.
+    let candy_machine = match CandyMachine::try_deserialize(&mut data.as_slice()) {
+        Ok(candy_machine) => candy_machine,
+        Err(_) => return Err(IngesterError::NotImplemented),
+    };
+    let key = Pubkey::new(account_update.pubkey().unwrap().0.as_slice());
+    let slot = account_update.slot() as i64;
+    let authority_pda = find_candy_machine_authority_pda(&key);
+    let cm_data = candy_machine.data;
+
+    let model = candy_machine::ActiveModel {
+        id: Set(key.to_bytes().to_vec()),
+        features: Set(candy_machine.features as i64),
+        authority: Set(candy_machine.authority.to_bytes().to_vec()),
+        mint_authority: Set(candy_machine.mint_authority.to_bytes().to_vec()),
+        collection_mint: Set(candy_machine.collection_mint.to_bytes().to_vec()),
+        authority_pda: Set(authority_pda.to_bytes().to_vec()),
+        items_available: Set(cm_data.items_available as i64),
+        items_redeemed: Set(candy_machine.items_redeemed as i64),
+        symbol: Set(cm_data.symbol.trim().replace('\0', "")),
+        seller_fee_basis_points: Set(cm_data.seller_fee_basis_points as i32),
+        max_supply: Set(cm_data.max_supply as i64),
+        is_mutable: Set(cm_data.is_mutable),
+        slot_updated: Set(slot),
+    };
+    let mut query = candy_machine::Entity::insert(model)
+        .on_conflict(
+            OnConflict::columns([candy_machine::Column::Id])
+                .update_columns([
+                    candy_machine::Column::Features,
+                    candy_machine::Column::Authority,
+                    candy_machine::Column::MintAuthority,
+                    candy_machine::Column::CollectionMint,
+                    candy_machine::Column::AuthorityPda,
+                    candy_machine::Column::ItemsAvailable,
+                    candy_machine::Column::ItemsRedeemed,
+                    candy_machine::Column::Symbol,
+                    candy_machine::Column::SellerFeeBasisPoints,
+                    candy_machine::Column::MaxSupply,
+                    candy_machine::Column::IsMutable,
+                    candy_machine::Column::SlotUpdated,
+                ])
+                .to_owned(),
+        )
+        .build(DbBackend::Postgres);
+    query.sql = format!(
+        "{} WHERE excluded.slot_updated > candy_machine.slot_updated",
+        query.sql
+    );
+
+    let txn = db.begin().await?;
+    let indexed = candy_machine::Entity::find_by_id(key.to_bytes().to_vec())
+        .one(&txn)
+        .await?
+        .is_some();
+    txn.execute(query).await?;
+    // Assets are linked to the machine as their metadata is indexed, those indexed before the
+    // machine itself are linked once, when it is first seen.
+    if !indexed {
+        link_minted_assets(&key, &authority_pda, slot, &txn).await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+async fn link_minted_assets(
+    candy_machine: &Pubkey,
+    authority_pda: &Pubkey,
+    slot: i64,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "INSERT INTO asset_grouping (asset_id, group_key, group_value, seq, slot_updated, verified) \
+         SELECT asset_id, 'candy_machine', $1, 0, $2, true FROM asset_creators \
+         WHERE creator = $3 AND position = 0 AND verified \
+         ON CONFLICT (asset_id, group_key) DO NOTHING",
+        vec![
+            candy_machine.to_string().into(),
+            slot.into(),
+            authority_pda.to_bytes().to_vec().into(),
+        ],
+    ))
+    .await?;
+    Ok(())
+}
+
+/// A closed candy machine has been withdrawn, the assets it minted stay linked to it.
+pub async fn handle_candy_machine_account_closure(
+    key: FBPubkey,
+    slot: u64,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    candy_machine::Entity::delete_many()
+        .filter(candy_machine::Column::Id.eq(key.0.to_vec()))
+        .filter(candy_machine::Column::SlotUpdated.lte(slot as i64))
+        .exec(txn)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use mpl_candy_guard::guards::GuardSet;
+use serde_json::{json, Map, Value};
+
+/// The configuration of the guards enabled in a guard set, keyed by guard name.  The freeze and
+/// program gate guards are not reported yet.
+pub fn guard_set_to_json(guards: &GuardSet) -> Value {
+    let mut json = Map::new();
+    let mut add = |name: &str, config: Value| {
+        json.insert(name.to_string(), config);
+    };
+    if let Some(g) = &guards.bot_tax {
+        add(
+            "botTax",
+            json!({ "lamports": g.lamports, "lastInstruction": g.last_instruction }),
+        );
+    }
+    if let Some(g) = &guards.sol_payment {
+        add(
+            "solPayment",
+            json!({ "lamports": g.lamports, "destination": g.destination.to_string() }),
+        );
+    }
+    if let Some(g) = &guards.token_payment {
+        add(
+            "tokenPayment",
+            json!({
+                "amount": g.amount,
+                "mint": g.mint.to_string(),
+                "destinationAta": g.destination_ata.to_string(),
+            }),
+        );
+    }
+    if let Some(g) = &guards.start_date {
+        add("startDate", json!({ "date": g.date }));
+    }
+    if let Some(g) = &guards.end_date {
+        add("endDate", json!({ "date": g.date }));
+    }
+    if let Some(g) = &guards.third_party_signer {
+        add(
+            "thirdPartySigner",
+            json!({ "signerKey": g.signer_key.to_string() }),
+        );
+    }
+    if let Some(g) = &guards.token_gate {
+        add(
+            "tokenGate",
+            json!({ "mint": g.mint.to_string(), "amount": g.amount }),
+        );
+    }
+    if let Some(g) = &guards.gatekeeper {
+        add(
+            "gatekeeper",
+            json!({
+                "gatekeeperNetwork": g.gatekeeper_network.to_string(),
+                "expireOnUse": g.expire_on_use,
+            }),
+        );
+    }
+    if let Some(g) = &guards.allow_list {
+        add(
+            "allowList",
+            json!({ "merkleRoot": hex::encode(g.merkle_root) }),
+        );
+    }
+    if let Some(g) = &guards.mint_limit {
+        add("mintLimit", json!({ "id": g.id, "limit": g.limit }));
+    }
+    if let Some(g) = &guards.nft_payment {
+        add(
+            "nftPayment",
+            json!({
+                "requiredCollection": g.required_collection.to_string(),
+                "destination": g.destination.to_string(),
+            }),
+        );
+    }
+    if let Some(g) = &guards.redeemed_amount {
+        add("redeemedAmount", json!({ "maximum": g.maximum }));
+    }
+    if let Some(g) = &guards.address_gate {
+        add("addressGate", json!({ "address": g.address.to_string() }));
+    }
+    if let Some(g) = &guards.nft_gate {
+        add(
+            "nftGate",
+            json!({ "requiredCollection": g.required_collection.to_string() }),
+        );
+    }
+    if let Some(g) = &guards.nft_burn {
+        add(
+            "nftBurn",
+            json!({ "requiredCollection": g.required_collection.to_string() }),
+        );
+    }
+    if let Some(g) = &guards.token_burn {
+        add(
+            "tokenBurn",
+            json!({ "amount": g.amount, "mint": g.mint.to_string() }),
+        );
+    }
+    Value::Object(json)
+}
@@ -0,0 +1,6 @@
+mod candy_guard;
+mod candy_machine_core;
+mod guards;
+
+pub use candy_guard::*;
+pub use candy_machine_core::*;
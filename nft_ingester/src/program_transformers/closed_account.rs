@@ -0,0 +1,42 @@
+use crate::IngesterError;
+use digital_asset_types::dao::closed_accounts;
+use plerkle_serialization::{AccountInfo, Pubkey as FBPubkey};
+use sea_orm::{
+    query::*, sea_query::OnConflict, ActiveValue::Set, ConnectionTrait, DatabaseTransaction,
+    DbBackend, EntityTrait,
+};
+
+/// An account is closed once the runtime drained its lamports, the data left behind (if any)
+/// is no longer meaningful and must not be handed to the program parsers.
+pub fn is_closed_account(acct: &AccountInfo) -> bool {
+    acct.lamports() == 0 || acct.data().map(|d| d.is_empty()).unwrap_or(true)
+}
+
+pub async fn save_closed_account(
+    key: &FBPubkey,
+    owner: &FBPubkey,
+    slot: u64,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    let model = closed_accounts::ActiveModel {
+        pubkey: Set(key.0.to_vec()),
+        owner: Set(owner.0.to_vec()),
+        slot_closed: Set(slot as i64),
+    };
+    let mut query = closed_accounts::Entity::insert(model)
+        .on_conflict(
+            OnConflict::columns([closed_accounts::Column::Pubkey])
+                .update_columns([
+                    closed_accounts::Column::Owner,
+                    closed_accounts::Column::SlotClosed,
+                ])
+                .to_owned(),
+        )
+        .build(DbBackend::Postgres);
+    query.sql = format!(
+        "{} WHERE excluded.slot_closed > closed_accounts.slot_closed",
+        query.sql
+    );
+    txn.execute(query).await?;
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! The creators of an asset, kept in `asset_creators` in the order its metadata lists them.
+use digital_asset_types::dao::asset_creators;
+use sea_orm::{
+    entity::*, query::*, ActiveValue::Set, DatabaseTransaction, DbBackend, DbErr, EntityTrait,
+    Statement,
+};
+use std::collections::HashSet;
+
+/// A creator as listed in the metadata of an asset.
+pub struct Creator {
+    pub address: Vec<u8>,
+    pub share: u8,
+    pub verified: bool,
+}
+
+/// Statements replacing the creators of an asset with `creators`, as written at `seq` and
+/// `slot`.  Every row of the asset is rewritten, so that positions stay consecutive and a
+/// reordered creator doesn't collide with `asset_creators_unique (asset_id, creator)`.  Nothing
+/// is written if the rows come from a later update, ordered by sequence number then slot.
+pub async fn replace_creators(
+    asset_id: &[u8],
+    creators: Vec<Creator>,
+    seq: i64,
+    slot: i64,
+    txn: &DatabaseTransaction,
+) -> Result<Vec<Statement>, DbErr> {
+    let written_later = Condition::any()
+        .add(asset_creators::Column::Seq.gt(seq))
+        .add(
+            Condition::all()
+                .add(asset_creators::Column::Seq.eq(seq))
+                .add(asset_creators::Column::SlotUpdated.gt(slot)),
+        );
+    let later = asset_creators::Entity::find()
+        .filter(asset_creators::Column::AssetId.eq(asset_id.to_vec()))
+        .filter(written_later.clone())
+        .one(txn)
+        .await?;
+    if later.is_some() {
+        return Ok(vec![]);
+    }
+
+    let mut statements = vec![asset_creators::Entity::delete_many()
+        .filter(asset_creators::Column::AssetId.eq(asset_id.to_vec()))
+        .filter(written_later.not())
+        .build(DbBackend::Postgres)];
+    // A creator listed twice keeps its first position.
+    let mut seen = HashSet::new();
+    let rows: Vec<_> = creators
+        .into_iter()
+        .filter(|c| seen.insert(c.address.clone()))
+        .enumerate()
+        .map(|(i, c)| asset_creators::ActiveModel {
+            asset_id: Set(asset_id.to_vec()),
+            creator: Set(c.address),
+            share: Set(c.share as i32),
+            verified: Set(c.verified),
+            seq: Set(seq),
+            slot_updated: Set(slot),
+            position: Set(i as i16),
+            ..Default::default()
+        })
+        .collect();
+    if !rows.is_empty() {
+        statements.push(asset_creators::Entity::insert_many(rows).build(DbBackend::Postgres));
+    }
+    Ok(statements)
+}
@@ -0,0 +1,436 @@
+use crate::{
+    program_transformers::{
+        auction_house::{
+            handle_auction_house_account, handle_auction_house_account_closure,
+            handle_auction_house_instruction, AUCTION_HOUSE_PROGRAM_ID,
+        },
+        batch::BatchRow,
+        beneficial_owner::{
+            handle_token_owned_escrow_account, parse_token_owned_escrow, BeneficialOwnerResolver,
+        },
+        bubblegum::{handle_bubblegum_account, handle_bubblegum_instruction},
+        candy_machine::{
+            handle_candy_guard_account, handle_candy_guard_account_closure,
+            handle_candy_machine_account, handle_candy_machine_account_closure,
+        },
+        hydra::{handle_hydra_account, handle_hydra_account_closure, HYDRA_PROGRAM_ID},
+        token::{
+            handle_token_program_account, handle_token_program_account_closure,
+            save_token_program_rows, token_program_row,
+        },
+        token_metadata::{handle_token_metadata_account, handle_token_metadata_account_closure},
+    },
+    IngesterError, TaskData,
+};
+use async_trait::async_trait;
+use blockbuster::{
+    instruction::InstructionBundle,
+    program_handler::ProgramParser,
+    programs::{
+        bubblegum::BubblegumParser, token_account::TokenAccountParser,
+        token_metadata::TokenMetadataParser, ProgramParseResult,
+    },
+};
+use plerkle_serialization::{AccountInfo, Pubkey as FBPubkey};
+use sea_orm::{DatabaseConnection, DatabaseTransaction};
+use solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Indexes the accounts and instructions of a single program.  Handlers are registered with
+/// the `ProgramTransformer`, which routes everything owned or invoked by `key` to them.
+/// Anything a handler doesn't index should be answered with `IngesterError::NotImplemented`,
+/// the default for all methods, so the message is acknowledged without being retried.
+#[async_trait]
+pub trait ProgramHandler: Send + Sync {
+    /// Name used to enable the handler through the `program_handlers` configuration.
+    fn name(&self) -> &'static str;
+
+    /// Program whose accounts and instructions are handled.
+    fn key(&self) -> Pubkey;
+
+    async fn handle_instruction<'a>(
+        &self,
+        _bundle: &'a InstructionBundle<'a>,
+        _db: &DatabaseConnection,
+        _task_sender: &UnboundedSender<TaskData>,
+    ) -> Result<(), IngesterError> {
+        Err(IngesterError::NotImplemented)
+    }
+
+    async fn handle_account<'a>(
+        &self,
+        _account_update: &'a AccountInfo<'a>,
+        _db: &DatabaseConnection,
+        _task_sender: &UnboundedSender<TaskData>,
+    ) -> Result<(), IngesterError> {
+        Err(IngesterError::NotImplemented)
+    }
+
+    /// The state an instruction updates, `keys` being its accounts.  Instructions with the same
+    /// key are processed in the order they were received, those without one alongside any
+    /// other.
+    fn instruction_ordering_key(&self, _keys: &[FBPubkey]) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// The state an account update updates, the account itself by default.
+    fn account_ordering_key(&self, account_update: &AccountInfo) -> Option<Vec<u8>> {
+        account_update.pubkey().map(|k| k.0.to_vec())
+    }
+
+    /// The row an account update writes when it is batched with others, none for the updates
+    /// only `handle_account` indexes.
+    fn batch_row(&self, _account_update: &AccountInfo) -> Option<BatchRow> {
+        None
+    }
+
+    /// Writes the rows `batch_row` made of a batch, the newest of each account, inside the
+    /// transaction flushing the batch.
+    async fn save_batch(
+        &self,
+        _rows: Vec<BatchRow>,
+        _txn: &DatabaseTransaction,
+    ) -> Result<(), IngesterError> {
+        Err(IngesterError::NotImplemented)
+    }
+
+    /// Called inside the transaction recording a closed account of the program, so that
+    /// whatever was derived from the account can be tombstoned along with it.
+    async fn handle_closed_account(
+        &self,
+        _key: FBPubkey,
+        _slot: u64,
+        _txn: &DatabaseTransaction,
+    ) -> Result<(), IngesterError> {
+        Ok(())
+    }
+}
+
+/// Handlers for the programs indexed out of the box, `resolver` recognises the escrows that
+/// hold tokens on behalf of a wallet.
+pub fn default_handlers(resolver: Arc<BeneficialOwnerResolver>) -> Vec<Arc<dyn ProgramHandler>> {
+    vec![
+        Arc::new(BubblegumHandler),
+        Arc::new(TokenMetadataHandler),
+        Arc::new(TokenHandler::new(resolver)),
+        Arc::new(CandyMachineHandler),
+        Arc::new(CandyGuardHandler),
+        Arc::new(HydraHandler),
+        Arc::new(AuctionHouseHandler),
+    ]
+}
+
+pub struct BubblegumHandler;
+
+#[async_trait]
+impl ProgramHandler for BubblegumHandler {
+    fn name(&self) -> &'static str {
+        "bubblegum"
+    }
+
+    fn key(&self) -> Pubkey {
+        BubblegumParser {}.key()
+    }
+
+    // The tree authority, a PDA of the tree, is the first account of the tree instructions.
+    fn instruction_ordering_key(&self, keys: &[FBPubkey]) -> Option<Vec<u8>> {
+        keys.first().map(|k| k.0.to_vec())
+    }
+
+    async fn handle_instruction<'a>(
+        &self,
+        bundle: &'a InstructionBundle<'a>,
+        db: &DatabaseConnection,
+        task_sender: &UnboundedSender<TaskData>,
+    ) -> Result<(), IngesterError> {
+        let result = BubblegumParser {}.handle_instruction(bundle)?;
+        match result.result_type() {
+            ProgramParseResult::Bubblegum(parsing_result) => {
+                handle_bubblegum_instruction(parsing_result, bundle, db, task_sender).await
+            }
+            _ => Err(IngesterError::NotImplemented),
+        }
+    }
+
+    // Bubblegum accounts are not parsed by blockbuster.
+    async fn handle_account<'a>(
+        &self,
+        account_update: &'a AccountInfo<'a>,
+        db: &DatabaseConnection,
+        _task_sender: &UnboundedSender<TaskData>,
+    ) -> Result<(), IngesterError> {
+        handle_bubblegum_account(account_update, db).await
+    }
+}
+
+// `Key::MetadataV1` and the offset of the mint in a metadata account, after the key and the
+// update authority.
+const METADATA_V1_KEY: u8 = 4;
+const METADATA_MINT_OFFSET: usize = 33;
+
+pub struct TokenMetadataHandler;
+
+#[async_trait]
+impl ProgramHandler for TokenMetadataHandler {
+    fn name(&self) -> &'static str {
+        "token_metadata"
+    }
+
+    fn key(&self) -> Pubkey {
+        TokenMetadataParser {}.key()
+    }
+
+    // Metadata accounts are ordered by their mint, the other accounts by themselves.
+    fn account_ordering_key(&self, account_update: &AccountInfo) -> Option<Vec<u8>> {
+        match account_update.data().map(|d| d.bytes()) {
+            Some(data) if data.len() >= METADATA_MINT_OFFSET + 32 && data[0] == METADATA_V1_KEY => {
+                Some(data[METADATA_MINT_OFFSET..METADATA_MINT_OFFSET + 32].to_vec())
+            }
+            _ => account_update.pubkey().map(|k| k.0.to_vec()),
+        }
+    }
+
+    async fn handle_account<'a>(
+        &self,
+        account_update: &'a AccountInfo<'a>,
+        db: &DatabaseConnection,
+        task_sender: &UnboundedSender<TaskData>,
+    ) -> Result<(), IngesterError> {
+        // Token owned escrows are not parsed by blockbuster.
+        let escrow = account_update
+            .data()
+            .and_then(|d| parse_token_owned_escrow(d.bytes()));
+        if let Some(escrow) = escrow {
+            return handle_token_owned_escrow_account(account_update, escrow, db).await;
+        }
+        let result = TokenMetadataParser {}.handle_account(account_update)?;
+        match result.result_type() {
+            ProgramParseResult::TokenMetadata(parsing_result) => {
+                handle_token_metadata_account(account_update, parsing_result, db, task_sender).await
+            }
+            _ => Err(IngesterError::NotImplemented),
+        }
+    }
+
+    async fn handle_closed_account(
+        &self,
+        key: FBPubkey,
+        slot: u64,
+        txn: &DatabaseTransaction,
+    ) -> Result<(), IngesterError> {
+        handle_token_metadata_account_closure(key, slot, txn).await
+    }
+}
+
+pub struct TokenHandler {
+    resolver: Arc<BeneficialOwnerResolver>,
+}
+
+impl TokenHandler {
+    pub fn new(resolver: Arc<BeneficialOwnerResolver>) -> Self {
+        TokenHandler { resolver }
+    }
+}
+
+#[async_trait]
+impl ProgramHandler for TokenHandler {
+    fn name(&self) -> &'static str {
+        "token"
+    }
+
+    fn key(&self) -> Pubkey {
+        TokenAccountParser {}.key()
+    }
+
+    // Token accounts are ordered by their mint, mints by themselves.
+    fn account_ordering_key(&self, account_update: &AccountInfo) -> Option<Vec<u8>> {
+        match account_update.data().map(|d| d.bytes()) {
+            Some(data) if data.len() == spl_token::state::Account::LEN => Some(data[..32].to_vec()),
+            _ => account_update.pubkey().map(|k| k.0.to_vec()),
+        }
+    }
+
+    fn batch_row(&self, account_update: &AccountInfo) -> Option<BatchRow> {
+        let result = TokenAccountParser {}.handle_account(account_update).ok()?;
+        match result.result_type() {
+            ProgramParseResult::TokenProgramAccount(parsing_result) => {
+                token_program_row(account_update, parsing_result)
+            }
+            _ => None,
+        }
+    }
+
+    async fn save_batch(
+        &self,
+        rows: Vec<BatchRow>,
+        txn: &DatabaseTransaction,
+    ) -> Result<(), IngesterError> {
+        save_token_program_rows(rows, txn, &self.resolver).await
+    }
+
+    async fn handle_account<'a>(
+        &self,
+        account_update: &'a AccountInfo<'a>,
+        db: &DatabaseConnection,
+        task_sender: &UnboundedSender<TaskData>,
+    ) -> Result<(), IngesterError> {
+        let result = TokenAccountParser {}.handle_account(account_update)?;
+        match result.result_type() {
+            ProgramParseResult::TokenProgramAccount(parsing_result) => {
+                handle_token_program_account(
+                    account_update,
+                    parsing_result,
+                    db,
+                    task_sender,
+                    &self.resolver,
+                )
+                .await
+            }
+            _ => Err(IngesterError::NotImplemented),
+        }
+    }
+
+    async fn handle_closed_account(
+        &self,
+        key: FBPubkey,
+        slot: u64,
+        txn: &DatabaseTransaction,
+    ) -> Result<(), IngesterError> {
+        handle_token_program_account_closure(key, slot, txn).await
+    }
+}
+
+// Candy machine and candy guard accounts are not parsed by blockbuster.
+pub struct CandyMachineHandler;
+
+#[async_trait]
+impl ProgramHandler for CandyMachineHandler {
+    fn name(&self) -> &'static str {
+        "candy_machine"
+    }
+
+    fn key(&self) -> Pubkey {
+        mpl_candy_machine_core::id()
+    }
+
+    async fn handle_account<'a>(
+        &self,
+        account_update: &'a AccountInfo<'a>,
+        db: &DatabaseConnection,
+        _task_sender: &UnboundedSender<TaskData>,
+    ) -> Result<(), IngesterError> {
+        handle_candy_machine_account(account_update, db).await
+    }
+
+    async fn handle_closed_account(
+        &self,
+        key: FBPubkey,
+        slot: u64,
+        txn: &DatabaseTransaction,
+    ) -> Result<(), IngesterError> {
+        handle_candy_machine_account_closure(key, slot, txn).await
+    }
+}
+
+pub struct CandyGuardHandler;
+
+#[async_trait]
+impl ProgramHandler for CandyGuardHandler {
+    fn name(&self) -> &'static str {
+        "candy_guard"
+    }
+
+    fn key(&self) -> Pubkey {
+        mpl_candy_guard::id()
+    }
+
+    async fn handle_account<'a>(
+        &self,
+        account_update: &'a AccountInfo<'a>,
+        db: &DatabaseConnection,
+        _task_sender: &UnboundedSender<TaskData>,
+    ) -> Result<(), IngesterError> {
+        handle_candy_guard_account(account_update, db).await
+    }
+
+    async fn handle_closed_account(
+        &self,
+        key: FBPubkey,
+        slot: u64,
+        txn: &DatabaseTransaction,
+    ) -> Result<(), IngesterError> {
+        handle_candy_guard_account_closure(key, slot, txn).await
+    }
+}
+
+pub struct HydraHandler;
+
+#[async_trait]
+impl ProgramHandler for HydraHandler {
+    fn name(&self) -> &'static str {
+        "hydra"
+    }
+
+    fn key(&self) -> Pubkey {
+        HYDRA_PROGRAM_ID
+    }
+
+    async fn handle_account<'a>(
+        &self,
+        account_update: &'a AccountInfo<'a>,
+        db: &DatabaseConnection,
+        _task_sender: &UnboundedSender<TaskData>,
+    ) -> Result<(), IngesterError> {
+        handle_hydra_account(account_update, db).await
+    }
+
+    async fn handle_closed_account(
+        &self,
+        key: FBPubkey,
+        slot: u64,
+        txn: &DatabaseTransaction,
+    ) -> Result<(), IngesterError> {
+        handle_hydra_account_closure(key, slot, txn).await
+    }
+}
+
+pub struct AuctionHouseHandler;
+
+#[async_trait]
+impl ProgramHandler for AuctionHouseHandler {
+    fn name(&self) -> &'static str {
+        "auction_house"
+    }
+
+    fn key(&self) -> Pubkey {
+        AUCTION_HOUSE_PROGRAM_ID
+    }
+
+    async fn handle_instruction<'a>(
+        &self,
+        bundle: &'a InstructionBundle<'a>,
+        db: &DatabaseConnection,
+        _task_sender: &UnboundedSender<TaskData>,
+    ) -> Result<(), IngesterError> {
+        handle_auction_house_instruction(bundle, db).await
+    }
+
+    async fn handle_account<'a>(
+        &self,
+        account_update: &'a AccountInfo<'a>,
+        db: &DatabaseConnection,
+        _task_sender: &UnboundedSender<TaskData>,
+    ) -> Result<(), IngesterError> {
+        handle_auction_house_account(account_update, db).await
+    }
+
+    async fn handle_closed_account(
+        &self,
+        key: FBPubkey,
+        slot: u64,
+        txn: &DatabaseTransaction,
+    ) -> Result<(), IngesterError> {
+        handle_auction_house_account_closure(key, slot, txn).await
+    }
+}
@@ -0,0 +1,154 @@
+mod state;
+
+pub use state::*;
+
+use crate::IngesterError;
+use digital_asset_types::dao::{fanout, fanout_membership};
+use plerkle_serialization::{AccountInfo, Pubkey as FBPubkey};
+use sea_orm::{
+    entity::*, query::*, sea_query::OnConflict, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, DbBackend, EntityTrait, Statement, TransactionTrait,
+};
+
+/// Classifies the royalties of an asset as going to a fanout when the fanout wallet is the only
+/// creator of the asset.  Run after the creators of the asset have been written.
+pub fn fanout_royalty(asset_id: Vec<u8>) -> Statement {
+    Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "UPDATE asset SET royalty_target_type = 'fanout', royalty_target = fanout.account_key \
+         FROM fanout \
+         WHERE asset.id = $1 \
+         AND fanout.account_key = ALL (SELECT creator FROM asset_creators WHERE asset_id = $1) \
+         AND EXISTS (SELECT 1 FROM asset_creators WHERE asset_id = $1)",
+        vec![asset_id.into()],
+    )
+}
+
+pub async fn handle_hydra_account<'a>(
+    account_update: &'a AccountInfo<'a>,
+    db: &DatabaseConnection,
+) -> Result<(), IngesterError> {
+    let data = account_update
+        .data()
+        .map(|d| d.iter().collect::<Vec<u8>>())
+        .unwrap_or_default();
+    let key = account_update.pubkey().unwrap().0.to_vec();
+    let slot = account_update.slot() as i64;
+    match parse_hydra_account(&data) {
+        Some(HydraAccount::Fanout(f)) => save_fanout(key, slot, f, db).await,
+        Some(HydraAccount::FanoutMembershipVoucher(v)) => {
+            let model = fanout_membership::ActiveModel {
+                id: Set(key),
+                fanout: Set(v.fanout.to_bytes().to_vec()),
+                membership_key: Set(v.membership_key.to_bytes().to_vec()),
+                shares: Set(v.shares as i64),
+                total_inflow: Set(v.total_inflow as i64),
+                slot_updated: Set(slot),
+            };
+            let mut query = fanout_membership::Entity::insert(model)
+                .on_conflict(
+                    OnConflict::columns([fanout_membership::Column::Id])
+                        .update_columns([
+                            fanout_membership::Column::Fanout,
+                            fanout_membership::Column::MembershipKey,
+                            fanout_membership::Column::Shares,
+                            fanout_membership::Column::TotalInflow,
+                            fanout_membership::Column::SlotUpdated,
+                        ])
+                        .to_owned(),
+                )
+                .build(DbBackend::Postgres);
+            query.sql = format!(
+                "{} WHERE excluded.slot_updated > fanout_membership.slot_updated",
+                query.sql
+            );
+            db.execute(query).await?;
+            Ok(())
+        }
+        None => Err(IngesterError::NotImplemented),
+    }
+}
+
+async fn save_fanout(
+    key: Vec<u8>,
+    slot: i64,
+    f: Fanout,
+    db: &DatabaseConnection,
+) -> Result<(), IngesterError> {
+    let wallet = f.account_key.to_bytes().to_vec();
+    let model = fanout::ActiveModel {
+        id: Set(key.clone()),
+        authority: Set(f.authority.to_bytes().to_vec()),
+        name: Set(f.name.trim().replace('\0', "")),
+        account_key: Set(wallet.clone()),
+        total_shares: Set(f.total_shares as i64),
+        total_members: Set(f.total_members as i64),
+        total_available_shares: Set(f.total_available_shares as i64),
+        total_inflow: Set(f.total_inflow as i64),
+        membership_model: Set(f.membership_model.as_str().to_string()),
+        membership_mint: Set(f.membership_mint.map(|m| m.to_bytes().to_vec())),
+        slot_updated: Set(slot),
+    };
+    let mut query = fanout::Entity::insert(model)
+        .on_conflict(
+            OnConflict::columns([fanout::Column::Id])
+                .update_columns([
+                    fanout::Column::Authority,
+                    fanout::Column::Name,
+                    fanout::Column::AccountKey,
+                    fanout::Column::TotalShares,
+                    fanout::Column::TotalMembers,
+                    fanout::Column::TotalAvailableShares,
+                    fanout::Column::TotalInflow,
+                    fanout::Column::MembershipModel,
+                    fanout::Column::MembershipMint,
+                    fanout::Column::SlotUpdated,
+                ])
+                .to_owned(),
+        )
+        .build(DbBackend::Postgres);
+    query.sql = format!(
+        "{} WHERE excluded.slot_updated > fanout.slot_updated",
+        query.sql
+    );
+
+    let txn = db.begin().await?;
+    let indexed = fanout::Entity::find_by_id(key).one(&txn).await?.is_some();
+    txn.execute(query).await?;
+    // Assets indexed before the fanout are classified once, when it is first seen.  Later
+    // assets are classified as they are indexed.
+    if !indexed {
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "UPDATE asset SET royalty_target_type = 'fanout', royalty_target = $1 \
+             WHERE id IN (SELECT asset_id FROM asset_creators WHERE creator = $1) \
+             AND NOT EXISTS \
+             (SELECT 1 FROM asset_creators WHERE asset_id = asset.id AND creator <> $1)",
+            vec![wallet.into()],
+        ))
+        .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+/// A closed voucher is a member that left the fanout.  Assets paying royalties to a closed
+/// fanout keep their classification, the wallet still receives the funds.
+pub async fn handle_hydra_account_closure(
+    key: FBPubkey,
+    slot: u64,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    let slot_i = slot as i64;
+    fanout_membership::Entity::delete_many()
+        .filter(fanout_membership::Column::Id.eq(key.0.to_vec()))
+        .filter(fanout_membership::Column::SlotUpdated.lte(slot_i))
+        .exec(txn)
+        .await?;
+    fanout::Entity::delete_many()
+        .filter(fanout::Column::Id.eq(key.0.to_vec()))
+        .filter(fanout::Column::SlotUpdated.lte(slot_i))
+        .exec(txn)
+        .await?;
+    Ok(())
+}
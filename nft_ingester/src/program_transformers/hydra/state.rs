@@ -0,0 +1,141 @@
+//! Layout of the Hydra accounts the ingester indexes.  Hydra is not a dependency of the
+//! workspace, so the anchor accounts are decoded here.
+use anchor_lang::solana_program::hash::hash;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::{pubkey, pubkey::Pubkey};
+
+pub const HYDRA_PROGRAM_ID: Pubkey = pubkey!("hyDQ4Nz1eYyegS6JfenyKwKzYxRsCWCriYSAjtzP4Vg");
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum MembershipModel {
+    Wallet,
+    Token,
+    NFT,
+}
+
+impl MembershipModel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MembershipModel::Wallet => "wallet",
+            MembershipModel::Token => "token",
+            MembershipModel::NFT => "nft",
+        }
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Fanout {
+    pub authority: Pubkey,
+    pub name: String,
+    // The wallet holding the funds to distribute, royalties are paid to it.
+    pub account_key: Pubkey,
+    pub total_shares: u64,
+    pub total_members: u64,
+    pub total_inflow: u64,
+    pub last_snapshot_amount: u64,
+    pub bump_seed: u8,
+    pub account_owner_bump_seed: u8,
+    pub total_available_shares: u64,
+    pub membership_model: MembershipModel,
+    pub total_staked_shares: Option<u64>,
+    pub membership_mint: Option<Pubkey>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FanoutMembershipVoucher {
+    pub fanout: Pubkey,
+    pub total_inflow: u64,
+    pub last_inflow: u64,
+    pub bump_seed: u8,
+    // The member wallet, or the membership NFT mint for the NFT membership model.
+    pub membership_key: Pubkey,
+    pub shares: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum HydraAccount {
+    Fanout(Fanout),
+    FanoutMembershipVoucher(FanoutMembershipVoucher),
+}
+
+fn account_discriminator(name: &str) -> [u8; 8] {
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&hash(format!("account:{}", name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+/// Decodes a fanout or a membership voucher, other Hydra accounts are not indexed.
+pub fn parse_hydra_account(data: &[u8]) -> Option<HydraAccount> {
+    if data.len() < 8 {
+        return None;
+    }
+    let (discriminator, mut rest) = data.split_at(8);
+    if discriminator == account_discriminator("Fanout") {
+        Fanout::deserialize(&mut rest)
+            .ok()
+            .map(HydraAccount::Fanout)
+    } else if discriminator == account_discriminator("FanoutMembershipVoucher") {
+        FanoutMembershipVoucher::deserialize(&mut rest)
+            .ok()
+            .map(HydraAccount::FanoutMembershipVoucher)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account_data(name: &str, account: impl BorshSerialize) -> Vec<u8> {
+        let mut data = account_discriminator(name).to_vec();
+        data.extend(account.try_to_vec().unwrap());
+        // Accounts are allocated with room to spare.
+        data.extend([0; 64]);
+        data
+    }
+
+    #[test]
+    fn parses_fanouts_and_vouchers() {
+        let fanout = Fanout {
+            authority: Pubkey::new_unique(),
+            name: "royalties".to_string(),
+            account_key: Pubkey::new_unique(),
+            total_shares: 100,
+            total_members: 2,
+            total_inflow: 0,
+            last_snapshot_amount: 0,
+            bump_seed: 255,
+            account_owner_bump_seed: 254,
+            total_available_shares: 0,
+            membership_model: MembershipModel::Wallet,
+            total_staked_shares: None,
+            membership_mint: None,
+        };
+        let voucher = FanoutMembershipVoucher {
+            fanout: Pubkey::new_unique(),
+            total_inflow: 10,
+            last_inflow: 5,
+            bump_seed: 253,
+            membership_key: Pubkey::new_unique(),
+            shares: 60,
+        };
+        assert_eq!(
+            parse_hydra_account(&account_data("Fanout", fanout.clone())),
+            Some(HydraAccount::Fanout(fanout))
+        );
+        assert_eq!(
+            parse_hydra_account(&account_data("FanoutMembershipVoucher", voucher.clone())),
+            Some(HydraAccount::FanoutMembershipVoucher(voucher))
+        );
+    }
+
+    #[test]
+    fn ignores_other_accounts() {
+        assert_eq!(parse_hydra_account(&[1, 2, 3]), None);
+        assert_eq!(
+            parse_hydra_account(&account_data("FanoutMint", 42u64)),
+            None
+        );
+    }
+}
@@ -1,62 +1,72 @@
-use blockbuster::{
-    instruction::InstructionBundle,
-    program_handler::ProgramParser,
-    programs::{
-        bubblegum::BubblegumParser, token_account::TokenAccountParser,
-        token_metadata::TokenMetadataParser, ProgramParseResult,
-    },
-};
-
 use crate::{error::IngesterError, TaskData};
-use blockbuster::instruction::IxPair;
+use blockbuster::instruction::{InstructionBundle, IxPair};
 use plerkle_serialization::{AccountInfo, Pubkey as FBPubkey, TransactionInfo};
-use sea_orm::{DatabaseConnection, SqlxPostgresConnector};
+use sea_orm::{DatabaseConnection, SqlxPostgresConnector, TransactionTrait};
 use solana_sdk::pubkey::Pubkey;
 use sqlx::PgPool;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::{
     order_instructions,
-    program_transformers::{
-        bubblegum::handle_bubblegum_instruction, token::handle_token_program_account,
-        token_metadata::handle_token_metadata_account,
-    },
+    program_transformers::closed_account::{is_closed_account, save_closed_account},
+    slots::set_write_slot,
 };
 
+mod auction_house;
+mod batch;
+mod beneficial_owner;
 mod bubblegum;
+mod candy_machine;
+mod closed_account;
+mod creators;
+mod handler;
+mod hydra;
 mod token;
 mod token_metadata;
 
+pub use batch::{AccountBatch, BatchRow, DEFAULT_BATCH_LATENCY, DEFAULT_BATCH_SIZE};
+pub use beneficial_owner::BeneficialOwnerResolver;
+pub use handler::*;
+
+/// Condition of an upsert from an account update, which only overwrites rows written by an
+/// earlier update.  Updates are ordered by slot, then by write version within a slot.
+pub(crate) fn newer_update(table: &str) -> String {
+    format!(
+        "(excluded.slot_updated, excluded.write_version) > ({0}.slot_updated, {0}.write_version)",
+        table
+    )
+}
+
 pub struct ProgramTransformer {
     storage: DatabaseConnection,
     task_sender: UnboundedSender<TaskData>,
-    matchers: HashMap<Pubkey, Box<dyn ProgramParser>>,
-    key_set: HashSet<Pubkey>
+    handlers: HashMap<Pubkey, Arc<dyn ProgramHandler>>,
+    key_set: HashSet<Pubkey>,
 }
 
 impl ProgramTransformer {
     pub fn new(pool: PgPool, task_sender: UnboundedSender<TaskData>) -> Self {
-        let mut matchers: HashMap<Pubkey, Box<dyn ProgramParser>> = HashMap::with_capacity(1);
-        let bgum = BubblegumParser {};
-        let token_metadata = TokenMetadataParser {};
-        let token = TokenAccountParser {};
-        matchers.insert(bgum.key(), Box::new(bgum));
-        matchers.insert(token_metadata.key(), Box::new(token_metadata));
-        matchers.insert(token.key(), Box::new(token));
-        let hs = matchers.iter().fold(HashSet::new(), |mut acc, (k, _)| {
-            acc.insert(*k);
-            acc
-        });
         let pool: PgPool = pool;
         ProgramTransformer {
             storage: SqlxPostgresConnector::from_sqlx_postgres_pool(pool),
             task_sender,
-            matchers,
-            key_set: hs,
+            handlers: HashMap::new(),
+            key_set: HashSet::new(),
         }
     }
 
+    /// Routes the accounts and instructions of the handler's program to it, replacing any
+    /// handler previously registered for the same program.
+    pub fn register(&mut self, handler: Arc<dyn ProgramHandler>) {
+        let key = handler.key();
+        self.key_set.insert(key);
+        self.handlers.insert(key, handler);
+    }
+
     pub fn break_transaction<'i>(
         &self,
         tx: &'i TransactionInfo<'i>,
@@ -65,31 +75,74 @@ impl ProgramTransformer {
         order_instructions(ref_set, tx)
     }
 
-    pub fn match_program(&self, key: &FBPubkey) -> Option<&Box<dyn ProgramParser>> {
-        self.matchers.get(&Pubkey::new(key.0.as_slice()))
+    pub fn match_program(&self, key: &FBPubkey) -> Option<&Arc<dyn ProgramHandler>> {
+        self.handlers.get(&Pubkey::new(key.0.as_slice()))
+    }
+
+    /// What the transaction's instructions update, transactions sharing a key are processed in
+    /// the order they were received.
+    pub fn transaction_ordering_keys(&self, tx: &TransactionInfo) -> Vec<Vec<u8>> {
+        let keys: Vec<FBPubkey> = tx
+            .account_keys()
+            .unwrap_or_default()
+            .iter()
+            .copied()
+            .collect();
+        self.break_transaction(tx)
+            .into_iter()
+            .filter_map(|((program, instruction), _)| {
+                let handler = self.match_program(&program)?;
+                let ix_keys: Vec<FBPubkey> = instruction
+                    .accounts()?
+                    .iter()
+                    .filter_map(|a| keys.get(a as usize).copied())
+                    .collect();
+                handler.instruction_ordering_key(&ix_keys)
+            })
+            .collect()
+    }
+
+    /// What the account update updates, see `ProgramHandler::account_ordering_key`.
+    pub fn account_ordering_key(&self, acct: &AccountInfo) -> Option<Vec<u8>> {
+        match acct.owner().and_then(|owner| self.match_program(owner)) {
+            Some(handler) => handler.account_ordering_key(acct),
+            None => acct.pubkey().map(|k| k.0.to_vec()),
+        }
+    }
+
+    /// The handler and the row of an account update that can be batched, see
+    /// `ProgramHandler::batch_row`.  Closed accounts are never batched.
+    pub fn batch_row(&self, acct: &AccountInfo) -> Option<(Pubkey, BatchRow)> {
+        if is_closed_account(acct) {
+            return None;
+        }
+        let handler = self.match_program(acct.owner()?)?;
+        handler.batch_row(acct).map(|row| (handler.key(), row))
+    }
+
+    /// Writes the rows of a batch, all of them in one transaction.
+    pub async fn save_batch(
+        &self,
+        rows: HashMap<Pubkey, Vec<BatchRow>>,
+    ) -> Result<(), IngesterError> {
+        let txn = self.storage.begin().await?;
+        for (program, rows) in rows {
+            if let Some(handler) = self.handlers.get(&program) {
+                handler.save_batch(rows, &txn).await?;
+            }
+        }
+        txn.commit().await?;
+        Ok(())
     }
 
     pub async fn handle_instruction<'a>(
         &self,
         ix: &'a InstructionBundle<'a>,
     ) -> Result<(), IngesterError> {
-        if let Some(program) = self.match_program(&ix.program) {
-            let result = program.handle_instruction(ix)?;
-            let concrete = result.result_type();
-
-            match concrete {
-                ProgramParseResult::Bubblegum(parsing_result) => {
-                    handle_bubblegum_instruction(
-                        parsing_result,
-                        ix,
-                        &self.storage,
-                        &self.task_sender,
-                    )
-                    .await
-                }
-
-                _ => Err(IngesterError::NotImplemented),
-            }?;
+        if let Some(handler) = self.match_program(&ix.program) {
+            handler
+                .handle_instruction(ix, &self.storage, &self.task_sender)
+                .await?;
         }
         Ok(())
     }
@@ -99,31 +152,32 @@ impl ProgramTransformer {
         acct: AccountInfo<'b>,
     ) -> Result<(), IngesterError> {
         let owner = acct.owner().unwrap();
-        if let Some(program) = self.match_program(owner) {
-            let result = program.handle_account(&acct)?;
-            let concrete = result.result_type();
-            match concrete {
-                ProgramParseResult::TokenMetadata(parsing_result) => {
-                    handle_token_metadata_account(
-                        &acct,
-                        parsing_result,
-                        &self.storage,
-                        &self.task_sender,
-                    )
-                    .await
-                }
-                ProgramParseResult::TokenProgramAccount(parsing_result) => {
-                    handle_token_program_account(
-                        &acct,
-                        parsing_result,
-                        &self.storage,
-                        &self.task_sender,
-                    )
-                    .await
-                }
-                _ => Err(IngesterError::NotImplemented),
-            }?;
+        if let Some(handler) = self.match_program(owner) {
+            if is_closed_account(&acct) {
+                return self.handle_closed_account(handler, &acct).await;
+            }
+            handler
+                .handle_account(&acct, &self.storage, &self.task_sender)
+                .await?;
         }
         Ok(())
     }
+
+    /// Closed accounts can't be parsed, so they are recorded here for every program and each
+    /// handler only gets the chance to tombstone whatever it derived from the account.
+    async fn handle_closed_account<'b>(
+        &self,
+        handler: &Arc<dyn ProgramHandler>,
+        acct: &AccountInfo<'b>,
+    ) -> Result<(), IngesterError> {
+        let key = *acct.pubkey().unwrap();
+        let owner = *acct.owner().unwrap();
+        let slot = acct.slot();
+        let txn = self.storage.begin().await?;
+        set_write_slot(&txn, Some(slot)).await?;
+        save_closed_account(&key, &owner, slot, &txn).await?;
+        handler.handle_closed_account(key, slot, &txn).await?;
+        txn.commit().await?;
+        Ok(())
+    }
 }
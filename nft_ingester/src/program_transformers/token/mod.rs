@@ -1,13 +1,22 @@
-use crate::{IngesterError, TaskData};
+use crate::{
+    program_transformers::{
+        batch::BatchRow,
+        beneficial_owner::{update_escrowed_assets, BeneficialOwnerResolver},
+        newer_update,
+    },
+    slots::set_write_slot,
+    IngesterError, TaskData,
+};
 use blockbuster::programs::token_account::TokenProgramAccount;
-use digital_asset_types::dao::{asset, token_accounts, tokens};
-use plerkle_serialization::AccountInfo;
+use digital_asset_types::dao::{asset, sea_orm_active_enums::OwnerType, token_accounts, tokens};
+use plerkle_serialization::{AccountInfo, Pubkey as FBPubkey};
 use sea_orm::{
-    entity::*, query::*, sea_query::OnConflict, ActiveValue::Set, ConnectionTrait,
-    DatabaseConnection, DbBackend, EntityTrait,
+    entity::*, query::*, sea_query::Expr, sea_query::OnConflict, ConnectionTrait,
+    DatabaseConnection, DatabaseTransaction, DbBackend, EntityTrait, Statement, TransactionTrait,
 };
 use solana_sdk::program_option::COption;
 use spl_token::state::AccountState;
+use std::collections::{BTreeMap, HashSet};
 use tokio::sync::mpsc::UnboundedSender;
 
 pub async fn handle_token_program_account<'a, 'b, 'c>(
@@ -15,68 +24,44 @@ pub async fn handle_token_program_account<'a, 'b, 'c>(
     parsing_result: &'b TokenProgramAccount,
     db: &'c DatabaseConnection,
     _task_manager: &UnboundedSender<TaskData>,
+    resolver: &BeneficialOwnerResolver,
 ) -> Result<(), IngesterError> {
-    let key = *account_update.pubkey().unwrap();
-    let key_bytes = key.0.to_vec();
-    let spl_token_program = account_update.owner().unwrap().0.to_vec();
-    match &parsing_result {
+    let row =
+        token_program_row(account_update, parsing_result).ok_or(IngesterError::NotImplemented)?;
+    let txn = db.begin().await?;
+    save_token_program_rows(vec![row], &txn, resolver).await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+/// The row a token account or mint update writes.
+pub fn token_program_row(
+    account_update: &AccountInfo,
+    parsing_result: &TokenProgramAccount,
+) -> Option<BatchRow> {
+    let key = account_update.pubkey()?.0.to_vec();
+    let token_program = account_update.owner()?.0.to_vec();
+    let slot_updated = account_update.slot() as i64;
+    let write_version = account_update.write_version() as i64;
+    match parsing_result {
         TokenProgramAccount::TokenAccount(ta) => {
-            let mint = ta.mint.to_bytes().to_vec();
             let delegate: Option<Vec<u8>> = match ta.delegate {
                 COption::Some(d) => Some(d.to_bytes().to_vec()),
                 COption::None => None,
             };
-            let frozen = match ta.state {
-                AccountState::Frozen => true,
-                _ => false,
-            };
-            let owner = ta.owner.to_bytes().to_vec();
-            let model = token_accounts::ActiveModel {
-                pubkey: Set(key_bytes),
-                mint: Set(mint.clone()),
-                delegate: Set(delegate),
-                owner: Set(owner.clone()),
-                frozen: Set(frozen),
-                delegated_amount: Set(ta.delegated_amount as i64),
-                token_program: Set(spl_token_program),
-                slot_updated: Set(account_update.slot() as i64),
-                amount: Set(ta.amount as i64),
-                close_authority: Set(None),
-            };
-
-            let mut query = token_accounts::Entity::insert(model)
-                .on_conflict(
-                    OnConflict::columns([token_accounts::Column::Pubkey])
-                        .update_columns([
-                            token_accounts::Column::Mint,
-                            token_accounts::Column::DelegatedAmount,
-                            token_accounts::Column::Delegate,
-                            token_accounts::Column::Amount,
-                            token_accounts::Column::Frozen,
-                            token_accounts::Column::TokenProgram,
-                            token_accounts::Column::Owner,
-                            token_accounts::Column::CloseAuthority,
-                            token_accounts::Column::SlotUpdated,
-                        ])
-                        .to_owned(),
-                )
-                .build(DbBackend::Postgres);
-            query.sql = format!(
-                "{} WHERE excluded.slot_updated > token_accounts.slot_updated",
-                query.sql
-            );
-            db.execute(query).await?;
-
-            let asset_update: Option<asset::Model> = asset::Entity::find_by_id(mint)
-                .filter(asset::Column::OwnerType.eq("single"))
-                .one(db)
-                .await?;
-            if let Some(asset) = asset_update {
-                let mut active: asset::ActiveModel = asset.into();
-                active.owner = Set(Some(owner));
-                active.save(db).await?;
-            }
-            Ok(())
+            Some(BatchRow::TokenAccount(token_accounts::Model {
+                pubkey: key,
+                mint: ta.mint.to_bytes().to_vec(),
+                amount: ta.amount as i64,
+                owner: ta.owner.to_bytes().to_vec(),
+                frozen: matches!(ta.state, AccountState::Frozen),
+                close_authority: None,
+                delegate,
+                delegated_amount: ta.delegated_amount as i64,
+                slot_updated,
+                token_program,
+                write_version,
+            }))
         }
         TokenProgramAccount::Mint(m) => {
             let freeze_auth: Option<Vec<u8>> = match m.freeze_authority {
@@ -87,42 +72,559 @@ pub async fn handle_token_program_account<'a, 'b, 'c>(
                 COption::Some(d) => Some(d.to_bytes().to_vec()),
                 COption::None => None,
             };
-            let model = tokens::ActiveModel {
-                mint: Set(key_bytes),
-                token_program: Set(spl_token_program),
-                slot_updated: Set(account_update.slot() as i64),
-                supply: Set(m.supply as i64),
-                decimals: Set(m.decimals as i32),
-                close_authority: Set(None),
-                extension_data: Set(None),
-                mint_authority: Set(mint_auth),
-                freeze_authority: Set(freeze_auth),
-            };
+            Some(BatchRow::Mint(tokens::Model {
+                mint: key,
+                supply: m.supply as i64,
+                decimals: m.decimals as i32,
+                token_program,
+                mint_authority: mint_auth,
+                freeze_authority: freeze_auth,
+                close_authority: None,
+                extension_data: None,
+                slot_updated,
+                write_version,
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Writes token account and mint rows with one upsert per table, each row only overwriting
+/// what an earlier update wrote.  A row per account at most.
+pub async fn save_token_program_rows(
+    rows: Vec<BatchRow>,
+    txn: &DatabaseTransaction,
+    resolver: &BeneficialOwnerResolver,
+) -> Result<(), IngesterError> {
+    let mut token_accounts = Vec::new();
+    let mut mints = Vec::new();
+    for row in rows {
+        match row {
+            BatchRow::TokenAccount(ta) => token_accounts.push(ta),
+            BatchRow::Mint(m) => mints.push(m),
+        }
+    }
+    save_token_accounts(token_accounts, txn, resolver).await?;
+    save_mints(mints, txn).await
+}
+
+// Condition of an asset owner update from the token account update with the given slot and
+// write version.  Write versions are global, so updates of different accounts are ordered too.
+const NEWER_OWNER: &str = "(asset.owner_slot_updated, asset.owner_write_version) < ($1, $2)";
+
+/// Keys of the rows an upsert `RETURNING` them wrote.
+async fn written_keys(
+    mut query: Statement,
+    table: &str,
+    key: &str,
+    txn: &DatabaseTransaction,
+) -> Result<HashSet<Vec<u8>>, IngesterError> {
+    query.sql = format!(
+        "{} WHERE {} RETURNING {}.{}",
+        query.sql,
+        newer_update(table),
+        table,
+        key
+    );
+    let mut written = HashSet::new();
+    for row in txn.query_all(query).await? {
+        written.insert(row.try_get::<Vec<u8>>("", key)?);
+    }
+    Ok(written)
+}
+
+async fn save_token_accounts(
+    token_accounts: Vec<token_accounts::Model>,
+    txn: &DatabaseTransaction,
+    resolver: &BeneficialOwnerResolver,
+) -> Result<(), IngesterError> {
+    if token_accounts.is_empty() {
+        return Ok(());
+    }
+    let models = token_accounts
+        .iter()
+        .cloned()
+        .map(token_accounts::ActiveModel::from);
+    let query = token_accounts::Entity::insert_many(models)
+        .on_conflict(
+            OnConflict::columns([token_accounts::Column::Pubkey])
+                .update_columns([
+                    token_accounts::Column::Mint,
+                    token_accounts::Column::DelegatedAmount,
+                    token_accounts::Column::Delegate,
+                    token_accounts::Column::Amount,
+                    token_accounts::Column::Frozen,
+                    token_accounts::Column::TokenProgram,
+                    token_accounts::Column::Owner,
+                    token_accounts::Column::CloseAuthority,
+                    token_accounts::Column::SlotUpdated,
+                    token_accounts::Column::WriteVersion,
+                ])
+                .to_owned(),
+        )
+        .build(DbBackend::Postgres);
+    let written = written_keys(query, "token_accounts", "pubkey", txn).await?;
+
+    // The version guard leaves the rows of stale updates untouched, in which case their asset
+    // must not be touched either.  Only the account holding the single token of a single owner
+    // asset determines its ownership.
+    let owned: Vec<_> = token_accounts
+        .into_iter()
+        .filter(|ta| ta.amount == 1 && written.contains(&ta.pubkey))
+        .collect();
+    for ta in &owned {
+        // The asset keeps its `slot_updated`, the write is made for the slot of the account.
+        set_write_slot(txn, Some(ta.slot_updated as u64)).await?;
+        let (mint, owner) = (ta.mint.clone(), ta.owner.clone());
+        let previous = asset::Entity::find_by_id(mint.clone())
+            .filter(asset::Column::OwnerType.eq(OwnerType::Single))
+            .one(txn)
+            .await?;
+        let beneficial_owner = resolver
+            .resolve(&mint, &owner, previous.as_ref(), txn)
+            .await?;
+        // The update of another account of the mint, like the account the token was transferred
+        // out of, may be older than the one the asset's owner was taken from.
+        let res = asset::Entity::update_many()
+            .col_expr(asset::Column::Owner, Expr::value(Some(owner.clone())))
+            .col_expr(asset::Column::Delegate, Expr::value(ta.delegate.clone()))
+            .col_expr(asset::Column::Frozen, Expr::value(ta.frozen))
+            .col_expr(
+                asset::Column::BeneficialOwner,
+                Expr::value(beneficial_owner.clone()),
+            )
+            .col_expr(
+                asset::Column::OwnerSlotUpdated,
+                Expr::value(ta.slot_updated),
+            )
+            .col_expr(
+                asset::Column::OwnerWriteVersion,
+                Expr::value(ta.write_version),
+            )
+            .filter(asset::Column::Id.eq(mint.clone()))
+            .filter(asset::Column::OwnerType.eq(OwnerType::Single))
+            .filter(Expr::cust_with_values(
+                NEWER_OWNER,
+                vec![ta.slot_updated, ta.write_version],
+            ))
+            .exec(txn)
+            .await?;
+        if previous.is_some() && res.rows_affected > 0 {
+            update_escrowed_assets(&mint, beneficial_owner.or(Some(owner)), txn).await?;
+        }
+    }
+    if !owned.is_empty() {
+        set_write_slot(txn, None).await?;
+    }
+    Ok(())
+}
+
+async fn save_mints(
+    mints: Vec<tokens::Model>,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    if mints.is_empty() {
+        return Ok(());
+    }
+    let models = mints.iter().cloned().map(tokens::ActiveModel::from);
+    let query = tokens::Entity::insert_many(models)
+        .on_conflict(
+            OnConflict::columns([tokens::Column::Mint])
+                .update_columns([
+                    tokens::Column::Supply,
+                    tokens::Column::TokenProgram,
+                    tokens::Column::MintAuthority,
+                    tokens::Column::CloseAuthority,
+                    tokens::Column::ExtensionData,
+                    tokens::Column::SlotUpdated,
+                    tokens::Column::Decimals,
+                    tokens::Column::FreezeAuthority,
+                    tokens::Column::WriteVersion,
+                ])
+                .to_owned(),
+        )
+        .build(DbBackend::Postgres);
+    let written = written_keys(query, "tokens", "mint", txn).await?;
+
+    // A single owner asset whose supply went to zero has been burnt.  The supply is also zero
+    // right after the mint is created, so the flag is cleared again once the token is minted.
+    // The updates are made for the slot of the mints, see `set_write_slot`.
+    let mut updates: BTreeMap<(bool, i64), Vec<Vec<u8>>> = BTreeMap::new();
+    for m in mints.into_iter().filter(|m| written.contains(&m.mint)) {
+        updates
+            .entry((m.supply == 0, m.slot_updated))
+            .or_default()
+            .push(m.mint);
+    }
+    for ((burnt, slot), mints) in &updates {
+        set_write_slot(txn, Some(*slot as u64)).await?;
+        asset::Entity::update_many()
+            .col_expr(asset::Column::Burnt, Expr::value(*burnt))
+            .filter(asset::Column::Id.is_in(mints.clone()))
+            .filter(asset::Column::OwnerType.eq(OwnerType::Single))
+            .exec(txn)
+            .await?;
+    }
+    if !updates.is_empty() {
+        set_write_slot(txn, None).await?;
+    }
+    Ok(())
+}
 
-            let mut query = tokens::Entity::insert(model)
-                .on_conflict(
-                    OnConflict::columns([tokens::Column::Mint])
-                        .update_columns([
-                            tokens::Column::Supply,
-                            tokens::Column::TokenProgram,
-                            tokens::Column::MintAuthority,
-                            tokens::Column::CloseAuthority,
-                            tokens::Column::ExtensionData,
-                            tokens::Column::SlotUpdated,
-                            tokens::Column::Decimals,
-                            tokens::Column::FreezeAuthority,
-                        ])
-                        .to_owned(),
+/// Tombstones the token account or mint behind a closed token program account.  The rows are
+/// kept with a zero balance / supply so the last known owner and mint stay queryable, the
+/// closure itself is recorded in `closed_accounts`.
+pub async fn handle_token_program_account_closure(
+    key: FBPubkey,
+    slot: u64,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    let key_bytes = key.0.to_vec();
+    let slot_i = slot as i64;
+    let token_account = token_accounts::Entity::find_by_id(key_bytes.clone())
+        .filter(token_accounts::Column::SlotUpdated.lte(slot_i))
+        .one(txn)
+        .await?;
+    if let Some(ta) = token_account {
+        token_accounts::Entity::update_many()
+            .col_expr(token_accounts::Column::Amount, Expr::value(0i64))
+            .col_expr(token_accounts::Column::DelegatedAmount, Expr::value(0i64))
+            .col_expr(
+                token_accounts::Column::Delegate,
+                Expr::value(Option::<Vec<u8>>::None),
+            )
+            .col_expr(token_accounts::Column::SlotUpdated, Expr::value(slot_i))
+            .filter(token_accounts::Column::Pubkey.eq(key_bytes))
+            .exec(txn)
+            .await?;
+
+        // Only the account still holding the token owned the asset, an emptied account that is
+        // closed after a transfer says nothing about the current owner.
+        if ta.amount > 0 {
+            asset::Entity::update_many()
+                .col_expr(asset::Column::Owner, Expr::value(Option::<Vec<u8>>::None))
+                .col_expr(
+                    asset::Column::Delegate,
+                    Expr::value(Option::<Vec<u8>>::None),
                 )
-                .build(DbBackend::Postgres);
-            query.sql = format!(
-                "{} WHERE excluded.slot_updated > tokens.slot_updated",
-                query.sql
-            );
-            db.execute(query).await?;
-            Ok(())
+                .col_expr(
+                    asset::Column::BeneficialOwner,
+                    Expr::value(Option::<Vec<u8>>::None),
+                )
+                .filter(asset::Column::Id.eq(ta.mint))
+                .filter(asset::Column::Owner.eq(ta.owner))
+                .filter(asset::Column::OwnerType.eq(OwnerType::Single))
+                .exec(txn)
+                .await?;
         }
-        _ => Err(IngesterError::NotImplemented),
-    }?;
+        return Ok(());
+    }
+
+    let token = tokens::Entity::find_by_id(key_bytes.clone())
+        .filter(tokens::Column::SlotUpdated.lte(slot_i))
+        .one(txn)
+        .await?;
+    if token.is_some() {
+        tokens::Entity::update_many()
+            .col_expr(tokens::Column::Supply, Expr::value(0i64))
+            .col_expr(tokens::Column::SlotUpdated, Expr::value(slot_i))
+            .filter(tokens::Column::Mint.eq(key_bytes.clone()))
+            .exec(txn)
+            .await?;
+        asset::Entity::update_many()
+            .col_expr(asset::Column::Burnt, Expr::value(true))
+            .filter(asset::Column::Id.eq(key_bytes))
+            .exec(txn)
+            .await?;
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digital_asset_types::dao::token_owned_escrow;
+    use flatbuffers::FlatBufferBuilder;
+    use plerkle_serialization::{root_as_account_info, AccountInfoArgs};
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult};
+    use solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+    use spl_token::state::Account;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    fn serialize_update(key: &Pubkey, slot: u64, write_version: u64, data: &[u8]) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::new();
+        let pubkey = FBPubkey(key.to_bytes());
+        let owner = FBPubkey(spl_token::id().to_bytes());
+        let data = Some(builder.create_vector(data));
+        let account_info = AccountInfo::create(
+            &mut builder,
+            &AccountInfoArgs {
+                pubkey: Some(&pubkey),
+                lamports: 1,
+                owner: Some(&owner),
+                executable: false,
+                rent_epoch: 0,
+                data,
+                write_version,
+                slot,
+                is_startup: false,
+                seen_at: 0,
+            },
+        );
+        builder.finish(account_info, None);
+        builder.finished_data().to_vec()
+    }
+
+    #[tokio::test]
+    async fn upserts_are_guarded_by_slot_and_write_version() {
+        let key = Pubkey::new_unique();
+        let account = Account {
+            mint: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            amount: 1000,
+            state: AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut data = vec![0; Account::LEN];
+        Account::pack(account, &mut data).unwrap();
+        let bytes = serialize_update(&key, 100, 12, &data);
+        let account_update = root_as_account_info(&bytes).unwrap();
+
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<token_accounts::Model>::new()])
+            .into_connection();
+        let (sender, _receiver) = unbounded_channel();
+        handle_token_program_account(
+            &account_update,
+            &TokenProgramAccount::TokenAccount(account),
+            &db,
+            &sender,
+            &BeneficialOwnerResolver::default(),
+        )
+        .await
+        .unwrap();
+
+        // The row is only overwritten by an update with a greater slot, or with a greater write
+        // version within the same slot.
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(log.contains(
+            "WHERE (excluded.slot_updated, excluded.write_version) > \
+             (token_accounts.slot_updated, token_accounts.write_version) \
+             RETURNING token_accounts.pubkey"
+        ));
+        assert!(log.contains("BigInt(Some(100))"));
+        assert!(log.contains("BigInt(Some(12))"));
+    }
+
+    #[tokio::test]
+    async fn writes_batches_with_one_upsert_per_table() {
+        let mut rows: Vec<BatchRow> = (0..3)
+            .map(|_| {
+                BatchRow::TokenAccount(token_accounts::Model {
+                    pubkey: Pubkey::new_unique().to_bytes().to_vec(),
+                    mint: Pubkey::new_unique().to_bytes().to_vec(),
+                    amount: 1,
+                    owner: Pubkey::new_unique().to_bytes().to_vec(),
+                    frozen: false,
+                    close_authority: None,
+                    delegate: None,
+                    delegated_amount: 0,
+                    slot_updated: 100,
+                    token_program: spl_token::id().to_bytes().to_vec(),
+                    write_version: 1,
+                })
+            })
+            .collect();
+        rows.push(BatchRow::Mint(tokens::Model {
+            mint: Pubkey::new_unique().to_bytes().to_vec(),
+            supply: 1,
+            decimals: 0,
+            token_program: spl_token::id().to_bytes().to_vec(),
+            mint_authority: None,
+            freeze_authority: None,
+            close_authority: None,
+            extension_data: None,
+            slot_updated: 100,
+            write_version: 2,
+        }));
+
+        // None of the rows is newer than what is stored, so the assets are left alone.
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<token_accounts::Model>::new()])
+            .append_query_results(vec![Vec::<tokens::Model>::new()])
+            .into_connection();
+        let txn = db.begin().await.unwrap();
+        save_token_program_rows(rows, &txn, &BeneficialOwnerResolver::default())
+            .await
+            .unwrap();
+        txn.commit().await.unwrap();
+
+        let log = format!("{:?}", db.into_transaction_log());
+        assert_eq!(log.matches("INSERT INTO").count(), 2);
+        assert_eq!(log.matches("RETURNING").count(), 2);
+        assert!(!log.contains(r#"UPDATE \"asset\""#));
+    }
+
+    fn holder(
+        pubkey: &Pubkey,
+        mint: &Pubkey,
+        slot_updated: i64,
+        write_version: i64,
+    ) -> token_accounts::Model {
+        token_accounts::Model {
+            pubkey: pubkey.to_bytes().to_vec(),
+            mint: mint.to_bytes().to_vec(),
+            amount: 1,
+            owner: Pubkey::new_unique().to_bytes().to_vec(),
+            frozen: false,
+            close_authority: None,
+            delegate: None,
+            delegated_amount: 0,
+            slot_updated,
+            token_program: spl_token::id().to_bytes().to_vec(),
+            write_version,
+        }
+    }
+
+    #[tokio::test]
+    async fn late_updates_of_the_previous_holder_leave_the_asset_owner_alone() {
+        let mint = Pubkey::new_unique();
+        // The token moved from the sender's account to the receiver's, and the update of the
+        // sender's account from before the transfer is delivered last.
+        let receiver = holder(&Pubkey::new_unique(), &mint, 101, 5);
+        let sender = holder(&Pubkey::new_unique(), &mint, 100, 9);
+        for (ta, owner_updated) in [(receiver, true), (sender, false)] {
+            let db = MockDatabase::new(DatabaseBackend::Postgres)
+                .append_query_results(vec![vec![ta.clone()]])
+                .append_query_results(vec![Vec::<asset::Model>::new()])
+                .append_query_results(vec![Vec::<token_owned_escrow::Model>::new()])
+                .append_exec_results(
+                    [1, owner_updated as u64, 1]
+                        .into_iter()
+                        .map(|rows_affected| MockExecResult {
+                            last_insert_id: 0,
+                            rows_affected,
+                        })
+                        .collect(),
+                )
+                .into_connection();
+            let txn = db.begin().await.unwrap();
+            save_token_program_rows(
+                vec![BatchRow::TokenAccount(ta.clone())],
+                &txn,
+                &BeneficialOwnerResolver::default(),
+            )
+            .await
+            .unwrap();
+            txn.commit().await.unwrap();
+
+            let log = format!("{:?}", db.into_transaction_log());
+            let update = log.find(r#"UPDATE \"asset\""#).unwrap();
+            let guard = log
+                .find("(asset.owner_slot_updated, asset.owner_write_version) < (")
+                .unwrap();
+            assert!(update < guard);
+            let versions = format!(
+                "BigInt(Some({})), BigInt(Some({}))",
+                ta.slot_updated, ta.write_version
+            );
+            assert_eq!(log[update..].matches(&versions).count(), 2);
+        }
+    }
+
+    /// The transaction log of closing `key` at `slot` against a database answering the lookups
+    /// of the closed token account and mint with `token_account` and `mint`.
+    async fn close(
+        key: &Pubkey,
+        slot: u64,
+        token_account: Option<token_accounts::Model>,
+        mint: Option<tokens::Model>,
+    ) -> String {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![token_account.into_iter().collect::<Vec<_>>()])
+            .append_query_results(vec![mint.into_iter().collect::<Vec<_>>()])
+            .append_exec_results(
+                (0..2)
+                    .map(|_| MockExecResult {
+                        last_insert_id: 0,
+                        rows_affected: 1,
+                    })
+                    .collect(),
+            )
+            .into_connection();
+        let txn = db.begin().await.unwrap();
+        handle_token_program_account_closure(FBPubkey(key.to_bytes()), slot, &txn)
+            .await
+            .unwrap();
+        txn.commit().await.unwrap();
+        format!("{:?}", db.into_transaction_log())
+    }
+
+    #[tokio::test]
+    async fn closing_a_token_account_empties_it_and_clears_the_asset_owner() {
+        let key = Pubkey::new_unique();
+        let log = close(
+            &key,
+            150,
+            Some(holder(&key, &Pubkey::new_unique(), 100, 1)),
+            None,
+        )
+        .await;
+
+        let emptied = log
+            .find(r#"UPDATE \"token_accounts\" SET \"amount\""#)
+            .unwrap();
+        assert!(log[emptied..]
+            .contains(r#"\"delegated_amount\" = $2, \"delegate\" = $3, \"slot_updated\" = $4"#));
+        assert!(log[emptied..]
+            .contains("BigInt(Some(0)), BigInt(Some(0)), Bytes(None), BigInt(Some(150))"));
+        assert!(log.contains(r#"UPDATE \"asset\" SET \"owner\" = $1, \"delegate\" = $2"#));
+        assert!(!log.contains(r#"UPDATE \"tokens\""#));
+    }
+
+    #[tokio::test]
+    async fn closing_an_emptied_token_account_leaves_the_asset_owner_alone() {
+        let key = Pubkey::new_unique();
+        let emptied = token_accounts::Model {
+            amount: 0,
+            ..holder(&key, &Pubkey::new_unique(), 100, 1)
+        };
+        let log = close(&key, 150, Some(emptied), None).await;
+
+        assert!(log.contains(r#"UPDATE \"token_accounts\" SET \"amount\""#));
+        assert!(!log.contains(r#"UPDATE \"asset\""#));
+    }
+
+    #[tokio::test]
+    async fn closing_a_mint_zeroes_its_supply_and_burns_the_asset() {
+        let key = Pubkey::new_unique();
+        let mint = tokens::Model {
+            mint: key.to_bytes().to_vec(),
+            supply: 1,
+            decimals: 0,
+            token_program: spl_token::id().to_bytes().to_vec(),
+            mint_authority: None,
+            freeze_authority: None,
+            close_authority: None,
+            extension_data: None,
+            slot_updated: 100,
+            write_version: 1,
+        };
+        let log = close(&key, 150, None, Some(mint)).await;
+
+        assert!(log.contains(r#"UPDATE \"tokens\" SET \"supply\" = $1, \"slot_updated\" = $2"#));
+        assert!(log.contains("BigInt(Some(0)), BigInt(Some(150))"));
+        assert!(log.contains(r#"UPDATE \"asset\" SET \"burnt\" = $1"#));
+        assert!(log.contains("Bool(Some(true))"));
+    }
+
+    #[tokio::test]
+    async fn closures_older_than_the_rows_leave_them_alone() {
+        // Both lookups only find rows last updated at or before the closure.
+        let log = close(&Pubkey::new_unique(), 150, None, None).await;
+
+        assert!(log.contains(r#"\"token_accounts\".\"slot_updated\" <= $2"#));
+        assert!(log.contains(r#"\"tokens\".\"slot_updated\" <= $2"#));
+        assert!(!log.contains("UPDATE"));
+    }
+}
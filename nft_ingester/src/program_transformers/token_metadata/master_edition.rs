@@ -1,4 +1,4 @@
-use crate::IngesterError;
+use crate::{program_transformers::newer_update, IngesterError};
 use blockbuster::token_metadata::state::{Key, MasterEditionV1, MasterEditionV2};
 use digital_asset_types::dao::{
     asset, asset_v1_account_attachments,
@@ -13,6 +13,7 @@ use sea_orm::{
 pub async fn save_v2_master_edition(
     id: FBPubkey,
     slot: u64,
+    write_version: u64,
     me_data: &MasterEditionV2,
     txn: &DatabaseTransaction,
 ) -> Result<(), IngesterError> {
@@ -20,6 +21,7 @@ pub async fn save_v2_master_edition(
         V1AccountAttachments::MasterEditionV2,
         id,
         slot,
+        write_version,
         me_data,
         txn,
     )
@@ -29,6 +31,7 @@ pub async fn save_v2_master_edition(
 pub async fn save_v1_master_edition(
     id: FBPubkey,
     slot: u64,
+    write_version: u64,
     me_data: &MasterEditionV1,
     txn: &DatabaseTransaction,
 ) -> Result<(), IngesterError> {
@@ -41,6 +44,7 @@ pub async fn save_v1_master_edition(
         V1AccountAttachments::MasterEditionV1,
         id,
         slot,
+        write_version,
         &bridge,
         txn,
     )
@@ -51,6 +55,7 @@ pub async fn save_master_edition(
     _version: V1AccountAttachments,
     id: FBPubkey,
     slot: u64,
+    write_version: u64,
     me_data: &MasterEditionV2,
     txn: &DatabaseTransaction,
 ) -> Result<(), IngesterError> {
@@ -69,6 +74,7 @@ pub async fn save_master_edition(
         attachment_type: Set(V1AccountAttachments::MasterEditionV1),
         data: Set(Some(ser)),
         slot_updated: Set(slot as i64),
+        write_version: Set(write_version as i64),
         ..Default::default()
     };
 
@@ -79,17 +85,24 @@ pub async fn save_master_edition(
         updatable.update(txn).await?;
     }
 
-    let query = asset_v1_account_attachments::Entity::insert(model)
+    let mut query = asset_v1_account_attachments::Entity::insert(model)
         .on_conflict(
             OnConflict::columns([asset_v1_account_attachments::Column::Id])
                 .update_columns([
                     asset_v1_account_attachments::Column::AttachmentType,
                     asset_v1_account_attachments::Column::Data,
                     asset_v1_account_attachments::Column::SlotUpdated,
+                    asset_v1_account_attachments::Column::WriteVersion,
                 ])
                 .to_owned(),
         )
         .build(DbBackend::Postgres);
+    // The attachment created along with the metadata has no data yet, whatever its slot.
+    query.sql = format!(
+        "{} WHERE {} OR asset_v1_account_attachments.data IS NULL",
+        query.sql,
+        newer_update("asset_v1_account_attachments")
+    );
     txn.execute(query).await?;
     Ok(())
 }
@@ -6,11 +6,15 @@ use crate::{
         master_edition::{save_v1_master_edition, save_v2_master_edition},
         v1_asset::save_v1_asset,
     },
+    slots::set_write_slot,
     IngesterError, TaskData,
 };
 use blockbuster::programs::token_metadata::{TokenMetadataAccountData, TokenMetadataAccountState};
-use plerkle_serialization::AccountInfo;
-use sea_orm::{DatabaseConnection, TransactionTrait};
+use digital_asset_types::dao::{asset, token_owned_escrow};
+use plerkle_serialization::{AccountInfo, Pubkey as FBPubkey};
+use sea_orm::{
+    entity::*, query::*, sea_query::Expr, DatabaseConnection, DatabaseTransaction, TransactionTrait,
+};
 use tokio::sync::mpsc::UnboundedSender;
 
 pub async fn handle_token_metadata_account<'a, 'b, 'c>(
@@ -21,22 +25,25 @@ pub async fn handle_token_metadata_account<'a, 'b, 'c>(
 ) -> Result<(), IngesterError> {
     let txn = db.begin().await?;
     let key = *account_update.pubkey().unwrap();
+    let slot = account_update.slot();
+    set_write_slot(&txn, Some(slot)).await?;
+    let write_version = account_update.write_version();
     match &parsing_result.data {
         // TokenMetadataAccountData::EditionV1(e) => {}
         TokenMetadataAccountData::MasterEditionV1(m) => {
-            save_v1_master_edition(key, account_update.slot(), m, &txn).await?;
+            save_v1_master_edition(key, slot, write_version, m, &txn).await?;
             txn.commit().await?;
             Ok(())
         }
         TokenMetadataAccountData::MetadataV1(m) => {
             let task =
-                save_v1_asset(m.mint.as_ref().into(), account_update.slot(), m, &txn).await?;
+                save_v1_asset(m.mint.as_ref().into(), key, slot, write_version, m, &txn).await?;
             txn.commit().await?;
             task_manager.send(task)?;
             Ok(())
         }
         TokenMetadataAccountData::MasterEditionV2(m) => {
-            save_v2_master_edition(key, account_update.slot(), m, &txn).await?;
+            save_v2_master_edition(key, slot, write_version, m, &txn).await?;
             txn.commit().await?;
             Ok(())
         }
@@ -47,3 +54,75 @@ pub async fn handle_token_metadata_account<'a, 'b, 'c>(
     }?;
     Ok(())
 }
+
+/// A deleted metadata account burns the asset it describes, the asset is looked up through the
+/// metadata address recorded when the metadata was indexed.  A deleted token owned escrow is
+/// forgotten.
+pub async fn handle_token_metadata_account_closure(
+    key: FBPubkey,
+    slot: u64,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    let slot_i = slot as i64;
+    asset::Entity::update_many()
+        .col_expr(asset::Column::Burnt, Expr::value(true))
+        .col_expr(asset::Column::Owner, Expr::value(Option::<Vec<u8>>::None))
+        .col_expr(
+            asset::Column::Delegate,
+            Expr::value(Option::<Vec<u8>>::None),
+        )
+        .col_expr(asset::Column::Frozen, Expr::value(false))
+        .col_expr(asset::Column::SlotUpdated, Expr::value(slot_i))
+        .filter(asset::Column::MetadataAddress.eq(key.0.to_vec()))
+        .filter(asset::Column::SlotUpdated.lte(slot_i))
+        .exec(txn)
+        .await?;
+    token_owned_escrow::Entity::delete_many()
+        .filter(token_owned_escrow::Column::Id.eq(key.0.to_vec()))
+        .filter(token_owned_escrow::Column::SlotUpdated.lte(slot_i))
+        .exec(txn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult};
+    use solana_sdk::pubkey::Pubkey;
+
+    #[tokio::test]
+    async fn deleting_metadata_burns_its_asset_and_forgets_escrows() {
+        let key = Pubkey::new_unique();
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_exec_results(
+                (0..2)
+                    .map(|_| MockExecResult {
+                        last_insert_id: 0,
+                        rows_affected: 1,
+                    })
+                    .collect(),
+            )
+            .into_connection();
+        let txn = db.begin().await.unwrap();
+        handle_token_metadata_account_closure(FBPubkey(key.to_bytes()), 150, &txn)
+            .await
+            .unwrap();
+        txn.commit().await.unwrap();
+
+        // Only rows written at or before the closure are touched.
+        let log = format!("{:?}", db.into_transaction_log());
+        let burnt = log
+            .find(r#"UPDATE \"asset\" SET \"burnt\" = $1, \"owner\" = $2, \"delegate\" = $3, \"frozen\" = $4, \"slot_updated\" = $5"#)
+            .unwrap();
+        assert!(log[burnt..].contains(r#"WHERE \"asset\".\"metadata_address\" = $6"#));
+        assert!(log[burnt..].contains(r#"AND \"asset\".\"slot_updated\" <= $7"#));
+        assert!(log[burnt..].contains(
+            "Bool(Some(true)), Bytes(None), Bytes(None), Bool(Some(false)), BigInt(Some(150))"
+        ));
+        let deleted = log
+            .find(r#"DELETE FROM \"token_owned_escrow\" WHERE \"token_owned_escrow\".\"id\" = $1"#)
+            .unwrap();
+        assert!(log[deleted..].contains(r#"AND \"token_owned_escrow\".\"slot_updated\" <= $2"#));
+    }
+}
@@ -1,13 +1,22 @@
-use crate::{IngesterError, TaskData};
+use crate::{
+    program_transformers::{
+        auction_house::link_trades,
+        beneficial_owner::{escrow_beneficial_owner, update_escrowed_assets},
+        creators::{replace_creators, Creator},
+        hydra::fanout_royalty,
+        newer_update,
+    },
+    IngesterError, TaskData,
+};
 use blockbuster::token_metadata::{
     pda::find_master_edition_account,
-    state::{Metadata, TokenStandard, UseMethod, Uses},
+    state::{CollectionDetails, Metadata, TokenStandard, UseMethod, Uses},
 };
 use chrono::Utc;
 use digital_asset_types::{
     dao::{
-        asset, asset_authority, asset_creators, asset_data, asset_grouping,
-        asset_v1_account_attachments,
+        asset, asset_authority, asset_data, asset_grouping, asset_v1_account_attachments,
+        candy_machine, collection,
         sea_orm_active_enums::{
             ChainMutability, Mutability, OwnerType, RoyaltyTargetType, SpecificationAssetClass,
             SpecificationVersions, V1AccountAttachments,
@@ -19,10 +28,9 @@ use digital_asset_types::{
 use num_traits::FromPrimitive;
 use plerkle_serialization::Pubkey as FBPubkey;
 use sea_orm::{
-    entity::*, query::*, sea_query::OnConflict, ActiveValue::Set, ConnectionTrait,
+    entity::*, query::*, sea_query::Expr, sea_query::OnConflict, ActiveValue::Set, ConnectionTrait,
     DatabaseTransaction, DbBackend, DbErr, EntityTrait, JsonValue,
 };
-use std::collections::HashSet;
 
 use crate::tasks::{common::task::DownloadMetadata, IntoTaskData};
 use sea_orm::{FromQueryResult, JoinType};
@@ -34,11 +42,15 @@ struct OwnershipTokenModel {
     owner: Vec<u8>,
     delegate: Option<Vec<u8>>,
     token_account_amount: i64,
+    owner_slot_updated: i64,
+    owner_write_version: i64,
 }
 
 pub async fn save_v1_asset(
     id: FBPubkey,
+    metadata_address: FBPubkey,
     slot: u64,
+    write_version: u64,
     metadata: &Metadata,
     txn: &DatabaseTransaction,
 ) -> Result<TaskData, IngesterError> {
@@ -50,6 +62,7 @@ pub async fn save_v1_asset(
     let authority = metadata.update_authority.to_bytes().to_vec();
     let id = id.0;
     let slot_i = slot as i64;
+    let write_version_i = write_version as i64;
     let uri = data.uri.trim().replace('\0', "");
     if uri.is_empty() {
         return Err(IngesterError::DeserializationError(
@@ -76,6 +89,8 @@ pub async fn save_v1_asset(
                 .column_as(token_accounts::Column::Amount, "token_account_amount")
                 .column_as(token_accounts::Column::Owner, "owner")
                 .column_as(token_accounts::Column::Delegate, "delegate")
+                .column_as(token_accounts::Column::SlotUpdated, "owner_slot_updated")
+                .column_as(token_accounts::Column::WriteVersion, "owner_write_version")
                 .join(
                     JoinType::InnerJoin,
                     tokens::Entity::belongs_to(token_accounts::Entity)
@@ -83,6 +98,10 @@ pub async fn save_v1_asset(
                         .to(token_accounts::Column::Mint)
                         .into(),
                 )
+                // The account holding the token, as of its newest update.
+                .order_by_desc(token_accounts::Column::Amount)
+                .order_by_desc(token_accounts::Column::SlotUpdated)
+                .order_by_desc(token_accounts::Column::WriteVersion)
                 .into_model::<OwnershipTokenModel>()
                 .one(txn)
                 .await?;
@@ -99,6 +118,7 @@ pub async fn save_v1_asset(
                     close_authority: None,
                     extension_data: None,
                     slot_updated: 0,
+                    write_version: 0,
                 };
                 let token_account = token_accounts::Model {
                     pubkey: vec![],
@@ -110,8 +130,9 @@ pub async fn save_v1_asset(
                     frozen: false,
                     close_authority: None,
                     delegated_amount: 0,
-                    slot_updated: 0,
+                    slot_updated: t.owner_slot_updated,
                     token_program: vec![],
+                    write_version: t.owner_write_version,
                 };
                 (token, Some(token_account))
             }))
@@ -134,12 +155,18 @@ pub async fn save_v1_asset(
         None => (Set(1), NotSet),
     };
 
-    let (owner, delegate) = match token_result {
-        Some((_token, token_account)) => match token_account {
-            Some(account) => (Set(Some(account.owner)), Set(account.delegate)),
-            None => (NotSet, NotSet),
-        },
-        None => (NotSet, NotSet),
+    let (owner, delegate, owner_slot_updated, owner_write_version) = match token_result {
+        Some((_token, Some(account))) => (
+            Set(Some(account.owner)),
+            Set(account.delegate),
+            Set(account.slot_updated),
+            Set(account.write_version),
+        ),
+        _ => (NotSet, NotSet, NotSet, NotSet),
+    };
+    let holder = match &owner {
+        ActiveValue::Set(owner) => owner.clone(),
+        _ => None,
     };
 
     let mut chain_data = ChainDataV1 {
@@ -170,6 +197,7 @@ pub async fn save_v1_asset(
         metadata_mutability: Set(Mutability::Mutable),
         slot_updated: Set(slot_i),
         id: Set(id.to_vec()),
+        write_version: Set(write_version_i),
     };
     let mut query = asset_data::Entity::insert(asset_data_model)
         .on_conflict(
@@ -181,14 +209,12 @@ pub async fn save_v1_asset(
                     asset_data::Column::Metadata,
                     asset_data::Column::MetadataMutability,
                     asset_data::Column::SlotUpdated,
+                    asset_data::Column::WriteVersion,
                 ])
                 .to_owned(),
         )
         .build(DbBackend::Postgres);
-    query.sql = format!(
-        "{} WHERE excluded.slot_updated > asset_data.slot_updated",
-        query.sql
-    );
+    query.sql = format!("{} WHERE {}", query.sql, newer_update("asset_data"));
     let _res = txn.execute(query).await?;
 
     // Insert into `asset` table.
@@ -214,6 +240,10 @@ pub async fn save_v1_asset(
         asset_data: Set(Some(id.to_vec())),
         slot_updated: Set(slot_i),
         burnt: Set(false),
+        metadata_address: Set(Some(metadata_address.0.to_vec())),
+        write_version: Set(write_version_i),
+        owner_slot_updated,
+        owner_write_version,
         ..Default::default()
     };
 
@@ -241,16 +271,57 @@ pub async fn save_v1_asset(
                     asset::Column::AssetData,
                     asset::Column::SlotUpdated,
                     asset::Column::Burnt,
+                    asset::Column::MetadataAddress,
+                    asset::Column::WriteVersion,
+                    asset::Column::OwnerSlotUpdated,
+                    asset::Column::OwnerWriteVersion,
                 ])
                 .to_owned(),
         )
         .build(DbBackend::Postgres);
-    query.sql = format!(
-        "{} WHERE excluded.slot_updated > asset.slot_updated",
-        query.sql
-    );
+    query.sql = format!("{} WHERE {}", query.sql, newer_update("asset"));
     txn.execute(query).await?;
 
+    // Tokens held by a token owned escrow belong to whoever the escrow belongs to, and the
+    // escrows based on this asset belong to its holder.
+    if let Some(holder) = holder {
+        if let Some(beneficial_owner) = escrow_beneficial_owner(&holder, txn).await? {
+            asset::Entity::update_many()
+                .col_expr(
+                    asset::Column::BeneficialOwner,
+                    Expr::value(Some(beneficial_owner)),
+                )
+                .filter(asset::Column::Id.eq(id.to_vec()))
+                .filter(asset::Column::Owner.eq(holder))
+                .exec(txn)
+                .await?;
+        }
+        if let Some(asset) = asset::Entity::find_by_id(id.to_vec()).one(txn).await? {
+            update_escrowed_assets(&id, asset.beneficial_owner.or(asset.owner), txn).await?;
+        }
+    }
+
+    // Sized collections carry their size in the collection mint's own metadata.
+    if let Some(CollectionDetails::V1 { size }) = metadata.collection_details {
+        let model = collection::ActiveModel {
+            id: Set(id.to_vec()),
+            size: Set(Some(size as i64)),
+            slot_updated: Set(slot_i),
+        };
+        let mut query = collection::Entity::insert(model)
+            .on_conflict(
+                OnConflict::columns([collection::Column::Id])
+                    .update_columns([collection::Column::Size, collection::Column::SlotUpdated])
+                    .to_owned(),
+            )
+            .build(DbBackend::Postgres);
+        query.sql = format!(
+            "{} WHERE excluded.slot_updated > collection.slot_updated",
+            query.sql
+        );
+        txn.execute(query).await?;
+    }
+
     let attachment = asset_v1_account_attachments::ActiveModel {
         id: Set(edition_attachment_address.to_bytes().to_vec()),
         slot_updated: Set(slot_i),
@@ -266,129 +337,135 @@ pub async fn save_v1_asset(
         )
         .build(DbBackend::Postgres);
     txn.execute(query).await?;
-    // Insert into `asset_creators` table.
+    // Reconcile `asset_creators` with the metadata.
     let creators = data.creators.unwrap_or_default();
-    if !creators.is_empty() {
-        let mut db_creators = Vec::with_capacity(creators.len());
-        let mut creators_set = HashSet::new();
-        let existing_creators: Vec<asset_creators::Model> = asset_creators::Entity::find()
-            .filter(asset_creators::Column::AssetId.eq(id.to_vec()))
-            .filter(asset_creators::Column::SlotUpdated.lt(slot_i))
-            .all(txn)
-            .await?;
-        let existing_len = existing_creators.len();
-        let incoming_len = creators.len();
-        if existing_len > incoming_len {
-            let idx_to_delete = (existing_len - incoming_len) - 1;
-            asset_creators::Entity::delete_many()
+    let first_verified_creator = creators
+        .first()
+        .filter(|c| c.verified)
+        .map(|c| c.address.to_bytes().to_vec());
+    let creators = creators
+        .into_iter()
+        .map(|c| Creator {
+            address: c.address.to_bytes().to_vec(),
+            share: c.share,
+            verified: c.verified,
+        })
+        .collect();
+    for query in replace_creators(&id, creators, 0, slot_i, txn).await? {
+        txn.execute(query).await?;
+    }
+
+    // The asset is written above as paying its creators, royalties paid to a Hydra fanout
+    // wallet are classified once the creators are known.
+    txn.execute(fanout_royalty(id.to_vec())).await?;
+
+    // Insert into `asset_authority` table.
+    let model = asset_authority::ActiveModel {
+        asset_id: Set(id.to_vec()),
+        authority: Set(authority),
+        seq: Set(0),
+        slot_updated: Set(slot_i),
+        ..Default::default()
+    };
+    let mut query = asset_authority::Entity::insert(model)
+        .on_conflict(
+            OnConflict::columns([asset_authority::Column::AssetId])
+                .update_columns([
+                    asset_authority::Column::Authority,
+                    asset_authority::Column::Seq,
+                    asset_authority::Column::SlotUpdated,
+                ])
+                .to_owned(),
+        )
+        .build(DbBackend::Postgres);
+    query.sql = format!(
+        "{} WHERE excluded.slot_updated > asset_authority.slot_updated",
+        query.sql
+    );
+    txn.execute(query).await?;
+
+    // Reconcile `asset_grouping` with the metadata.  Unverified collection claims are kept with
+    // `verified = false`, a removed collection drops the grouping.
+    match &metadata.collection {
+        Some(c) => {
+            let model = asset_grouping::ActiveModel {
+                asset_id: Set(id.to_vec()),
+                group_key: Set("collection".to_string()),
+                group_value: Set(c.key.to_string()),
+                seq: Set(0),
+                slot_updated: Set(slot_i),
+                verified: Set(c.verified),
+                ..Default::default()
+            };
+
+            let mut query = asset_grouping::Entity::insert(model)
+                .on_conflict(
+                    OnConflict::columns([
+                        asset_grouping::Column::AssetId,
+                        asset_grouping::Column::GroupKey,
+                    ])
+                    .update_columns([
+                        asset_grouping::Column::GroupKey,
+                        asset_grouping::Column::GroupValue,
+                        asset_grouping::Column::Seq,
+                        asset_grouping::Column::SlotUpdated,
+                        asset_grouping::Column::Verified,
+                    ])
+                    .to_owned(),
+                )
+                .build(DbBackend::Postgres);
+            query.sql = format!(
+                "{} WHERE excluded.slot_updated > asset_grouping.slot_updated AND excluded.seq >= asset_grouping.seq",
+                query.sql
+            );
+            txn.execute(query).await?;
+        }
+        None => {
+            asset_grouping::Entity::delete_many()
                 .filter(
                     Condition::all()
-                        .add(asset_creators::Column::AssetId.eq(id.to_vec()))
-                        .add(asset_creators::Column::Position.gte(idx_to_delete as i16))
-                        .add(asset_creators::Column::SlotUpdated.lt(slot_i)),
+                        .add(asset_grouping::Column::AssetId.eq(id.to_vec()))
+                        .add(asset_grouping::Column::GroupKey.eq("collection"))
+                        .add(asset_grouping::Column::SlotUpdated.lt(slot_i)),
                 )
                 .exec(txn)
                 .await?;
         }
-        for (i, c) in creators.into_iter().enumerate() {
-            if creators_set.contains(&c.address) {
-                continue;
-            }
-            db_creators.push(asset_creators::ActiveModel {
+    }
+
+    // Assets minted by a candy machine have its authority PDA as their verified first creator.
+    if let Some(creator) = first_verified_creator {
+        let minted_by = candy_machine::Entity::find()
+            .filter(candy_machine::Column::AuthorityPda.eq(creator))
+            .one(txn)
+            .await?;
+        if let Some(candy_machine) = minted_by {
+            let model = asset_grouping::ActiveModel {
                 asset_id: Set(id.to_vec()),
-                creator: Set(c.address.to_bytes().to_vec()),
-                share: Set(c.share as i32),
-                verified: Set(c.verified),
-                seq: Set(0), // do we need this here @micheal-danenberg?
+                group_key: Set("candy_machine".to_string()),
+                group_value: Set(bs58::encode(candy_machine.id).into_string()),
+                seq: Set(0),
                 slot_updated: Set(slot_i),
-                position: Set(i as i16),
+                verified: Set(true),
                 ..Default::default()
-            });
-            creators_set.insert(c.address);
-        }
-
-        let mut query = asset_creators::Entity::insert_many(db_creators)
-            .on_conflict(
-                OnConflict::columns([
-                    asset_creators::Column::AssetId,
-                    asset_creators::Column::Position,
-                ])
-                .update_columns([
-                    asset_creators::Column::Creator,
-                    asset_creators::Column::Share,
-                    asset_creators::Column::Verified,
-                    asset_creators::Column::Seq,
-                    asset_creators::Column::SlotUpdated,
-                ])
-                .to_owned(),
-            )
-            .build(DbBackend::Postgres);
-        query.sql = format!(
-            "{} WHERE excluded.slot_updated > asset_creators.slot_updated",
-            query.sql
-        );
-        txn.execute(query).await?;
-
-        // Insert into `asset_authority` table.
-        let model = asset_authority::ActiveModel {
-            asset_id: Set(id.to_vec()),
-            authority: Set(authority),
-            seq: Set(0),
-            slot_updated: Set(slot_i),
-            ..Default::default()
-        };
-        let mut query = asset_authority::Entity::insert(model)
-            .on_conflict(
-                OnConflict::columns([asset_authority::Column::AssetId])
-                    .update_columns([
-                        asset_authority::Column::Authority,
-                        asset_authority::Column::Seq,
-                        asset_authority::Column::SlotUpdated,
+            };
+            let query = asset_grouping::Entity::insert(model)
+                .on_conflict(
+                    OnConflict::columns([
+                        asset_grouping::Column::AssetId,
+                        asset_grouping::Column::GroupKey,
                     ])
+                    .do_nothing()
                     .to_owned(),
-            )
-            .build(DbBackend::Postgres);
-        query.sql = format!(
-            "{} WHERE excluded.slot_updated > asset_authority.slot_updated",
-            query.sql
-        );
-        txn.execute(query).await?;
-
-        // Insert into `asset_grouping` table.
-        if let Some(c) = &metadata.collection {
-            if c.verified {
-                let model = asset_grouping::ActiveModel {
-                    asset_id: Set(id.to_vec()),
-                    group_key: Set("collection".to_string()),
-                    group_value: Set(c.key.to_string()),
-                    seq: Set(0),
-                    slot_updated: Set(slot_i),
-                    ..Default::default()
-                };
-
-                let mut query = asset_grouping::Entity::insert(model)
-                    .on_conflict(
-                        OnConflict::columns([
-                            asset_grouping::Column::AssetId,
-                            asset_grouping::Column::GroupKey,
-                        ])
-                        .update_columns([
-                            asset_grouping::Column::GroupKey,
-                            asset_grouping::Column::GroupValue,
-                            asset_grouping::Column::Seq,
-                            asset_grouping::Column::SlotUpdated,
-                        ])
-                        .to_owned(),
-                    )
-                    .build(DbBackend::Postgres);
-                query.sql = format!(
-                    "{} WHERE excluded.slot_updated > asset_grouping.slot_updated AND excluded.seq >= asset_grouping.seq",
-                    query.sql
-                );
-                txn.execute(query).await?;
-            }
+                )
+                .build(DbBackend::Postgres);
+            txn.execute(query).await?;
         }
     }
+
+    // Sells and bids placed before the metadata was indexed don't know the asset yet.
+    link_trades(&id, metadata_address.0.as_slice(), txn).await?;
+
     let mut task = DownloadMetadata {
         asset_data_id: id.to_vec(),
         uri,
@@ -397,3 +474,192 @@ pub async fn save_v1_asset(
     task.sanitize();
     task.into_task_data()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blockbuster::token_metadata::state::{Collection, Creator as MetadataCreator, Data};
+    use digital_asset_types::dao::asset_creators;
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult, TransactionTrait};
+    use solana_sdk::pubkey::Pubkey;
+
+    fn metadata(creators: &[Pubkey], collection: Option<Collection>) -> Metadata {
+        Metadata {
+            mint: Pubkey::new_unique(),
+            data: Data {
+                name: "Test".to_string(),
+                symbol: "TEST".to_string(),
+                uri: "https://example.com/test.json".to_string(),
+                seller_fee_basis_points: 500,
+                creators: Some(
+                    creators
+                        .iter()
+                        .map(|address| MetadataCreator {
+                            address: *address,
+                            verified: false,
+                            share: 50,
+                        })
+                        .collect(),
+                ),
+            },
+            collection,
+            ..Default::default()
+        }
+    }
+
+    /// The statements saving `metadata` at slot 100, `creators` being the asset's creator rows
+    /// written after it.
+    async fn save(metadata: &Metadata, creators: Vec<asset_creators::Model>) -> String {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![Vec::<tokens::Model>::new()])
+            .append_query_results(vec![creators])
+            .append_exec_results(
+                (0..16)
+                    .map(|_| MockExecResult {
+                        last_insert_id: 0,
+                        rows_affected: 1,
+                    })
+                    .collect(),
+            )
+            .into_connection();
+        let txn = db.begin().await.unwrap();
+        save_v1_asset(
+            FBPubkey(metadata.mint.to_bytes()),
+            FBPubkey(Pubkey::new_unique().to_bytes()),
+            100,
+            1,
+            metadata,
+            &txn,
+        )
+        .await
+        .unwrap();
+        txn.commit().await.unwrap();
+        format!("{:?}", db.into_transaction_log())
+    }
+
+    /// The logged statement starting with `sql`.
+    fn statement<'a>(log: &'a str, sql: &str) -> Option<&'a str> {
+        let start = log.find(sql)?;
+        let end = log[start..]
+            .find("Statement {")
+            .map_or(log.len(), |end| start + end);
+        Some(&log[start..end])
+    }
+
+    #[tokio::test]
+    async fn reordered_creators_replace_every_row() {
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let log = save(&metadata(&[second, first], None), vec![]).await;
+
+        // Rows are deleted whatever their position, so that `(asset_id, creator)` stays unique.
+        let deleted = statement(
+            &log,
+            r#"DELETE FROM \"asset_creators\" WHERE \"asset_creators\".\"asset_id\" = $1"#,
+        )
+        .unwrap();
+        assert!(!deleted.contains("position"));
+        let inserted = statement(&log, r#"INSERT INTO \"asset_creators\""#).unwrap();
+        assert!(!inserted.contains("ON CONFLICT"));
+        let second_at = inserted.find(&format!("{:?}", second.to_bytes())).unwrap();
+        let first_at = inserted.find(&format!("{:?}", first.to_bytes())).unwrap();
+        assert!(second_at < first_at);
+        assert!(inserted.contains("SmallInt(Some(0))"));
+        assert!(inserted.contains("SmallInt(Some(1))"));
+    }
+
+    #[tokio::test]
+    async fn creators_dropped_from_the_metadata_are_deleted() {
+        let log = save(&metadata(&[], None), vec![]).await;
+
+        assert!(statement(
+            &log,
+            r#"DELETE FROM \"asset_creators\" WHERE \"asset_creators\".\"asset_id\" = $1"#
+        )
+        .is_some());
+        assert!(statement(&log, r#"INSERT INTO \"asset_creators\""#).is_none());
+    }
+
+    #[tokio::test]
+    async fn duplicate_creators_keep_positions_consecutive() {
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let log = save(&metadata(&[first, first, second], None), vec![]).await;
+
+        let inserted = statement(&log, r#"INSERT INTO \"asset_creators\""#).unwrap();
+        assert_eq!(
+            inserted.matches(&format!("{:?}", first.to_bytes())).count(),
+            1
+        );
+        assert!(inserted.contains("SmallInt(Some(0))"));
+        assert!(inserted.contains("SmallInt(Some(1))"));
+        assert!(!inserted.contains("SmallInt(Some(2))"));
+    }
+
+    #[tokio::test]
+    async fn creators_written_later_are_left_alone() {
+        let metadata = metadata(&[Pubkey::new_unique()], None);
+        let later = asset_creators::Model {
+            id: 1,
+            asset_id: metadata.mint.to_bytes().to_vec(),
+            creator: Pubkey::new_unique().to_bytes().to_vec(),
+            share: 100,
+            verified: true,
+            seq: 0,
+            slot_updated: 200,
+            position: 0,
+        };
+        let log = save(&metadata, vec![later]).await;
+
+        assert!(statement(&log, r#"DELETE FROM \"asset_creators\""#).is_none());
+        assert!(statement(&log, r#"INSERT INTO \"asset_creators\""#).is_none());
+    }
+
+    #[tokio::test]
+    async fn unverified_collections_are_kept_unverified() {
+        let key = Pubkey::new_unique();
+        let collection = Collection {
+            verified: false,
+            key,
+        };
+        let log = save(&metadata(&[], Some(collection)), vec![]).await;
+
+        let grouping = statement(&log, r#"INSERT INTO \"asset_grouping\""#).unwrap();
+        assert!(grouping.contains(r#"\"verified\" = \"excluded\".\"verified\""#));
+        assert!(grouping.contains(&format!("String(Some({:?}))", key.to_string())));
+        assert!(grouping.contains("Bool(Some(false))"));
+    }
+
+    #[tokio::test]
+    async fn removed_collections_drop_the_grouping() {
+        let log = save(&metadata(&[], None), vec![]).await;
+
+        let deleted = statement(
+            &log,
+            r#"DELETE FROM \"asset_grouping\" WHERE \"asset_grouping\".\"asset_id\" = $1"#,
+        )
+        .unwrap();
+        assert!(deleted.contains(r#"AND \"asset_grouping\".\"group_key\" = $2"#));
+        assert!(deleted.contains(r#"AND \"asset_grouping\".\"slot_updated\" < $3"#));
+        assert!(statement(&log, r#"INSERT INTO \"asset_grouping\""#).is_none());
+    }
+
+    #[tokio::test]
+    async fn sized_collections_record_their_size_unless_written_later() {
+        let mut metadata = metadata(&[], None);
+        metadata.collection_details = Some(CollectionDetails::V1 { size: 42 });
+        let log = save(&metadata, vec![]).await;
+
+        let collection = statement(&log, r#"INSERT INTO \"collection\""#).unwrap();
+        assert!(collection.contains(r#"ON CONFLICT (\"id\") DO UPDATE SET"#));
+        assert!(collection.contains(r#"\"size\" = \"excluded\".\"size\""#));
+        assert!(collection.contains("WHERE excluded.slot_updated > collection.slot_updated"));
+        assert!(collection.contains("BigInt(Some(42))"));
+        assert!(collection.contains("BigInt(Some(100))"));
+    }
+
+    #[tokio::test]
+    async fn unsized_collections_leave_the_size_alone() {
+        let log = save(&metadata(&[], None), vec![]).await;
+
+        assert!(statement(&log, r#"INSERT INTO \"collection\""#).is_none());
+    }
+}
@@ -0,0 +1,531 @@
+//! Recording of the messages read from the messenger streams, and their replay.  A recording
+//! holds one file per stream, `<dir>/<stream>.rec`, made of the raw plerkle flatbuffers in the
+//! order they were received, each prefixed with its receive time and its length.
+//!
+//! `RecordingMessenger` records the messages of the messenger it wraps while the ingester
+//! processes them, `FileMessenger` reads a recording back into the ingester without Redis.
+use crate::metrics::safe_metric;
+use async_trait::async_trait;
+use cadence_macros::statsd_count;
+use chrono::Utc;
+use plerkle_messenger::{Messenger, MessengerConfig, MessengerError, MessengerType, RecvData};
+use std::{
+    collections::HashMap,
+    io::{ErrorKind, SeekFrom},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+    time::{sleep, sleep_until, Instant},
+};
+
+pub const RECORD_DIR_KEY: &str = "record_dir";
+pub const REPLAY_DIR_KEY: &str = "replay_dir";
+pub const REPLAY_ORIGINAL_PACING_KEY: &str = "replay_original_pacing";
+
+const MAGIC: &[u8; 8] = b"DASREC01";
+// Receive time in milliseconds and length of the message.
+const RECORD_HEADER_SIZE: usize = 12;
+const REPLAY_BATCH_SIZE: usize = 100;
+// Bytes read from a recording at once.
+const REPLAY_READ_SIZE: usize = 64 * 1024;
+// Pause between two reads of a stream whose recording has been replayed entirely.
+const END_OF_RECORDING_WAIT: Duration = Duration::from_secs(1);
+
+fn recording_path(dir: &Path, stream_key: &str) -> PathBuf {
+    dir.join(format!("{}.rec", stream_key))
+}
+
+fn config_dir(config: &MessengerConfig, key: &str) -> Result<PathBuf, MessengerError> {
+    config
+        .connection_config
+        .get(key)
+        .and_then(|d| d.clone().into_string())
+        .map(PathBuf::from)
+        .ok_or(MessengerError::ConfigurationError {
+            msg: format!("Recording directory missing: {}", key),
+        })
+}
+
+fn encode_record(received_at: i64, data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(RECORD_HEADER_SIZE + data.len());
+    bytes.extend(received_at.to_le_bytes());
+    bytes.extend((data.len() as u32).to_le_bytes());
+    bytes.extend(data);
+    bytes
+}
+
+/// Length of the record at the start of `bytes`, header included, none while it isn't whole.
+fn record_len(bytes: &[u8]) -> Option<usize> {
+    let header = bytes.get(..RECORD_HEADER_SIZE)?;
+    let len = RECORD_HEADER_SIZE + u32::from_le_bytes(header[8..].try_into().unwrap()) as usize;
+    if bytes.len() >= len {
+        Some(len)
+    } else {
+        None
+    }
+}
+
+fn received_at(record: &[u8]) -> i64 {
+    i64::from_le_bytes(record[..8].try_into().unwrap())
+}
+
+/// Length of a recording up to the end of its last whole record, 0 if it doesn't even hold the
+/// magic.  A recorder killed while writing leaves the record it was writing cut short.
+async fn whole_records_len(file: &mut File) -> std::io::Result<u64> {
+    let len = file.metadata().await?.len();
+    if len < MAGIC.len() as u64 {
+        return Ok(0);
+    }
+    file.seek(SeekFrom::Start(0)).await?;
+    let mut magic = [0; MAGIC.len()];
+    file.read_exact(&mut magic).await?;
+    if &magic != MAGIC {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            "Not a recording".to_string(),
+        ));
+    }
+    let mut end = MAGIC.len() as u64;
+    let mut header = [0; RECORD_HEADER_SIZE];
+    while end + RECORD_HEADER_SIZE as u64 <= len {
+        file.read_exact(&mut header).await?;
+        let next = end
+            + RECORD_HEADER_SIZE as u64
+            + u32::from_le_bytes(header[8..].try_into().unwrap()) as u64;
+        if next > len {
+            break;
+        }
+        file.seek(SeekFrom::Start(next)).await?;
+        end = next;
+    }
+    Ok(end)
+}
+
+/// Appends messages to the recordings of a directory.
+struct Recorder {
+    dir: PathBuf,
+    files: HashMap<&'static str, File>,
+}
+
+impl Recorder {
+    fn new(dir: PathBuf) -> Self {
+        Recorder {
+            dir,
+            files: HashMap::new(),
+        }
+    }
+
+    async fn file(&mut self, stream_key: &'static str) -> std::io::Result<&mut File> {
+        if !self.files.contains_key(stream_key) {
+            fs::create_dir_all(&self.dir).await?;
+            let mut file = OpenOptions::new()
+                .create(true)
+                .read(true)
+                .write(true)
+                .open(recording_path(&self.dir, stream_key))
+                .await?;
+            // Messages are appended after the last whole record, over one cut short.
+            let end = whole_records_len(&mut file).await?;
+            file.set_len(end).await?;
+            file.seek(SeekFrom::Start(end)).await?;
+            if end == 0 {
+                file.write_all(MAGIC).await?;
+            }
+            self.files.insert(stream_key, file);
+        }
+        Ok(self.files.get_mut(stream_key).unwrap())
+    }
+
+    async fn record(&mut self, stream_key: &'static str, data: &[u8]) -> std::io::Result<()> {
+        let record = encode_record(Utc::now().timestamp_millis(), data);
+        self.file(stream_key).await?.write_all(&record).await
+    }
+}
+
+/// Records every message `T` receives to the directory under `record_dir` in the connection
+/// config.  Messages are handed to the ingester even when they can't be recorded.
+pub struct RecordingMessenger<T: Messenger> {
+    inner: T,
+    recorder: Recorder,
+}
+
+#[async_trait]
+impl<T: Messenger> Messenger for RecordingMessenger<T> {
+    async fn new(config: MessengerConfig) -> Result<Self, MessengerError> {
+        let dir = config_dir(&config, RECORD_DIR_KEY)?;
+        Ok(RecordingMessenger {
+            inner: T::new(config).await?,
+            recorder: Recorder::new(dir),
+        })
+    }
+
+    fn messenger_type(&self) -> MessengerType {
+        self.inner.messenger_type()
+    }
+
+    async fn add_stream(&mut self, stream_key: &'static str) -> Result<(), MessengerError> {
+        self.inner.add_stream(stream_key).await
+    }
+
+    async fn set_buffer_size(&mut self, stream_key: &'static str, max_buffer_size: usize) {
+        self.inner
+            .set_buffer_size(stream_key, max_buffer_size)
+            .await
+    }
+
+    async fn send(&mut self, stream_key: &'static str, bytes: &[u8]) -> Result<(), MessengerError> {
+        self.inner.send(stream_key, bytes).await
+    }
+
+    async fn recv(&mut self, stream_key: &'static str) -> Result<Vec<RecvData>, MessengerError> {
+        let data = self.inner.recv(stream_key).await?;
+        for item in data.iter() {
+            if let Err(err) = self.recorder.record(stream_key, &item.data).await {
+                println!("Error recording message {}: {}", item.id, err);
+                safe_metric(|| {
+                    statsd_count!("ingester.recording_error", 1, "stream" => stream_key);
+                });
+            }
+        }
+        Ok(data)
+    }
+
+    async fn stream_size(&mut self, stream_key: &'static str) -> Result<u64, MessengerError> {
+        self.inner.stream_size(stream_key).await
+    }
+
+    async fn ack_msg(
+        &mut self,
+        stream_key: &'static str,
+        ids: &[String],
+    ) -> Result<(), MessengerError> {
+        self.inner.ack_msg(stream_key, ids).await
+    }
+}
+
+/// Position of a `FileMessenger` in the recording of a stream.  Records are read into `buffer`
+/// and only taken out of it once they are handed over, so that a `recv` cancelled while reading
+/// or waiting for a record to be due loses nothing.
+struct Replay {
+    file: Option<File>,
+    // Bytes read from the recording that haven't been replayed yet.
+    buffer: Vec<u8>,
+    // When the first message was replayed, and when it had been received.
+    start: Option<(Instant, i64)>,
+    replayed: u64,
+    finished: bool,
+}
+
+impl Replay {
+    /// A stream without a recording replays as an empty one.
+    async fn open(dir: &Path, stream_key: &str) -> std::io::Result<Self> {
+        let file = match File::open(recording_path(dir, stream_key)).await {
+            Ok(mut file) => {
+                let mut magic = [0; MAGIC.len()];
+                file.read_exact(&mut magic).await?;
+                if &magic != MAGIC {
+                    return Err(std::io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Not a recording: {}", stream_key),
+                    ));
+                }
+                Some(file)
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+        Ok(Replay {
+            file,
+            buffer: Vec::new(),
+            start: None,
+            replayed: 0,
+            finished: false,
+        })
+    }
+
+    /// Reads until the buffer starts with a whole record, false at the end of the recording.  A
+    /// record cut short, by a recorder that was killed while writing it, ends the recording.
+    async fn fill(&mut self) -> std::io::Result<bool> {
+        while record_len(&self.buffer).is_none() {
+            let file = match self.file.as_mut() {
+                Some(file) => file,
+                None => return Ok(false),
+            };
+            self.buffer.reserve(REPLAY_READ_SIZE);
+            if file.read_buf(&mut self.buffer).await? == 0 {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// When a record received at `received_at` is replayed to keep the intervals it was
+    /// received at.
+    fn due(&mut self, received_at: i64) -> Instant {
+        let (started, first_received_at) = *self.start.get_or_insert((Instant::now(), received_at));
+        started + Duration::from_millis((received_at - first_received_at).max(0) as u64)
+    }
+
+    async fn next_batch(&mut self, original_pacing: bool) -> std::io::Result<Vec<RecvData>> {
+        let mut batch = Vec::new();
+        if self.fill().await? {
+            if original_pacing {
+                let due = self.due(received_at(&self.buffer));
+                sleep_until(due).await;
+            }
+            // Nothing is awaited while the batch is taken out of the buffer.
+            let mut taken = 0;
+            while batch.len() < REPLAY_BATCH_SIZE {
+                let record = &self.buffer[taken..];
+                let (len, received_at) = match record_len(record) {
+                    Some(len) => (len, received_at(record)),
+                    None => break,
+                };
+                if original_pacing && self.due(received_at) > Instant::now() {
+                    break;
+                }
+                self.replayed += 1;
+                batch.push(RecvData {
+                    id: self.replayed.to_string(),
+                    tries: 0,
+                    data: self.buffer[taken + RECORD_HEADER_SIZE..taken + len].to_vec(),
+                });
+                taken += len;
+            }
+            self.buffer.drain(..taken);
+        }
+        if batch.is_empty() {
+            self.finished = true;
+            sleep(END_OF_RECORDING_WAIT).await;
+        }
+        Ok(batch)
+    }
+}
+
+/// Replays the recording in the directory under `replay_dir` in the connection config, as
+/// fast as the ingester processes it or, with `replay_original_pacing`, at the pace it was
+/// recorded at.  Messages are delivered once whether they are acknowledged or not, and
+/// messages sent are appended to the recording.
+pub struct FileMessenger {
+    dir: PathBuf,
+    original_pacing: bool,
+    recorder: Recorder,
+    replays: HashMap<&'static str, Replay>,
+}
+
+#[async_trait]
+impl Messenger for FileMessenger {
+    async fn new(config: MessengerConfig) -> Result<Self, MessengerError> {
+        let dir = config_dir(&config, REPLAY_DIR_KEY)?;
+        let original_pacing = config
+            .connection_config
+            .get(REPLAY_ORIGINAL_PACING_KEY)
+            .and_then(|p| p.to_bool())
+            .unwrap_or(false);
+        Ok(FileMessenger {
+            dir: dir.clone(),
+            original_pacing,
+            recorder: Recorder::new(dir),
+            replays: HashMap::new(),
+        })
+    }
+
+    fn messenger_type(&self) -> MessengerType {
+        MessengerType::Invalid
+    }
+
+    async fn add_stream(&mut self, _stream_key: &'static str) -> Result<(), MessengerError> {
+        Ok(())
+    }
+
+    async fn set_buffer_size(&mut self, _stream_key: &'static str, _max_buffer_size: usize) {}
+
+    async fn send(&mut self, stream_key: &'static str, bytes: &[u8]) -> Result<(), MessengerError> {
+        self.recorder
+            .record(stream_key, bytes)
+            .await
+            .map_err(|err| MessengerError::SendError {
+                msg: err.to_string(),
+            })
+    }
+
+    async fn recv(&mut self, stream_key: &'static str) -> Result<Vec<RecvData>, MessengerError> {
+        if !self.replays.contains_key(stream_key) {
+            let replay = Replay::open(&self.dir, stream_key).await.map_err(|err| {
+                MessengerError::ReceiveError {
+                    msg: err.to_string(),
+                }
+            })?;
+            self.replays.insert(stream_key, replay);
+        }
+        let replay = self.replays.get_mut(stream_key).unwrap();
+        let was_finished = replay.finished;
+        let batch = replay
+            .next_batch(self.original_pacing)
+            .await
+            .map_err(|err| MessengerError::ReceiveError {
+                msg: err.to_string(),
+            })?;
+        if replay.finished && !was_finished {
+            println!("Replayed {} messages of {}", replay.replayed, stream_key);
+        }
+        Ok(batch)
+    }
+
+    async fn stream_size(&mut self, _stream_key: &'static str) -> Result<u64, MessengerError> {
+        Ok(0)
+    }
+
+    async fn ack_msg(
+        &mut self,
+        _stream_key: &'static str,
+        _ids: &[String],
+    ) -> Result<(), MessengerError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand_string;
+    use figment::value::{Dict, Value};
+    use plerkle_messenger::{ACCOUNT_STREAM, TRANSACTION_STREAM};
+
+    fn replay_config(dir: &Path, original_pacing: bool) -> MessengerConfig {
+        let mut connection_config = Dict::new();
+        connection_config.insert(
+            REPLAY_DIR_KEY.to_string(),
+            Value::from(dir.to_str().unwrap()),
+        );
+        connection_config.insert(
+            REPLAY_ORIGINAL_PACING_KEY.to_string(),
+            Value::from(original_pacing),
+        );
+        MessengerConfig {
+            messenger_type: MessengerType::Invalid,
+            connection_config,
+        }
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("das-recording-{}", rand_string()))
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_messages_in_order() {
+        let dir = temp_dir();
+        let mut recorder = FileMessenger::new(replay_config(&dir, false))
+            .await
+            .unwrap();
+        for data in [b"first".to_vec(), b"second".to_vec(), vec![]] {
+            recorder.send(TRANSACTION_STREAM, &data).await.unwrap();
+        }
+
+        let mut replay = FileMessenger::new(replay_config(&dir, false))
+            .await
+            .unwrap();
+        let batch = replay.recv(TRANSACTION_STREAM).await.unwrap();
+        let data: Vec<&[u8]> = batch.iter().map(|m| m.data.as_slice()).collect();
+        assert_eq!(data, vec![&b"first"[..], &b"second"[..], &b""[..]]);
+        assert_eq!(batch[0].id, "1");
+        assert!(replay.recv(TRANSACTION_STREAM).await.unwrap().is_empty());
+        // Streams that weren't recorded are empty.
+        assert!(replay.recv(ACCOUNT_STREAM).await.unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn replays_at_the_original_pace() {
+        let dir = temp_dir();
+        fs::create_dir_all(&dir).await.unwrap();
+        let mut recording = MAGIC.to_vec();
+        recording.extend(encode_record(1_000, b"first"));
+        recording.extend(encode_record(1_300, b"second"));
+        // An unfinished record ends the recording.
+        recording.extend(&encode_record(1_400, b"third")[..RECORD_HEADER_SIZE + 2]);
+        fs::write(recording_path(&dir, ACCOUNT_STREAM), recording)
+            .await
+            .unwrap();
+
+        let mut replay = FileMessenger::new(replay_config(&dir, true)).await.unwrap();
+        let started = Instant::now();
+        assert_eq!(replay.recv(ACCOUNT_STREAM).await.unwrap().len(), 1);
+        assert_eq!(replay.recv(ACCOUNT_STREAM).await.unwrap().len(), 1);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(replay.recv(ACCOUNT_STREAM).await.unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn cancelled_receives_lose_no_messages() {
+        let dir = temp_dir();
+        fs::create_dir_all(&dir).await.unwrap();
+        let mut recording = MAGIC.to_vec();
+        recording.extend(encode_record(1_000, b"first"));
+        recording.extend(encode_record(1_300, b"second"));
+        fs::write(recording_path(&dir, ACCOUNT_STREAM), recording)
+            .await
+            .unwrap();
+
+        let mut replay = FileMessenger::new(replay_config(&dir, true)).await.unwrap();
+        assert_eq!(replay.recv(ACCOUNT_STREAM).await.unwrap().len(), 1);
+        // Cancelled while waiting for the second message to be due.
+        let cancelled =
+            tokio::time::timeout(Duration::from_millis(50), replay.recv(ACCOUNT_STREAM)).await;
+        assert!(cancelled.is_err());
+        let batch = replay.recv(ACCOUNT_STREAM).await.unwrap();
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].data, b"second");
+        assert_eq!(batch[0].id, "2");
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn appends_after_the_last_whole_record() {
+        let dir = temp_dir();
+        fs::create_dir_all(&dir).await.unwrap();
+        let mut recording = MAGIC.to_vec();
+        recording.extend(encode_record(1_000, b"first"));
+        recording.extend(&encode_record(1_100, b"cut short")[..RECORD_HEADER_SIZE + 3]);
+        fs::write(recording_path(&dir, TRANSACTION_STREAM), recording)
+            .await
+            .unwrap();
+
+        let mut recorder = FileMessenger::new(replay_config(&dir, false))
+            .await
+            .unwrap();
+        recorder.send(TRANSACTION_STREAM, b"second").await.unwrap();
+
+        let mut replay = FileMessenger::new(replay_config(&dir, false))
+            .await
+            .unwrap();
+        let batch = replay.recv(TRANSACTION_STREAM).await.unwrap();
+        let data: Vec<&[u8]> = batch.iter().map(|m| m.data.as_slice()).collect();
+        assert_eq!(data, vec![&b"first"[..], &b"second"[..]]);
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_files_that_are_not_recordings() {
+        let dir = temp_dir();
+        fs::create_dir_all(&dir).await.unwrap();
+        fs::write(recording_path(&dir, TRANSACTION_STREAM), b"not a recording")
+            .await
+            .unwrap();
+
+        let mut replay = FileMessenger::new(replay_config(&dir, false))
+            .await
+            .unwrap();
+        assert!(replay.recv(TRANSACTION_STREAM).await.is_err());
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
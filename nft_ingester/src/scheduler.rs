@@ -0,0 +1,153 @@
+//! Ordering of the messages of a batch.  Messages updating the same state, a tree for Bubblegum
+//! instructions or an asset for account updates, form a partition and are processed one after
+//! the other in the order they were received.  Partitions are processed in parallel.
+use futures::{stream, Future, StreamExt};
+use std::collections::HashMap;
+
+pub const DEFAULT_PARALLELISM: usize = 32;
+
+/// Groups the jobs whose keys overlap, each group listing its jobs in submission order.  A job
+/// without keys is a group of its own.
+pub fn partition(keys: &[Vec<Vec<u8>>]) -> Vec<Vec<usize>> {
+    // Union-find over the groups, a job touching several groups merges them.
+    fn find(parents: &mut [usize], mut group: usize) -> usize {
+        while parents[group] != group {
+            parents[group] = parents[parents[group]];
+            group = parents[group];
+        }
+        group
+    }
+
+    let mut parents: Vec<usize> = Vec::with_capacity(keys.len());
+    let mut owners: HashMap<&[u8], usize> = HashMap::new();
+    let mut job_groups = Vec::with_capacity(keys.len());
+    for job_keys in keys {
+        let group = parents.len();
+        parents.push(group);
+        for key in job_keys {
+            match owners.get(key.as_slice()) {
+                Some(&owner) => {
+                    let owner = find(&mut parents, owner);
+                    let root = find(&mut parents, group);
+                    parents[owner.max(root)] = owner.min(root);
+                }
+                None => {
+                    owners.insert(key.as_slice(), group);
+                }
+            }
+        }
+        job_groups.push(group);
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_index: HashMap<usize, usize> = HashMap::new();
+    for (job, group) in job_groups.into_iter().enumerate() {
+        let root = find(&mut parents, group);
+        let index = *group_index.entry(root).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[index].push(job);
+    }
+    groups
+}
+
+/// Runs `jobs` keyed by the state they update, at most `parallelism` partitions at once.  The
+/// outputs come back in no particular order.
+pub async fn run_partitioned<F>(jobs: Vec<(Vec<Vec<u8>>, F)>, parallelism: usize) -> Vec<F::Output>
+where
+    F: Future,
+{
+    let (keys, jobs): (Vec<_>, Vec<_>) = jobs.into_iter().unzip();
+    let mut jobs: Vec<Option<F>> = jobs.into_iter().map(Some).collect();
+    let partitions: Vec<Vec<F>> = partition(&keys)
+        .into_iter()
+        .map(|p| p.into_iter().filter_map(|job| jobs[job].take()).collect())
+        .collect();
+    stream::iter(partitions)
+        .map(|partition| async move {
+            let mut outputs = Vec::with_capacity(partition.len());
+            for job in partition {
+                outputs.push(job.await);
+            }
+            outputs
+        })
+        .buffer_unordered(parallelism.max(1))
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+    use tokio::time::{sleep, Duration};
+
+    fn keys(keys: &[&[&str]]) -> Vec<Vec<Vec<u8>>> {
+        keys.iter()
+            .map(|k| k.iter().map(|k| k.as_bytes().to_vec()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn partitions_overlapping_keys() {
+        assert_eq!(
+            partition(&keys(&[&["a"], &["b"], &["a"], &[], &["c"], &["b"]])),
+            vec![vec![0, 2], vec![1, 5], vec![3], vec![4]]
+        );
+        // The fourth job joins the partitions of the first and the third.
+        assert_eq!(
+            partition(&keys(&[&["a"], &["b"], &["c"], &["c", "a"], &["b"]])),
+            vec![vec![0, 2, 3], vec![1, 4]]
+        );
+        assert!(partition(&[]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn runs_partitions_in_order_and_in_parallel() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let jobs = ["a", "b", "c", "a", "b", "a"]
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let log = log.clone();
+                let running = running.clone();
+                let max_running = max_running.clone();
+                let job = async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(now, Ordering::SeqCst);
+                    // Later jobs finish sooner, so only the scheduler keeps them in order.
+                    sleep(Duration::from_millis(30 - 5 * i as u64)).await;
+                    log.lock().unwrap().push((*key, i));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    i
+                };
+                (vec![key.as_bytes().to_vec()], job)
+            })
+            .collect();
+
+        let mut outputs = run_partitioned(jobs, 2).await;
+        outputs.sort_unstable();
+        assert_eq!(outputs, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+        let log = log.lock().unwrap();
+        for key in ["a", "b", "c"] {
+            let order: Vec<usize> = log
+                .iter()
+                .filter(|(k, _)| *k == key)
+                .map(|(_, i)| *i)
+                .collect();
+            let mut sorted = order.clone();
+            sorted.sort_unstable();
+            assert_eq!(order, sorted);
+        }
+    }
+}
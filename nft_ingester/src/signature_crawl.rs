@@ -0,0 +1,370 @@
+//! Crawl of the signatures of a tree, newest first, for the backfiller.  The crawl of a tree with
+//! millions of signatures takes hours, so it is checkpointed after every page: the last signature
+//! seen in `signature_crawls` and the slots found so far in `signature_crawl_slots`.  The slots of
+//! a page are plugged before its checkpoint, and a crawl that failed or was interrupted resumes
+//! after the last checkpoint instead of starting over.
+use crate::error::IngesterError;
+use chrono::{Duration, NaiveDateTime, Utc};
+use digital_asset_types::dao::{signature_crawl_slots, signature_crawls};
+use sea_orm::{
+    entity::*,
+    query::*,
+    sea_query::{Expr, OnConflict},
+    ConnectionTrait, DatabaseConnection, DbBackend, DbErr, Statement, TransactionTrait,
+};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signature, slot_history::Slot};
+use std::{
+    collections::{BTreeSet, HashSet},
+    str::FromStr,
+};
+
+// Signatures returned by a `getSignaturesForAddress` call, fewer means the crawl is over.
+const PAGE_SIZE: usize = 1000;
+// Seconds after which a crawl that hasn't been touched, which it is at every checkpoint and
+// every slot plugged, is taken for abandoned by a backfiller that was restarted.
+const STALE_CRAWL_SECS: i64 = 3600;
+
+/// A page of signatures of the tree.
+pub struct CrawlPage {
+    /// Slots of the page the crawl hadn't found yet, oldest first.
+    pub new_slots: Vec<Slot>,
+    last_signature: Signature,
+    last_page: bool,
+}
+
+pub struct SignatureCrawl {
+    tree: Pubkey,
+    before: Option<Signature>,
+    done: bool,
+}
+
+impl SignatureCrawl {
+    /// Resumes the crawl of `tree` after its last checkpoint, or starts it from the newest
+    /// signature.
+    pub async fn resume(db: &impl ConnectionTrait, tree: Pubkey) -> Result<Self, IngesterError> {
+        let key = tree.to_bytes().to_vec();
+        // Touching the crawl keeps it from being taken for an abandoned one.
+        let query = signature_crawls::Entity::insert(signature_crawls::ActiveModel {
+            tree: Set(key.clone()),
+            last_signature: Set(None),
+            updated_at: Set(Utc::now().naive_utc()),
+        })
+        .on_conflict(
+            OnConflict::columns([signature_crawls::Column::Tree])
+                .update_columns([signature_crawls::Column::UpdatedAt])
+                .to_owned(),
+        )
+        .build(DbBackend::Postgres);
+        db.execute(query).await?;
+
+        let before = signature_crawls::Entity::find_by_id(key)
+            .one(db)
+            .await?
+            .and_then(|crawl| crawl.last_signature)
+            .map(|sig| parse_signature(&sig))
+            .transpose()?;
+        Ok(Self {
+            tree,
+            before,
+            done: false,
+        })
+    }
+
+    /// The next page of signatures, none once the oldest signature has been seen.
+    pub async fn next_page(
+        &self,
+        rpc_client: &RpcClient,
+        db: &impl ConnectionTrait,
+    ) -> Result<Option<CrawlPage>, IngesterError> {
+        if self.done {
+            return Ok(None);
+        }
+        let sigs = rpc_client
+            .get_signatures_for_address_with_config(
+                &self.tree,
+                GetConfirmedSignaturesForAddress2Config {
+                    before: self.before,
+                    until: None,
+                    ..GetConfirmedSignaturesForAddress2Config::default()
+                },
+            )
+            .await
+            .map_err(|e| {
+                IngesterError::RpcGetDataError(format!(
+                    "GetSignaturesForAddressWithConfig failed {}",
+                    e
+                ))
+            })?;
+        let last_signature = match sigs.last() {
+            Some(sig) => parse_signature(&sig.signature)?,
+            None => return Ok(None),
+        };
+
+        let slots: BTreeSet<Slot> = sigs.iter().map(|sig| sig.slot).collect();
+        let found: HashSet<Slot> = signature_crawl_slots::Entity::find()
+            .filter(signature_crawl_slots::Column::Tree.eq(self.tree.to_bytes().to_vec()))
+            .filter(signature_crawl_slots::Column::Slot.is_in(slots.iter().map(|s| *s as i64)))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|s| s.slot as Slot)
+            .collect();
+        Ok(Some(CrawlPage {
+            new_slots: slots.into_iter().filter(|s| !found.contains(s)).collect(),
+            last_signature,
+            last_page: sigs.len() < PAGE_SIZE,
+        }))
+    }
+
+    /// Checkpoints the crawl after `page`, whose slots have been plugged.
+    pub async fn checkpoint(
+        &mut self,
+        db: &DatabaseConnection,
+        page: CrawlPage,
+    ) -> Result<(), IngesterError> {
+        let key = self.tree.to_bytes().to_vec();
+        let txn = db.begin().await?;
+        let query = signature_crawls::Entity::update_many()
+            .col_expr(
+                signature_crawls::Column::LastSignature,
+                Expr::value(Some(page.last_signature.to_string())),
+            )
+            .col_expr(
+                signature_crawls::Column::UpdatedAt,
+                Expr::value(Utc::now().naive_utc()),
+            )
+            .filter(signature_crawls::Column::Tree.eq(key.clone()))
+            .build(DbBackend::Postgres);
+        txn.execute(query).await?;
+        if !page.new_slots.is_empty() {
+            let slots = page
+                .new_slots
+                .iter()
+                .map(|slot| signature_crawl_slots::ActiveModel {
+                    tree: Set(key.clone()),
+                    slot: Set(*slot as i64),
+                });
+            let query = signature_crawl_slots::Entity::insert_many(slots)
+                .on_conflict(
+                    OnConflict::columns([
+                        signature_crawl_slots::Column::Tree,
+                        signature_crawl_slots::Column::Slot,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                )
+                .build(DbBackend::Postgres);
+            txn.execute(query).await?;
+        }
+        txn.commit().await?;
+
+        self.before = Some(page.last_signature);
+        self.done = page.last_page;
+        Ok(())
+    }
+
+    /// Keeps the crawl from being taken for an abandoned one while the slots of a page are
+    /// plugged.
+    pub async fn touch(&self, db: &impl ConnectionTrait) -> Result<(), IngesterError> {
+        signature_crawls::Entity::update_many()
+            .col_expr(
+                signature_crawls::Column::UpdatedAt,
+                Expr::value(Utc::now().naive_utc()),
+            )
+            .filter(signature_crawls::Column::Tree.eq(self.tree.to_bytes().to_vec()))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Forgets the crawl once all of its slots are plugged, the next backfill of the tree starts
+    /// over from the newest signature.
+    pub async fn finish(self, db: &impl ConnectionTrait) -> Result<(), IngesterError> {
+        let key = self.tree.to_bytes().to_vec();
+        signature_crawl_slots::Entity::delete_many()
+            .filter(signature_crawl_slots::Column::Tree.eq(key.clone()))
+            .exec(db)
+            .await?;
+        signature_crawls::Entity::delete_by_id(key).exec(db).await?;
+        Ok(())
+    }
+}
+
+/// Unlocks the trees of crawls that haven't been touched for `STALE_CRAWL_SECS` at `now`, so
+/// that they are resumed by the next backfiller picking them.  Returns the rows unlocked.
+pub async fn unlock_abandoned_crawls(
+    db: &impl ConnectionTrait,
+    now: NaiveDateTime,
+) -> Result<u64, DbErr> {
+    let stale = now - Duration::seconds(STALE_CRAWL_SECS);
+    let res = db
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "UPDATE backfill_items SET locked = FALSE\n\
+            WHERE backfill_items.locked = TRUE\n\
+            AND backfill_items.failed = FALSE\n\
+            AND backfill_items.tree IN (SELECT tree FROM signature_crawls WHERE updated_at < $1)",
+            vec![stale.into()],
+        ))
+        .await?;
+    Ok(res.rows_affected())
+}
+
+fn parse_signature(sig: &str) -> Result<Signature, IngesterError> {
+    Signature::from_str(sig).map_err(|e| {
+        IngesterError::RpcDataUnsupportedFormat(format!("Failed to parse signature {}", e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_rpc::mock_rpc;
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult};
+    use serde_json::{json, Value};
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    };
+
+    /// An RPC node answering `getSignaturesForAddress` with `pages`, one per call, that records
+    /// the `before` of every call.
+    fn signatures_rpc(pages: Vec<Vec<(Signature, Slot)>>) -> (RpcClient, Arc<Mutex<Vec<Value>>>) {
+        let pages = Mutex::new(VecDeque::from(pages));
+        let befores = Arc::new(Mutex::new(Vec::new()));
+        let befores_cloned = befores.clone();
+        let url = mock_rpc(move |method, params| {
+            assert_eq!(method, "getSignaturesForAddress");
+            befores_cloned
+                .lock()
+                .unwrap()
+                .push(params[1]["before"].clone());
+            let page = pages.lock().unwrap().pop_front().unwrap_or_default();
+            page.into_iter()
+                .map(|(sig, slot)| {
+                    json!({
+                        "signature": sig.to_string(),
+                        "slot": slot,
+                        "err": null,
+                        "memo": null,
+                        "blockTime": null,
+                        "confirmationStatus": "finalized",
+                    })
+                })
+                .collect()
+        });
+        (RpcClient::new(url), befores)
+    }
+
+    fn signatures(slots: impl Iterator<Item = Slot>) -> Vec<(Signature, Slot)> {
+        slots.map(|slot| (Signature::new_unique(), slot)).collect()
+    }
+
+    fn checkpoint(tree: Pubkey, last_signature: Option<Signature>) -> signature_crawls::Model {
+        signature_crawls::Model {
+            tree: tree.to_bytes().to_vec(),
+            last_signature: last_signature.map(|sig| sig.to_string()),
+            updated_at: Utc::now().naive_utc(),
+        }
+    }
+
+    fn exec_results(n: usize) -> Vec<MockExecResult> {
+        (0..n)
+            .map(|_| MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn resumes_after_the_last_checkpoint() {
+        let tree = Pubkey::new_unique();
+        let checkpointed = Signature::new_unique();
+        // A full page, ten signatures a slot, and the last page.
+        let full_page = signatures((0..PAGE_SIZE as u64).map(|i| 500 - i / 10));
+        let last_page = signatures([401, 300].into_iter());
+        let last_of_full_page = full_page.last().unwrap().0;
+        let (rpc_client, befores) = signatures_rpc(vec![full_page, last_page]);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![checkpoint(tree, Some(checkpointed))]])
+            .append_query_results(vec![Vec::<signature_crawl_slots::Model>::new()])
+            .append_query_results(vec![vec![signature_crawl_slots::Model {
+                tree: tree.to_bytes().to_vec(),
+                slot: 401,
+            }]])
+            .append_exec_results(exec_results(7))
+            .into_connection();
+
+        let mut crawl = SignatureCrawl::resume(&db, tree).await.unwrap();
+        let mut plugged = Vec::new();
+        while let Some(page) = crawl.next_page(&rpc_client, &db).await.unwrap() {
+            plugged.push(page.new_slots.clone());
+            crawl.checkpoint(&db, page).await.unwrap();
+        }
+        crawl.finish(&db).await.unwrap();
+
+        assert_eq!(
+            *befores.lock().unwrap(),
+            vec![
+                json!(checkpointed.to_string()),
+                json!(last_of_full_page.to_string())
+            ]
+        );
+        assert_eq!(plugged[0], (401..=500).collect::<Vec<_>>());
+        // Slot 401 was already found on the previous page.
+        assert_eq!(plugged[1], vec![300]);
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(log.contains(&last_of_full_page.to_string()));
+        assert!(log.contains(r#"DELETE FROM \"signature_crawls\""#));
+    }
+
+    #[tokio::test]
+    async fn starts_from_the_newest_signature_without_a_checkpoint() {
+        let tree = Pubkey::new_unique();
+        let (rpc_client, befores) = signatures_rpc(vec![Vec::new()]);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![checkpoint(tree, None)]])
+            .append_exec_results(exec_results(1))
+            .into_connection();
+
+        let crawl = SignatureCrawl::resume(&db, tree).await.unwrap();
+        assert!(crawl.next_page(&rpc_client, &db).await.unwrap().is_none());
+        assert_eq!(*befores.lock().unwrap(), vec![Value::Null]);
+    }
+
+    #[tokio::test]
+    async fn touching_a_crawl_moves_its_update_time() {
+        let tree = Pubkey::new_unique();
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![checkpoint(tree, None)]])
+            .append_exec_results(exec_results(2))
+            .into_connection();
+
+        let crawl = SignatureCrawl::resume(&db, tree).await.unwrap();
+        crawl.touch(&db).await.unwrap();
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(log.contains(r#"UPDATE \"signature_crawls\" SET \"updated_at\""#));
+    }
+
+    #[tokio::test]
+    async fn unlocks_the_trees_of_crawls_untouched_for_an_hour() {
+        let now = NaiveDateTime::from_timestamp(1_675_000_000, 0);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_exec_results(vec![MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 2,
+            }])
+            .into_connection();
+
+        assert_eq!(unlock_abandoned_crawls(&db, now).await.unwrap(), 2);
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(log.contains("UPDATE backfill_items SET locked = FALSE"));
+        assert!(log.contains("WHERE backfill_items.locked = TRUE"));
+        assert!(log.contains("SELECT tree FROM signature_crawls WHERE updated_at < $1"));
+        assert!(log.contains(&format!("{:?}", now - Duration::seconds(3600))));
+    }
+}
@@ -0,0 +1,556 @@
+//! Forks.  The plugin streams accounts and transactions as soon as their slot is processed,
+//! before it is known whether the slot ends up on the rooted chain.  Slot notifications are
+//! recorded in `slots`, and while a slot isn't rooted every write made for it to the asset
+//! tables is recorded in `unconfirmed_writes` by the `record_unconfirmed_write` trigger, along
+//! with what the row was before.  Once a slot is rooted, the slots since the previous root that
+//! aren't on its chain were abandoned and their writes are taken back, unless a surviving slot
+//! wrote the row again since, in which case the row is rebuilt from the surviving writes.
+use crate::{error::IngesterError, metrics::safe_metric};
+use cadence_macros::statsd_count;
+use chrono::Utc;
+use digital_asset_types::dao::{sea_orm_active_enums::SlotStatus, slots, unconfirmed_writes};
+use plerkle_serialization::{SlotStatusInfo, Status};
+use sea_orm::{
+    entity::*,
+    query::*,
+    sea_query::{Expr, OnConflict},
+    ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend, EntityTrait, JsonValue,
+    Statement, TransactionTrait,
+};
+use std::collections::{HashMap, HashSet};
+
+// Tables with the `record_unconfirmed_write` trigger, with their primary key.
+const TRACKED_TABLES: [(&str, &str); 8] = [
+    ("asset", "id"),
+    ("asset_data", "id"),
+    ("asset_authority", "id"),
+    ("asset_creators", "id"),
+    ("asset_grouping", "id"),
+    ("collection", "id"),
+    ("tokens", "mint"),
+    ("token_accounts", "pubkey"),
+];
+
+pub fn slot_status(info: &SlotStatusInfo) -> SlotStatus {
+    match info.status() {
+        Status::Rooted => SlotStatus::Rooted,
+        Status::Confirmed => SlotStatus::Confirmed,
+        _ => SlotStatus::Processed,
+    }
+}
+
+/// Sets the slot the writes of the transaction are made for, in place of the `slot_updated` of
+/// the rows written.  Needed by writes that leave `slot_updated` alone.
+pub async fn set_write_slot(
+    db: &impl ConnectionTrait,
+    slot: Option<u64>,
+) -> Result<(), IngesterError> {
+    let slot = slot.map(|s| s.to_string()).unwrap_or_default();
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT set_config('das.write_slot', $1, true)",
+        vec![slot.into()],
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Records a slot notification.  A slot only moves forward, from processed to confirmed to
+/// rooted, and its parent is kept from the first notification that has it.  Returns the slots
+/// abandoned when a slot is rooted, whose writes have been taken back.
+pub async fn save_slot_status(
+    db: &DatabaseConnection,
+    slot: u64,
+    parent: Option<u64>,
+    status: SlotStatus,
+) -> Result<Vec<i64>, IngesterError> {
+    let rooted = status == SlotStatus::Rooted;
+    let model = slots::ActiveModel {
+        slot: Set(slot as i64),
+        parent: Set(parent.map(|p| p as i64)),
+        status: Set(status),
+        updated_at: Set(Utc::now().naive_utc()),
+    };
+    let mut query = slots::Entity::insert(model)
+        .on_conflict(
+            OnConflict::column(slots::Column::Slot)
+                .update_column(slots::Column::UpdatedAt)
+                .to_owned(),
+        )
+        .build(DbBackend::Postgres);
+    // The statuses are declared in the order slots go through them.
+    query.sql = format!(
+        "{}, status = GREATEST(excluded.status, slots.status), \
+         parent = COALESCE(slots.parent, excluded.parent)",
+        query.sql
+    );
+
+    let txn = db.begin().await?;
+    txn.execute(query).await?;
+    let abandoned = if rooted {
+        let abandoned = root(slot as i64, &txn).await?;
+        // Read by the trigger for every row written, in place of the newest rooted slot.
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "INSERT INTO last_rooted_slot (id, slot) VALUES (true, $1) ON CONFLICT (id) \
+             DO UPDATE SET slot = GREATEST(last_rooted_slot.slot, excluded.slot)",
+            vec![(slot as i64).into()],
+        ))
+        .await?;
+        abandoned
+    } else {
+        Vec::new()
+    };
+    txn.commit().await?;
+    Ok(abandoned)
+}
+
+/// The chain of `root` back to the previous root, none if a parent along the way is unknown.
+fn root_chain(
+    parents: &HashMap<i64, Option<i64>>,
+    root: i64,
+    previous_root: i64,
+) -> Option<HashSet<i64>> {
+    let mut chain = HashSet::new();
+    let mut slot = root;
+    while slot > previous_root {
+        chain.insert(slot);
+        slot = parents.get(&slot).copied().flatten()?;
+    }
+    Some(chain)
+}
+
+/// Takes back the writes of the slots `root` abandoned and forgets the writes up to it, which
+/// are final.
+async fn root(root: i64, txn: &DatabaseTransaction) -> Result<Vec<i64>, IngesterError> {
+    let previous_root = slots::Entity::find()
+        .filter(slots::Column::Status.eq(SlotStatus::Rooted))
+        .filter(slots::Column::Slot.lt(root))
+        .order_by_desc(slots::Column::Slot)
+        .one(txn)
+        .await?
+        .map(|s| s.slot);
+    // Nothing is recorded before the first root.
+    let previous_root = match previous_root {
+        Some(previous_root) => previous_root,
+        None => return Ok(Vec::new()),
+    };
+    let since_previous_root = slots::Entity::find()
+        .filter(slots::Column::Slot.gt(previous_root))
+        .filter(slots::Column::Slot.lte(root))
+        .all(txn)
+        .await?;
+    let parents: HashMap<i64, Option<i64>> = since_previous_root
+        .iter()
+        .map(|s| (s.slot, s.parent))
+        .collect();
+
+    let mut abandoned = Vec::new();
+    match root_chain(&parents, root, previous_root) {
+        Some(chain) => {
+            let writes = unconfirmed_writes::Entity::find()
+                .filter(unconfirmed_writes::Column::Slot.gt(previous_root))
+                .filter(unconfirmed_writes::Column::Slot.lte(root))
+                .filter(unconfirmed_writes::Column::Slot.is_not_in(chain.clone()))
+                .order_by_desc(unconfirmed_writes::Column::Id)
+                .all(txn)
+                .await?;
+            abandoned = parents
+                .keys()
+                .chain(writes.iter().map(|w| &w.slot))
+                .filter(|s| !chain.contains(s))
+                .copied()
+                .collect::<HashSet<i64>>()
+                .into_iter()
+                .collect();
+            abandoned.sort_unstable();
+            roll_back(writes, txn).await?;
+            slots::Entity::update_many()
+                .set(slots::ActiveModel {
+                    status: Set(SlotStatus::Dead),
+                    ..Default::default()
+                })
+                .filter(slots::Column::Slot.is_in(abandoned.clone()))
+                .exec(txn)
+                .await?;
+        }
+        None => {
+            println!(
+                "Slot {} rooted with an unknown chain, the writes since slot {} are kept",
+                root, previous_root
+            );
+            safe_metric(|| {
+                statsd_count!("ingester.slot_chain_unknown", 1);
+            });
+        }
+    }
+    unconfirmed_writes::Entity::delete_many()
+        .filter(unconfirmed_writes::Column::Slot.lte(root))
+        .exec(txn)
+        .await?;
+    Ok(abandoned)
+}
+
+/// The statement writing a row of a tracked table back as `image`, deleting it if none.  None
+/// for a table that isn't tracked.
+fn restore_statement(
+    table_name: &str,
+    row_key: &JsonValue,
+    image: Option<&JsonValue>,
+) -> Option<Statement> {
+    let (table, key) = TRACKED_TABLES
+        .iter()
+        .find(|(table, _)| *table == table_name)?;
+    Some(match image {
+        None => Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            &format!(
+                "DELETE FROM {0} WHERE {1} = \
+                 (jsonb_populate_record(NULL::{0}, jsonb_build_object('{1}', $1::jsonb))).{1}",
+                table, key
+            ),
+            vec![row_key.clone().into()],
+        ),
+        Some(image) => {
+            let columns: Vec<String> = image
+                .as_object()?
+                .keys()
+                .filter(|c| c.as_str() != *key)
+                .map(|c| format!("\"{}\"", c))
+                .collect();
+            let excluded: Vec<String> = columns.iter().map(|c| format!("excluded.{}", c)).collect();
+            Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                &format!(
+                    "INSERT INTO {0} SELECT * FROM jsonb_populate_record(NULL::{0}, $1) \
+                     ON CONFLICT ({1}) DO UPDATE SET ({2}) = ROW({3})",
+                    table,
+                    key,
+                    columns.join(", "),
+                    excluded.join(", ")
+                ),
+                vec![image.clone().into()],
+            )
+        }
+    })
+}
+
+/// The statement taking back a write: the row is deleted if the write inserted it, else it is
+/// written back as it was.  None for a table that isn't tracked.
+fn undo_statement(write: &unconfirmed_writes::Model) -> Option<Statement> {
+    restore_statement(&write.table_name, &write.row_key, write.previous.as_ref())
+}
+
+/// `row` with the columns `write` changed, none if it deleted the row.
+fn replay(row: Option<JsonValue>, write: &unconfirmed_writes::Model) -> Option<JsonValue> {
+    let written = write.written.as_ref()?;
+    match (row, write.previous.as_ref().and_then(|p| p.as_object())) {
+        (Some(JsonValue::Object(mut row)), Some(previous)) => {
+            for (column, value) in written.as_object()? {
+                if previous.get(column) != Some(value) {
+                    row.insert(column.clone(), value.clone());
+                }
+            }
+            Some(JsonValue::Object(row))
+        }
+        // The write inserted the row, or wrote over one the abandoned writes left.
+        _ => Some(written.clone()),
+    }
+}
+
+/// Rebuilds a row from its recorded `history`, oldest first, leaving the `abandoned` writes out:
+/// starting from the row before the history, every other write changes the columns it changed
+/// when it was made.  Returns the row and the writes whose row before them changed, with it.
+fn rebuild(
+    history: &[unconfirmed_writes::Model],
+    abandoned: &HashSet<i64>,
+) -> (Option<JsonValue>, Vec<(i64, Option<JsonValue>)>) {
+    let mut row = history.first().and_then(|w| w.previous.clone());
+    let mut previous = Vec::new();
+    for write in history.iter().filter(|w| !abandoned.contains(&w.id)) {
+        if write.previous != row {
+            previous.push((write.id, row.clone()));
+        }
+        row = replay(row, write);
+    }
+    (row, previous)
+}
+
+/// Takes back `writes`, newest first, without recording what is written doing so.  A row written
+/// again by a slot that wasn't abandoned is rebuilt from the writes of the surviving slots
+/// instead, so that their writes aren't lost.
+async fn roll_back(
+    writes: Vec<unconfirmed_writes::Model>,
+    txn: &DatabaseTransaction,
+) -> Result<(), IngesterError> {
+    if writes.is_empty() {
+        return Ok(());
+    }
+    txn.execute(Statement::from_string(
+        DbBackend::Postgres,
+        "SELECT set_config('das.rolling_back', 'on', true)".to_string(),
+    ))
+    .await?;
+    let count = writes.len();
+    let abandoned: HashSet<i64> = writes.iter().map(|w| w.id).collect();
+    let rows = writes.iter().fold(Condition::any(), |rows, w| {
+        rows.add(
+            Condition::all()
+                .add(unconfirmed_writes::Column::TableName.eq(w.table_name.clone()))
+                .add(unconfirmed_writes::Column::RowKey.eq(w.row_key.clone())),
+        )
+    });
+    let mut histories: HashMap<(String, String), Vec<unconfirmed_writes::Model>> = HashMap::new();
+    for write in unconfirmed_writes::Entity::find()
+        .filter(rows)
+        .order_by_asc(unconfirmed_writes::Column::Id)
+        .all(txn)
+        .await?
+    {
+        histories
+            .entry((write.table_name.clone(), write.row_key.to_string()))
+            .or_default()
+            .push(write);
+    }
+    let mut rebuilt = HashSet::new();
+    for write in writes {
+        let row = (write.table_name.clone(), write.row_key.to_string());
+        let history = histories.get(&row).map(Vec::as_slice).unwrap_or_default();
+        let overwritten = history
+            .iter()
+            .any(|w| w.id > write.id && !abandoned.contains(&w.id));
+        if !overwritten {
+            if let Some(statement) = undo_statement(&write) {
+                txn.execute(statement).await?;
+            }
+        } else if rebuilt.insert(row) {
+            let (image, previous) = rebuild(history, &abandoned);
+            if let Some(statement) =
+                restore_statement(&write.table_name, &write.row_key, image.as_ref())
+            {
+                txn.execute(statement).await?;
+            }
+            for (id, image) in previous {
+                unconfirmed_writes::Entity::update_many()
+                    .col_expr(unconfirmed_writes::Column::Previous, Expr::value(image))
+                    .filter(unconfirmed_writes::Column::Id.eq(id))
+                    .exec(txn)
+                    .await?;
+            }
+        }
+    }
+    txn.execute(Statement::from_string(
+        DbBackend::Postgres,
+        "SELECT set_config('das.rolling_back', 'off', true)".to_string(),
+    ))
+    .await?;
+    safe_metric(|| {
+        statsd_count!("ingester.rolled_back_writes", count as i64);
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult};
+    use serde_json::json;
+
+    fn write(
+        id: i64,
+        slot: i64,
+        table: &str,
+        previous: Option<serde_json::Value>,
+    ) -> unconfirmed_writes::Model {
+        unconfirmed_writes::Model {
+            id,
+            slot,
+            table_name: table.to_string(),
+            row_key: json!("\\x01"),
+            previous,
+            written: None,
+        }
+    }
+
+    fn overwrite(
+        id: i64,
+        slot: i64,
+        previous: serde_json::Value,
+        written: serde_json::Value,
+    ) -> unconfirmed_writes::Model {
+        unconfirmed_writes::Model {
+            written: Some(written),
+            ..write(id, slot, "asset", Some(previous))
+        }
+    }
+
+    fn asset(owner: &str, delegate: Option<&str>) -> serde_json::Value {
+        json!({"id": "\\x01", "owner": owner, "delegate": delegate})
+    }
+
+    fn slot(slot: i64, parent: Option<i64>, status: SlotStatus) -> slots::Model {
+        slots::Model {
+            slot,
+            parent,
+            status,
+            updated_at: Utc::now().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn follows_the_chain_back_to_the_previous_root() {
+        // 10 <- 11 <- 13 is the rooted chain, 12 forked off 11.
+        let parents: HashMap<i64, Option<i64>> =
+            [(11, Some(10)), (12, Some(11)), (13, Some(11))].into();
+        assert_eq!(root_chain(&parents, 13, 10), Some([11, 13].into()));
+        assert_eq!(root_chain(&parents, 10, 10), Some(HashSet::new()));
+
+        let parents: HashMap<i64, Option<i64>> = [(12, Some(11)), (13, None)].into();
+        assert_eq!(root_chain(&parents, 12, 10), None);
+        assert_eq!(root_chain(&parents, 13, 10), None);
+    }
+
+    #[test]
+    fn undoes_inserts_with_deletes_and_updates_with_the_previous_row() {
+        let insert = undo_statement(&write(1, 12, "asset", None)).unwrap();
+        assert!(insert.sql.starts_with("DELETE FROM asset WHERE id = "));
+
+        let previous = json!({"pubkey": "\\x01", "owner": "\\x02", "amount": 1});
+        let update = undo_statement(&write(2, 12, "token_accounts", Some(previous))).unwrap();
+        assert!(update
+            .sql
+            .starts_with("INSERT INTO token_accounts SELECT * FROM jsonb_populate_record"));
+        assert!(update.sql.ends_with(
+            r#"ON CONFLICT (pubkey) DO UPDATE SET ("owner", "amount") = ROW(excluded."owner", excluded."amount")"#
+        ));
+
+        assert!(undo_statement(&write(3, 12, "raw_txn", None)).is_none());
+    }
+
+    #[tokio::test]
+    async fn rooting_a_slot_takes_back_the_writes_of_abandoned_forks() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![slot(10, Some(9), SlotStatus::Rooted)]])
+            .append_query_results(vec![vec![
+                slot(11, Some(10), SlotStatus::Confirmed),
+                slot(12, Some(11), SlotStatus::Processed),
+                slot(13, Some(11), SlotStatus::Rooted),
+            ]])
+            .append_query_results(vec![vec![
+                write(
+                    7,
+                    12,
+                    "asset",
+                    Some(json!({"id": "\\x01", "owner": "\\x02"})),
+                ),
+                write(5, 12, "asset", None),
+            ]])
+            .append_query_results(vec![vec![
+                write(5, 12, "asset", None),
+                write(
+                    7,
+                    12,
+                    "asset",
+                    Some(json!({"id": "\\x01", "owner": "\\x02"})),
+                ),
+            ]])
+            .append_exec_results(
+                (0..9)
+                    .map(|_| MockExecResult {
+                        last_insert_id: 0,
+                        rows_affected: 1,
+                    })
+                    .collect(),
+            )
+            .into_connection();
+
+        let abandoned = save_slot_status(&db, 13, Some(11), SlotStatus::Rooted)
+            .await
+            .unwrap();
+        assert_eq!(abandoned, vec![12]);
+
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(log.contains("GREATEST(excluded.status, slots.status)"));
+        let newest = log.find("INSERT INTO asset SELECT").unwrap();
+        let oldest = log.find("DELETE FROM asset").unwrap();
+        assert!(newest < oldest);
+        assert!(log.contains(r#"UPDATE \"slots\" SET \"status\""#));
+        assert!(log.contains(r#"DELETE FROM \"unconfirmed_writes\""#));
+        assert!(log.contains("INSERT INTO last_rooted_slot (id, slot) VALUES (true, $1)"));
+    }
+
+    #[test]
+    fn rebuilds_a_row_from_the_writes_of_surviving_slots() {
+        // Fork slot 12 sets the owner and the delegate, then slot 13 of the rooted chain sets the
+        // owner and slot 14 the delegate.
+        let history = vec![
+            overwrite(5, 12, asset("A", None), asset("X", Some("D"))),
+            overwrite(6, 13, asset("X", Some("D")), asset("B", Some("D"))),
+            overwrite(8, 14, asset("B", Some("D")), asset("B", Some("E"))),
+        ];
+        let (row, previous) = rebuild(&history, &[5].into());
+        assert_eq!(row, Some(asset("B", Some("E"))));
+        assert_eq!(
+            previous,
+            vec![(6, Some(asset("A", None))), (8, Some(asset("B", None)))]
+        );
+
+        // The fork inserted the row, slot 13 wrote over it, slot 14 deleted it.
+        let history = vec![
+            write(5, 12, "asset", None),
+            overwrite(6, 13, asset("X", None), asset("B", None)),
+        ];
+        let (row, previous) = rebuild(&history, &[5].into());
+        assert_eq!(row, Some(asset("B", None)));
+        assert_eq!(previous, vec![(6, None)]);
+        let mut deleted = write(8, 14, "asset", Some(asset("B", None)));
+        deleted.written = None;
+        let (row, _) = rebuild(&[history, vec![deleted]].concat(), &[5].into());
+        assert_eq!(row, None);
+    }
+
+    #[tokio::test]
+    async fn rooting_a_slot_keeps_what_the_chain_wrote_after_an_abandoned_fork() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![slot(10, Some(9), SlotStatus::Rooted)]])
+            .append_query_results(vec![vec![
+                slot(11, Some(10), SlotStatus::Confirmed),
+                slot(12, Some(11), SlotStatus::Processed),
+                slot(13, Some(11), SlotStatus::Rooted),
+            ]])
+            // Fork slot 12 set the owner to X, then slot 13 of the rooted chain set it to B.
+            .append_query_results(vec![vec![overwrite(
+                5,
+                12,
+                asset("A", None),
+                asset("X", Some("D")),
+            )]])
+            .append_query_results(vec![vec![
+                overwrite(5, 12, asset("A", None), asset("X", Some("D"))),
+                overwrite(6, 13, asset("X", Some("D")), asset("B", Some("D"))),
+            ]])
+            .append_exec_results(
+                (0..9)
+                    .map(|_| MockExecResult {
+                        last_insert_id: 0,
+                        rows_affected: 1,
+                    })
+                    .collect(),
+            )
+            .into_connection();
+
+        let abandoned = save_slot_status(&db, 13, Some(11), SlotStatus::Rooted)
+            .await
+            .unwrap();
+        assert_eq!(abandoned, vec![12]);
+
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(!log.contains("DELETE FROM asset"));
+        assert_eq!(log.matches("INSERT INTO asset SELECT").count(), 1);
+        let restored = log.find("INSERT INTO asset SELECT").unwrap();
+        let owner = log.find(r#""owner": String("B")"#).unwrap();
+        let delegate = log.find(r#""delegate": Null"#).unwrap();
+        assert!(restored < owner && restored < delegate);
+        assert!(log.contains(r#"UPDATE \"unconfirmed_writes\" SET \"previous\""#));
+    }
+}
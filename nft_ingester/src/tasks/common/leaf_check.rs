@@ -0,0 +1,463 @@
+use crate::{
+    metrics::safe_metric,
+    tasks::{FromTaskData, IntoTaskData},
+    BgTask, IngesterError, TaskData,
+};
+use async_trait::async_trait;
+use blockbuster::programs::bubblegum::LeafSchema;
+use cadence_macros::statsd_count;
+use digital_asset_types::dao::{asset, backfill_items, cl_items};
+use sea_orm::{
+    entity::*, query::*, sea_query::Expr, ConnectionTrait, DatabaseTransaction, DbBackend,
+    FromQueryResult, SqlxPostgresConnector, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use sqlx::{Pool, Postgres};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+const TASK_NAME: &str = "CheckLeaves";
+const PAGE_SIZE: u64 = 1000;
+const EMPTY_LEAF: [u8; 32] = [0; 32];
+
+/// Checks that the leaf stored with each compressed asset, the leaf in `cl_items` and the leaf
+/// recomputed from the asset's fields all agree.  Checks every tree when `tree` is not set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CheckLeaves {
+    pub tree: Option<String>,
+    pub repair: bool,
+}
+
+/// Leaf checks of every tree enqueued every `interval` seconds, repairing what they find with
+/// `repair`.
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct LeafCheckConfig {
+    pub interval: u64,
+    pub repair: Option<bool>,
+}
+
+impl IntoTaskData for CheckLeaves {
+    fn into_task_data(self) -> Result<TaskData, IngesterError> {
+        let data =
+            serde_json::to_value(self).map_err(<serde_json::Error as Into<IngesterError>>::into)?;
+        Ok(TaskData {
+            name: TASK_NAME,
+            data,
+            created_at: None,
+        })
+    }
+}
+
+impl FromTaskData<CheckLeaves> for CheckLeaves {
+    fn from_task_data(data: TaskData) -> Result<Self, IngesterError> {
+        serde_json::from_value(data.data).map_err(|e| e.into())
+    }
+}
+
+/// The fields of a compressed asset its leaf is made of.
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct LeafFields {
+    pub id: Vec<u8>,
+    pub owner: Option<Vec<u8>>,
+    pub delegate: Option<Vec<u8>>,
+    pub nonce: i64,
+    pub data_hash: Option<String>,
+    pub creator_hash: Option<String>,
+    pub leaf: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LeafStatus {
+    Consistent,
+    // The asset fields match the tree but `asset.leaf` is stale.  Repaired by copying the
+    // tree leaf.
+    StaleLeaf { tree_leaf: Vec<u8> },
+    // The asset fields don't hash to the leaf in the tree, so some instruction was missed.
+    // Repaired by replaying the tree.
+    StaleFields,
+    // The leaf isn't in `cl_items`, or the fields needed to hash it are missing.
+    Unknown,
+}
+
+#[derive(Debug, Default)]
+pub struct LeafCheckReport {
+    pub checked: u64,
+    pub stale_leaves: u64,
+    pub stale_fields: u64,
+    pub unknown: u64,
+}
+
+impl Display for LeafCheckReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "checked {} leaves: {} stale leaves, {} stale assets, {} unknown",
+            self.checked, self.stale_leaves, self.stale_fields, self.unknown
+        )
+    }
+}
+
+/// Recomputes the leaf of a compressed asset from its fields.  A missing delegate means the
+/// owner is the delegate.
+pub fn compute_leaf(fields: &LeafFields) -> Option<[u8; 32]> {
+    let owner = Pubkey::new(fields.owner.as_ref()?);
+    let delegate = match &fields.delegate {
+        Some(d) => Pubkey::new(d),
+        None => owner,
+    };
+    let data_hash = decode_hash(fields.data_hash.as_ref()?)?;
+    let creator_hash = decode_hash(fields.creator_hash.as_ref()?)?;
+    let schema = LeafSchema::new_v0(
+        Pubkey::new(&fields.id),
+        owner,
+        delegate,
+        fields.nonce as u64,
+        data_hash,
+        creator_hash,
+    );
+    Some(schema.to_node())
+}
+
+/// The raw address of a tree given in base58.
+pub fn decode_tree(tree: &str) -> Result<Vec<u8>, IngesterError> {
+    Pubkey::from_str(tree)
+        .map(|t| t.to_bytes().to_vec())
+        .map_err(|e| IngesterError::DeserializationError(format!("Invalid tree {}: {}", tree, e)))
+}
+
+fn decode_hash(hash: &str) -> Option<[u8; 32]> {
+    bs58::decode(hash).into_vec().ok()?.try_into().ok()
+}
+
+/// Compares an asset with the leaf indexed for it in the tree.  Redeemed assets have their leaf
+/// zeroed in both places until they are decompressed.
+pub fn check_leaf(fields: &LeafFields, tree_leaf: Option<&[u8]>) -> LeafStatus {
+    let tree_leaf = match tree_leaf {
+        Some(l) => l,
+        None => return LeafStatus::Unknown,
+    };
+    let asset_leaf = fields.leaf.as_deref();
+    if tree_leaf == EMPTY_LEAF {
+        return match asset_leaf {
+            Some(l) if l == EMPTY_LEAF => LeafStatus::Consistent,
+            _ => LeafStatus::StaleFields,
+        };
+    }
+    match compute_leaf(fields) {
+        Some(computed) if computed == tree_leaf => {
+            if asset_leaf == Some(tree_leaf) {
+                LeafStatus::Consistent
+            } else {
+                LeafStatus::StaleLeaf {
+                    tree_leaf: tree_leaf.to_vec(),
+                }
+            }
+        }
+        Some(_) => LeafStatus::StaleFields,
+        None => LeafStatus::Unknown,
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct CompressedTree {
+    tree_id: Vec<u8>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct TreeLeaf {
+    leaf_idx: i64,
+    hash: Vec<u8>,
+}
+
+/// Checks the leaves of every compressed asset of `tree` (or of all trees).  With `repair`,
+/// stale `asset.leaf` values are overwritten and trees with stale assets are flagged with
+/// `force_chk` so the backfiller replays them.  Each page of assets is checked and repaired in
+/// its own transaction.
+pub async fn check_leaves(
+    conn: &(impl ConnectionTrait + TransactionTrait),
+    tree: Option<Vec<u8>>,
+    repair: bool,
+) -> Result<LeafCheckReport, IngesterError> {
+    let trees = match tree {
+        Some(tree) => vec![tree],
+        None => {
+            let query = asset::Entity::find()
+                .select_only()
+                .column(asset::Column::TreeId)
+                .distinct()
+                .filter(asset::Column::Compressed.eq(true))
+                .filter(asset::Column::TreeId.is_not_null())
+                .build(DbBackend::Postgres);
+            CompressedTree::find_by_statement(query)
+                .all(conn)
+                .await?
+                .into_iter()
+                .map(|t| t.tree_id)
+                .collect()
+        }
+    };
+
+    let mut report = LeafCheckReport::default();
+    for tree in trees {
+        let tree_string = bs58::encode(&tree).into_string();
+        let mut stale_fields = false;
+        let mut last_nonce = -1;
+        loop {
+            let txn = conn.begin().await?;
+            let assets = asset::Entity::find()
+                .select_only()
+                .column(asset::Column::Id)
+                .column(asset::Column::Owner)
+                .column(asset::Column::Delegate)
+                .column(asset::Column::Nonce)
+                .column(asset::Column::DataHash)
+                .column(asset::Column::CreatorHash)
+                .column(asset::Column::Leaf)
+                .filter(asset::Column::TreeId.eq(tree.clone()))
+                .filter(asset::Column::Compressed.eq(true))
+                .filter(asset::Column::Burnt.eq(false))
+                .filter(asset::Column::Nonce.gt(last_nonce))
+                .order_by_asc(asset::Column::Nonce)
+                .limit(PAGE_SIZE)
+                .into_model::<LeafFields>()
+                .all(&txn)
+                .await?;
+            if assets.is_empty() {
+                txn.commit().await?;
+                break;
+            }
+            last_nonce = assets.last().map(|a| a.nonce).unwrap_or(last_nonce);
+
+            let tree_leaves = get_tree_leaves(&txn, &tree, &assets).await?;
+            for fields in assets.iter() {
+                report.checked += 1;
+                let status = check_leaf(fields, tree_leaves.get(&fields.nonce).map(Vec::as_slice));
+                match status {
+                    LeafStatus::Consistent => {}
+                    LeafStatus::StaleLeaf { tree_leaf } => {
+                        report.stale_leaves += 1;
+                        println!(
+                            "Stale leaf for asset {} in tree {tree_string}",
+                            bs58::encode(&fields.id).into_string()
+                        );
+                        if repair {
+                            asset::Entity::update_many()
+                                .col_expr(asset::Column::Leaf, Expr::value(tree_leaf))
+                                .filter(asset::Column::Id.eq(fields.id.clone()))
+                                .exec(&txn)
+                                .await?;
+                        }
+                    }
+                    LeafStatus::StaleFields => {
+                        report.stale_fields += 1;
+                        stale_fields = true;
+                        println!(
+                            "Asset {} does not hash to its leaf in tree {tree_string}",
+                            bs58::encode(&fields.id).into_string()
+                        );
+                    }
+                    LeafStatus::Unknown => report.unknown += 1,
+                }
+            }
+            txn.commit().await?;
+        }
+        if stale_fields && repair {
+            backfill_items::Entity::update_many()
+                .col_expr(backfill_items::Column::ForceChk, Expr::value(true))
+                .filter(backfill_items::Column::Tree.eq(tree.clone()))
+                .exec(conn)
+                .await?;
+        }
+    }
+    safe_metric(|| {
+        statsd_count!("ingester.leaf_check.stale_leaf", report.stale_leaves as i64);
+        statsd_count!(
+            "ingester.leaf_check.stale_fields",
+            report.stale_fields as i64
+        );
+    });
+    Ok(report)
+}
+
+/// Loads the latest level 0 node of each asset's leaf, keyed by leaf index.
+async fn get_tree_leaves(
+    conn: &impl ConnectionTrait,
+    tree: &[u8],
+    assets: &[LeafFields],
+) -> Result<HashMap<i64, Vec<u8>>, IngesterError> {
+    let leaf_indexes: Vec<i64> = assets.iter().map(|a| a.nonce).collect();
+    let mut query = cl_items::Entity::find()
+        .select_only()
+        .column(cl_items::Column::LeafIdx)
+        .column(cl_items::Column::Hash)
+        .filter(cl_items::Column::Tree.eq(tree))
+        .filter(cl_items::Column::Level.eq(0i64))
+        .filter(cl_items::Column::LeafIdx.is_in(leaf_indexes))
+        .order_by_asc(cl_items::Column::LeafIdx)
+        .order_by_desc(cl_items::Column::Seq)
+        .build(DbBackend::Postgres);
+    query.sql = query
+        .sql
+        .replace("SELECT", "SELECT DISTINCT ON (cl_items.leaf_idx)");
+    let leaves = TreeLeaf::find_by_statement(query).all(conn).await?;
+    Ok(leaves.into_iter().map(|l| (l.leaf_idx, l.hash)).collect())
+}
+
+/// Runs leaf checks with its own connections rather than the task's transaction, so that a
+/// check of every tree doesn't hold one transaction open for its whole length.
+pub struct CheckLeavesTask {
+    pub pool: Pool<Postgres>,
+}
+
+#[async_trait]
+impl BgTask for CheckLeavesTask {
+    fn name(&self) -> &'static str {
+        TASK_NAME
+    }
+
+    fn lock_duration(&self) -> i64 {
+        600
+    }
+
+    fn max_attempts(&self) -> i16 {
+        1
+    }
+
+    async fn task(
+        &self,
+        _db: &DatabaseTransaction,
+        data: serde_json::Value,
+    ) -> Result<(), IngesterError> {
+        let check: CheckLeaves = serde_json::from_value(data)?;
+        let tree = check.tree.as_deref().map(decode_tree).transpose()?;
+        let db = SqlxPostgresConnector::from_sqlx_postgres_pool(self.pool.clone());
+        let report = check_leaves(&db, tree, check.repair).await?;
+        println!("Leaf check {}", report);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DatabaseBackend, MockDatabase, Value};
+    use std::collections::BTreeMap;
+
+    fn fields(owner: Pubkey, delegate: Option<Pubkey>) -> LeafFields {
+        LeafFields {
+            id: Pubkey::new_unique().to_bytes().to_vec(),
+            owner: Some(owner.to_bytes().to_vec()),
+            delegate: delegate.map(|d| d.to_bytes().to_vec()),
+            nonce: 3,
+            data_hash: Some(bs58::encode([1; 32]).into_string()),
+            creator_hash: Some(bs58::encode([2; 32]).into_string()),
+            leaf: None,
+        }
+    }
+
+    #[test]
+    fn missing_delegate_is_the_owner() {
+        let owner = Pubkey::new_unique();
+        let without = fields(owner, None);
+        let with = LeafFields {
+            delegate: Some(owner.to_bytes().to_vec()),
+            ..without.clone()
+        };
+        assert_eq!(compute_leaf(&without), compute_leaf(&with));
+    }
+
+    #[test]
+    fn classifies_leaves() {
+        let mut asset = fields(Pubkey::new_unique(), Some(Pubkey::new_unique()));
+        let leaf = compute_leaf(&asset).unwrap().to_vec();
+
+        assert_eq!(check_leaf(&asset, None), LeafStatus::Unknown);
+        assert_eq!(
+            check_leaf(&asset, Some(leaf.as_slice())),
+            LeafStatus::StaleLeaf {
+                tree_leaf: leaf.clone()
+            }
+        );
+
+        asset.leaf = Some(leaf.clone());
+        assert_eq!(
+            check_leaf(&asset, Some(leaf.as_slice())),
+            LeafStatus::Consistent
+        );
+        assert_eq!(
+            check_leaf(&asset, Some(&[9; 32][..])),
+            LeafStatus::StaleFields
+        );
+
+        asset.owner = Some(Pubkey::new_unique().to_bytes().to_vec());
+        assert_eq!(
+            check_leaf(&asset, Some(leaf.as_slice())),
+            LeafStatus::StaleFields
+        );
+    }
+
+    #[test]
+    fn redeemed_leaves_are_empty_in_both_places() {
+        let mut asset = fields(Pubkey::new_unique(), None);
+        assert_eq!(
+            check_leaf(&asset, Some(&EMPTY_LEAF[..])),
+            LeafStatus::StaleFields
+        );
+        asset.leaf = Some(EMPTY_LEAF.to_vec());
+        assert_eq!(
+            check_leaf(&asset, Some(&EMPTY_LEAF[..])),
+            LeafStatus::Consistent
+        );
+    }
+
+    fn row(fields: &LeafFields) -> BTreeMap<&'static str, Value> {
+        BTreeMap::from([
+            ("id", fields.id.clone().into()),
+            ("owner", fields.owner.clone().into()),
+            ("delegate", fields.delegate.clone().into()),
+            ("nonce", fields.nonce.into()),
+            ("data_hash", fields.data_hash.clone().into()),
+            ("creator_hash", fields.creator_hash.clone().into()),
+            ("leaf", fields.leaf.clone().into()),
+        ])
+    }
+
+    #[tokio::test]
+    async fn checks_each_page_in_its_own_transaction() {
+        let mut asset = fields(Pubkey::new_unique(), None);
+        let leaf = compute_leaf(&asset).unwrap().to_vec();
+        asset.leaf = Some(leaf.clone());
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results(vec![vec![row(&asset)]])
+            .append_query_results(vec![vec![BTreeMap::from([
+                ("leaf_idx", Value::from(asset.nonce)),
+                ("hash", leaf.into()),
+            ])]])
+            .append_query_results(vec![Vec::<BTreeMap<&str, Value>>::new()])
+            .into_connection();
+
+        let tree = Pubkey::new_unique().to_bytes().to_vec();
+        let report = check_leaves(&db, Some(tree), false).await.unwrap();
+        assert_eq!(report.checked, 1);
+        assert_eq!(
+            report.stale_leaves + report.stale_fields + report.unknown,
+            0
+        );
+        assert_eq!(db.into_transaction_log().len(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_tree_addresses() {
+        let tree = Pubkey::new_unique();
+        assert_eq!(
+            decode_tree(&tree.to_string()).unwrap(),
+            tree.to_bytes().to_vec()
+        );
+        assert!(decode_tree("not a tree").is_err());
+        assert!(decode_tree(&bs58::encode([1; 8]).into_string()).is_err());
+    }
+}
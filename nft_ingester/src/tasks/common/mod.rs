@@ -3,6 +3,7 @@ use blockbuster::programs::bubblegum::ChangeLogEventV1;
 use digital_asset_types::dao::{backfill_items, cl_items};
 use sea_orm::{entity::*, query::*, sea_query::OnConflict, DatabaseTransaction, DbBackend};
 
+pub mod leaf_check;
 pub mod task;
 
 pub async fn save_changelog_event(